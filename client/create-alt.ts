@@ -45,6 +45,11 @@ async function main() {
     SHIELDED_POOL_PROGRAM_ID
   );
 
+  const [feeVaultPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("fee_vault")],
+    SHIELDED_POOL_PROGRAM_ID
+  );
+
   console.log("Vault PDA:", vaultPda.toBase58());
   console.log("State PDA:", statePda.toBase58());
 
@@ -62,6 +67,7 @@ async function main() {
     relayer.publicKey,            // Payer
     vaultPda,                     // Pool Vault
     statePda,                     // Pool State
+    feeVaultPda,                  // Fee Vault
     SHIELDED_POOL_PROGRAM_ID,     // Main Program
     ZK_VERIFIER_PROGRAM_ID,       // ZK Program
     AUDIT_VERIFIER_PROGRAM_ID,    // Audit Program
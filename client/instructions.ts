@@ -0,0 +1,280 @@
+/**
+ * Shielded pool instruction builders.
+ *
+ * These mirror the Rust builders in `shielded_pool_program/src/client.rs`:
+ * same account order, same data layout. When a handler's layout changes,
+ * update both.
+ */
+
+import { getAddressEncoder, getProgramDerivedAddress, type Address } from "@solana/kit";
+import { SYSTEM_PROGRAM_ADDRESS } from "@solana-program/system";
+
+export const INSTRUCTION = {
+    INITIALIZE: 0,
+    DEPOSIT: 1,
+    WITHDRAW: 2,
+};
+
+/** Account roles as `@solana/kit` numbers them. */
+const READONLY = 0;
+const WRITABLE = 1;
+const WRITABLE_SIGNER = 3;
+
+export type InstructionAccount = { address: Address; role: number };
+export type Instruction = {
+    programAddress: Address;
+    accounts: InstructionAccount[];
+    data: Uint8Array;
+};
+
+/** Pool configuration written by `Initialize`. */
+export interface PoolConfig {
+    depositFeeBps: number;
+    feeCollector: Address;
+    treeDepth: number;
+    minWithdraw: bigint;
+    proofLen: number;
+    publicInputCount: number;
+    minDepositAgeSlots: bigint;
+    /** Root the pool starts from; omitted, the program computes the empty-tree root. */
+    initialRoot?: Uint8Array;
+}
+
+/**
+ * The rest of the pool config, set in the same `Initialize` write. Mirrors
+ * `InitializeParams`; flags are 0 or 1.
+ */
+export interface InitializeParams {
+    withdrawFeeBps: number;
+    minVerifierComputeUnits: bigint;
+    auditRequiredFromSlot: bigint;
+    recipientMaxPerWindow: bigint;
+    recipientWindowSlots: bigint;
+    rebateMint: Address | null;
+    rebateAmount: bigint;
+    ownerGated: number;
+    uniqueCommitments: number;
+    requireRecipientSignature: number;
+    requireWalletRecipient: number;
+    relayerRegistry: number;
+    authorityThreshold: number;
+    coAuthorities: Address[];
+    altVerifier: Address | null;
+    altProofLen: number;
+    publicInputOrder: number[];
+}
+
+/** Public witness header: [count: u32 BE][0: u32][count: u32 BE]. */
+const WITNESS_HEADER_LEN = 12;
+
+const encoder = new TextEncoder();
+
+async function pda(programId: Address, seeds: Uint8Array[]): Promise<Address> {
+    const [address] = await getProgramDerivedAddress({ programAddress: programId, seeds });
+    return address;
+}
+
+export const statePda = (programId: Address) => pda(programId, [encoder.encode("pool_state")]);
+export const vaultPda = (programId: Address) => pda(programId, [encoder.encode("vault")]);
+export const feeVaultPda = (programId: Address) => pda(programId, [encoder.encode("fee_vault")]);
+/** `asset_id` of the native SOL vault, whose nullifiers keep the legacy seeds. */
+export const NATIVE_ASSET_ID = new Uint8Array(32);
+
+/** Asset-scoped seeds: [prefix, asset_id, key], or [prefix, key] for SOL. */
+function assetSeeds(prefix: string, assetId: Uint8Array, key: Uint8Array): Uint8Array[] {
+    return assetId.every((byte) => byte === 0)
+        ? [encoder.encode(prefix), key]
+        : [encoder.encode(prefix), assetId, key];
+}
+
+export const nullifierPda = (
+    programId: Address,
+    nullifier: Uint8Array,
+    assetId: Uint8Array = NATIVE_ASSET_ID
+) => pda(programId, assetSeeds("nullifier", assetId, nullifier));
+export const nullifierSetPda = (
+    programId: Address,
+    nullifier: Uint8Array,
+    assetId: Uint8Array = NATIVE_ASSET_ID
+) => pda(programId, assetSeeds("nullifier_set", assetId, nullifier.slice(0, 1)));
+export const auditRecordPda = (programId: Address, waCommitment: Uint8Array) =>
+    pda(programId, [encoder.encode("audit"), waCommitment]);
+export const commitmentPda = (programId: Address, commitment: Uint8Array) =>
+    pda(programId, [encoder.encode("commitment"), commitment]);
+export const denylistEntryPda = (programId: Address, recipient: Address) =>
+    pda(programId, [encoder.encode("denylist"), addressBytes(recipient)]);
+export const recipientLimitPda = (programId: Address, recipient: Address) =>
+    pda(programId, [encoder.encode("recipient_limit"), addressBytes(recipient)]);
+export const relayerEntryPda = (programId: Address, relayer: Address) =>
+    pda(programId, [encoder.encode("relayer"), addressBytes(relayer)]);
+
+function addressBytes(address: Address): Uint8Array {
+    return Uint8Array.from(getAddressEncoder().encode(address));
+}
+
+function u16Le(value: number): Uint8Array {
+    const bytes = new Uint8Array(2);
+    new DataView(bytes.buffer).setUint16(0, value, true);
+    return bytes;
+}
+
+function u32Le(value: number): Uint8Array {
+    const bytes = new Uint8Array(4);
+    new DataView(bytes.buffer).setUint32(0, value, true);
+    return bytes;
+}
+
+function u64Le(value: bigint): Uint8Array {
+    const bytes = new Uint8Array(8);
+    new DataView(bytes.buffer).setBigUint64(0, value, true);
+    return bytes;
+}
+
+function concat(parts: Uint8Array[]): Uint8Array {
+    const out = new Uint8Array(parts.reduce((len, part) => len + part.length, 0));
+    let offset = 0;
+    for (const part of parts) {
+        out.set(part, offset);
+        offset += part.length;
+    }
+    return out;
+}
+
+function initializeParamsBytes(params: InitializeParams): Uint8Array {
+    const key = (address: Address | null) => (address ? addressBytes(address) : new Uint8Array(32));
+    return concat([
+        u16Le(params.withdrawFeeBps),
+        u64Le(params.minVerifierComputeUnits),
+        u64Le(params.auditRequiredFromSlot),
+        u64Le(params.recipientMaxPerWindow),
+        u64Le(params.recipientWindowSlots),
+        key(params.rebateMint),
+        u64Le(params.rebateAmount),
+        Uint8Array.of(
+            params.ownerGated,
+            params.uniqueCommitments,
+            params.requireRecipientSignature,
+            params.requireWalletRecipient,
+            params.relayerRegistry,
+            params.authorityThreshold,
+            params.coAuthorities.length
+        ),
+        key(params.coAuthorities[0] ?? null),
+        key(params.coAuthorities[1] ?? null),
+        key(params.altVerifier),
+        u32Le(params.altProofLen),
+        Uint8Array.from(params.publicInputOrder),
+    ]);
+}
+
+/**
+ * `Initialize` data: [deposit_fee_bps: u16][fee_collector: 32][tree_depth: u8]
+ * [min_withdraw: u64][proof_len: u32][public_input_count: u8]
+ * [min_deposit_age_slots: u64]([initial_root: 32][InitializeParams: 186]?)?
+ *
+ * With `params` the root is always written (all zeros when unset) and the
+ * rebate mint is appended to the accounts when the rebate is on.
+ */
+export async function initialize(
+    programId: Address,
+    payer: Address,
+    config: PoolConfig,
+    params?: InitializeParams
+): Promise<Instruction> {
+    const initialRoot = config.initialRoot ?? (params ? new Uint8Array(32) : new Uint8Array());
+    const data = concat([
+        Uint8Array.of(INSTRUCTION.INITIALIZE),
+        u16Le(config.depositFeeBps),
+        addressBytes(config.feeCollector),
+        Uint8Array.of(config.treeDepth),
+        u64Le(config.minWithdraw),
+        u32Le(config.proofLen),
+        Uint8Array.of(config.publicInputCount),
+        u64Le(config.minDepositAgeSlots),
+        initialRoot,
+        params ? initializeParamsBytes(params) : new Uint8Array(),
+    ]);
+
+    return {
+        programAddress: programId,
+        accounts: [
+            { address: payer, role: WRITABLE_SIGNER },
+            { address: await statePda(programId), role: WRITABLE },
+            { address: await vaultPda(programId), role: WRITABLE },
+            { address: await feeVaultPda(programId), role: WRITABLE },
+            { address: SYSTEM_PROGRAM_ADDRESS, role: READONLY },
+            ...(params?.rebateMint && params.rebateAmount > 0n
+                ? [{ address: params.rebateMint, role: READONLY }]
+                : []),
+        ],
+        data,
+    };
+}
+
+/** `Deposit` data: [amount: u64][commitment: 32][prev_root: 32][new_root: 32] */
+export async function deposit(
+    programId: Address,
+    payer: Address,
+    amount: bigint,
+    commitment: Uint8Array,
+    prevRoot: Uint8Array,
+    newRoot: Uint8Array
+): Promise<Instruction> {
+    return {
+        programAddress: programId,
+        accounts: [
+            { address: payer, role: WRITABLE_SIGNER },
+            { address: await statePda(programId), role: WRITABLE },
+            { address: await vaultPda(programId), role: WRITABLE },
+            { address: await feeVaultPda(programId), role: WRITABLE },
+            { address: SYSTEM_PROGRAM_ADDRESS, role: READONLY },
+            { address: await commitmentPda(programId, commitment), role: WRITABLE },
+        ],
+        data: concat([
+            Uint8Array.of(INSTRUCTION.DEPOSIT),
+            u64Le(amount),
+            commitment,
+            prevRoot,
+            newRoot,
+        ]),
+    };
+}
+
+/**
+ * `Withdraw` from the raw proof and public witness. The nullifier and
+ * wa_commitment are read from the witness at their default positions
+ * (inputs 1 and 4).
+ */
+export async function withdraw(
+    programId: Address,
+    verifierId: Address,
+    payer: Address,
+    recipient: Address,
+    proof: Uint8Array,
+    witness: Uint8Array
+): Promise<Instruction> {
+    const input = (index: number) =>
+        witness.slice(WITNESS_HEADER_LEN + 32 * index, WITNESS_HEADER_LEN + 32 * (index + 1));
+    const nullifier = input(1);
+    const waCommitment = input(4);
+
+    return {
+        programAddress: programId,
+        accounts: [
+            { address: payer, role: WRITABLE_SIGNER },
+            { address: recipient, role: WRITABLE },
+            { address: await vaultPda(programId), role: WRITABLE },
+            { address: await statePda(programId), role: WRITABLE },
+            { address: await nullifierPda(programId, nullifier), role: WRITABLE },
+            { address: verifierId, role: READONLY },
+            { address: await auditRecordPda(programId, waCommitment), role: READONLY },
+            { address: await nullifierSetPda(programId, nullifier), role: READONLY },
+            { address: SYSTEM_PROGRAM_ADDRESS, role: READONLY },
+            { address: await denylistEntryPda(programId, recipient), role: READONLY },
+            { address: await recipientLimitPda(programId, recipient), role: WRITABLE },
+            { address: await feeVaultPda(programId), role: WRITABLE },
+            { address: await relayerEntryPda(programId, payer), role: READONLY },
+        ],
+        data: concat([Uint8Array.of(INSTRUCTION.WITHDRAW), proof, witness]),
+    };
+}
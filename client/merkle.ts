@@ -17,7 +17,7 @@ function getPoseidon(): Poseidon {
     return poseidonInstance;
 }
 
-const TREE_DEPTH = 16;
+export const TREE_DEPTH = 16;
 
 export function poseidonHash2(left: bigint, right: bigint): bigint {
     const poseidon = getPoseidon();
@@ -28,13 +28,11 @@ import {
     assertIsTransactionWithBlockhashLifetime,
     sendAndConfirmTransactionFactory,
     getSignatureFromTransaction,
-    getProgramDerivedAddress,
     getAddressEncoder,
     type Address,
     type KeyPairSigner,
 } from "@solana/kit";
 import { getSetComputeUnitLimitInstruction } from "@solana-program/compute-budget";
-import { SYSTEM_PROGRAM_ADDRESS } from "@solana-program/system";
 import fs from "fs";
 import path from "path";
 import crypto from "crypto";
@@ -49,6 +47,7 @@ import {
     type IdentityKeypair,
 } from "./merkle.js";
 import { generateProof, type CircuitConfig } from "./proof.helper.js";
+import { deposit, withdraw, type Instruction } from "./instructions.js";
 
 // ============================================
 // Configuration
@@ -79,12 +78,6 @@ const keypairDir = path.join(repoRoot, "keypair");
 const senderWalletPath = path.join(keypairDir, "sender.json");
 const relayerWalletPath = path.join(keypairDir, "relayer.json");
 
-const INSTRUCTION = {
-    INITIALIZE: 0,
-    DEPOSIT: 1,
-    WITHDRAW: 2,
-};
-
 // Payroll amounts (in lamports)
 // Note: Must be >= rent-exempt minimum (~890,880 lamports) for new accounts
 const ALICE_AMOUNT = 1_000_000n;   // 0.001 SOL (300 USDC equivalent for demo)
@@ -112,16 +105,6 @@ function fieldToBytes(f: bigint): Uint8Array {
     return Uint8Array.from(Buffer.from(hex, "hex"));
 }
 
-function u64ToLeBytes(value: bigint): Uint8Array {
-    const bytes = new Uint8Array(8);
-    let v = value;
-    for (let i = 0; i < 8; i += 1) {
-        bytes[i] = Number(v & 0xffn);
-        v >>= 8n;
-    }
-    return bytes;
-}
-
 function recipientFieldFromPubkey(pubkey: Address): string {
     const pubkeyBytes = getAddressEncoder().encode(pubkey);
     const trimmed = pubkeyBytes.slice(0, 30);
@@ -139,13 +122,6 @@ function randomField(): bigint {
     return BigInt("0x" + bytes.toString("hex"));
 }
 
-type InstructionAccount = { address: Address; role: number };
-type Instruction = {
-    programAddress: Address;
-    accounts: InstructionAccount[];
-    data: Uint8Array;
-};
-
 async function sendTransaction(
     sendAndConfirm: ReturnType<typeof sendAndConfirmTransactionFactory>,
     rpc: ReturnType<typeof createSolanaRpc>,
@@ -236,18 +212,10 @@ async function main() {
     console.log(`  CHARLIE: ${charlie.address}`);
     console.log(`  DAVID:   ${david.address}`);
 
-    // Derive PDAs
-    const [statePda] = await getProgramDerivedAddress({
-        programAddress: SHIELDED_POOL_PROGRAM_ID,
-        seeds: [new TextEncoder().encode("pool_state")],
-    });
-    const [vaultPda] = await getProgramDerivedAddress({
-        programAddress: SHIELDED_POOL_PROGRAM_ID,
-        seeds: [new TextEncoder().encode("vault")],
-    });
-
-    // Initialize Merkle Tree
+    // Initialize Merkle Tree, and a mirror of the pool's tree that each
+    // deposit advances from its previous root to its new one
     const mt = new ShieldedPoolMerkleTree();
+    const poolTree = new ShieldedPoolMerkleTree();
 
     // Create payroll entries
     const payrollEntries: PayrollEntry[] = [
@@ -283,24 +251,18 @@ async function main() {
     console.log("-".repeat(70));
 
     for (const entry of payrollEntries) {
-        const root = mt.getRoot();
-        
-        const depositData = new Uint8Array(1 + 8 + 32 + 32);
-        depositData[0] = INSTRUCTION.DEPOSIT;
-        depositData.set(u64ToLeBytes(entry.amount), 1);
-        depositData.set(fieldToBytes(entry.commitment), 1 + 8);
-        depositData.set(fieldToBytes(root), 1 + 8 + 32);
-
-        const depositIx: Instruction = {
-            programAddress: SHIELDED_POOL_PROGRAM_ID,
-            accounts: [
-                { address: sender.address, role: 3 },
-                { address: statePda, role: 1 },
-                { address: vaultPda, role: 1 },
-                { address: SYSTEM_PROGRAM_ADDRESS, role: 0 },
-            ],
-            data: depositData,
-        };
+        const prevRoot = poolTree.getRoot();
+        poolTree.insert(entry.commitment);
+        const newRoot = poolTree.getRoot();
+
+        const depositIx = await deposit(
+            SHIELDED_POOL_PROGRAM_ID,
+            sender.address,
+            entry.amount,
+            fieldToBytes(entry.commitment),
+            fieldToBytes(prevRoot),
+            fieldToBytes(newRoot)
+        );
 
         await sendTransaction(
             sendAndConfirm,
@@ -361,30 +323,14 @@ async function main() {
     console.log("-".repeat(70));
 
     for (const { entry, proofResult } of proofResults) {
-        const nullifierBytes = Buffer.from(entry.nullifier.toString(16).padStart(64, '0'), 'hex');
-        const [nullifierPda] = await getProgramDerivedAddress({
-            programAddress: SHIELDED_POOL_PROGRAM_ID,
-            seeds: [new TextEncoder().encode("nullifier"), nullifierBytes],
-        });
-
-        const data = new Uint8Array(1 + proofResult.proof.length + proofResult.publicWitness.length);
-        data[0] = INSTRUCTION.WITHDRAW;
-        data.set(proofResult.proof, 1);
-        data.set(proofResult.publicWitness, 1 + proofResult.proof.length);
-
-        const withdrawIx: Instruction = {
-            programAddress: SHIELDED_POOL_PROGRAM_ID,
-            accounts: [
-                { address: relayer.address, role: 3 },
-                { address: entry.recipient.address, role: 1 },
-                { address: vaultPda, role: 1 },
-                { address: statePda, role: 1 },
-                { address: nullifierPda, role: 1 },
-                { address: ZK_VERIFIER_PROGRAM_ID, role: 0 },
-                { address: SYSTEM_PROGRAM_ADDRESS, role: 0 },
-            ],
-            data,
-        };
+        const withdrawIx = await withdraw(
+            SHIELDED_POOL_PROGRAM_ID,
+            ZK_VERIFIER_PROGRAM_ID,
+            relayer.address,
+            entry.recipient.address,
+            proofResult.proof,
+            proofResult.publicWitness
+        );
 
         await sendTransaction(
             sendAndConfirm,
@@ -14,7 +14,6 @@ import {
     assertIsTransactionWithBlockhashLifetime,
     sendAndConfirmTransactionFactory,
     getSignatureFromTransaction,
-    getProgramDerivedAddress,
     getAddressEncoder,
     type Address,
     type KeyPairSigner,
@@ -32,8 +31,18 @@ import {
     calculateCommitment,
     calculateNullifier,
     ShieldedPoolMerkleTree,
+    TREE_DEPTH,
 } from "./merkle.js";
 import { generateProof, type CircuitConfig } from "./proof.helper.js";
+import {
+    deposit,
+    initialize,
+    nullifierPda as deriveNullifierPda,
+    statePda as deriveStatePda,
+    vaultPda as deriveVaultPda,
+    withdraw,
+    type Instruction,
+} from "./instructions.js";
 
 // Helius RPC (primary) - Get your free API key at https://dev.helius.xyz/
 // Falls back to Solana devnet if Helius fails
@@ -62,12 +71,6 @@ const circuitConfig: CircuitConfig = {
 const keypairDir = path.join(repoRoot, "keypair");
 const senderWalletPath = path.join(keypairDir, "sender.json");
 
-const INSTRUCTION = {
-    INITIALIZE: 0,
-    DEPOSIT: 1,
-    WITHDRAW: 2,
-};
-
 async function loadKeypair(filePath: string): Promise<KeyPairSigner> {
     if (!fs.existsSync(filePath)) {
         throw new Error(`Keypair not found: ${filePath}`);
@@ -85,16 +88,6 @@ function fieldToBytes(f: bigint): Uint8Array {
     return Uint8Array.from(Buffer.from(hex, "hex"));
 }
 
-function u64ToLeBytes(value: bigint): Uint8Array {
-    const bytes = new Uint8Array(8);
-    let v = value;
-    for (let i = 0; i < 8; i += 1) {
-        bytes[i] = Number(v & 0xffn);
-        v >>= 8n;
-    }
-    return bytes;
-}
-
 function recipientFieldFromPubkey(pubkey: Address): string {
     const pubkeyBytes = getAddressEncoder().encode(pubkey);
     const trimmed = pubkeyBytes.slice(0, 30);
@@ -122,13 +115,6 @@ function logBusinessAccounts(label: string, accounts: Array<{ name: string; addr
     }
 }
 
-type InstructionAccount = { address: Address; role: number };
-type Instruction = {
-    programAddress: Address;
-    accounts: InstructionAccount[];
-    data: Uint8Array;
-};
-
 async function sendTransaction(
     sendAndConfirm: ReturnType<typeof sendAndConfirmTransactionFactory>,
     rpc: ReturnType<typeof createSolanaRpc>,
@@ -225,8 +211,9 @@ async function main() {
     const commitment = calculateCommitment(identity.publicKey, amount, randomness);
     console.log(`Commitment: ${fieldToHex(commitment)}`);
 
-    // 4. Off-chain Merkle Tree
+    // 4. Off-chain Merkle Tree; the pool starts from its empty root
     const mt = new ShieldedPoolMerkleTree();
+    const emptyRoot = mt.getRoot();
     const index = mt.insert(commitment);
     const root = mt.getRoot();
     
@@ -269,93 +256,75 @@ async function main() {
 
     // 7. Derive PDAs
     console.log("\nPreparing Transaction...");
-    const [statePda] = await getProgramDerivedAddress({
-        programAddress: SHIELDED_POOL_PROGRAM_ID,
-        seeds: [new TextEncoder().encode("pool_state")],
-    });
-    const [vaultPda] = await getProgramDerivedAddress({
-        programAddress: SHIELDED_POOL_PROGRAM_ID,
-        seeds: [new TextEncoder().encode("vault")],
-    });
-
-    // Nullifier PDA: ["nullifier", nullifier_hash]
-    const nullifierBytes = Buffer.from(nullifier.toString(16).padStart(64, '0'), 'hex');
-    const [nullifierPda] = await getProgramDerivedAddress({
-        programAddress: SHIELDED_POOL_PROGRAM_ID,
-        seeds: [new TextEncoder().encode("nullifier"), nullifierBytes],
-    });
+    const statePda = await deriveStatePda(SHIELDED_POOL_PROGRAM_ID);
+    const vaultPda = await deriveVaultPda(SHIELDED_POOL_PROGRAM_ID);
+    const nullifierPda = await deriveNullifierPda(SHIELDED_POOL_PROGRAM_ID, fieldToBytes(nullifier));
     console.log(`State PDA:  ${statePda}`);
     console.log(`Vault PDA:  ${vaultPda}`);
     console.log(`Nullifier:  ${nullifierPda}`);
 
-    const initIx = {
-        programAddress: SHIELDED_POOL_PROGRAM_ID,
-        accounts: [
-            { address: relayer.address, role: 3 },
-            { address: statePda, role: 1 },
-            { address: vaultPda, role: 1 },
-            { address: SYSTEM_PROGRAM_ADDRESS, role: 0 },
-        ],
-        data: new Uint8Array([INSTRUCTION.INITIALIZE]),
-    };
+    const initIx = await initialize(SHIELDED_POOL_PROGRAM_ID, relayer.address, {
+        depositFeeBps: 0,
+        feeCollector: relayer.address,
+        treeDepth: TREE_DEPTH,
+        minWithdraw: 0n,
+        proofLen: proofResult.proof.length,
+        publicInputCount: 5,
+        minDepositAgeSlots: 0n,
+        initialRoot: fieldToBytes(emptyRoot),
+    });
     const initAccounts = [
         { name: "fee_payer", address: relayer.address },
         { name: "state_pda", address: statePda },
         { name: "vault_pda", address: vaultPda },
+        { name: "fee_vault_pda", address: initIx.accounts[3].address },
         { name: "system_program", address: SYSTEM_PROGRAM_ADDRESS },
     ];
 
-    const depositData = new Uint8Array(1 + 8 + 32 + 32);
-    depositData[0] = INSTRUCTION.DEPOSIT;
-    depositData.set(u64ToLeBytes(amount), 1);
-    depositData.set(fieldToBytes(commitment), 1 + 8);
-    depositData.set(fieldToBytes(root), 1 + 8 + 32);
-
-    const depositIx = {
-        programAddress: SHIELDED_POOL_PROGRAM_ID,
-        accounts: [
-            { address: sender.address, role: 3 },
-            { address: statePda, role: 1 },
-            { address: vaultPda, role: 1 },
-            { address: SYSTEM_PROGRAM_ADDRESS, role: 0 },
-        ],
-        data: depositData,
-    };
+    const depositIx = await deposit(
+        SHIELDED_POOL_PROGRAM_ID,
+        sender.address,
+        amount,
+        fieldToBytes(commitment),
+        fieldToBytes(emptyRoot),
+        fieldToBytes(root)
+    );
     const depositAccounts = [
         { name: "sender", address: sender.address },
         { name: "state_pda", address: statePda },
         { name: "vault_pda", address: vaultPda },
+        { name: "fee_vault_pda", address: depositIx.accounts[3].address },
         { name: "system_program", address: SYSTEM_PROGRAM_ADDRESS },
+        { name: "commitment_pda", address: depositIx.accounts[5].address },
     ];
 
-    // Withdraw instruction data: [WITHDRAW, proof, witness]
-    const data = new Uint8Array(1 + proofResult.proof.length + proofResult.publicWitness.length);
-    data[0] = INSTRUCTION.WITHDRAW;
-    data.set(proofResult.proof, 1);
-    data.set(proofResult.publicWitness, 1 + proofResult.proof.length);
-
-    const withdrawIx = {
-        programAddress: SHIELDED_POOL_PROGRAM_ID,
-        accounts: [
-            { address: relayer.address, role: 3 },  // fee payer (relayer)
-            { address: recipientPubkey, role: 1 },  // recipient
-            { address: vaultPda, role: 1 },         // vault
-            { address: statePda, role: 1 },         // state
-            { address: nullifierPda, role: 1 },     // nullifier
-            { address: ZK_VERIFIER_PROGRAM_ID, role: 0 },
-            { address: SYSTEM_PROGRAM_ADDRESS, role: 0 },
-        ],
-        data,
-    };
-    const withdrawAccounts = [
-        { name: "fee_payer", address: relayer.address },
-        { name: "recipient", address: recipientPubkey },
-        { name: "vault_pda", address: vaultPda },
-        { name: "state_pda", address: statePda },
-        { name: "nullifier_pda", address: nullifierPda },
-        { name: "verifier_program", address: ZK_VERIFIER_PROGRAM_ID },
-        { name: "system_program", address: SYSTEM_PROGRAM_ADDRESS },
+    const withdrawIx = await withdraw(
+        SHIELDED_POOL_PROGRAM_ID,
+        ZK_VERIFIER_PROGRAM_ID,
+        relayer.address,
+        recipientPubkey,
+        proofResult.proof,
+        proofResult.publicWitness
+    );
+    const withdrawAccountNames = [
+        "fee_payer",
+        "recipient",
+        "vault_pda",
+        "state_pda",
+        "nullifier_pda",
+        "verifier_program",
+        "audit_record_pda",
+        "nullifier_set_pda",
+        "system_program",
+        "denylist_entry_pda",
+        "recipient_limit_pda",
+        "fee_vault_pda",
+        "relayer_entry_pda",
     ];
+    const withdrawAccounts = withdrawIx.accounts.map((account, i) => ({
+        name: withdrawAccountNames[i],
+        address: account.address,
+    }));
 
     logBusinessAccounts("\nInitialize Accounts:", initAccounts);
     console.log("Sending Initialize Transaction...");
@@ -385,10 +354,8 @@ async function main() {
 
     const corruptedProof = Uint8Array.from(proofResult.proof);
     corruptedProof[0] ^= 0xff;
-    const corruptedData = new Uint8Array(1 + corruptedProof.length + proofResult.publicWitness.length);
-    corruptedData[0] = INSTRUCTION.WITHDRAW;
+    const corruptedData = Uint8Array.from(withdrawIx.data);
     corruptedData.set(corruptedProof, 1);
-    corruptedData.set(proofResult.publicWitness, 1 + corruptedProof.length);
     const corruptedWithdrawIx = { ...withdrawIx, data: corruptedData };
 
     const wrongRecipientSigner = await generateKeyPairSigner();
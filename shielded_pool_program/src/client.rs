@@ -0,0 +1,1164 @@
+//! Host-side builders for the program's instructions.
+//!
+//! These mirror the account order and data layout each handler parses, so
+//! clients don't hand-assemble them.
+
+use pinocchio::Address;
+use solana_program_error::ProgramError;
+
+use crate::{
+    instructions::{
+        configure_rebate::TOKEN_PROGRAM_ID,
+        initialize::InitializeParams,
+        instruction,
+        migrate_state::BPF_LOADER_UPGRADEABLE_ID,
+        submit_audit::AUDIT_VERIFIER_PROGRAM_ID,
+        update_config::{
+            CONFIG_DEPOSIT_FEE_BPS, CONFIG_MIN_DEPOSIT_AGE_SLOTS, CONFIG_MIN_WITHDRAW,
+            CONFIG_WITHDRAW_FEE_BPS,
+        },
+        withdraw::ZK_VERIFIER_PROGRAM_ID,
+    },
+    state::{NullifierSeeds, VaultHeader, ViewingData},
+    witness::parse_public_inputs,
+};
+
+/// An account passed to an instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountMeta {
+    pub address: Address,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl AccountMeta {
+    pub fn writable(address: Address, is_signer: bool) -> Self {
+        Self {
+            address,
+            is_signer,
+            is_writable: true,
+        }
+    }
+
+    pub fn readonly(address: Address, is_signer: bool) -> Self {
+        Self {
+            address,
+            is_signer,
+            is_writable: false,
+        }
+    }
+}
+
+/// A ready-to-send instruction for this program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub program_id: Address,
+    pub accounts: Vec<AccountMeta>,
+    pub data: Vec<u8>,
+}
+
+/// Pool configuration written by `Initialize`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoolConfig {
+    pub deposit_fee_bps: u16,
+    pub fee_collector: Address,
+    pub tree_depth: u8,
+    pub min_withdraw: u64,
+    pub proof_len: u32,
+    pub public_input_count: u8,
+    pub min_deposit_age_slots: u64,
+    /// Root the pool starts from. `None` starts from the empty-tree root for
+    /// `tree_depth`, computed on chain.
+    pub initial_root: Option<[u8; 32]>,
+}
+
+pub fn state_pda() -> (Address, u8) {
+    Address::find_program_address(&[b"pool_state"], &crate::ID)
+}
+
+pub fn vault_pda() -> (Address, u8) {
+    Address::find_program_address(&[b"vault"], &crate::ID)
+}
+
+pub fn nullifier_pda(nullifier: &[u8; 32]) -> (Address, u8) {
+    nullifier_pda_for_asset(&VaultHeader::NATIVE_ASSET_ID, nullifier)
+}
+
+pub fn nullifier_set_pda(nullifier: &[u8; 32]) -> (Address, u8) {
+    nullifier_set_pda_for_asset(&VaultHeader::NATIVE_ASSET_ID, nullifier)
+}
+
+/// Nullifier PDA of `nullifier` in asset `asset_id`; the native asset keeps
+/// the legacy asset-less seeds.
+pub fn nullifier_pda_for_asset(asset_id: &[u8; 32], nullifier: &[u8; 32]) -> (Address, u8) {
+    NullifierSeeds::nullifier(asset_id, nullifier).find_address(&crate::ID)
+}
+
+pub fn nullifier_set_pda_for_asset(asset_id: &[u8; 32], nullifier: &[u8; 32]) -> (Address, u8) {
+    NullifierSeeds::shard(asset_id, nullifier).find_address(&crate::ID)
+}
+
+pub fn audit_record_pda(wa_commitment: &[u8; 32]) -> (Address, u8) {
+    Address::find_program_address(&[b"audit", wa_commitment], &crate::ID)
+}
+
+pub fn commitment_pda(commitment: &[u8; 32]) -> (Address, u8) {
+    Address::find_program_address(&[b"commitment", commitment], &crate::ID)
+}
+
+pub fn fee_vault_pda() -> (Address, u8) {
+    Address::find_program_address(&[b"fee_vault"], &crate::ID)
+}
+
+pub fn denylist_entry_pda(recipient: &Address) -> (Address, u8) {
+    Address::find_program_address(&[b"denylist", recipient.as_ref()], &crate::ID)
+}
+
+pub fn relayer_entry_pda(relayer: &Address) -> (Address, u8) {
+    Address::find_program_address(&[b"relayer", relayer.as_ref()], &crate::ID)
+}
+
+pub fn recipient_limit_pda(recipient: &Address) -> (Address, u8) {
+    Address::find_program_address(&[b"recipient_limit", recipient.as_ref()], &crate::ID)
+}
+
+pub fn escrow_pda(recipient: &Address) -> (Address, u8) {
+    Address::find_program_address(&[b"escrow", recipient.as_ref()], &crate::ID)
+}
+
+pub fn root_snapshots_pda() -> (Address, u8) {
+    Address::find_program_address(&[b"root_snapshots"], &crate::ID)
+}
+
+pub fn rebate_authority_pda() -> (Address, u8) {
+    Address::find_program_address(&[b"rebate_authority"], &crate::ID)
+}
+
+pub fn viewing_data_pda(
+    wa_commitment: &[u8; 32],
+    viewing_key: &[u8; 32],
+    registrant: &Address,
+) -> (Address, u8) {
+    Address::find_program_address(
+        &[b"viewing", wa_commitment, viewing_key, registrant.as_ref()],
+        &crate::ID,
+    )
+}
+
+/// Fields changed by `UpdateConfig`; `None` leaves a field as it is.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigUpdate {
+    pub deposit_fee_bps: Option<u16>,
+    pub withdraw_fee_bps: Option<u16>,
+    pub min_withdraw: Option<u64>,
+    pub min_deposit_age_slots: Option<u64>,
+}
+
+pub fn initialize(payer: &Address, config: &PoolConfig) -> Instruction {
+    let mut data = Vec::with_capacity(89);
+    data.push(instruction::INITIALIZE);
+    data.extend_from_slice(&config.deposit_fee_bps.to_le_bytes());
+    data.extend_from_slice(config.fee_collector.as_ref());
+    data.push(config.tree_depth);
+    data.extend_from_slice(&config.min_withdraw.to_le_bytes());
+    data.extend_from_slice(&config.proof_len.to_le_bytes());
+    data.push(config.public_input_count);
+    data.extend_from_slice(&config.min_deposit_age_slots.to_le_bytes());
+    if let Some(initial_root) = &config.initial_root {
+        data.extend_from_slice(initial_root);
+    }
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(payer.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+            AccountMeta::writable(vault_pda().0, false),
+            AccountMeta::writable(fee_vault_pda().0, false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+        ],
+        data,
+    }
+}
+
+/// `initialize` with the rest of the config set in the same write. The rebate
+/// mint is passed when `params` turn the rebate on.
+pub fn initialize_with_params(
+    payer: &Address,
+    config: &PoolConfig,
+    params: &InitializeParams,
+) -> Instruction {
+    let mut ix = initialize(
+        payer,
+        &PoolConfig {
+            initial_root: Some(config.initial_root.unwrap_or([0u8; 32])),
+            ..config.clone()
+        },
+    );
+    ix.data
+        .extend_from_slice(&params.withdraw_fee_bps.to_le_bytes());
+    ix.data
+        .extend_from_slice(&params.min_verifier_compute_units.to_le_bytes());
+    ix.data
+        .extend_from_slice(&params.audit_required_from_slot.to_le_bytes());
+    ix.data
+        .extend_from_slice(&params.recipient_max_per_window.to_le_bytes());
+    ix.data
+        .extend_from_slice(&params.recipient_window_slots.to_le_bytes());
+    ix.data.extend_from_slice(&params.rebate_mint);
+    ix.data
+        .extend_from_slice(&params.rebate_amount.to_le_bytes());
+    ix.data.extend_from_slice(&[
+        params.owner_gated,
+        params.unique_commitments,
+        params.require_recipient_signature,
+        params.require_wallet_recipient,
+        params.relayer_registry,
+        params.authority_threshold,
+        params.co_authority_count,
+    ]);
+    for co_authority in &params.co_authorities {
+        ix.data.extend_from_slice(co_authority);
+    }
+    ix.data.extend_from_slice(&params.alt_verifier);
+    ix.data
+        .extend_from_slice(&params.alt_proof_len.to_le_bytes());
+    ix.data.extend_from_slice(&params.public_input_order);
+
+    if params.rebate_amount > 0 {
+        ix.accounts.push(AccountMeta::readonly(
+            Address::new_from_array(params.rebate_mint),
+            false,
+        ));
+    }
+    ix
+}
+
+pub fn deposit(
+    payer: &Address,
+    amount: u64,
+    commitment: &[u8; 32],
+    prev_root: &[u8; 32],
+    new_root: &[u8; 32],
+) -> Instruction {
+    let mut data = Vec::with_capacity(105);
+    data.push(instruction::DEPOSIT);
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(commitment);
+    data.extend_from_slice(prev_root);
+    data.extend_from_slice(new_root);
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(payer.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+            AccountMeta::writable(vault_pda().0, false),
+            AccountMeta::writable(fee_vault_pda().0, false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+            AccountMeta::writable(commitment_pda(commitment).0, false),
+        ],
+        data,
+    }
+}
+
+/// Builds a `Deposit` that also logs `encrypted_note` (the note's secrets
+/// encrypted to its owner) in the deposit event, for wallet recovery.
+pub fn deposit_with_note(
+    payer: &Address,
+    amount: u64,
+    commitment: &[u8; 32],
+    prev_root: &[u8; 32],
+    new_root: &[u8; 32],
+    encrypted_note: &[u8],
+) -> Instruction {
+    let mut ix = deposit(payer, amount, commitment, prev_root, new_root);
+    ix.data[0] = instruction::DEPOSIT_WITH_NOTE;
+    ix.data.extend_from_slice(encrypted_note);
+    ix
+}
+
+/// Builds a `DepositBatch` from `(gross amount, commitment)` pairs, inserted
+/// in order on top of `prev_root` to reach `new_root`.
+pub fn deposit_batch(
+    payer: &Address,
+    deposits: &[(u64, [u8; 32])],
+    prev_root: &[u8; 32],
+    new_root: &[u8; 32],
+) -> Instruction {
+    let mut data = Vec::with_capacity(66 + deposits.len() * 40);
+    data.push(instruction::DEPOSIT_BATCH);
+    data.push(deposits.len() as u8);
+    data.extend_from_slice(prev_root);
+    data.extend_from_slice(new_root);
+    for (amount, commitment) in deposits {
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(commitment);
+    }
+
+    let mut accounts = vec![
+        AccountMeta::writable(payer.clone(), true),
+        AccountMeta::writable(state_pda().0, false),
+        AccountMeta::writable(vault_pda().0, false),
+        AccountMeta::writable(fee_vault_pda().0, false),
+        AccountMeta::readonly(pinocchio_system::ID, false),
+    ];
+    accounts.extend(
+        deposits
+            .iter()
+            .map(|(_, commitment)| AccountMeta::writable(commitment_pda(commitment).0, false)),
+    );
+
+    Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    }
+}
+
+/// Builds a withdraw from the raw proof and public witness. The nullifier and
+/// wa_commitment used for PDA derivation are read from the witness itself, at
+/// their positions in the default public input order.
+pub fn withdraw(
+    payer: &Address,
+    recipient: &Address,
+    proof: &[u8],
+    witness: &[u8],
+) -> Result<Instruction, ProgramError> {
+    let mut payload = Vec::with_capacity(proof.len() + witness.len());
+    payload.extend_from_slice(proof);
+    payload.extend_from_slice(witness);
+
+    let inputs = parse_public_inputs(
+        &payload,
+        proof.len(),
+        crate::instructions::withdraw::PUBLIC_INPUTS,
+    )?;
+    let nullifier = inputs.get(1)?.0;
+    let wa_commitment = inputs.get(4)?.0;
+
+    let mut data = Vec::with_capacity(1 + payload.len());
+    data.push(instruction::WITHDRAW);
+    data.extend_from_slice(&payload);
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(payer.clone(), true),
+            AccountMeta::writable(recipient.clone(), false),
+            AccountMeta::writable(vault_pda().0, false),
+            AccountMeta::writable(state_pda().0, false),
+            AccountMeta::writable(nullifier_pda(&nullifier).0, false),
+            AccountMeta::readonly(ZK_VERIFIER_PROGRAM_ID, false),
+            AccountMeta::readonly(audit_record_pda(&wa_commitment).0, false),
+            AccountMeta::readonly(nullifier_set_pda(&nullifier).0, false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+            AccountMeta::readonly(denylist_entry_pda(recipient).0, false),
+            AccountMeta::writable(recipient_limit_pda(recipient).0, false),
+            AccountMeta::writable(fee_vault_pda().0, false),
+            AccountMeta::readonly(relayer_entry_pda(payer).0, false),
+        ],
+        data,
+    })
+}
+
+/// Builds a partial withdraw whose circuit also outputs a change note. The
+/// caller inserts that note into its local tree at `prev_root`, giving
+/// `new_root`.
+pub fn withdraw_with_change(
+    payer: &Address,
+    recipient: &Address,
+    proof: &[u8],
+    witness: &[u8],
+    prev_root: &[u8; 32],
+    new_root: &[u8; 32],
+) -> Result<Instruction, ProgramError> {
+    let mut ix = withdraw(payer, recipient, proof, witness)?;
+    ix.data.extend_from_slice(prev_root);
+    ix.data.extend_from_slice(new_root);
+    Ok(ix)
+}
+
+/// Turns a `Withdraw` built above into a `WithdrawWithMemo` that logs `memo`.
+/// The memo is not bound to the proof.
+pub fn with_memo(mut ix: Instruction, memo: &[u8]) -> Instruction {
+    let mut data = Vec::with_capacity(ix.data.len() + 2 + memo.len());
+    data.push(instruction::WITHDRAW_WITH_MEMO);
+    data.push(memo.len() as u8);
+    data.extend_from_slice(memo);
+    data.extend_from_slice(&ix.data[1..]);
+    ix.data = data;
+    ix
+}
+
+/// Appends the vault and audit record bumps to a withdraw-family instruction
+/// built above, so the program checks those PDAs without a bump search. Call
+/// it after any change note roots have been added.
+pub fn with_pda_bumps(mut ix: Instruction, wa_commitment: &[u8; 32]) -> Instruction {
+    ix.data.push(vault_pda().1);
+    ix.data.push(audit_record_pda(wa_commitment).1);
+    ix
+}
+
+/// Routes a withdraw-family instruction built above to `verifier`, e.g. the
+/// pool's alternate verifier, whose proof length the proof must match.
+pub fn with_verifier(mut ix: Instruction, verifier: &Address) -> Instruction {
+    ix.accounts[5] = AccountMeta::readonly(verifier.clone(), false);
+    ix
+}
+
+/// Builds a withdraw that then CPIs into `target_program` with `call_accounts`
+/// and `call_data`. The recipient should sign if the target moves its funds.
+pub fn withdraw_and_call(
+    payer: &Address,
+    recipient: &Address,
+    proof: &[u8],
+    witness: &[u8],
+    target_program: &Address,
+    call_accounts: &[AccountMeta],
+    call_data: &[u8],
+) -> Result<Instruction, ProgramError> {
+    let call_data_len =
+        u16::try_from(call_data.len()).map_err(|_| ProgramError::InvalidInstructionData)?;
+    let mut ix = withdraw(payer, recipient, proof, witness)?;
+
+    let mut data = Vec::with_capacity(3 + call_data.len() + ix.data.len() - 1);
+    data.push(instruction::WITHDRAW_AND_CALL);
+    data.extend_from_slice(&call_data_len.to_le_bytes());
+    data.extend_from_slice(call_data);
+    data.extend_from_slice(&ix.data[1..]);
+    ix.data = data;
+
+    ix.accounts
+        .push(AccountMeta::readonly(target_program.clone(), false));
+    ix.accounts.extend_from_slice(call_accounts);
+    Ok(ix)
+}
+
+/// Builds a withdraw that parks the payout in the recipient's escrow.
+pub fn withdraw_to_escrow(
+    payer: &Address,
+    recipient: &Address,
+    proof: &[u8],
+    witness: &[u8],
+) -> Result<Instruction, ProgramError> {
+    let mut ix = withdraw(payer, recipient, proof, witness)?;
+    ix.data[0] = instruction::WITHDRAW_TO_ESCROW;
+    ix.accounts
+        .push(AccountMeta::writable(escrow_pda(recipient).0, false));
+    Ok(ix)
+}
+
+/// Builds a dry run of the same withdraw; see `simulate_withdraw`.
+pub fn simulate_withdraw(
+    payer: &Address,
+    recipient: &Address,
+    proof: &[u8],
+    witness: &[u8],
+) -> Result<Instruction, ProgramError> {
+    let mut ix = withdraw(payer, recipient, proof, witness)?;
+    ix.data[0] = instruction::SIMULATE_WITHDRAW;
+    Ok(ix)
+}
+
+/// Builds a withdraw that also pays `split_recipients`, in the order the
+/// witness binds their (recipient, amount) pairs.
+pub fn withdraw_split(
+    payer: &Address,
+    recipient: &Address,
+    split_recipients: &[Address],
+    proof: &[u8],
+    witness: &[u8],
+) -> Result<Instruction, ProgramError> {
+    let mut ix = withdraw(payer, recipient, proof, witness)?;
+    ix.data[0] = instruction::WITHDRAW_SPLIT;
+    for split_recipient in split_recipients {
+        ix.accounts
+            .push(AccountMeta::writable(split_recipient.clone(), false));
+        ix.accounts.push(AccountMeta::readonly(
+            denylist_entry_pda(split_recipient).0,
+            false,
+        ));
+        ix.accounts.push(AccountMeta::writable(
+            recipient_limit_pda(split_recipient).0,
+            false,
+        ));
+    }
+    Ok(ix)
+}
+
+pub fn claim_escrow(recipient: &Address) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(recipient.clone(), true),
+            AccountMeta::writable(escrow_pda(recipient).0, false),
+        ],
+        data: vec![instruction::CLAIM_ESCROW],
+    }
+}
+
+/// Builds a read of the pool's configuration and counters; see `get_pool_info`.
+pub fn get_pool_info() -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::readonly(state_pda().0, false)],
+        data: vec![instruction::GET_POOL_INFO],
+    }
+}
+
+/// Builds a read of one root history chunk; see `get_root_history`.
+pub fn get_root_history(chunk: u8) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::readonly(state_pda().0, false)],
+        data: vec![instruction::GET_ROOT_HISTORY, chunk],
+    }
+}
+
+/// Builds a sweep of accrued fees to the treasury (`fee_collector`).
+pub fn withdraw_fees(authority: &Address, treasury: &Address) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::readonly(state_pda().0, false),
+            AccountMeta::writable(fee_vault_pda().0, false),
+            AccountMeta::writable(treasury.clone(), false),
+        ],
+        data: vec![instruction::WITHDRAW_FEES],
+    }
+}
+
+/// Builds a reclaim of a spent nullifier's rent: the nullifier moves into its
+/// nullifier-set shard and the freed rent goes to `treasury`.
+pub fn reclaim_nullifier_rent(
+    authority: &Address,
+    nullifier: &[u8; 32],
+    treasury: &Address,
+) -> Instruction {
+    let mut data = Vec::with_capacity(33);
+    data.push(instruction::RECLAIM_NULLIFIER_RENT);
+    data.extend_from_slice(nullifier);
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(authority.clone(), true),
+            AccountMeta::readonly(state_pda().0, false),
+            AccountMeta::writable(nullifier_pda(nullifier).0, false),
+            AccountMeta::writable(nullifier_set_pda(nullifier).0, false),
+            AccountMeta::writable(treasury.clone(), false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+        ],
+        data,
+    }
+}
+
+/// Builds a close of the audit record the withdrawal `proof` and `witness`
+/// spent against, refunding its rent to `treasury`.
+pub fn close_audit_record(
+    authority: &Address,
+    treasury: &Address,
+    proof: &[u8],
+    witness: &[u8],
+) -> Result<Instruction, ProgramError> {
+    let mut data = Vec::with_capacity(1 + proof.len() + witness.len());
+    data.push(instruction::CLOSE_AUDIT_RECORD);
+    data.extend_from_slice(proof);
+    data.extend_from_slice(witness);
+
+    let inputs = parse_public_inputs(
+        &data[1..],
+        proof.len(),
+        crate::instructions::withdraw::PUBLIC_INPUTS,
+    )?;
+    let nullifier = inputs.get(1)?.0;
+    let wa_commitment = inputs.get(4)?.0;
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::readonly(state_pda().0, false),
+            AccountMeta::writable(audit_record_pda(&wa_commitment).0, false),
+            AccountMeta::readonly(nullifier_pda(&nullifier).0, false),
+            AccountMeta::readonly(nullifier_set_pda(&nullifier).0, false),
+            AccountMeta::writable(treasury.clone(), false),
+            AccountMeta::readonly(ZK_VERIFIER_PROGRAM_ID, false),
+        ],
+        data,
+    })
+}
+
+/// Builds a denylist update: `deny` adds `recipient`, otherwise removes it.
+pub fn update_denylist(authority: &Address, recipient: &Address, deny: bool) -> Instruction {
+    let mut data = Vec::with_capacity(34);
+    data.push(instruction::UPDATE_DENYLIST);
+    data.extend_from_slice(recipient.as_ref());
+    data.push(deny as u8);
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+            AccountMeta::writable(denylist_entry_pda(recipient).0, false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+        ],
+        data,
+    }
+}
+
+pub fn grow_root_history(authority: &Address, new_size: u32) -> Instruction {
+    let mut data = Vec::with_capacity(5);
+    data.push(instruction::GROW_ROOT_HISTORY);
+    data.extend_from_slice(&new_size.to_le_bytes());
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+        ],
+        data,
+    }
+}
+
+/// Builds a shrink of the root history to its newest `new_size` roots; the
+/// freed rent goes back to `authority`.
+pub fn compact_root_history(authority: &Address, new_size: u32) -> Instruction {
+    let mut data = Vec::with_capacity(5);
+    data.push(instruction::COMPACT_ROOT_HISTORY);
+    data.extend_from_slice(&new_size.to_le_bytes());
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data,
+    }
+}
+
+pub fn configure_rebate(
+    authority: &Address,
+    enabled: bool,
+    rebate_mint: &Address,
+    rebate_amount: u64,
+) -> Instruction {
+    let mut data = Vec::with_capacity(10);
+    data.push(instruction::CONFIGURE_REBATE);
+    data.push(enabled as u8);
+    data.extend_from_slice(&rebate_amount.to_le_bytes());
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+            AccountMeta::readonly(rebate_mint.clone(), false),
+        ],
+        data,
+    }
+}
+
+/// Accounts to append to a `withdraw` instruction while the pool pays a
+/// rebate. `rebate_vault` is a token account of `rebate_mint` owned by
+/// `rebate_authority_pda()`.
+pub fn rebate_accounts(
+    rebate_vault: &Address,
+    recipient_token_account: &Address,
+    rebate_mint: &Address,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::writable(rebate_vault.clone(), false),
+        AccountMeta::readonly(rebate_authority_pda().0, false),
+        AccountMeta::writable(recipient_token_account.clone(), false),
+        AccountMeta::readonly(rebate_mint.clone(), false),
+        AccountMeta::readonly(TOKEN_PROGRAM_ID, false),
+    ]
+}
+
+/// Builds a submit_audit from the raw audit proof and public witness.
+pub fn submit_audit(
+    payer: &Address,
+    proof: &[u8],
+    witness: &[u8],
+) -> Result<Instruction, ProgramError> {
+    let mut payload = Vec::with_capacity(proof.len() + witness.len());
+    payload.extend_from_slice(proof);
+    payload.extend_from_slice(witness);
+
+    let inputs = parse_public_inputs(&payload, proof.len(), 1)?;
+    let wa_commitment = inputs.get(0)?.0;
+
+    let mut data = Vec::with_capacity(1 + payload.len());
+    data.push(instruction::SUBMIT_AUDIT);
+    data.extend_from_slice(&payload);
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(payer.clone(), true),
+            AccountMeta::writable(audit_record_pda(&wa_commitment).0, false),
+            AccountMeta::readonly(AUDIT_VERIFIER_PROGRAM_ID, false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+        ],
+        data,
+    })
+}
+
+/// Builds a batched audit submission from one `(proof, witness)` pair per
+/// audit record; each is verified by its own CPI.
+pub fn submit_audit_batch(
+    payer: &Address,
+    audits: &[(&[u8], &[u8])],
+) -> Result<Instruction, ProgramError> {
+    let mut accounts = vec![
+        AccountMeta::writable(payer.clone(), true),
+        AccountMeta::readonly(AUDIT_VERIFIER_PROGRAM_ID, false),
+        AccountMeta::readonly(pinocchio_system::ID, false),
+    ];
+    let mut data = vec![instruction::SUBMIT_AUDIT_BATCH];
+    for (proof, witness) in audits {
+        let payload_start = data.len();
+        data.extend_from_slice(proof);
+        data.extend_from_slice(witness);
+
+        let inputs = parse_public_inputs(&data[payload_start..], proof.len(), 2)?;
+        let wa_commitment = inputs.get(0)?.0;
+        accounts.push(AccountMeta::writable(
+            audit_record_pda(&wa_commitment).0,
+            false,
+        ));
+    }
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    })
+}
+
+pub fn verify_and_store_root(authority: &Address, expected_root: &[u8; 32]) -> Instruction {
+    let mut data = Vec::with_capacity(33);
+    data.push(instruction::VERIFY_AND_STORE_ROOT);
+    data.extend_from_slice(expected_root);
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(authority.clone(), true),
+            AccountMeta::readonly(state_pda().0, false),
+            AccountMeta::writable(root_snapshots_pda().0, false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+        ],
+        data,
+    }
+}
+
+pub fn get_root_snapshots(chunk: u8) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::readonly(root_snapshots_pda().0, false)],
+        data: vec![instruction::GET_ROOT_SNAPSHOTS, chunk],
+    }
+}
+
+/// Builds a migration of the pool state to the current layout. Pools with
+/// co-authorities need `with_co_signers` as for any privileged instruction.
+pub fn migrate_state(authority: &Address) -> Instruction {
+    let program_data =
+        Address::find_program_address(&[crate::ID.as_ref()], &BPF_LOADER_UPGRADEABLE_ID).0;
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+            AccountMeta::readonly(program_data, false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+            AccountMeta::writable(vault_pda().0, false),
+        ],
+        data: vec![instruction::MIGRATE_STATE],
+    }
+}
+
+/// Builds the start of an emergency wind-down: deposits stop and the vault
+/// drain unlocks after the timelock.
+pub fn initiate_emergency(authority: &Address) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data: vec![instruction::INITIATE_EMERGENCY],
+    }
+}
+
+/// Builds a drain of the vault to the treasury (`fee_collector`) once the
+/// emergency timelock has elapsed.
+pub fn emergency_withdraw(authority: &Address, treasury: &Address) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+            AccountMeta::writable(vault_pda().0, false),
+            AccountMeta::writable(treasury.clone(), false),
+        ],
+        data: vec![instruction::EMERGENCY_WITHDRAW],
+    }
+}
+
+/// Builds a registration of `payload`, encrypted to the auditor's
+/// `viewing_key`, for `wa_commitment`.
+pub fn register_viewing_data(
+    payer: &Address,
+    wa_commitment: &[u8; 32],
+    viewing_key: &[u8; 32],
+    payload: &[u8],
+) -> Result<Instruction, ProgramError> {
+    if payload.is_empty() || payload.len() > ViewingData::MAX_DATA_LEN {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut data = Vec::with_capacity(65 + payload.len());
+    data.push(instruction::REGISTER_VIEWING_DATA);
+    data.extend_from_slice(wa_commitment);
+    data.extend_from_slice(viewing_key);
+    data.extend_from_slice(payload);
+
+    Ok(Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(payer.clone(), true),
+            AccountMeta::writable(viewing_data_pda(wa_commitment, viewing_key, payer).0, false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+        ],
+        data,
+    })
+}
+
+/// Builds a per-recipient withdrawal cap of `max_per_window` lamports per
+/// `window_slots` slots; a `max_per_window` of 0 turns it off.
+pub fn configure_recipient_limit(
+    authority: &Address,
+    max_per_window: u64,
+    window_slots: u64,
+) -> Instruction {
+    let mut data = Vec::with_capacity(17);
+    data.push(instruction::CONFIGURE_RECIPIENT_LIMIT);
+    data.extend_from_slice(&max_per_window.to_le_bytes());
+    data.extend_from_slice(&window_slots.to_le_bytes());
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data,
+    }
+}
+
+/// Builds an owner gate toggle; while on, withdrawals need the note owner's
+/// signature as payer or recipient.
+pub fn configure_owner_gate(authority: &Address, enabled: bool) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data: vec![instruction::CONFIGURE_OWNER_GATE, enabled as u8],
+    }
+}
+
+/// Builds a withdraw fee update, in basis points of the gross amount.
+pub fn configure_withdraw_fee(authority: &Address, withdraw_fee_bps: u16) -> Instruction {
+    let mut data = Vec::with_capacity(3);
+    data.push(instruction::CONFIGURE_WITHDRAW_FEE);
+    data.extend_from_slice(&withdraw_fee_bps.to_le_bytes());
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data,
+    }
+}
+
+/// Builds a reserves attestation read; see `proof_of_reserves`.
+pub fn proof_of_reserves() -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(state_pda().0, false),
+            AccountMeta::readonly(vault_pda().0, false),
+        ],
+        data: vec![instruction::PROOF_OF_RESERVES],
+    }
+}
+
+/// Builds a read of the audit record for `wa_commitment`; see `get_audit_record`.
+pub fn get_audit_record(wa_commitment: &[u8; 32]) -> Instruction {
+    let mut data = Vec::with_capacity(33);
+    data.push(instruction::GET_AUDIT_RECORD);
+    data.extend_from_slice(wa_commitment);
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![AccountMeta::readonly(
+            audit_record_pda(wa_commitment).0,
+            false,
+        )],
+        data,
+    }
+}
+
+/// Builds an update of the slot from which withdrawals need an audit record.
+pub fn configure_audit_cutoff(authority: &Address, audit_required_from_slot: u64) -> Instruction {
+    let mut data = Vec::with_capacity(9);
+    data.push(instruction::CONFIGURE_AUDIT_CUTOFF);
+    data.extend_from_slice(&audit_required_from_slot.to_le_bytes());
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data,
+    }
+}
+
+/// Builds an update of the compute units required before the verifier CPI.
+pub fn configure_compute_guard(
+    authority: &Address,
+    min_verifier_compute_units: u64,
+) -> Instruction {
+    let mut data = Vec::with_capacity(9);
+    data.push(instruction::CONFIGURE_COMPUTE_GUARD);
+    data.extend_from_slice(&min_verifier_compute_units.to_le_bytes());
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data,
+    }
+}
+
+/// Builds an update of the pool's co-authorities and signature threshold.
+/// Under an existing multi-authority setup, add the co-signers with
+/// `with_co_signers`.
+pub fn configure_authorities(
+    authority: &Address,
+    threshold: u8,
+    co_authorities: &[Address],
+) -> Instruction {
+    let mut data = Vec::with_capacity(1 + 32 * co_authorities.len());
+    data.push(instruction::CONFIGURE_AUTHORITIES);
+    data.push(threshold);
+    for co_authority in co_authorities {
+        data.extend_from_slice(co_authority.as_ref());
+    }
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data,
+    }
+}
+
+/// Appends co-authority signers to a privileged instruction so it meets the
+/// pool's authority threshold.
+pub fn with_co_signers(mut ix: Instruction, co_signers: &[Address]) -> Instruction {
+    ix.accounts.extend(
+        co_signers
+            .iter()
+            .map(|co_signer| AccountMeta::readonly(co_signer.clone(), true)),
+    );
+    ix
+}
+
+/// Builds a switch of the unique-commitment guard.
+pub fn configure_commitment_guard(authority: &Address, enabled: bool) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data: vec![instruction::CONFIGURE_COMMITMENT_GUARD, enabled as u8],
+    }
+}
+
+/// Builds a reservation of the nullifier PDA, to send before a withdraw that
+/// is too close to the transaction size or compute limits.
+pub fn reserve_nullifier(payer: &Address, nullifier: &[u8; 32]) -> Instruction {
+    let mut data = Vec::with_capacity(33);
+    data.push(instruction::RESERVE_NULLIFIER);
+    data.extend_from_slice(nullifier);
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(payer.clone(), true),
+            AccountMeta::writable(nullifier_pda(nullifier).0, false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+        ],
+        data,
+    }
+}
+
+/// Builds a switch of the recipient signature requirement. While it is on,
+/// mark the recipient of withdraw instructions as a signer.
+pub fn configure_recipient_signature(authority: &Address, required: bool) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data: vec![instruction::CONFIGURE_RECIPIENT_SIGNATURE, required as u8],
+    }
+}
+
+/// Builds a switch of the wallet recipient requirement. While it is on,
+/// withdrawals to program-owned recipients are rejected.
+pub fn configure_wallet_recipient(authority: &Address, required: bool) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data: vec![instruction::CONFIGURE_WALLET_RECIPIENT, required as u8],
+    }
+}
+
+/// Builds a solvency check; see `check_solvency`.
+pub fn check_solvency() -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(state_pda().0, false),
+            AccountMeta::readonly(vault_pda().0, false),
+        ],
+        data: vec![instruction::CHECK_SOLVENCY],
+    }
+}
+
+/// Builds a relayer registry update: `approve` registers `relayer`, otherwise
+/// removes it.
+pub fn update_relayer(authority: &Address, relayer: &Address, approve: bool) -> Instruction {
+    let mut data = Vec::with_capacity(34);
+    data.push(instruction::UPDATE_RELAYER);
+    data.extend_from_slice(relayer.as_ref());
+    data.push(approve as u8);
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::writable(authority.clone(), true),
+            AccountMeta::readonly(state_pda().0, false),
+            AccountMeta::writable(relayer_entry_pda(relayer).0, false),
+            AccountMeta::readonly(pinocchio_system::ID, false),
+        ],
+        data,
+    }
+}
+
+/// Builds a switch of the relayer registry. While it is on, fee-bearing
+/// withdrawals must be submitted by a registered relayer; their fee still
+/// goes to the fee vault.
+pub fn configure_relayer_registry(authority: &Address, enabled: bool) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data: vec![instruction::CONFIGURE_RELAYER_REGISTRY, enabled as u8],
+    }
+}
+
+/// Builds an update of the alternate withdraw verifier; a `proof_len` of 0
+/// clears it.
+pub fn configure_alt_verifier(
+    authority: &Address,
+    verifier: &Address,
+    proof_len: u32,
+) -> Instruction {
+    let mut data = Vec::with_capacity(37);
+    data.push(instruction::CONFIGURE_ALT_VERIFIER);
+    data.extend_from_slice(verifier.as_ref());
+    data.extend_from_slice(&proof_len.to_le_bytes());
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data,
+    }
+}
+
+/// Builds an `UpdateConfig` applying every field set in `update` at once.
+pub fn update_config(authority: &Address, update: &ConfigUpdate) -> Instruction {
+    let mask = update.deposit_fee_bps.map_or(0, |_| CONFIG_DEPOSIT_FEE_BPS)
+        | update
+            .withdraw_fee_bps
+            .map_or(0, |_| CONFIG_WITHDRAW_FEE_BPS)
+        | update.min_withdraw.map_or(0, |_| CONFIG_MIN_WITHDRAW)
+        | update
+            .min_deposit_age_slots
+            .map_or(0, |_| CONFIG_MIN_DEPOSIT_AGE_SLOTS);
+
+    let mut data = Vec::with_capacity(22);
+    data.push(instruction::UPDATE_CONFIG);
+    data.push(mask);
+    data.extend_from_slice(&update.deposit_fee_bps.unwrap_or(0).to_le_bytes());
+    data.extend_from_slice(&update.withdraw_fee_bps.unwrap_or(0).to_le_bytes());
+    data.extend_from_slice(&update.min_withdraw.unwrap_or(0).to_le_bytes());
+    data.extend_from_slice(&update.min_deposit_age_slots.unwrap_or(0).to_le_bytes());
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data,
+    }
+}
+
+/// Builds an update of the withdraw public input order: the witness position
+/// of root, nullifier, recipient, amount and wa_commitment, in that order.
+pub fn configure_public_input_order(authority: &Address, order: [u8; 5]) -> Instruction {
+    let mut data = Vec::with_capacity(6);
+    data.push(instruction::CONFIGURE_PUBLIC_INPUT_ORDER);
+    data.extend_from_slice(&order);
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::readonly(authority.clone(), true),
+            AccountMeta::writable(state_pda().0, false),
+        ],
+        data,
+    }
+}
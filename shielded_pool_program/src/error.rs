@@ -0,0 +1,71 @@
+use solana_program_error::ProgramError;
+
+/// Program-specific errors, surfaced as `ProgramError::Custom(code)`.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShieldedPoolError {
+    /// The vault PDA's data is not exactly a `VaultHeader`.
+    VaultHasData = 0,
+    /// The withdraw recipient is one of the pool's own accounts.
+    InvalidRecipient = 1,
+    /// Every leaf of the commitment tree is already used.
+    TreeFull = 2,
+    /// The withdrawal amount is below the pool's configured minimum.
+    BelowMinWithdraw = 3,
+    /// The amount field element does not fit in a u64.
+    AmountOutOfRange = 4,
+    /// The same account was passed in two slots that must be distinct.
+    DuplicateAccount = 5,
+    /// The payer cannot fund the rent for the nullifier account.
+    InsufficientRentForNullifier = 6,
+    /// The deposit's new root equals the current root.
+    StaleRoot = 7,
+    /// The referenced root is younger than the pool's minimum deposit age.
+    RootTooRecent = 8,
+    /// The deposit or withdrawal amount is zero.
+    ZeroAmount = 9,
+    /// The deposit's previous root is no longer the current root.
+    DepositRootRace = 10,
+    /// The withdraw recipient is on the pool's denylist.
+    RecipientDenied = 11,
+    /// The vault header is missing or names another asset or pool.
+    InvalidVaultHeader = 12,
+    /// The witness public inputs differ from the values the program enforced.
+    PublicInputMismatch = 13,
+    /// More accounts were passed than the instruction takes.
+    TooManyAccounts = 14,
+    /// The instruction data is empty, so there is no instruction discriminator.
+    MissingInstruction = 15,
+    /// The instruction discriminator does not name any instruction.
+    UnknownInstruction = 16,
+    /// The pool is paused for an emergency wind-down.
+    PoolPaused = 17,
+    /// The emergency timelock has not elapsed yet.
+    EmergencyTimelock = 18,
+    /// The withdrawal would take the recipient past its per-window limit.
+    RecipientLimitExceeded = 19,
+    /// The pool is owner-gated and the note owner did not sign.
+    MissingOwnerSignature = 20,
+    /// The audit record PDA holds data this program did not write for it.
+    AuditRecordCorrupt = 21,
+    /// Too few compute units are left to run the proof verifier.
+    InsufficientComputeBudget = 22,
+    /// Fewer distinct authority keys signed than the pool's threshold.
+    AuthorityThresholdNotMet = 23,
+    /// The commitment was already deposited.
+    DuplicateCommitment = 24,
+    /// The pool only takes fee-bearing withdrawals from registered relayers.
+    UnregisteredRelayer = 25,
+    /// A client-supplied bump does not derive the expected PDA.
+    InvalidBump = 26,
+    /// The nullifier-set shard already holds `NullifierSet::CAPACITY` entries.
+    NullifierSetFull = 27,
+    /// The pool pays a withdraw rebate, which this withdraw variant can't pay.
+    RebateUnsupported = 28,
+}
+
+impl From<ShieldedPoolError> for ProgramError {
+    fn from(e: ShieldedPoolError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
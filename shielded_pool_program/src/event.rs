@@ -0,0 +1,168 @@
+use pinocchio::Address;
+
+use crate::runtime::log_data;
+
+/// Tag in the first byte of every event payload.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Withdraw = 0,
+    Deposit = 1,
+    ConfigUpdated = 2,
+    WithdrawMemo = 3,
+    RebateSkipped = 4,
+}
+
+/// Emitted after a successful withdrawal.
+///
+/// Layout: [kind: u8][nullifier: [u8; 32]][recipient: [u8; 32]][amount: u64 LE]
+/// [nullifier_account: [u8; 32]]
+/// `amount` is what the recipient received, after the withdraw fee.
+/// `nullifier_account` is the canonical nullifier PDA the withdrawal created
+/// (or finalized), so indexers can track spends without deriving it.
+pub struct WithdrawEvent {
+    pub nullifier: [u8; 32],
+    pub recipient: Address,
+    pub amount: u64,
+    pub nullifier_account: Address,
+}
+
+impl WithdrawEvent {
+    pub const LEN: usize = 1 + 32 + 32 + 8 + 32;
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0] = EventKind::Withdraw as u8;
+        bytes[1..33].copy_from_slice(&self.nullifier);
+        bytes[33..65].copy_from_slice(self.recipient.as_ref());
+        bytes[65..73].copy_from_slice(&self.amount.to_le_bytes());
+        bytes[73..105].copy_from_slice(self.nullifier_account.as_ref());
+        bytes
+    }
+
+    pub fn emit(&self) {
+        log_data(&[&self.to_bytes()]);
+    }
+}
+
+/// Emitted after a `WithdrawWithMemo`, following its `WithdrawEvent`.
+///
+/// Layout: [kind: u8][nullifier: [u8; 32]], then `memo` as a second data field.
+pub struct WithdrawMemoEvent<'a> {
+    pub nullifier: [u8; 32],
+    /// Caller-supplied plaintext, not bound to the proof
+    pub memo: &'a [u8],
+}
+
+impl WithdrawMemoEvent<'_> {
+    pub const LEN: usize = 1 + 32;
+
+    pub fn to_bytes(&self) -> [u8; WithdrawMemoEvent::LEN] {
+        let mut bytes = [0u8; WithdrawMemoEvent::LEN];
+        bytes[0] = EventKind::WithdrawMemo as u8;
+        bytes[1..33].copy_from_slice(&self.nullifier);
+        bytes
+    }
+
+    pub fn emit(&self) {
+        log_data(&[&self.to_bytes(), self.memo]);
+    }
+}
+
+/// Emitted for every commitment inserted into the tree.
+///
+/// Layout: [kind: u8][commitment: [u8; 32]][leaf_index: u64 LE][amount: u64 LE]
+///
+/// `amount` is the net amount shielded, after the deposit fee. A non-empty
+/// `encrypted_note` is logged as a second data field.
+pub struct DepositEvent<'a> {
+    pub commitment: [u8; 32],
+    pub leaf_index: u64,
+    pub amount: u64,
+    /// Note secrets encrypted to the owner, for recovering notes from chain
+    /// history; empty unless deposited with `DepositWithNote`
+    pub encrypted_note: &'a [u8],
+}
+
+impl DepositEvent<'_> {
+    pub const LEN: usize = 1 + 32 + 8 + 8;
+
+    pub fn to_bytes(&self) -> [u8; DepositEvent::LEN] {
+        let mut bytes = [0u8; DepositEvent::LEN];
+        bytes[0] = EventKind::Deposit as u8;
+        bytes[1..33].copy_from_slice(&self.commitment);
+        bytes[33..41].copy_from_slice(&self.leaf_index.to_le_bytes());
+        bytes[41..49].copy_from_slice(&self.amount.to_le_bytes());
+        bytes
+    }
+
+    pub fn emit(&self) {
+        if self.encrypted_note.is_empty() {
+            log_data(&[&self.to_bytes()]);
+        } else {
+            log_data(&[&self.to_bytes(), self.encrypted_note]);
+        }
+    }
+}
+
+/// Emitted by `UpdateConfig`, with every updatable field as it stands after
+/// the update.
+///
+/// Layout: [kind: u8][mask: u8][deposit_fee_bps: u16 LE][withdraw_fee_bps: u16 LE]
+///         [min_withdraw: u64 LE][min_deposit_age_slots: u64 LE]
+///
+/// `mask` flags the fields the update set.
+pub struct ConfigUpdatedEvent {
+    pub mask: u8,
+    pub deposit_fee_bps: u16,
+    pub withdraw_fee_bps: u16,
+    pub min_withdraw: u64,
+    pub min_deposit_age_slots: u64,
+}
+
+impl ConfigUpdatedEvent {
+    pub const LEN: usize = 1 + 1 + 2 + 2 + 8 + 8;
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0] = EventKind::ConfigUpdated as u8;
+        bytes[1] = self.mask;
+        bytes[2..4].copy_from_slice(&self.deposit_fee_bps.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.withdraw_fee_bps.to_le_bytes());
+        bytes[6..14].copy_from_slice(&self.min_withdraw.to_le_bytes());
+        bytes[14..22].copy_from_slice(&self.min_deposit_age_slots.to_le_bytes());
+        bytes
+    }
+
+    pub fn emit(&self) {
+        log_data(&[&self.to_bytes()]);
+    }
+}
+
+/// Emitted when a withdrawal pays no rebate because the rebate vault holds
+/// less than `amount`. The withdrawal itself still succeeds.
+///
+/// Layout: [kind: u8][recipient: [u8; 32]][amount: u64 LE][vault_balance: u64 LE]
+pub struct RebateSkippedEvent {
+    pub recipient: Address,
+    /// The configured rebate that was not paid
+    pub amount: u64,
+    pub vault_balance: u64,
+}
+
+impl RebateSkippedEvent {
+    pub const LEN: usize = 1 + 32 + 8 + 8;
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0] = EventKind::RebateSkipped as u8;
+        bytes[1..33].copy_from_slice(self.recipient.as_ref());
+        bytes[33..41].copy_from_slice(&self.amount.to_le_bytes());
+        bytes[41..49].copy_from_slice(&self.vault_balance.to_le_bytes());
+        bytes
+    }
+
+    pub fn emit(&self) {
+        log_data(&[&self.to_bytes()]);
+    }
+}
@@ -0,0 +1,41 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{
+    runtime::{self, set_return_data},
+    state::{check_vault, load_state},
+};
+
+/// Return data layout: [delta: i64]
+pub const CHECK_SOLVENCY_RETURN_DATA_LEN: usize = 8;
+
+/// Returns `vault_lamports - rent_reserve - total_shielded`, the vault
+/// balance not accounted for by deposits less withdrawals, little-endian.
+/// Zero means the books balance. A positive delta means lamports reached the
+/// vault outside a deposit (a donation or direct transfer); a negative one
+/// means the vault is short, as after an `EmergencyWithdraw`.
+pub fn process_check_solvency(accounts: &[AccountView], _data: &[u8]) -> ProgramResult {
+    // Accounts: [state, vault]
+    check_account_count(accounts, 2, 2)?;
+    let [state_account, vault] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let total_shielded = load_state(state_account, &crate::ID)?.total_shielded;
+    check_vault(vault, &crate::ID, state_account.address(), None)?;
+
+    let rent_reserve = runtime::rent()?.try_minimum_balance(vault.data_len())?;
+    let delta = vault.lamports() as i128 - rent_reserve as i128 - total_shielded as i128;
+    let delta = delta.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+
+    set_return_data(&delta.to_le_bytes());
+
+    log!(if delta == 0 {
+        "Vault balance matches tracked total"
+    } else {
+        "Vault balance differs from tracked total"
+    });
+    Ok(())
+}
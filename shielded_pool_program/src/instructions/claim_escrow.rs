@@ -0,0 +1,45 @@
+use pinocchio::{AccountView, Address, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+/// Sweeps the recipient's escrow, including its rent, to the recipient and
+/// closes it.
+pub fn process_claim_escrow(accounts: &[AccountView], _data: &[u8]) -> ProgramResult {
+    // Accounts: [recipient, escrow]
+    check_account_count(accounts, 2, 2)?;
+    let [recipient, escrow] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !recipient.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !recipient.is_writable() || !escrow.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (derived_escrow_pda, _) =
+        Address::find_program_address(&[b"escrow", recipient.address().as_ref()], &crate::ID);
+    if escrow.address() != &derived_escrow_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !escrow.owned_by(&crate::ID) || escrow.lamports() == 0 {
+        log_error!("Escrow not found");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let claimed = escrow.lamports();
+    recipient.set_lamports(
+        recipient
+            .lamports()
+            .checked_add(claimed)
+            .ok_or(ProgramError::ArithmeticOverflow)?,
+    );
+    escrow.close()?;
+
+    log!("Escrow claimed");
+    Ok(())
+}
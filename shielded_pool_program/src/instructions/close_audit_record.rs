@@ -0,0 +1,123 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_instruction_view::InstructionView;
+use solana_program_error::ProgramError;
+
+use super::{
+    check_account_count,
+    withdraw::{parse_withdraw_witness, ZK_VERIFIER_PROGRAM_ID},
+};
+
+use crate::{
+    runtime::invoke,
+    state::{
+        is_nullifier_reserved, load_audit_record, load_state, NullifierSeeds, NullifierSet,
+        ShieldedPoolState, VaultHeader,
+    },
+};
+
+/// Closes the audit record of a `wa_commitment` whose note has been spent,
+/// sending its rent to the treasury (`fee_collector`).
+///
+/// The program keeps no link from a nullifier to the identity that spent it,
+/// so the authority passes the spend's withdraw proof and witness. The
+/// verifier ties the nullifier and the `wa_commitment` to one note, and the
+/// nullifier must be spent, either as its PDA or in its nullifier-set shard.
+/// One record covers every note of an identity: closing it makes the
+/// identity's remaining notes submit a new audit before they can withdraw,
+/// so the authority should only close records it deems stale.
+pub fn process_close_audit_record(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, audit_record, nullifier, nullifier_set,
+    // treasury, zk_verifier] followed by any co-signers
+    check_account_count(accounts, 7, 7 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let (accounts, co_signers) = accounts.split_at(7);
+    let [authority, state_account, audit_record, nullifier_account, nullifier_set, treasury, zk_verifier] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !audit_record.is_writable() || !treasury.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [withdraw_proof][withdraw_witness], as the spend sent them.
+    let (proof_len, public_input_count, public_input_order) = {
+        let state = load_state(state_account, &crate::ID)?;
+        state.check_authority(authority, co_signers)?;
+        if treasury.address().as_array() != &state.fee_collector {
+            log_error!("Invalid treasury");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let proof_len = if zk_verifier.address() == &ZK_VERIFIER_PROGRAM_ID {
+            state.proof_len
+        } else if state.alt_proof_len > 0 && zk_verifier.address().as_array() == &state.alt_verifier
+        {
+            state.alt_proof_len
+        } else {
+            log_error!("Unknown withdraw verifier");
+            return Err(ProgramError::IncorrectProgramId);
+        };
+        (
+            proof_len,
+            state.public_input_count,
+            state.public_input_order,
+        )
+    };
+
+    let inputs = parse_withdraw_witness(data, proof_len as usize, &public_input_order)?;
+    if inputs.inputs.len() != public_input_count as usize || inputs.payload.len() != data.len() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    load_audit_record(audit_record, &crate::ID, &inputs.wa_commitment, None)?;
+
+    // The note is spent if its nullifier PDA exists, or if that PDA was
+    // reclaimed into the nullifier-set shard.
+    let (derived_nullifier_pda, _) =
+        NullifierSeeds::nullifier(&VaultHeader::NATIVE_ASSET_ID, &inputs.nullifier)
+            .find_address(&crate::ID);
+    if nullifier_account.address() != &derived_nullifier_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let (derived_set_pda, _) =
+        NullifierSeeds::shard(&VaultHeader::NATIVE_ASSET_ID, &inputs.nullifier)
+            .find_address(&crate::ID);
+    if nullifier_set.address() != &derived_set_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let spent_pda = nullifier_account.owned_by(&crate::ID)
+        && nullifier_account.lamports() > 0
+        && !is_nullifier_reserved(nullifier_account, &crate::ID)?;
+    let spent_in_set = nullifier_set.owned_by(&crate::ID)
+        && NullifierSet::contains(&nullifier_set.try_borrow()?, &inputs.nullifier);
+    if !spent_pda && !spent_in_set {
+        log_error!("Note not spent");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    log!("Verifying ZK proof...");
+    let verify_ix = InstructionView {
+        program_id: zk_verifier.address(),
+        accounts: &[],
+        data: inputs.payload,
+    };
+    invoke(&verify_ix, &[])?;
+
+    let reclaimed = audit_record.lamports();
+    treasury.set_lamports(
+        treasury
+            .lamports()
+            .checked_add(reclaimed)
+            .ok_or(ProgramError::ArithmeticOverflow)?,
+    );
+    audit_record.close()?;
+
+    log!("Audit record closed");
+    Ok(())
+}
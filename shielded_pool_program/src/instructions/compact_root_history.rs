@@ -0,0 +1,77 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{
+    runtime,
+    state::{load_state, split_state_mut, ShieldedPoolState, ROOT_HISTORY_SIZE},
+};
+
+/// Shrinks a grown root history ring back down, keeping only the newest
+/// `new_size` roots, and refunds the freed rent to the authority. Spends
+/// against the dropped roots are rejected from then on; the current root and
+/// the kept roots stay valid.
+pub fn process_compact_root_history(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !authority.is_writable() || !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [new_size: u32]
+    let new_size = u32::from_le_bytes(
+        data.try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    let old_size = {
+        let state = load_state(state_account, &crate::ID)?;
+        state.check_authority(authority, co_signers)?;
+        state.history_size()
+    };
+
+    // The inline roots are part of the state and always kept.
+    if !new_size.is_power_of_two()
+        || (new_size as usize) < ROOT_HISTORY_SIZE
+        || new_size as usize >= old_size
+    {
+        log_error!("Invalid root history size");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    {
+        // The state was validated above; only re-borrow it here.
+        let mut state_data = state_account.try_borrow_mut()?;
+        let (state, extra_roots) = split_state_mut(&mut state_data);
+        state.shrink_history(extra_roots, new_size as usize);
+        state.normalize();
+    }
+
+    let new_len = ShieldedPoolState::account_len(new_size as usize);
+    state_account.resize(new_len)?;
+
+    let refund = state_account
+        .lamports()
+        .saturating_sub(runtime::rent()?.try_minimum_balance(new_len)?);
+    if refund > 0 {
+        state_account.set_lamports(state_account.lamports() - refund);
+        authority.set_lamports(
+            authority
+                .lamports()
+                .checked_add(refund)
+                .ok_or(ProgramError::ArithmeticOverflow)?,
+        );
+    }
+
+    log!("Root history compacted");
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::{check_account_count, withdraw::ZK_VERIFIER_PROGRAM_ID};
+
+use crate::state::{load_state_mut, ShieldedPoolState};
+
+/// Sets or clears the alternate withdraw verifier. While set, a withdrawal
+/// may name either verifier; its proof must then have that verifier's
+/// length. Used to accept old and new circuits side by side during an
+/// upgrade. A `proof_len` of 0 clears it.
+pub fn process_configure_alt_verifier(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [verifier: [u8; 32]] [proof_len: u32]
+    if data.len() != 36 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let verifier: [u8; 32] = data[0..32]
+        .try_into()
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let proof_len = u32::from_le_bytes(
+        data[32..36]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    if proof_len > 0
+        && (proof_len > ShieldedPoolState::MAX_PROOF_LEN
+            || verifier == [0u8; 32]
+            || verifier == ZK_VERIFIER_PROGRAM_ID.to_bytes())
+    {
+        log_error!("Invalid alternate verifier");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    state.alt_verifier = if proof_len > 0 { verifier } else { [0u8; 32] };
+    state.alt_proof_len = proof_len;
+    state.normalize();
+
+    log!(if proof_len > 0 {
+        "Alternate verifier set"
+    } else {
+        "Alternate verifier cleared"
+    });
+    Ok(())
+}
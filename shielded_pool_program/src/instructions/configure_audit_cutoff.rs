@@ -0,0 +1,41 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::state::{load_state_mut, ShieldedPoolState};
+
+/// Sets the slot from which withdrawals need an audit record. Notes deposited
+/// before it can still be withdrawn, without a record, against a root added
+/// before it for as long as that root stays in the history. A slot of 0
+/// requires an audit record for every withdrawal.
+pub fn process_configure_audit_cutoff(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [audit_required_from_slot: u64]
+    let audit_required_from_slot = u64::from_le_bytes(
+        data.try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    state.audit_required_from_slot = audit_required_from_slot;
+    state.normalize();
+
+    log!("Audit cutoff updated");
+    Ok(())
+}
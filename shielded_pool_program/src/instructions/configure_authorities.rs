@@ -0,0 +1,66 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::state::{load_state_mut, ShieldedPoolState};
+
+/// Sets the co-authorities sharing control of the pool with `authority` and
+/// how many distinct authority keys must sign each privileged instruction.
+/// Co-signers are passed after an instruction's own accounts. The change
+/// itself needs the current threshold of signatures. A threshold of 1 with no
+/// co-authorities is the single-authority default.
+pub fn process_configure_authorities(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [threshold: u8] [co_authorities: [[u8; 32]; n]]
+    let (&threshold, keys) = data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    if !keys.len().is_multiple_of(32) {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let count = keys.len() / 32;
+    if count > ShieldedPoolState::MAX_CO_AUTHORITIES {
+        log_error!("Too many co-authorities");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if threshold == 0 || threshold as usize > count + 1 {
+        log_error!("Authority threshold out of range");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    // Every key must be distinct, or fewer keys than the threshold could
+    // reach it.
+    let mut co_authorities = [[0u8; 32]; ShieldedPoolState::MAX_CO_AUTHORITIES];
+    for (i, key) in keys.chunks_exact(32).enumerate() {
+        if key == state.authority || co_authorities[..i].iter().any(|other| other == key) {
+            log_error!("Duplicate authority key");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        co_authorities[i].copy_from_slice(key);
+    }
+
+    state.co_authorities = co_authorities;
+    state.co_authority_count = count as u8;
+    state.authority_threshold = threshold;
+    state.normalize();
+
+    log!("Authorities updated");
+    Ok(())
+}
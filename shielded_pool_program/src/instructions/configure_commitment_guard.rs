@@ -0,0 +1,46 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::state::{load_state_mut, ShieldedPoolState};
+
+/// Turns the unique-commitment guard on or off. While on, each deposit must
+/// pass its commitment's `["commitment", commitment]` marker PDA, which it
+/// creates at the payer's expense, and a commitment that already has a marker
+/// is rejected. Commitments deposited while the guard was off have no marker.
+pub fn process_configure_commitment_guard(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [enabled: u8]
+    let enabled = match data {
+        [0] => false,
+        [1] => true,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    state.unique_commitments = enabled as u8;
+    state.normalize();
+
+    log!(if enabled {
+        "Commitment guard enabled"
+    } else {
+        "Commitment guard disabled"
+    });
+    Ok(())
+}
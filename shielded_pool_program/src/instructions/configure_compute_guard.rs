@@ -0,0 +1,45 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::state::{load_state_mut, ShieldedPoolState};
+
+/// Sets how many compute units must remain before a withdrawal invokes the
+/// proof verifier. Below that, the withdrawal fails early with
+/// `InsufficientComputeBudget` so clients know to raise their compute unit
+/// limit. A threshold of 0 turns the check off.
+pub fn process_configure_compute_guard(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [min_verifier_compute_units: u64]
+    let min_verifier_compute_units = u64::from_le_bytes(
+        data.try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    state.min_verifier_compute_units = min_verifier_compute_units;
+    state.normalize();
+
+    log!(if min_verifier_compute_units > 0 {
+        "Compute budget guard enabled"
+    } else {
+        "Compute budget guard disabled"
+    });
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::{check_account_count, withdraw::OWNER_INPUT};
+
+use crate::state::{load_state_mut, ShieldedPoolState};
+
+/// Turns the owner gate on or off. While on, every withdrawal must be signed
+/// by the Solana key in the witness's `OWNER_INPUT` public input, so only
+/// circuits that expose it (`public_input_count > OWNER_INPUT`) can be gated.
+pub fn process_configure_owner_gate(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [enabled: u8]
+    let enabled = match data {
+        [0] => false,
+        [1] => true,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    if enabled && (state.public_input_count as usize) <= OWNER_INPUT {
+        log_error!("Withdraw circuit has no owner input");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    state.owner_gated = enabled as u8;
+    state.normalize();
+
+    log!(if enabled {
+        "Owner gate enabled"
+    } else {
+        "Owner gate disabled"
+    });
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::{check_account_count, withdraw::PUBLIC_INPUTS};
+
+use crate::state::{load_state_mut, ShieldedPoolState};
+
+/// Sets where the withdraw circuit puts root, nullifier, recipient, amount
+/// and wa_commitment among its first `PUBLIC_INPUTS` public inputs, so a
+/// verifier rotation to a circuit that reorders them needs no redeploy. The
+/// order applies to every withdraw verifier, the alternate one included.
+pub fn process_configure_public_input_order(
+    accounts: &[AccountView],
+    data: &[u8],
+) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [position: u8; PUBLIC_INPUTS], one per input in the order
+    // root, nullifier, recipient, amount, wa_commitment
+    let order: [u8; PUBLIC_INPUTS] = data
+        .try_into()
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    // Every position must be used exactly once, so no input is read twice.
+    if (0..PUBLIC_INPUTS as u8).any(|position| !order.contains(&position)) {
+        log_error!("Public input order is not a permutation");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    state.public_input_order = order;
+    state.normalize();
+
+    log!("Public input order updated");
+    Ok(())
+}
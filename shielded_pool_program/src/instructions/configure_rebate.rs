@@ -0,0 +1,232 @@
+use pinocchio::{
+    cpi::{Seed, Signer},
+    AccountView, Address, ProgramResult,
+};
+use solana_instruction_view::{InstructionAccount, InstructionView};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{
+    error::ShieldedPoolError,
+    event::RebateSkippedEvent,
+    runtime::invoke_signed,
+    state::{load_state_mut, ShieldedPoolState},
+};
+
+/// SPL Token program, which holds the rebate vault and the recipient's token
+/// account.
+pub const TOKEN_PROGRAM_ID: Address =
+    Address::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// Accounts `Withdraw` takes after its own to pay the rebate:
+/// [rebate_vault, rebate_authority, recipient_token_account, rebate_mint, token_program]
+pub const REBATE_ACCOUNTS: usize = 5;
+
+/// SPL Token account and mint layouts, as far as they are read here.
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const MINT_LEN: usize = 82;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_INITIALIZED_OFFSET: usize = 45;
+/// SPL Token `TransferChecked` instruction tag.
+const TRANSFER_CHECKED: u8 = 12;
+
+/// Turns the withdraw rebate on or off. While on, every `Withdraw` and
+/// `WithdrawWithMemo` also moves `rebate_amount` of `rebate_mint` from a token
+/// account owned by the `["rebate_authority"]` PDA to the recipient's token
+/// account, and the withdraw variants that can't pay it are refused.
+pub fn process_configure_rebate(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, rebate_mint, ..co_signers]
+    check_account_count(accounts, 3, 3 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, rebate_mint, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [enabled: u8] [rebate_amount: u64]
+    if data.len() != 9 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let enabled = match data[0] {
+        0 => false,
+        1 => true,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+    let rebate_amount = u64::from_le_bytes(
+        data[1..9]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    // The mint is only read when turning the rebate on.
+    let decimals = if enabled {
+        if rebate_amount == 0 {
+            log_error!("Rebate amount is zero");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        read_mint_decimals(rebate_mint)?
+    } else {
+        0
+    };
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    state.rebate_enabled = enabled as u8;
+    if enabled {
+        state.rebate_mint = rebate_mint.address().to_bytes();
+        state.rebate_decimals = decimals;
+        state.rebate_amount = rebate_amount;
+    }
+    state.normalize();
+
+    log!(if enabled {
+        "Withdraw rebate enabled"
+    } else {
+        "Withdraw rebate disabled"
+    });
+    Ok(())
+}
+
+/// Refuse a withdraw variant that has no rebate accounts (escrow, split,
+/// call) while the pool pays a rebate, so no recipient goes without it.
+pub(crate) fn check_rebate_disabled(state_account: &AccountView) -> ProgramResult {
+    // The state was validated by the withdraw; only re-borrow it here.
+    let state_data = state_account.try_borrow()?;
+    let state: &ShieldedPoolState = bytemuck::from_bytes(&state_data[..ShieldedPoolState::LEN]);
+    if state.rebate_enabled != 0 {
+        log_error!("Withdraw variant can't pay the rebate");
+        return Err(ShieldedPoolError::RebateUnsupported.into());
+    }
+    Ok(())
+}
+
+/// Pay the configured rebate to `recipient` after a withdrawal. A no-op while
+/// the rebate is off. An underfunded rebate vault skips the rebate rather
+/// than blocking the withdrawal, and says so in a `RebateSkippedEvent`.
+pub(crate) fn pay_rebate(
+    state_account: &AccountView,
+    recipient: &AccountView,
+    rebate_accounts: &[AccountView],
+) -> ProgramResult {
+    // The state was validated by the withdraw; only re-borrow it here.
+    let (mint, decimals, amount) = {
+        let state_data = state_account.try_borrow()?;
+        let state: &ShieldedPoolState = bytemuck::from_bytes(&state_data[..ShieldedPoolState::LEN]);
+        if state.rebate_enabled == 0 {
+            return Ok(());
+        }
+        (
+            state.rebate_mint,
+            state.rebate_decimals,
+            state.rebate_amount,
+        )
+    };
+
+    let [rebate_vault, rebate_authority, recipient_token, rebate_mint, token_program] =
+        rebate_accounts
+    else {
+        log_error!("Missing rebate accounts");
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if token_program.address() != &TOKEN_PROGRAM_ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if rebate_mint.address().as_array() != &mint {
+        log_error!("Wrong rebate mint");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (derived_authority, authority_bump) =
+        Address::find_program_address(&[b"rebate_authority"], &crate::ID);
+    if rebate_authority.address() != &derived_authority {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let vault_balance = read_token_account(rebate_vault, &mint, &derived_authority)?;
+    read_token_account(recipient_token, &mint, recipient.address())?;
+
+    if vault_balance < amount {
+        log!("Rebate vault underfunded, skipping rebate");
+        RebateSkippedEvent {
+            recipient: recipient.address().clone(),
+            amount,
+            vault_balance,
+        }
+        .emit();
+        return Ok(());
+    }
+
+    let mut ix_data = [0u8; 10];
+    ix_data[0] = TRANSFER_CHECKED;
+    ix_data[1..9].copy_from_slice(&amount.to_le_bytes());
+    ix_data[9] = decimals;
+
+    let transfer_ix = InstructionView {
+        program_id: &TOKEN_PROGRAM_ID,
+        accounts: &[
+            InstructionAccount::writable(rebate_vault.address()),
+            InstructionAccount::readonly(rebate_mint.address()),
+            InstructionAccount::writable(recipient_token.address()),
+            InstructionAccount::readonly_signer(rebate_authority.address()),
+        ],
+        data: &ix_data,
+    };
+
+    let bump_seed = [authority_bump];
+    let seeds = [Seed::from(b"rebate_authority"), Seed::from(&bump_seed)];
+    let signer = [Signer::from(&seeds)];
+
+    invoke_signed(
+        &transfer_ix,
+        &[rebate_vault, rebate_mint, recipient_token, rebate_authority],
+        &signer,
+    )?;
+
+    log!("Rebate paid");
+    Ok(())
+}
+
+/// Decimals of an initialized SPL Token mint.
+pub(crate) fn read_mint_decimals(mint: &AccountView) -> Result<u8, ProgramError> {
+    if !mint.owned_by(&TOKEN_PROGRAM_ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+    let data = mint.try_borrow()?;
+    if data.len() != MINT_LEN || data[MINT_INITIALIZED_OFFSET] != 1 {
+        log_error!("Invalid rebate mint");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(data[MINT_DECIMALS_OFFSET])
+}
+
+/// Balance of an SPL Token account, after checking its mint and owner.
+fn read_token_account(
+    account: &AccountView,
+    mint: &[u8; 32],
+    owner: &Address,
+) -> Result<u64, ProgramError> {
+    if !account.owned_by(&TOKEN_PROGRAM_ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+    // Layout: [mint: [u8; 32]][owner: [u8; 32]][amount: u64]...
+    let data = account.try_borrow()?;
+    if data.len() != TOKEN_ACCOUNT_LEN || &data[0..32] != mint || &data[32..64] != owner.as_ref() {
+        log_error!("Invalid rebate token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(u64::from_le_bytes(
+        data[64..72]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    ))
+}
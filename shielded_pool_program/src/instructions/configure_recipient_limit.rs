@@ -0,0 +1,60 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::state::{load_state_mut, ShieldedPoolState};
+
+/// Caps how much a single recipient may withdraw per window of
+/// `window_slots` slots. Each recipient's running total is kept in its
+/// `["recipient_limit", recipient]` PDA, which withdrawals create on first
+/// use. A `max_per_window` of 0 turns the limit off.
+pub fn process_configure_recipient_limit(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [max_per_window: u64] [window_slots: u64]
+    if data.len() != 16 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let max_per_window = u64::from_le_bytes(
+        data[0..8]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    let window_slots = u64::from_le_bytes(
+        data[8..16]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    if max_per_window > 0 && window_slots == 0 {
+        log_error!("Recipient limit window is zero");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    state.recipient_max_per_window = max_per_window;
+    state.recipient_window_slots = if max_per_window > 0 { window_slots } else { 0 };
+    state.normalize();
+
+    log!(if max_per_window > 0 {
+        "Recipient limit enabled"
+    } else {
+        "Recipient limit disabled"
+    });
+    Ok(())
+}
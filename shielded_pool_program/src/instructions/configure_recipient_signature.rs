@@ -0,0 +1,48 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::state::{load_state_mut, ShieldedPoolState};
+
+/// Turns the recipient signature requirement on or off. While on, every
+/// withdrawal must be signed by its recipient, so relayers can only submit
+/// withdrawals the recipient co-signed.
+pub fn process_configure_recipient_signature(
+    accounts: &[AccountView],
+    data: &[u8],
+) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [required: u8]
+    let required = match data {
+        [0] => false,
+        [1] => true,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    state.require_recipient_signature = required as u8;
+    state.normalize();
+
+    log!(if required {
+        "Recipient signature required"
+    } else {
+        "Recipient signature not required"
+    });
+    Ok(())
+}
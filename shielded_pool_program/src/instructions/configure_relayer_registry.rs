@@ -0,0 +1,47 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::state::{load_state_mut, ShieldedPoolState};
+
+/// Turns the relayer registry on or off. While on, withdrawals that pay a
+/// withdraw fee must be submitted by a payer registered with `UpdateRelayer`;
+/// fee-free withdrawals stay open to anyone. The registry only decides who
+/// may submit: the withdraw fee is the pool's and goes to the fee vault
+/// whoever submits, so relayers are compensated off chain.
+pub fn process_configure_relayer_registry(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [enabled: u8]
+    let enabled = match data {
+        [0] => false,
+        [1] => true,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    state.relayer_registry = enabled as u8;
+    state.normalize();
+
+    log!(if enabled {
+        "Relayer registry enabled"
+    } else {
+        "Relayer registry disabled"
+    });
+    Ok(())
+}
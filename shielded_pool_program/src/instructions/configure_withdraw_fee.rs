@@ -0,0 +1,46 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::state::{load_state_mut, ShieldedPoolState};
+
+/// Sets the withdraw fee. Each withdrawal then sends
+/// `amount * withdraw_fee_bps / 10_000` of the proven (gross) amount to the
+/// fee vault, swept to `fee_collector` by `WithdrawFees`, and the rest to the
+/// recipient.
+pub fn process_configure_withdraw_fee(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [withdraw_fee_bps: u16]
+    let withdraw_fee_bps = u16::from_le_bytes(
+        data.try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    // A 100% fee would leave every recipient with nothing.
+    if withdraw_fee_bps >= ShieldedPoolState::MAX_FEE_BPS {
+        log_error!("Withdraw fee must be below 100%");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    state.withdraw_fee_bps = withdraw_fee_bps;
+    state.normalize();
+
+    log!("Withdraw fee updated");
+    Ok(())
+}
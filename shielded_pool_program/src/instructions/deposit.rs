@@ -1,16 +1,100 @@
-use pinocchio::{AccountView, Address, ProgramResult};
-use pinocchio_system::instructions::Transfer as SystemTransfer;
+use pinocchio::{
+    cpi::{Seed, Signer},
+    sysvars::rent::Rent,
+    AccountView, Address, ProgramResult,
+};
 use solana_program_error::ProgramError;
-use solana_program_log::log;
 
-use crate::state::ShieldedPoolState;
+use super::{check_account_count, check_system_program, initialize::create_fee_vault_if_missing};
+use crate::{
+    error::ShieldedPoolError,
+    event::DepositEvent,
+    runtime::{
+        self,
+        system::{CreateAccount, Transfer as SystemTransfer},
+    },
+    state::{check_vault, load_state, split_state_mut},
+};
+
+/// Largest encrypted note `DepositWithNote` logs.
+pub const MAX_ENCRYPTED_NOTE_LEN: usize = 256;
+
+/// `Deposit` data for a single commitment, in wire order.
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepositArgs {
+    pub amount: u64,
+    /// Note commitment, opaque to the program. Deposits carry no proof, so
+    /// the pool domain a circuit hashes into it is only checked when the note
+    /// is spent; see [`POOL_DOMAIN_INPUT`](super::withdraw::POOL_DOMAIN_INPUT).
+    pub commitment: [u8; 32],
+    pub prev_root: [u8; 32],
+    pub new_root: [u8; 32],
+}
+
+impl DepositArgs {
+    pub const LEN: usize = 104;
+
+    /// Parse exactly `LEN` bytes, with Borsh when the `borsh` feature is on.
+    pub fn parse(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        #[cfg(feature = "borsh")]
+        return borsh::from_slice(data).map_err(|_| ProgramError::InvalidInstructionData);
+
+        #[cfg(not(feature = "borsh"))]
+        Ok(Self {
+            amount: crate::state::decode_amount_le(&data[0..8])?,
+            commitment: data[8..40]
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            prev_root: data[40..72]
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            new_root: data[72..104]
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+        })
+    }
+}
 
 pub fn process_deposit(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
-    // Accounts: [payer, state, vault, system_program]
-    let [payer, state_account, vault, _system_program] = accounts else {
+    // Several notes in one deposit go through `DepositBatch`; any other
+    // length is rejected by the parser.
+    deposit(accounts, DepositArgs::parse(data)?, &[])
+}
+
+/// `Deposit` followed by the note's secrets encrypted to its owner. The
+/// program only logs the encrypted note in the `DepositEvent`, so wallets can
+/// recover their notes from chain history; it is never stored in an account.
+pub fn process_deposit_with_note(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Data layout: [deposit_args: DepositArgs] [encrypted_note]
+    let (args, encrypted_note) = data
+        .split_at_checked(DepositArgs::LEN)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    if encrypted_note.is_empty() || encrypted_note.len() > MAX_ENCRYPTED_NOTE_LEN {
+        log_error!("Encrypted note length out of range");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    deposit(accounts, DepositArgs::parse(args)?, encrypted_note)
+}
+
+fn deposit(accounts: &[AccountView], args: DepositArgs, encrypted_note: &[u8]) -> ProgramResult {
+    // Accounts: [payer, state, vault, fee_vault, system_program, commitment_marker?]
+    // The marker is only read when the pool requires unique commitments.
+    check_account_count(accounts, 5, 6)?;
+    let [payer, state_account, vault, fee_vault, system_program, markers @ ..] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
+    check_system_program(system_program)?;
+
     if !payer.is_signer() {
         return Err(ProgramError::MissingRequiredSignature);
     }
@@ -19,59 +103,186 @@ pub fn process_deposit(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
         return Err(ProgramError::InvalidAccountData);
     }
 
-    // Data layout: [amount: u64] [commitment: [u8; 32]] [new_root: [u8; 32]]
-    if data.len() != 72 {
-        return Err(ProgramError::InvalidInstructionData);
+    // Data layout: [amount: u64] [commitment: [u8; 32]] [prev_root: [u8; 32]] [new_root: [u8; 32]]
+    // `amount` is the gross deposit; the commitment must encode the net amount
+    // (amount - deposit fee) since only that much reaches the vault.
+    // `prev_root` is the root the client inserted the commitment into.
+    let DepositArgs {
+        amount,
+        commitment,
+        prev_root,
+        new_root,
+    } = args;
+
+    // A zero deposit would still spend a leaf and a root history slot.
+    if amount == 0 {
+        log_error!("Deposit amount is zero");
+        return Err(ShieldedPoolError::ZeroAmount.into());
+    }
+
+    log!("Processing Deposit");
+
+    check_vault(vault, &crate::ID, state_account.address(), None)?;
+
+    // Split the deposit into the protocol fee and the net shielded amount.
+    let (fee, net_amount, unique_commitments) = {
+        let state = load_state(state_account, &crate::ID)?;
+
+        if state.paused != 0 {
+            log_error!("Pool is paused");
+            return Err(ShieldedPoolError::PoolPaused.into());
+        }
+
+        if !state.has_capacity() {
+            log_error!("Commitment tree is full");
+            return Err(ShieldedPoolError::TreeFull.into());
+        }
+
+        // A deposit built on an outdated root would corrupt the tree; the
+        // client must rebuild against the current root and retry.
+        if prev_root != state.current_root {
+            log_error!("Deposit built on a stale root");
+            return Err(ShieldedPoolError::DepositRootRace.into());
+        }
+
+        // Inserting a new commitment always changes the root; an unchanged
+        // root is stale or replayed and would desync the history.
+        if new_root == state.current_root {
+            log_error!("New root equals current root");
+            return Err(ShieldedPoolError::StaleRoot.into());
+        }
+
+        let fee = state.deposit_fee(amount);
+        (fee, amount - fee, state.unique_commitments != 0)
+    };
+
+    if net_amount == 0 {
+        log_error!("Deposit amount does not cover the fee");
+        return Err(ProgramError::InsufficientFunds);
     }
-    let amount = u64::from_le_bytes(
-        data[0..8]
-            .try_into()
-            .map_err(|_| ProgramError::InvalidInstructionData)?,
-    );
-    let _commitment: [u8; 32] = data[8..40]
-        .try_into()
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
-    let new_root: [u8; 32] = data[40..72]
-        .try_into()
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
-
-    log("Processing Deposit");
-
-    // Transfer SOL to the vault.
+
+    if unique_commitments {
+        let [marker] = markers else {
+            log_error!("Missing commitment marker");
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        mark_commitment(payer, marker, &commitment, &runtime::rent()?)?;
+    }
+
+    // Transfer the fee to the fee vault and the rest to the vault, so the
+    // vault only ever holds shielded principal.
+    if fee > 0 {
+        if !fee_vault.is_writable() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        create_fee_vault_if_missing(payer, fee_vault)?;
+        SystemTransfer {
+            from: payer,
+            to: fee_vault,
+            lamports: fee,
+        }
+        .invoke()?;
+    }
+
     SystemTransfer {
         from: payer,
         to: vault,
-        lamports: amount,
+        lamports: net_amount,
     }
     .invoke()?;
 
-    // Update the stored Merkle root.
-    if state_account.address() != &Address::find_program_address(&[b"pool_state"], &crate::ID).0 {
-        return Err(ProgramError::InvalidAccountData);
-    }
+    // Update the stored Merkle root. The state was validated above; only
+    // re-borrow it here rather than deriving the PDA again.
+    let mut state_data = state_account.try_borrow_mut()?;
+    let (state, extra_roots) = split_state_mut(&mut state_data);
+
+    let leaf_index = state.next_leaf_index;
+    state.add_root(extra_roots, new_root, runtime::clock()?.slot);
+    state.next_leaf_index += 1;
+    state.deposit_count = state.deposit_count.saturating_add(1);
+    state.total_shielded = add_shielded(state.total_shielded, net_amount)?;
+    state.normalize();
 
-    if !state_account.owned_by(&crate::ID) {
-        return Err(ProgramError::InvalidAccountOwner);
+    DepositEvent {
+        commitment,
+        leaf_index,
+        amount: net_amount,
+        encrypted_note,
     }
+    .emit();
 
-    if vault.address() != &Address::find_program_address(&[b"vault"], &crate::ID).0 {
+    log!("Deposit successful, root updated");
+    Ok(())
+}
+
+/// `total_shielded` after shielding `net_amount` more. Unlike the deposit
+/// counters, which are statistics and saturate, the total backs the solvency
+/// checks, so an overflow fails the deposit instead of losing lamports from
+/// the books.
+pub(crate) fn add_shielded(total_shielded: u64, net_amount: u64) -> Result<u64, ProgramError> {
+    total_shielded.checked_add(net_amount).ok_or_else(|| {
+        log_error!("Total shielded amount overflows");
+        ProgramError::ArithmeticOverflow
+    })
+}
+
+/// Create the zero-space `["commitment", commitment]` marker PDA, rejecting a
+/// commitment that already has one. Like a nullifier, the marker is never
+/// closed, so each commitment can be deposited once.
+pub(crate) fn mark_commitment(
+    payer: &AccountView,
+    marker: &AccountView,
+    commitment: &[u8; 32],
+    rent: &Rent,
+) -> ProgramResult {
+    let (derived_pda, bump) =
+        Address::find_program_address(&[b"commitment", commitment], &crate::ID);
+    if marker.address() != &derived_pda {
         return Err(ProgramError::InvalidAccountData);
     }
 
-    if !vault.owned_by(&crate::ID) {
-        return Err(ProgramError::InvalidAccountOwner);
+    if marker.lamports() > 0 {
+        log_error!("Commitment already deposited");
+        return Err(ShieldedPoolError::DuplicateCommitment.into());
     }
 
-    let mut state_data = state_account.try_borrow_mut()?;
-    let state: &mut ShieldedPoolState =
-        bytemuck::from_bytes_mut(&mut state_data[..ShieldedPoolState::LEN]);
+    if !marker.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let bump_seed = [bump];
+    let seeds = [
+        Seed::from(b"commitment"),
+        Seed::from(commitment),
+        Seed::from(&bump_seed),
+    ];
+    let signer = [Signer::from(&seeds)];
 
-    if !state.is_initialized() {
-        return Err(ProgramError::UninitializedAccount);
+    CreateAccount {
+        from: payer,
+        to: marker,
+        lamports: rent.try_minimum_balance(0)?,
+        space: 0,
+        owner: &crate::ID,
     }
+    .invoke_signed(&signer)
+}
 
-    state.add_root(new_root);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    log("Deposit successful, root updated");
-    Ok(())
+    #[test]
+    fn add_shielded_fails_past_u64_max() {
+        assert_eq!(add_shielded(u64::MAX - 5, 5), Ok(u64::MAX));
+        assert_eq!(add_shielded(0, u64::MAX), Ok(u64::MAX));
+        assert_eq!(
+            add_shielded(u64::MAX - 5, 6),
+            Err(ProgramError::ArithmeticOverflow)
+        );
+        assert_eq!(
+            add_shielded(u64::MAX, 1),
+            Err(ProgramError::ArithmeticOverflow)
+        );
+    }
 }
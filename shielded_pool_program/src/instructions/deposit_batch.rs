@@ -0,0 +1,208 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::{
+    check_account_count, check_system_program,
+    deposit::{add_shielded, mark_commitment},
+    initialize::create_fee_vault_if_missing,
+};
+use crate::{
+    error::ShieldedPoolError,
+    event::DepositEvent,
+    runtime::{self, set_return_data, system::Transfer as SystemTransfer},
+    state::{check_vault, load_state, split_state_mut},
+};
+
+/// Most commitments inserted by one `DepositBatch`. Bounds the instruction
+/// data well inside a transaction and the number of events logged.
+pub const MAX_BATCH_DEPOSITS: usize = 16;
+/// Size of one `[amount: u64][commitment: [u8; 32]]` batch entry.
+const BATCH_ENTRY_LEN: usize = 8 + 32;
+
+/// Inserts several commitments in one instruction. Each entry is charged the
+/// deposit fee as if deposited alone, but the payer funds the vault and the
+/// fee vault with one transfer each. Only the root after the last insertion
+/// enters the root history, and it is returned as return data.
+pub fn process_deposit_batch(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [payer, state, vault, fee_vault, system_program, commitment_marker * count]
+    // The markers are only read when the pool requires unique commitments.
+    check_account_count(accounts, 5, 5 + MAX_BATCH_DEPOSITS)?;
+    let [payer, state_account, vault, fee_vault, system_program, markers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_system_program(system_program)?;
+
+    if !payer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() || !vault.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // As in `Deposit`, each amount is gross and its commitment encodes the
+    // net amount. `new_root` is the root after inserting every commitment, in
+    // order, on top of `prev_root`.
+    let batch = DepositBatch::parse(data)?;
+    let (count, prev_root, new_root) = (batch.count(), batch.prev_root, batch.new_root);
+
+    log!("Processing Deposit Batch");
+
+    check_vault(vault, &crate::ID, state_account.address(), None)?;
+
+    let (total_fee, total_net, unique_commitments) = {
+        let state = load_state(state_account, &crate::ID)?;
+
+        if state.paused != 0 {
+            log_error!("Pool is paused");
+            return Err(ShieldedPoolError::PoolPaused.into());
+        }
+
+        // Either every commitment fits or none is inserted.
+        if !state.has_capacity_for(count as u64) {
+            log_error!("Commitment tree cannot fit the batch");
+            return Err(ShieldedPoolError::TreeFull.into());
+        }
+
+        if prev_root != state.current_root {
+            log_error!("Deposit built on a stale root");
+            return Err(ShieldedPoolError::DepositRootRace.into());
+        }
+
+        if new_root == state.current_root {
+            log_error!("New root equals current root");
+            return Err(ShieldedPoolError::StaleRoot.into());
+        }
+
+        let mut total_fee = 0u64;
+        let mut total_net = 0u64;
+        for (amount, _) in batch.entries() {
+            if amount == 0 {
+                log_error!("Deposit amount is zero");
+                return Err(ShieldedPoolError::ZeroAmount.into());
+            }
+            let fee = state.deposit_fee(amount);
+            if amount - fee == 0 {
+                log_error!("Deposit amount does not cover the fee");
+                return Err(ProgramError::InsufficientFunds);
+            }
+            total_fee = total_fee
+                .checked_add(fee)
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+            total_net = total_net
+                .checked_add(amount - fee)
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+        }
+        (total_fee, total_net, state.unique_commitments != 0)
+    };
+
+    // A commitment repeated within the batch finds its marker already
+    // created by the earlier entry.
+    if unique_commitments {
+        if markers.len() != count {
+            log_error!("Expected one commitment marker per deposit");
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let rent = runtime::rent()?;
+        for ((_, commitment), marker) in batch.entries().zip(markers) {
+            mark_commitment(payer, marker, &commitment, &rent)?;
+        }
+    }
+
+    if total_fee > 0 {
+        if !fee_vault.is_writable() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        create_fee_vault_if_missing(payer, fee_vault)?;
+        SystemTransfer {
+            from: payer,
+            to: fee_vault,
+            lamports: total_fee,
+        }
+        .invoke()?;
+    }
+
+    SystemTransfer {
+        from: payer,
+        to: vault,
+        lamports: total_net,
+    }
+    .invoke()?;
+
+    // The state was validated above; only re-borrow it here rather than
+    // deriving the PDA again.
+    let mut state_data = state_account.try_borrow_mut()?;
+    let (state, extra_roots) = split_state_mut(&mut state_data);
+
+    for (amount, commitment) in batch.entries() {
+        DepositEvent {
+            commitment,
+            leaf_index: state.next_leaf_index,
+            amount: amount - state.deposit_fee(amount),
+            encrypted_note: &[],
+        }
+        .emit();
+        state.next_leaf_index += 1;
+    }
+
+    state.add_root(extra_roots, new_root, runtime::clock()?.slot);
+    state.deposit_count = state.deposit_count.saturating_add(count as u64);
+    state.total_shielded = add_shielded(state.total_shielded, total_net)?;
+    state.normalize();
+
+    set_return_data(&new_root);
+
+    log!("Deposit batch successful, root updated");
+    Ok(())
+}
+
+/// `DepositBatch` data split into its roots and `(amount, commitment)`
+/// entries.
+pub struct DepositBatch<'a> {
+    pub prev_root: [u8; 32],
+    pub new_root: [u8; 32],
+    entries: &'a [u8],
+}
+
+impl<'a> DepositBatch<'a> {
+    /// Parse the data layout:
+    /// `[count: u8] [prev_root: [u8; 32]] [new_root: [u8; 32]]`
+    /// followed by exactly `count` `[amount: u64] [commitment: [u8; 32]]`
+    /// entries, with `count` between 1 and `MAX_BATCH_DEPOSITS`.
+    pub fn parse(data: &'a [u8]) -> Result<Self, ProgramError> {
+        let count = *data.first().ok_or(ProgramError::InvalidInstructionData)? as usize;
+        if count == 0 || count > MAX_BATCH_DEPOSITS {
+            log_error!("Batch size out of range");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if data.len() != 1 + 64 + count * BATCH_ENTRY_LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            prev_root: data[1..33]
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            new_root: data[33..65]
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            entries: &data[65..],
+        })
+    }
+
+    /// Number of entries in the batch.
+    pub fn count(&self) -> usize {
+        self.entries.len() / BATCH_ENTRY_LEN
+    }
+
+    /// The `(amount, commitment)` entries, in insertion order.
+    pub fn entries(&self) -> impl Iterator<Item = (u64, [u8; 32])> + 'a {
+        self.entries.chunks_exact(BATCH_ENTRY_LEN).map(|entry| {
+            let mut amount = [0u8; 8];
+            let mut commitment = [0u8; 32];
+            amount.copy_from_slice(&entry[..8]);
+            commitment.copy_from_slice(&entry[8..]);
+            (u64::from_le_bytes(amount), commitment)
+        })
+    }
+}
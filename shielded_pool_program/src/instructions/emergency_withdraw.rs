@@ -0,0 +1,74 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{
+    error::ShieldedPoolError,
+    runtime,
+    state::{check_vault, load_state, load_state_mut, ShieldedPoolState},
+};
+
+/// Moves the whole vault balance above its rent floor to the treasury
+/// (`fee_collector`). Only the pool authority may call it, and only once the
+/// timelock started by `InitiateEmergency` has elapsed. The drain leaves
+/// `total_shielded`; lamports sent to the vault outside a deposit are not
+/// tracked there, so they go out with it without underflowing the count.
+pub fn process_emergency_withdraw(accounts: &[AccountView], _data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, vault, treasury, ..co_signers]
+    check_account_count(accounts, 4, 4 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, vault, treasury, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() || !vault.is_writable() || !treasury.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    {
+        let state = load_state(state_account, &crate::ID)?;
+        state.check_authority(authority, co_signers)?;
+        if treasury.address().as_array() != &state.fee_collector {
+            log_error!("Invalid treasury");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if state.paused == 0 {
+            log_error!("Pool is not paused");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if runtime::clock()?.slot < state.emergency_unlock_slot {
+            log_error!("Emergency timelock has not elapsed");
+            return Err(ShieldedPoolError::EmergencyTimelock.into());
+        }
+    }
+
+    check_vault(vault, &crate::ID, state_account.address(), None)?;
+
+    // Keep the vault rent-exempt.
+    let min_balance = runtime::rent()?.try_minimum_balance(vault.data_len())?;
+    let drained = vault.lamports().saturating_sub(min_balance);
+    if drained == 0 {
+        log!("Vault is empty");
+        return Ok(());
+    }
+
+    {
+        let mut state = load_state_mut(state_account, &crate::ID)?;
+        state.total_shielded = state.total_shielded.saturating_sub(drained);
+    }
+
+    vault.set_lamports(vault.lamports() - drained);
+    treasury.set_lamports(
+        treasury
+            .lamports()
+            .checked_add(drained)
+            .ok_or(ProgramError::ArithmeticOverflow)?,
+    );
+
+    log!("Vault drained to treasury");
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{runtime::set_return_data, state::load_audit_record};
+
+/// Return data layout:
+/// [wa_commitment: [u8; 32]][ct_commitment: [u8; 32]][submitter: [u8; 32]][slot: u64 LE]
+pub const AUDIT_RECORD_RETURN_DATA_LEN: usize = 104;
+
+/// Confirms the audit record for a `wa_commitment` exists and returns its
+/// contents, so clients don't have to decode the account themselves. A
+/// missing record fails with `UninitializedAccount`. Records written before
+/// version 1 return a zero `ct_commitment`, `submitter` and `slot`.
+pub fn process_get_audit_record(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [audit_record]
+    check_account_count(accounts, 1, 1)?;
+    let [audit_record] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Data layout: [wa_commitment: [u8; 32]]
+    let wa_commitment: &[u8; 32] = data
+        .try_into()
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let record = load_audit_record(audit_record, &crate::ID, wa_commitment, None)?;
+
+    let mut return_data = [0u8; AUDIT_RECORD_RETURN_DATA_LEN];
+    return_data[..32].copy_from_slice(&record.wa_commitment);
+    return_data[32..64].copy_from_slice(&record.ct_commitment);
+    return_data[64..96].copy_from_slice(&record.submitter);
+    return_data[96..].copy_from_slice(&record.slot.to_le_bytes());
+    set_return_data(&return_data);
+
+    Ok(())
+}
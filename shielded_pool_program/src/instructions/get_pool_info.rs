@@ -0,0 +1,36 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{runtime::set_return_data, state::load_state};
+
+/// Return data layout:
+/// [version: u8][tree_depth: u8][deposit_fee_bps: u16][next_leaf_index: u64]
+/// [min_withdraw: u64][deposit_count: u64][withdraw_count: u64][current_root: [u8; 32]]
+pub const POOL_INFO_RETURN_DATA_LEN: usize = 1 + 1 + 2 + 8 + 8 + 8 + 8 + 32;
+
+/// Returns the pool's configuration and activity counters, so explorers don't
+/// have to decode the state account or scan logs. Integers are little-endian.
+pub fn process_get_pool_info(accounts: &[AccountView], _data: &[u8]) -> ProgramResult {
+    // Accounts: [state]
+    check_account_count(accounts, 1, 1)?;
+    let [state_account] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let state = load_state(state_account, &crate::ID)?;
+
+    let mut return_data = [0u8; POOL_INFO_RETURN_DATA_LEN];
+    return_data[0] = state.version;
+    return_data[1] = state.tree_depth;
+    return_data[2..4].copy_from_slice(&state.deposit_fee_bps.to_le_bytes());
+    return_data[4..12].copy_from_slice(&state.next_leaf_index.to_le_bytes());
+    return_data[12..20].copy_from_slice(&state.min_withdraw.to_le_bytes());
+    return_data[20..28].copy_from_slice(&state.deposit_count.to_le_bytes());
+    return_data[28..36].copy_from_slice(&state.withdraw_count.to_le_bytes());
+    return_data[36..68].copy_from_slice(&state.current_root);
+    set_return_data(&return_data);
+
+    Ok(())
+}
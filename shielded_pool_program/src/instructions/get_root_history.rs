@@ -0,0 +1,61 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{
+    runtime::set_return_data,
+    state::{load_state, split_state, ROOT_HISTORY_SIZE},
+};
+
+/// Roots returned per call; the whole ring does not fit in one return buffer.
+/// History sizes are powers of two of at least `ROOT_HISTORY_SIZE`, so every
+/// size is a whole number of chunks.
+pub const ROOT_HISTORY_CHUNK: usize = 16;
+const _: () = assert!(ROOT_HISTORY_SIZE.is_multiple_of(ROOT_HISTORY_CHUNK));
+
+/// Return data layout:
+/// [roots_index: u32][roots: [[u8; 32]; ROOT_HISTORY_CHUNK]][root_history_size: u32]
+pub const ROOT_HISTORY_RETURN_DATA_LEN: usize = 4 + ROOT_HISTORY_CHUNK * 32 + 4;
+
+/// Returns one chunk of the root history ring, in storage order: chunk `c`
+/// holds entries `c * ROOT_HISTORY_CHUNK..(c + 1) * ROOT_HISTORY_CHUNK`.
+///
+/// The newest root sits at `(roots_index - 1) % root_history_size`; walking
+/// backwards from there (wrapping) yields newest to oldest. Slots never
+/// written are all zeros.
+pub fn process_get_root_history(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [state]
+    check_account_count(accounts, 1, 1)?;
+    let [state_account] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Data layout: [chunk: u8]
+    let [chunk] = data else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+    let start = *chunk as usize * ROOT_HISTORY_CHUNK;
+
+    // Validate the account, then read the roots stored past the state too.
+    drop(load_state(state_account, &crate::ID)?);
+    let state_data = state_account.try_borrow()?;
+    let (state, extra_roots) = split_state(&state_data);
+    if start >= state.history_size() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut return_data = [0u8; ROOT_HISTORY_RETURN_DATA_LEN];
+    return_data[0..4].copy_from_slice(&state.roots_index.to_le_bytes());
+    for (i, dst) in return_data[4..4 + ROOT_HISTORY_CHUNK * 32]
+        .chunks_exact_mut(32)
+        .enumerate()
+    {
+        dst.copy_from_slice(state.history_entry(extra_roots, start + i).0);
+    }
+    return_data[4 + ROOT_HISTORY_CHUNK * 32..]
+        .copy_from_slice(&(state.history_size() as u32).to_le_bytes());
+    set_return_data(&return_data);
+
+    Ok(())
+}
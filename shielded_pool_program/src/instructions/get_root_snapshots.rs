@@ -0,0 +1,56 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{
+    runtime::set_return_data,
+    state::{check_root_snapshots, RootEntry, RootSnapshots},
+};
+
+/// Snapshots returned per call; the whole ring does not fit in one return
+/// buffer.
+pub const ROOT_SNAPSHOTS_CHUNK: usize = 16;
+const _: () = assert!(RootSnapshots::CAPACITY.is_multiple_of(ROOT_SNAPSHOTS_CHUNK));
+
+/// Return data layout:
+/// [count: u32][snapshots: [([root: [u8; 32]][slot: u64]); ROOT_SNAPSHOTS_CHUNK]]
+pub const ROOT_SNAPSHOTS_RETURN_DATA_LEN: usize = 4 + ROOT_SNAPSHOTS_CHUNK * RootEntry::LEN;
+
+/// Returns one chunk of the root snapshots ring, in storage order: chunk `c`
+/// holds entries `c * ROOT_SNAPSHOTS_CHUNK..(c + 1) * ROOT_SNAPSHOTS_CHUNK`.
+///
+/// The newest snapshot sits at `(count - 1) % RootSnapshots::CAPACITY`;
+/// walking backwards from there (wrapping) yields newest to oldest. Entries
+/// never written are all zeros.
+pub fn process_get_root_snapshots(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [root_snapshots]
+    check_account_count(accounts, 1, 1)?;
+    let [root_snapshots] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Data layout: [chunk: u8]
+    let [chunk] = data else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+    let start = *chunk as usize * ROOT_SNAPSHOTS_CHUNK;
+    if start >= RootSnapshots::CAPACITY {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    check_root_snapshots(root_snapshots, &crate::ID)?;
+
+    let snapshot_data = root_snapshots.try_borrow()?;
+    let (header, entries) = snapshot_data.split_at(RootSnapshots::LEN);
+    let header: &RootSnapshots = bytemuck::from_bytes(header);
+
+    let mut return_data = [0u8; ROOT_SNAPSHOTS_RETURN_DATA_LEN];
+    return_data[0..4].copy_from_slice(&header.count.to_le_bytes());
+    return_data[4..].copy_from_slice(
+        &entries[start * RootEntry::LEN..(start + ROOT_SNAPSHOTS_CHUNK) * RootEntry::LEN],
+    );
+    set_return_data(&return_data);
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::{check_account_count, check_system_program};
+
+use crate::{
+    runtime::{self, system::Transfer as SystemTransfer},
+    state::{load_state, split_state_mut, ShieldedPoolState},
+};
+
+/// Grows the root history ring so withdrawals can reference older roots.
+/// Entries past the inline `roots` are stored after the state; the authority
+/// pays for the extra rent. Existing roots stay valid and keep their order.
+pub fn process_grow_root_history(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, system_program, ..co_signers]
+    check_account_count(accounts, 3, 3 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, system_program, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_system_program(system_program)?;
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !authority.is_writable() || !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [new_size: u32]
+    let new_size = u32::from_le_bytes(
+        data.try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    let old_size = {
+        let state = load_state(state_account, &crate::ID)?;
+        state.check_authority(authority, co_signers)?;
+        state.history_size()
+    };
+
+    // Power-of-two sizes keep `roots_index` consistent across its wraparound.
+    if !new_size.is_power_of_two()
+        || new_size as usize <= old_size
+        || new_size > ShieldedPoolState::MAX_ROOT_HISTORY_SIZE
+    {
+        log_error!("Invalid root history size");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let new_len = ShieldedPoolState::account_len(new_size as usize);
+    let top_up = runtime::rent()?
+        .try_minimum_balance(new_len)?
+        .saturating_sub(state_account.lamports());
+    if top_up > 0 {
+        SystemTransfer {
+            from: authority,
+            to: state_account,
+            lamports: top_up,
+        }
+        .invoke()?;
+    }
+    // New bytes are zero-filled, i.e. empty history entries.
+    state_account.resize(new_len)?;
+
+    // The state was validated above; only re-borrow it here.
+    let mut state_data = state_account.try_borrow_mut()?;
+    let (state, extra_roots) = split_state_mut(&mut state_data);
+    state.grow_history(extra_roots, new_size as usize);
+    state.normalize();
+
+    log!("Root history grown");
+    Ok(())
+}
@@ -1,27 +1,395 @@
 use pinocchio::{
     cpi::{Seed, Signer},
-    sysvars::{rent::Rent, Sysvar},
     AccountView, Address, ProgramResult,
 };
-use pinocchio_system::instructions::CreateAccount;
 use solana_program_error::ProgramError;
-use solana_program_log::log;
 
-use crate::state::ShieldedPoolState;
+use super::{
+    check_account_count, check_system_program,
+    configure_rebate::read_mint_decimals,
+    withdraw::{OWNER_INPUT, PUBLIC_INPUTS, ZK_VERIFIER_PROGRAM_ID},
+};
+use crate::{
+    runtime::{self, system::CreateAccount},
+    state::{ShieldedPoolState, VaultHeader, ROOT_HISTORY_SIZE},
+    witness::MAX_PUBLIC_INPUTS,
+};
+
+/// Fixed part of the `Initialize` data, in wire order. All integers are LE.
+///
+/// | offset | size | field                      |
+/// |--------|------|----------------------------|
+/// | 0      | 2    | `deposit_fee_bps`          |
+/// | 2      | 32   | `fee_collector` (treasury) |
+/// | 34     | 1    | `tree_depth`               |
+/// | 35     | 8    | `min_withdraw`             |
+/// | 43     | 4    | `proof_len`                |
+/// | 47     | 1    | `public_input_count`       |
+/// | 48     | 8    | `min_deposit_age_slots`    |
+///
+/// The data may be followed by a 32-byte `initial_root`, and that by
+/// [`InitializeParams`] to set the rest of the config in the same write.
+/// Without them every other state field starts at its default and is changed
+/// through its configure instruction or `UpdateConfig`. The authority is the
+/// payer.
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InitializeArgs {
+    pub deposit_fee_bps: u16,
+    pub fee_collector: [u8; 32],
+    pub tree_depth: u8,
+    pub min_withdraw: u64,
+    pub proof_len: u32,
+    pub public_input_count: u8,
+    pub min_deposit_age_slots: u64,
+}
+
+impl InitializeArgs {
+    pub const LEN: usize = 56;
+
+    /// Parse exactly `LEN` bytes, with Borsh when the `borsh` feature is on.
+    pub fn parse(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        #[cfg(feature = "borsh")]
+        return borsh::from_slice(data).map_err(|_| ProgramError::InvalidInstructionData);
+
+        #[cfg(not(feature = "borsh"))]
+        Ok(Self {
+            deposit_fee_bps: u16::from_le_bytes(
+                data[0..2]
+                    .try_into()
+                    .map_err(|_| ProgramError::InvalidInstructionData)?,
+            ),
+            fee_collector: data[2..34]
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            tree_depth: data[34],
+            min_withdraw: u64::from_le_bytes(
+                data[35..43]
+                    .try_into()
+                    .map_err(|_| ProgramError::InvalidInstructionData)?,
+            ),
+            proof_len: u32::from_le_bytes(
+                data[43..47]
+                    .try_into()
+                    .map_err(|_| ProgramError::InvalidInstructionData)?,
+            ),
+            public_input_count: data[47],
+            min_deposit_age_slots: u64::from_le_bytes(
+                data[48..56]
+                    .try_into()
+                    .map_err(|_| ProgramError::InvalidInstructionData)?,
+            ),
+        })
+    }
+}
+
+/// The rest of the pool config, optionally following `InitializeArgs` and the
+/// initial root so a pool starts fully configured. All integers are LE; flags
+/// are 0 or 1.
+///
+/// | offset | size | field                         |
+/// |--------|------|-------------------------------|
+/// | 0      | 2    | `withdraw_fee_bps`            |
+/// | 2      | 8    | `min_verifier_compute_units`  |
+/// | 10     | 8    | `audit_required_from_slot`    |
+/// | 18     | 8    | `recipient_max_per_window`    |
+/// | 26     | 8    | `recipient_window_slots`      |
+/// | 34     | 32   | `rebate_mint`                 |
+/// | 66     | 8    | `rebate_amount`               |
+/// | 74     | 1    | `owner_gated`                 |
+/// | 75     | 1    | `unique_commitments`          |
+/// | 76     | 1    | `require_recipient_signature` |
+/// | 77     | 1    | `require_wallet_recipient`    |
+/// | 78     | 1    | `relayer_registry`            |
+/// | 79     | 1    | `authority_threshold`         |
+/// | 80     | 1    | `co_authority_count`          |
+/// | 81     | 64   | `co_authorities`              |
+/// | 145    | 32   | `alt_verifier`                |
+/// | 177    | 4    | `alt_proof_len`               |
+/// | 181    | 5    | `public_input_order`          |
+///
+/// Each field is bounded as by its configure instruction. A non-zero
+/// `rebate_amount` turns the rebate on and needs the `rebate_mint` account
+/// after the fixed accounts.
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InitializeParams {
+    pub withdraw_fee_bps: u16,
+    pub min_verifier_compute_units: u64,
+    pub audit_required_from_slot: u64,
+    pub recipient_max_per_window: u64,
+    pub recipient_window_slots: u64,
+    pub rebate_mint: [u8; 32],
+    pub rebate_amount: u64,
+    pub owner_gated: u8,
+    pub unique_commitments: u8,
+    pub require_recipient_signature: u8,
+    pub require_wallet_recipient: u8,
+    pub relayer_registry: u8,
+    pub authority_threshold: u8,
+    pub co_authority_count: u8,
+    pub co_authorities: [[u8; 32]; ShieldedPoolState::MAX_CO_AUTHORITIES],
+    pub alt_verifier: [u8; 32],
+    pub alt_proof_len: u32,
+    pub public_input_order: [u8; PUBLIC_INPUTS],
+}
+
+impl Default for InitializeParams {
+    /// The config a pool gets from `Initialize` without params.
+    fn default() -> Self {
+        Self {
+            withdraw_fee_bps: 0,
+            min_verifier_compute_units: 0,
+            audit_required_from_slot: 0,
+            recipient_max_per_window: 0,
+            recipient_window_slots: 0,
+            rebate_mint: [0u8; 32],
+            rebate_amount: 0,
+            owner_gated: 0,
+            unique_commitments: 0,
+            require_recipient_signature: 0,
+            require_wallet_recipient: 0,
+            relayer_registry: 0,
+            authority_threshold: 1,
+            co_authority_count: 0,
+            co_authorities: [[0u8; 32]; ShieldedPoolState::MAX_CO_AUTHORITIES],
+            alt_verifier: [0u8; 32],
+            alt_proof_len: 0,
+            public_input_order: ShieldedPoolState::DEFAULT_PUBLIC_INPUT_ORDER,
+        }
+    }
+}
+
+impl InitializeParams {
+    pub const LEN: usize = 186;
+
+    /// Parse exactly `LEN` bytes, with Borsh when the `borsh` feature is on.
+    pub fn parse(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        #[cfg(feature = "borsh")]
+        return borsh::from_slice(data).map_err(|_| ProgramError::InvalidInstructionData);
+
+        #[cfg(not(feature = "borsh"))]
+        {
+            let bytes = |start: usize, len: usize| &data[start..start + len];
+            let u64_at = |start: usize| -> Result<u64, ProgramError> {
+                Ok(u64::from_le_bytes(
+                    bytes(start, 8)
+                        .try_into()
+                        .map_err(|_| ProgramError::InvalidInstructionData)?,
+                ))
+            };
+            let key_at = |start: usize| -> Result<[u8; 32], ProgramError> {
+                bytes(start, 32)
+                    .try_into()
+                    .map_err(|_| ProgramError::InvalidInstructionData)
+            };
+            Ok(Self {
+                withdraw_fee_bps: u16::from_le_bytes([data[0], data[1]]),
+                min_verifier_compute_units: u64_at(2)?,
+                audit_required_from_slot: u64_at(10)?,
+                recipient_max_per_window: u64_at(18)?,
+                recipient_window_slots: u64_at(26)?,
+                rebate_mint: key_at(34)?,
+                rebate_amount: u64_at(66)?,
+                owner_gated: data[74],
+                unique_commitments: data[75],
+                require_recipient_signature: data[76],
+                require_wallet_recipient: data[77],
+                relayer_registry: data[78],
+                authority_threshold: data[79],
+                co_authority_count: data[80],
+                co_authorities: [key_at(81)?, key_at(113)?],
+                alt_verifier: key_at(145)?,
+                alt_proof_len: u32::from_le_bytes(
+                    bytes(177, 4)
+                        .try_into()
+                        .map_err(|_| ProgramError::InvalidInstructionData)?,
+                ),
+                public_input_order: bytes(181, PUBLIC_INPUTS)
+                    .try_into()
+                    .map_err(|_| ProgramError::InvalidInstructionData)?,
+            })
+        }
+    }
+
+    /// Check every field against the bounds its configure instruction
+    /// enforces, for a pool whose authority is `authority` and whose circuit
+    /// exposes `public_input_count` inputs.
+    fn validate(&self, authority: &[u8; 32], public_input_count: u8) -> ProgramResult {
+        if self.withdraw_fee_bps >= ShieldedPoolState::MAX_FEE_BPS {
+            log_error!("Withdraw fee must be below 100%");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        if [
+            self.owner_gated,
+            self.unique_commitments,
+            self.require_recipient_signature,
+            self.require_wallet_recipient,
+            self.relayer_registry,
+        ]
+        .iter()
+        .any(|&flag| flag > 1)
+        {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        if self.owner_gated != 0 && (public_input_count as usize) <= OWNER_INPUT {
+            log_error!("Withdraw circuit has no owner input");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        if self.recipient_max_per_window > 0 && self.recipient_window_slots == 0 {
+            log_error!("Recipient limit window is zero");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let count = self.co_authority_count as usize;
+        if count > ShieldedPoolState::MAX_CO_AUTHORITIES {
+            log_error!("Too many co-authorities");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if self.authority_threshold == 0 || self.authority_threshold as usize > count + 1 {
+            log_error!("Authority threshold out of range");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let co_authorities = &self.co_authorities[..count];
+        for (i, key) in co_authorities.iter().enumerate() {
+            if key == authority || co_authorities[..i].contains(key) {
+                log_error!("Duplicate authority key");
+                return Err(ProgramError::InvalidInstructionData);
+            }
+        }
+        if self.co_authorities[count..]
+            != [[0u8; 32]; ShieldedPoolState::MAX_CO_AUTHORITIES][count..]
+        {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        if self.alt_proof_len > 0
+            && (self.alt_proof_len > ShieldedPoolState::MAX_PROOF_LEN
+                || self.alt_verifier == [0u8; 32]
+                || self.alt_verifier == ZK_VERIFIER_PROGRAM_ID.to_bytes())
+        {
+            log_error!("Invalid alternate verifier");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        if (0..PUBLIC_INPUTS as u8).any(|position| !self.public_input_order.contains(&position)) {
+            log_error!("Public input order is not a permutation");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(())
+    }
+}
 
-pub fn process_initialize(accounts: &[AccountView], _data: &[u8]) -> ProgramResult {
-    let [payer, state_account, vault, _system_program] = accounts else {
+pub fn process_initialize(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [payer, state, vault, fee_vault, system_program, rebate_mint?]
+    // The mint is only read when the params turn the rebate on.
+    check_account_count(accounts, 5, 6)?;
+    let [payer, state_account, vault, fee_vault, system_program, rebate_mint @ ..] = accounts
+    else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
+    check_system_program(system_program)?;
+
     if !payer.is_signer() {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    if !state_account.is_writable() || !vault.is_writable() {
+    if !state_account.is_writable() || !vault.is_writable() || !fee_vault.is_writable() {
         return Err(ProgramError::InvalidAccountData);
     }
 
+    // Data layout: [InitializeArgs] ([initial_root: [u8; 32]] [InitializeParams]?)?
+    //
+    // `initial_root` is optional: a pool initialized without one starts at
+    // the empty-tree root for `tree_depth`. Clients pass one to carry over the
+    // root of a pool being migrated from. With params the root is always
+    // present, and all zeros means none.
+    let (args, trailer) = data
+        .split_at_checked(InitializeArgs::LEN)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let (initial_root, params): (Option<[u8; 32]>, _) = match trailer.len() {
+        0 => (None, InitializeParams::default()),
+        32 => (
+            Some(
+                trailer
+                    .try_into()
+                    .map_err(|_| ProgramError::InvalidInstructionData)?,
+            ),
+            InitializeParams::default(),
+        ),
+        len if len == 32 + InitializeParams::LEN => {
+            let (root, params) = trailer.split_at(32);
+            let root: [u8; 32] = root
+                .try_into()
+                .map_err(|_| ProgramError::InvalidInstructionData)?;
+            (
+                (root != [0u8; 32]).then_some(root),
+                InitializeParams::parse(params)?,
+            )
+        }
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+    let InitializeArgs {
+        deposit_fee_bps,
+        fee_collector,
+        tree_depth,
+        min_withdraw,
+        proof_len,
+        public_input_count,
+        min_deposit_age_slots,
+    } = InitializeArgs::parse(args)?;
+
+    // A 100% fee would take every deposit, leaving notes worth nothing.
+    if deposit_fee_bps >= ShieldedPoolState::MAX_FEE_BPS {
+        log_error!("Deposit fee must be below 100%");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    if !(ShieldedPoolState::MIN_TREE_DEPTH..=ShieldedPoolState::MAX_TREE_DEPTH)
+        .contains(&tree_depth)
+    {
+        log_error!("Tree depth out of range");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    if proof_len == 0
+        || proof_len > ShieldedPoolState::MAX_PROOF_LEN
+        || (public_input_count as usize) < PUBLIC_INPUTS
+        || public_input_count as usize > MAX_PUBLIC_INPUTS
+    {
+        log_error!("Invalid proof configuration");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    params.validate(payer.address().as_array(), public_input_count)?;
+    let rebate_decimals = match rebate_mint {
+        _ if params.rebate_amount == 0 => 0,
+        [mint] if mint.address().as_array() == &params.rebate_mint => read_mint_decimals(mint)?,
+        [_] => {
+            log_error!("Wrong rebate mint");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    };
+
     // Ensure the state account matches the expected PDA.
     let (pda, bump) = Address::find_program_address(&[b"pool_state"], &crate::ID);
     if state_account.address() != &pda {
@@ -32,8 +400,9 @@ pub fn process_initialize(accounts: &[AccountView], _data: &[u8]) -> ProgramResu
         return Err(ProgramError::InvalidAccountOwner);
     }
 
+    let rent = runtime::rent()?;
+
     if state_account.is_data_empty() {
-        let rent = Rent::get()?;
         let space = ShieldedPoolState::LEN as u64;
         let lamports = rent.try_minimum_balance(space as usize)?;
 
@@ -41,7 +410,7 @@ pub fn process_initialize(accounts: &[AccountView], _data: &[u8]) -> ProgramResu
         let seeds = [Seed::from(b"pool_state"), Seed::from(&bump_seed)];
         let signer = [Signer::from(&seeds)];
 
-        log("Creating ShieldedPoolState account");
+        log!("Creating ShieldedPoolState account");
         CreateAccount {
             from: payer,
             to: state_account,
@@ -58,15 +427,72 @@ pub fn process_initialize(accounts: &[AccountView], _data: &[u8]) -> ProgramResu
         bytemuck::from_bytes_mut(&mut data[..ShieldedPoolState::LEN]);
 
     if state.is_initialized() {
-        log("ShieldedPoolState already initialized");
+        log!("ShieldedPoolState already initialized");
         return Ok(());
     }
 
     state.discriminator = ShieldedPoolState::DISCRIMINATOR;
-    state.current_root = [0u8; 32]; // Initial root is zero.
-    state.roots = [[0u8; 32]; 32];
+    state.current_root = [0u8; 32];
+    state.roots = [[0u8; 32]; ROOT_HISTORY_SIZE];
     state.roots_index = 0;
-    state._padding = [0u8; 4];
+    state.deposit_fee_bps = deposit_fee_bps;
+    state.tree_depth = tree_depth;
+    state.version = ShieldedPoolState::VERSION;
+    state.fee_collector = fee_collector;
+    state.authority = payer.address().to_bytes();
+    state.next_leaf_index = 0;
+    state.min_withdraw = min_withdraw;
+    state.proof_len = proof_len;
+    state.public_input_count = public_input_count;
+    state.withdraw_fee_bps = params.withdraw_fee_bps;
+    state.min_deposit_age_slots = min_deposit_age_slots;
+    state.root_slots = [0u64; ROOT_HISTORY_SIZE];
+    state.denylist_count = 0;
+    state.deposit_count = 0;
+    state.withdraw_count = 0;
+    state.root_history_size = ROOT_HISTORY_SIZE as u32;
+    state.rebate_enabled = (params.rebate_amount > 0) as u8;
+    state.rebate_decimals = rebate_decimals;
+    state.rebate_mint = if params.rebate_amount > 0 {
+        params.rebate_mint
+    } else {
+        [0u8; 32]
+    };
+    state.rebate_amount = params.rebate_amount;
+    state.paused = 0;
+    state.owner_gated = params.owner_gated;
+    state.emergency_unlock_slot = 0;
+    state.recipient_max_per_window = params.recipient_max_per_window;
+    state.recipient_window_slots = if params.recipient_max_per_window > 0 {
+        params.recipient_window_slots
+    } else {
+        0
+    };
+    state.total_shielded = 0;
+    state.audit_required_from_slot = params.audit_required_from_slot;
+    state.min_verifier_compute_units = params.min_verifier_compute_units;
+    state.co_authorities = params.co_authorities;
+    state.authority_threshold = params.authority_threshold;
+    state.co_authority_count = params.co_authority_count;
+    state.unique_commitments = params.unique_commitments;
+    state.require_recipient_signature = params.require_recipient_signature;
+    state.require_wallet_recipient = params.require_wallet_recipient;
+    state.relayer_registry = params.relayer_registry;
+    state.alt_verifier = if params.alt_proof_len > 0 {
+        params.alt_verifier
+    } else {
+        [0u8; 32]
+    };
+    state.alt_proof_len = params.alt_proof_len;
+    state.public_input_order = params.public_input_order;
+    state.fill_zero_roots()?;
+
+    let initial_root = match initial_root {
+        Some(root) => root,
+        None => state.empty_root()?,
+    };
+    state.add_root(&mut [], initial_root, runtime::clock()?.slot);
+    state.normalize();
 
     // Create the vault PDA if missing.
     let (vault_pda, vault_bump) = Address::find_program_address(&[b"vault"], &crate::ID);
@@ -79,15 +505,14 @@ pub fn process_initialize(accounts: &[AccountView], _data: &[u8]) -> ProgramResu
     }
 
     if vault.is_data_empty() && vault.lamports() == 0 {
-        let rent = Rent::get()?;
-        let space = 0u64;
+        let space = VaultHeader::LEN as u64;
         let lamports = rent.try_minimum_balance(space as usize)?;
 
         let bump_seed = [vault_bump];
         let seeds = [Seed::from(b"vault"), Seed::from(&bump_seed)];
         let signer = [Signer::from(&seeds)];
 
-        log("Creating vault PDA");
+        log!("Creating vault PDA");
         CreateAccount {
             from: payer,
             to: vault,
@@ -96,8 +521,52 @@ pub fn process_initialize(accounts: &[AccountView], _data: &[u8]) -> ProgramResu
             owner: &crate::ID,
         }
         .invoke_signed(&signer)?;
+
+        let mut vault_data = vault.try_borrow_mut()?;
+        let header: &mut VaultHeader =
+            bytemuck::from_bytes_mut(&mut vault_data[..VaultHeader::LEN]);
+        *header = VaultHeader::new(state_account.address());
     }
 
-    log("ShieldedPoolState initialized");
+    create_fee_vault_if_missing(payer, fee_vault)?;
+
+    log!("ShieldedPoolState initialized");
     Ok(())
 }
+
+/// Create the fee vault PDA, which holds protocol fees apart from the
+/// shielded principal in `vault`. Pools initialized before it existed get it
+/// on their first fee-bearing deposit.
+pub(crate) fn create_fee_vault_if_missing(
+    payer: &AccountView,
+    fee_vault: &AccountView,
+) -> ProgramResult {
+    let (fee_vault_pda, fee_vault_bump) =
+        Address::find_program_address(&[b"fee_vault"], &crate::ID);
+    if fee_vault.address() != &fee_vault_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if fee_vault.lamports() > 0 {
+        if !fee_vault.owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+        return Ok(());
+    }
+
+    let lamports = runtime::rent()?.try_minimum_balance(0)?;
+
+    let bump_seed = [fee_vault_bump];
+    let seeds = [Seed::from(b"fee_vault"), Seed::from(&bump_seed)];
+    let signer = [Signer::from(&seeds)];
+
+    log!("Creating fee vault PDA");
+    CreateAccount {
+        from: payer,
+        to: fee_vault,
+        lamports,
+        space: 0,
+        owner: &crate::ID,
+    }
+    .invoke_signed(&signer)
+}
@@ -0,0 +1,49 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{
+    error::ShieldedPoolError,
+    runtime,
+    state::{load_state_mut, ShieldedPoolState},
+};
+
+/// Starts winding the pool down: deposits stop immediately, and
+/// `EmergencyWithdraw` unlocks `EMERGENCY_TIMELOCK_SLOTS` later. Withdrawals
+/// stay open in between so depositors can exit first. There is no way back;
+/// a paused pool stays paused.
+pub fn process_initiate_emergency(accounts: &[AccountView], _data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    // Re-initiating would push the unlock slot back; keep the first one.
+    if state.paused != 0 {
+        log_error!("Emergency already initiated");
+        return Err(ShieldedPoolError::PoolPaused.into());
+    }
+
+    state.paused = 1;
+    state.emergency_unlock_slot = runtime::clock()?
+        .slot
+        .checked_add(ShieldedPoolState::EMERGENCY_TIMELOCK_SLOTS)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    state.normalize();
+
+    log!("Emergency initiated");
+    Ok(())
+}
@@ -0,0 +1,283 @@
+use core::mem::offset_of;
+
+use bytemuck::Zeroable;
+use pinocchio::{sysvars::rent::Rent, AccountView, Address, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::{check_account_count, check_system_program};
+
+use crate::{
+    error::ShieldedPoolError,
+    runtime::{self, system::Transfer as SystemTransfer},
+    state::{ShieldedPoolState, VaultHeader, ROOT_HISTORY_SIZE},
+};
+
+/// BPF upgradeable loader, owner of this program's ProgramData account.
+pub const BPF_LOADER_UPGRADEABLE_ID: Address =
+    Address::from_str_const("BPFLoaderUpgradeab1e11111111111111111111111");
+
+/// Grows the state account to the current `ShieldedPoolState::LEN` and brings
+/// its layout up to `ShieldedPoolState::VERSION`, and gives a vault created
+/// before `VaultHeader` existed its header.
+///
+/// Versioned pools are gated on the stored authority, and from v15 on its
+/// co-authority threshold as for any privileged instruction. v0 pools predate
+/// the authority field, so for them the signer must be the program's upgrade
+/// authority and becomes the pool authority.
+pub fn process_migrate_state(accounts: &[AccountView], _data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, program_data, system_program, vault, ..co_signers]
+    check_account_count(accounts, 5, 5 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, program_data, system_program, vault, co_signers @ ..] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_system_program(system_program)?;
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() || !vault.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if state_account.address() != &Address::find_program_address(&[b"pool_state"], &crate::ID).0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !state_account.owned_by(&crate::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    // Fields never move between versions, so the version and authority can be
+    // read at their current offsets. v0 accounts have zero padding where the
+    // version byte now lives.
+    let (version, history_len) = {
+        let state_data = state_account.try_borrow()?;
+        if state_data.len() < ShieldedPoolState::V0_LEN
+            || state_data[..8] != ShieldedPoolState::DISCRIMINATOR
+        {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let version = state_data[offset_of!(ShieldedPoolState, version)];
+        if version > ShieldedPoolState::VERSION {
+            log_error!("State is newer than this program");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        // Versioned accounts must have exactly their layout's size (plus, from
+        // v6, their grown root history); anything else is not a layout this
+        // program wrote.
+        let history_len = if version >= 6 {
+            let start = offset_of!(ShieldedPoolState, root_history_size);
+            let history_size = u32::from_le_bytes(
+                state_data
+                    .get(start..start + 4)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or(ProgramError::InvalidAccountData)?,
+            );
+            ShieldedPoolState::account_len(history_size as usize) - ShieldedPoolState::LEN
+        } else {
+            0
+        };
+        if version >= 1
+            && state_data.len() != ShieldedPoolState::VERSION_LENS[version as usize] + history_len
+        {
+            log_error!("State size does not match its version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if version >= 15 {
+            // The co-authority fields exist from v15; fields added since read
+            // as zero, as a migration would leave them.
+            let mut state = ShieldedPoolState::zeroed();
+            let known = ShieldedPoolState::VERSION_LENS[version as usize];
+            bytemuck::bytes_of_mut(&mut state)[..known].copy_from_slice(&state_data[..known]);
+            state.check_authority(authority, co_signers)?;
+        } else if version >= 1 {
+            let start = offset_of!(ShieldedPoolState, authority);
+            if &state_data[start..start + 32] != authority.address().as_ref() {
+                log_error!("Signer is not the pool authority");
+                return Err(ProgramError::IncorrectAuthority);
+            }
+        }
+        (version, history_len)
+    };
+
+    let rent = runtime::rent()?;
+
+    if version == ShieldedPoolState::VERSION {
+        log!("State already at current version");
+    } else {
+        if version == 0 {
+            check_upgrade_authority(authority, program_data)?;
+        }
+        migrate_layout(authority, state_account, &rent, version, history_len)?;
+        log!("State migrated");
+    }
+
+    add_vault_header(authority, state_account, vault, &rent)?;
+
+    if version < 12 {
+        seed_total_shielded(state_account, vault, &rent)?;
+    }
+    Ok(())
+}
+
+/// Grow the state account from `version` to the current layout and fill in
+/// the new fields. The `history_len` bytes of root history stored after a
+/// grown (v6+) state move along to follow the larger state.
+fn migrate_layout(
+    authority: &AccountView,
+    state_account: &AccountView,
+    rent: &Rent,
+    version: u8,
+    history_len: usize,
+) -> ProgramResult {
+    // Fund and grow the account; new bytes are zero-filled.
+    let new_len = ShieldedPoolState::LEN + history_len;
+    let top_up = rent
+        .try_minimum_balance(new_len)?
+        .saturating_sub(state_account.lamports());
+    if top_up > 0 {
+        SystemTransfer {
+            from: authority,
+            to: state_account,
+            lamports: top_up,
+        }
+        .invoke()?;
+    }
+    state_account.resize(new_len)?;
+
+    let mut state_data = state_account.try_borrow_mut()?;
+    if history_len > 0 {
+        let old_len = ShieldedPoolState::VERSION_LENS[version as usize];
+        state_data.copy_within(old_len..old_len + history_len, ShieldedPoolState::LEN);
+        state_data[old_len..ShieldedPoolState::LEN].fill(0);
+    }
+    let state: &mut ShieldedPoolState =
+        bytemuck::from_bytes_mut(&mut state_data[..ShieldedPoolState::LEN]);
+
+    // Fill in non-zero defaults for every field added after `version`.
+    if version < 1 {
+        state.tree_depth = ShieldedPoolState::DEFAULT_TREE_DEPTH;
+        state.authority = authority.address().to_bytes();
+    }
+    if version < 2 {
+        state.proof_len = ShieldedPoolState::DEFAULT_PROOF_LEN;
+        state.public_input_count = ShieldedPoolState::DEFAULT_PUBLIC_INPUT_COUNT;
+    }
+    // v3 fields default to zero: no deposit age and root slots of 0, which
+    // treats every existing root as old enough. v4 starts with an empty
+    // denylist, v5 with zeroed deposit and withdraw counters.
+    if version < 6 {
+        state.root_history_size = ROOT_HISTORY_SIZE as u32;
+    }
+    // v7 starts with the withdraw rebate disabled, v8 unpaused with no
+    // emergency pending, v9 without a per-recipient limit, v10 without the
+    // owner gate, v11 without a withdraw fee, v13 requiring audits for every
+    // withdrawal, v14 without a compute budget guard. v12's `total_shielded`
+    // is seeded from the vault once it has been checked. v15 keeps the single
+    // authority, v16 allows repeated commitments, v17 needs no recipient
+    // signature, v18 accepts program-owned recipients, v19 accepts any
+    // relayer, v20 has no alternate verifier.
+    if version < 15 {
+        state.authority_threshold = 1;
+    }
+    if version < 21 {
+        state.public_input_order = ShieldedPoolState::DEFAULT_PUBLIC_INPUT_ORDER;
+    }
+    if version < 22 {
+        state.fill_zero_roots()?;
+    }
+    state.version = ShieldedPoolState::VERSION;
+    state.normalize();
+
+    Ok(())
+}
+
+/// Resize a zero-space vault to hold a `VaultHeader`. The authority pays the
+/// extra rent so every shielded lamport stays withdrawable.
+fn add_vault_header(
+    authority: &AccountView,
+    state_account: &AccountView,
+    vault: &AccountView,
+    rent: &Rent,
+) -> ProgramResult {
+    if vault.address() != &Address::find_program_address(&[b"vault"], &crate::ID).0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !vault.owned_by(&crate::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if vault.data_len() == VaultHeader::LEN {
+        return Ok(());
+    }
+    if vault.data_len() != 0 {
+        return Err(ShieldedPoolError::VaultHasData.into());
+    }
+
+    let top_up = rent
+        .try_minimum_balance(VaultHeader::LEN)?
+        .saturating_sub(rent.try_minimum_balance(0)?);
+    SystemTransfer {
+        from: authority,
+        to: vault,
+        lamports: top_up,
+    }
+    .invoke()?;
+    vault.resize(VaultHeader::LEN)?;
+
+    let mut vault_data = vault.try_borrow_mut()?;
+    let header: &mut VaultHeader = bytemuck::from_bytes_mut(&mut vault_data[..VaultHeader::LEN]);
+    *header = VaultHeader::new(state_account.address());
+
+    log!("Vault header added");
+    Ok(())
+}
+
+/// Pools before v12 never tracked `total_shielded`, so take everything the
+/// vault holds above its rent floor as owed to note holders.
+fn seed_total_shielded(
+    state_account: &AccountView,
+    vault: &AccountView,
+    rent: &Rent,
+) -> ProgramResult {
+    let total_shielded = vault
+        .lamports()
+        .saturating_sub(rent.try_minimum_balance(vault.data_len())?);
+
+    let mut state_data = state_account.try_borrow_mut()?;
+    let state: &mut ShieldedPoolState =
+        bytemuck::from_bytes_mut(&mut state_data[..ShieldedPoolState::LEN]);
+    state.total_shielded = total_shielded;
+    state.normalize();
+
+    Ok(())
+}
+
+/// Check that `authority` is the upgrade authority recorded in this program's
+/// ProgramData account.
+fn check_upgrade_authority(authority: &AccountView, program_data: &AccountView) -> ProgramResult {
+    let (program_data_address, _) =
+        Address::find_program_address(&[crate::ID.as_ref()], &BPF_LOADER_UPGRADEABLE_ID);
+    if program_data.address() != &program_data_address
+        || !program_data.owned_by(&BPF_LOADER_UPGRADEABLE_ID)
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // ProgramData layout: [tag: u32 = 3][slot: u64][authority: Option<[u8; 32]>]
+    let data = program_data.try_borrow()?;
+    if data.len() < 45 || data[0..4] != 3u32.to_le_bytes() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if data[12] != 1 || &data[13..45] != authority.address().as_ref() {
+        log_error!("Signer is not the upgrade authority");
+        return Err(ProgramError::IncorrectAuthority);
+    }
+
+    Ok(())
+}
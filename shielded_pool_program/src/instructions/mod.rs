@@ -1,16 +1,217 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use crate::error::ShieldedPoolError;
+
+pub mod check_solvency;
+pub mod claim_escrow;
+pub mod close_audit_record;
+pub mod compact_root_history;
+pub mod configure_alt_verifier;
+pub mod configure_audit_cutoff;
+pub mod configure_authorities;
+pub mod configure_commitment_guard;
+pub mod configure_compute_guard;
+pub mod configure_owner_gate;
+pub mod configure_public_input_order;
+pub mod configure_rebate;
+pub mod configure_recipient_limit;
+pub mod configure_recipient_signature;
+pub mod configure_relayer_registry;
+pub mod configure_wallet_recipient;
+pub mod configure_withdraw_fee;
 pub mod deposit;
+pub mod deposit_batch;
+pub mod emergency_withdraw;
+pub mod get_audit_record;
+pub mod get_pool_info;
+pub mod get_root_history;
+pub mod get_root_snapshots;
+pub mod grow_root_history;
 pub mod initialize;
+pub mod initiate_emergency;
+pub mod migrate_state;
+pub mod proof_of_reserves;
+pub mod reclaim_nullifier_rent;
+pub mod register_viewing_data;
+pub mod reserve_nullifier;
+pub mod simulate_withdraw;
 pub mod submit_audit;
+pub mod submit_audit_batch;
+pub mod update_config;
+pub mod update_denylist;
+pub mod update_relayer;
+pub mod verify_and_store_root;
 pub mod withdraw;
+pub mod withdraw_and_call;
+pub mod withdraw_fees;
+pub mod withdraw_split;
+pub mod withdraw_to_escrow;
+pub mod withdraw_with_memo;
 
 pub mod instruction {
     pub const INITIALIZE: u8 = 0;
     pub const DEPOSIT: u8 = 1;
     pub const WITHDRAW: u8 = 2;
     pub const SUBMIT_AUDIT: u8 = 3;
+    pub const SIMULATE_WITHDRAW: u8 = 4;
+    pub const RECLAIM_NULLIFIER_RENT: u8 = 5;
+    pub const MIGRATE_STATE: u8 = 6;
+    pub const WITHDRAW_TO_ESCROW: u8 = 7;
+    pub const CLAIM_ESCROW: u8 = 8;
+    pub const GET_ROOT_HISTORY: u8 = 9;
+    pub const WITHDRAW_FEES: u8 = 10;
+    pub const UPDATE_DENYLIST: u8 = 11;
+    pub const GET_POOL_INFO: u8 = 12;
+    pub const WITHDRAW_AND_CALL: u8 = 13;
+    pub const DEPOSIT_BATCH: u8 = 14;
+    pub const GROW_ROOT_HISTORY: u8 = 15;
+    pub const CONFIGURE_REBATE: u8 = 16;
+    pub const VERIFY_AND_STORE_ROOT: u8 = 17;
+    pub const GET_ROOT_SNAPSHOTS: u8 = 18;
+    pub const SUBMIT_AUDIT_BATCH: u8 = 19;
+    pub const INITIATE_EMERGENCY: u8 = 20;
+    pub const EMERGENCY_WITHDRAW: u8 = 21;
+    pub const REGISTER_VIEWING_DATA: u8 = 22;
+    pub const CONFIGURE_RECIPIENT_LIMIT: u8 = 23;
+    pub const CONFIGURE_OWNER_GATE: u8 = 24;
+    pub const COMPACT_ROOT_HISTORY: u8 = 25;
+    pub const CONFIGURE_WITHDRAW_FEE: u8 = 26;
+    pub const PROOF_OF_RESERVES: u8 = 27;
+    pub const GET_AUDIT_RECORD: u8 = 28;
+    pub const CONFIGURE_AUDIT_CUTOFF: u8 = 29;
+    pub const CONFIGURE_COMPUTE_GUARD: u8 = 30;
+    pub const CONFIGURE_AUTHORITIES: u8 = 31;
+    pub const CONFIGURE_COMMITMENT_GUARD: u8 = 32;
+    pub const RESERVE_NULLIFIER: u8 = 33;
+    pub const WITHDRAW_SPLIT: u8 = 34;
+    pub const CONFIGURE_RECIPIENT_SIGNATURE: u8 = 35;
+    pub const CONFIGURE_WALLET_RECIPIENT: u8 = 36;
+    pub const CHECK_SOLVENCY: u8 = 37;
+    pub const UPDATE_RELAYER: u8 = 38;
+    pub const CONFIGURE_RELAYER_REGISTRY: u8 = 39;
+    pub const CONFIGURE_ALT_VERIFIER: u8 = 40;
+    pub const DEPOSIT_WITH_NOTE: u8 = 41;
+    pub const UPDATE_CONFIG: u8 = 42;
+    pub const WITHDRAW_WITH_MEMO: u8 = 43;
+    pub const CONFIGURE_PUBLIC_INPUT_ORDER: u8 = 44;
+    pub const CLOSE_AUDIT_RECORD: u8 = 45;
 }
 
-pub use deposit::process_deposit;
+pub use check_solvency::process_check_solvency;
+pub use claim_escrow::process_claim_escrow;
+pub use close_audit_record::process_close_audit_record;
+pub use compact_root_history::process_compact_root_history;
+pub use configure_alt_verifier::process_configure_alt_verifier;
+pub use configure_audit_cutoff::process_configure_audit_cutoff;
+pub use configure_authorities::process_configure_authorities;
+pub use configure_commitment_guard::process_configure_commitment_guard;
+pub use configure_compute_guard::process_configure_compute_guard;
+pub use configure_owner_gate::process_configure_owner_gate;
+pub use configure_public_input_order::process_configure_public_input_order;
+pub use configure_rebate::process_configure_rebate;
+pub use configure_recipient_limit::process_configure_recipient_limit;
+pub use configure_recipient_signature::process_configure_recipient_signature;
+pub use configure_relayer_registry::process_configure_relayer_registry;
+pub use configure_wallet_recipient::process_configure_wallet_recipient;
+pub use configure_withdraw_fee::process_configure_withdraw_fee;
+pub use deposit::{process_deposit, process_deposit_with_note};
+pub use deposit_batch::process_deposit_batch;
+pub use emergency_withdraw::process_emergency_withdraw;
+pub use get_audit_record::process_get_audit_record;
+pub use get_pool_info::process_get_pool_info;
+pub use get_root_history::process_get_root_history;
+pub use get_root_snapshots::process_get_root_snapshots;
+pub use grow_root_history::process_grow_root_history;
 pub use initialize::process_initialize;
+pub use initiate_emergency::process_initiate_emergency;
+pub use migrate_state::process_migrate_state;
+pub use proof_of_reserves::process_proof_of_reserves;
+pub use reclaim_nullifier_rent::process_reclaim_nullifier_rent;
+pub use register_viewing_data::process_register_viewing_data;
+pub use reserve_nullifier::process_reserve_nullifier;
+pub use simulate_withdraw::process_simulate_withdraw;
 pub use submit_audit::process_submit_audit;
+pub use submit_audit_batch::process_submit_audit_batch;
+pub use update_config::process_update_config;
+pub use update_denylist::process_update_denylist;
+pub use update_relayer::process_update_relayer;
+pub use verify_and_store_root::process_verify_and_store_root;
 pub use withdraw::process_withdraw;
+pub use withdraw_and_call::process_withdraw_and_call;
+pub use withdraw_fees::process_withdraw_fees;
+pub use withdraw_split::process_withdraw_split;
+pub use withdraw_to_escrow::process_withdraw_to_escrow;
+pub use withdraw_with_memo::process_withdraw_with_memo;
+
+/// Require between `min` and `max` accounts, reporting too few and too many
+/// apart so malformed instructions are easy to diagnose.
+pub(crate) fn check_account_count(
+    accounts: &[AccountView],
+    min: usize,
+    max: usize,
+) -> ProgramResult {
+    if accounts.len() < min {
+        log_error!("Expected at least {} accounts, got {}", min, accounts.len());
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if accounts.len() > max {
+        log_error!("Expected at most {} accounts, got {}", max, accounts.len());
+        return Err(ShieldedPoolError::TooManyAccounts.into());
+    }
+    Ok(())
+}
+
+/// Reject a `system_program` account that is not the system program, rather
+/// than let the system CPI fail on it with a less clear error.
+pub(crate) fn check_system_program(account: &AccountView) -> ProgramResult {
+    if account.address() != &pinocchio_system::ID {
+        log_error!("Invalid system program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pinocchio::account::{RuntimeAccount, NOT_BORROWED};
+
+    use super::*;
+
+    /// `count` data-less accounts, checked against `min..=max`.
+    fn check(count: usize, min: usize, max: usize) -> ProgramResult {
+        let mut raw: Vec<RuntimeAccount> = (0..count)
+            .map(|_| RuntimeAccount {
+                borrow_state: NOT_BORROWED,
+                ..RuntimeAccount::default()
+            })
+            .collect();
+        // SAFETY: `raw` outlives the views and its accounts carry no data.
+        let accounts: Vec<AccountView> = raw
+            .iter_mut()
+            .map(|account| unsafe { AccountView::new_unchecked(account) })
+            .collect();
+        check_account_count(&accounts, min, max)
+    }
+
+    #[test]
+    fn check_account_count_accepts_the_bounds() {
+        assert_eq!(check(2, 2, 4), Ok(()));
+        assert_eq!(check(4, 2, 4), Ok(()));
+        assert_eq!(check(3, 3, 3), Ok(()));
+    }
+
+    #[test]
+    fn check_account_count_rejects_too_few() {
+        assert_eq!(check(0, 1, 4), Err(ProgramError::NotEnoughAccountKeys));
+        assert_eq!(check(1, 2, 4), Err(ProgramError::NotEnoughAccountKeys));
+    }
+
+    #[test]
+    fn check_account_count_rejects_too_many() {
+        let too_many = ProgramError::Custom(ShieldedPoolError::TooManyAccounts as u32);
+        assert_eq!(too_many, ProgramError::Custom(14));
+        assert_eq!(check(5, 2, 4), Err(too_many.clone()));
+        assert_eq!(check(1, 0, 0), Err(too_many));
+    }
+}
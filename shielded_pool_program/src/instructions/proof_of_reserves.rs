@@ -0,0 +1,38 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{
+    runtime::{self, set_return_data},
+    state::{check_vault, load_state},
+};
+
+/// Return data layout:
+/// [vault_lamports: u64][total_shielded: u64][rent_reserve: u64]
+pub const PROOF_OF_RESERVES_RETURN_DATA_LEN: usize = 8 + 8 + 8;
+
+/// Returns the vault balance, the lamports owed to note holders, and the
+/// vault's rent-exempt reserve, so anyone can check
+/// `vault_lamports - rent_reserve >= total_shielded` on chain. Integers are
+/// little-endian. An `EmergencyWithdraw` drain shows up as a shortfall.
+pub fn process_proof_of_reserves(accounts: &[AccountView], _data: &[u8]) -> ProgramResult {
+    // Accounts: [state, vault]
+    check_account_count(accounts, 2, 2)?;
+    let [state_account, vault] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let total_shielded = load_state(state_account, &crate::ID)?.total_shielded;
+    check_vault(vault, &crate::ID, state_account.address(), None)?;
+
+    let rent_reserve = runtime::rent()?.try_minimum_balance(vault.data_len())?;
+
+    let mut return_data = [0u8; PROOF_OF_RESERVES_RETURN_DATA_LEN];
+    return_data[0..8].copy_from_slice(&vault.lamports().to_le_bytes());
+    return_data[8..16].copy_from_slice(&total_shielded.to_le_bytes());
+    return_data[16..24].copy_from_slice(&rent_reserve.to_le_bytes());
+    set_return_data(&return_data);
+
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use pinocchio::{cpi::Signer, AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::{check_account_count, check_system_program};
+
+use crate::{
+    error::ShieldedPoolError,
+    runtime::{self, system::CreateAccount},
+    state::{
+        is_nullifier_reserved, load_state, NullifierSeeds, NullifierSet, ShieldedPoolState,
+        VaultHeader,
+    },
+};
+
+/// Moves a spent nullifier from its standalone PDA into the compact
+/// nullifier-set shard and closes the PDA, sending the freed rent to the
+/// treasury (`fee_collector`). The nullifier stays spent because withdraw
+/// checks both the PDA and the shard.
+pub fn process_reclaim_nullifier_rent(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, nullifier, nullifier_set, treasury, system_program]
+    // followed by any co-signers
+    check_account_count(accounts, 6, 6 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let (accounts, co_signers) = accounts.split_at(6);
+    let [authority, state_account, nullifier_account, nullifier_set, treasury, system_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_system_program(system_program)?;
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !nullifier_account.is_writable() || !nullifier_set.is_writable() || !treasury.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [nullifier: [u8; 32]]
+    let nullifier: [u8; 32] = data
+        .try_into()
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    // Only the pool authority may run maintenance, paying out to the treasury.
+    {
+        let state = load_state(state_account, &crate::ID)?;
+        state.check_authority(authority, co_signers)?;
+        if treasury.address().as_array() != &state.fee_collector {
+            log_error!("Invalid treasury");
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    // The nullifier PDA must exist, i.e. the note has been spent.
+    let (derived_nullifier_pda, _) =
+        NullifierSeeds::nullifier(&VaultHeader::NATIVE_ASSET_ID, &nullifier)
+            .find_address(&crate::ID);
+    if nullifier_account.address() != &derived_nullifier_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !nullifier_account.owned_by(&crate::ID)
+        || nullifier_account.lamports() == 0
+        || is_nullifier_reserved(nullifier_account, &crate::ID)?
+    {
+        log_error!("Nullifier not spent");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    // Create the shard on first use.
+    let shard_seeds = NullifierSeeds::shard(&VaultHeader::NATIVE_ASSET_ID, &nullifier);
+    let (derived_set_pda, set_bump) = shard_seeds.find_address(&crate::ID);
+    if nullifier_set.address() != &derived_set_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent = runtime::rent()?;
+
+    if nullifier_set.lamports() == 0 {
+        let space = NullifierSet::LEN;
+        let lamports = rent.try_minimum_balance(space)?;
+
+        let bump_seed = [set_bump];
+        let (seeds, seed_count) = shard_seeds.with_bump(&bump_seed);
+        let signer = [Signer::from(&seeds[..seed_count])];
+
+        log!("Creating nullifier set shard");
+        CreateAccount {
+            from: authority,
+            to: nullifier_set,
+            lamports,
+            space: space as u64,
+            owner: &crate::ID,
+        }
+        .invoke_signed(&signer)?;
+
+        let mut set_data = nullifier_set.try_borrow_mut()?;
+        let header: &mut NullifierSet =
+            bytemuck::from_bytes_mut(&mut set_data[..NullifierSet::LEN]);
+        header.discriminator = NullifierSet::DISCRIMINATOR;
+        header.count = 0;
+        header._padding = [0u8; 4];
+    }
+
+    if !nullifier_set.owned_by(&crate::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    // Insert the nullifier in order unless an earlier reclaim already
+    // recorded it.
+    let position = NullifierSet::search(&nullifier_set.try_borrow()?, &nullifier)?;
+    if let Err(index) = position {
+        let old_len = nullifier_set.data_len();
+        let count = (old_len - NullifierSet::LEN) / NullifierSet::ENTRY_LEN;
+        if count >= NullifierSet::CAPACITY {
+            log_error!("Nullifier set shard is full");
+            return Err(ShieldedPoolError::NullifierSetFull.into());
+        }
+
+        let new_len = old_len + NullifierSet::ENTRY_LEN;
+        nullifier_set.resize(new_len)?;
+
+        // Fund the extra shard space out of the reclaimed rent.
+        let top_up = rent
+            .try_minimum_balance(new_len)?
+            .saturating_sub(nullifier_set.lamports());
+        let remaining = nullifier_account
+            .lamports()
+            .checked_sub(top_up)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        nullifier_account.set_lamports(remaining);
+        nullifier_set.set_lamports(
+            nullifier_set
+                .lamports()
+                .checked_add(top_up)
+                .ok_or(ProgramError::ArithmeticOverflow)?,
+        );
+
+        let mut set_data = nullifier_set.try_borrow_mut()?;
+        let offset = NullifierSet::LEN + index * NullifierSet::ENTRY_LEN;
+        set_data.copy_within(offset..old_len, offset + NullifierSet::ENTRY_LEN);
+        set_data[offset..offset + NullifierSet::ENTRY_LEN].copy_from_slice(&nullifier);
+        let header: &mut NullifierSet =
+            bytemuck::from_bytes_mut(&mut set_data[..NullifierSet::LEN]);
+        header.count = (count + 1) as u32;
+    }
+
+    // Close the standalone nullifier PDA and send what is left to the treasury.
+    let reclaimed = nullifier_account.lamports();
+    treasury.set_lamports(
+        treasury
+            .lamports()
+            .checked_add(reclaimed)
+            .ok_or(ProgramError::ArithmeticOverflow)?,
+    );
+    nullifier_account.close()?;
+
+    log!("Nullifier rent reclaimed");
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use pinocchio::{
+    cpi::{Seed, Signer},
+    AccountView, Address, ProgramResult,
+};
+use solana_program_error::ProgramError;
+
+use super::{check_account_count, check_system_program};
+
+use crate::{
+    runtime::{self, system::CreateAccount},
+    state::ViewingData,
+};
+
+/// Stores a note payload encrypted to an auditor's viewing key, bound to a
+/// `wa_commitment`, in the `["viewing", wa_commitment, viewing_key, payer]`
+/// PDA. Keying the PDA by the registrant keeps anyone else from claiming a
+/// (wa_commitment, viewing_key) pair first. The program does not read the
+/// payload. Registering the same payload again is a no-op; a different one
+/// for the same PDA is rejected.
+pub fn process_register_viewing_data(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [payer, viewing_data, system_program]
+    check_account_count(accounts, 3, 3)?;
+    let [payer, viewing_data, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_system_program(system_program)?;
+
+    if !payer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Data layout: [wa_commitment: [u8; 32]] [viewing_key: [u8; 32]] [payload]
+    if data.len() <= 64 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (wa_commitment, rest) = data.split_at(32);
+    let (viewing_key, payload) = rest.split_at(32);
+    if payload.len() > ViewingData::MAX_DATA_LEN {
+        log_error!("Viewing data too large");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let (derived_pda, bump) = Address::find_program_address(
+        &[
+            b"viewing",
+            wa_commitment,
+            viewing_key,
+            payer.address().as_ref(),
+        ],
+        &crate::ID,
+    );
+    if viewing_data.address() != &derived_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let space = ViewingData::LEN + payload.len();
+
+    if viewing_data.lamports() > 0 {
+        if !viewing_data.owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+        let account_data = viewing_data.try_borrow()?;
+        if account_data.len() == space {
+            let (header, stored) = account_data.split_at(ViewingData::LEN);
+            let header: &ViewingData = bytemuck::from_bytes(header);
+            if header.is_initialized()
+                && header.wa_commitment == wa_commitment
+                && header.viewing_key == viewing_key
+                && stored == payload
+            {
+                log!("Viewing data already registered");
+                return Ok(());
+            }
+        }
+        log_error!("Different viewing data already registered");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    if !viewing_data.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let lamports = runtime::rent()?.try_minimum_balance(space)?;
+
+    let bump_seed = [bump];
+    let seeds = [
+        Seed::from(b"viewing"),
+        Seed::from(wa_commitment),
+        Seed::from(viewing_key),
+        Seed::from(payer.address().as_ref()),
+        Seed::from(&bump_seed),
+    ];
+    let signer = [Signer::from(&seeds)];
+
+    log!("Creating viewing data PDA");
+    CreateAccount {
+        from: payer,
+        to: viewing_data,
+        lamports,
+        space: space as u64,
+        owner: &crate::ID,
+    }
+    .invoke_signed(&signer)?;
+
+    let mut account_data = viewing_data.try_borrow_mut()?;
+    let (header, stored) = account_data.split_at_mut(ViewingData::LEN);
+    let header: &mut ViewingData = bytemuck::from_bytes_mut(header);
+    header.discriminator = ViewingData::DISCRIMINATOR;
+    header.wa_commitment.copy_from_slice(wa_commitment);
+    header.viewing_key.copy_from_slice(viewing_key);
+    header.registrant = payer.address().to_bytes();
+    header.data_len = payload.len() as u32;
+    stored.copy_from_slice(payload);
+
+    log!("Viewing data registered");
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use pinocchio::{cpi::Signer, AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::{check_account_count, check_system_program};
+
+use crate::{
+    runtime::{self, system::CreateAccount},
+    state::{is_nullifier_reserved, NullifierSeeds, VaultHeader, NULLIFIER_RESERVED},
+};
+
+/// Creates a nullifier PDA ahead of its withdrawal, marked reserved, so the
+/// withdraw transaction doesn't have to carry the account creation. A
+/// reservation does not spend the note: the withdrawal marks it spent, and
+/// until then it is not treated as spent. Reserving an already reserved
+/// nullifier is a no-op.
+pub fn process_reserve_nullifier(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [payer, nullifier, system_program]
+    check_account_count(accounts, 3, 3)?;
+    let [payer, nullifier_account, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_system_program(system_program)?;
+
+    if !payer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Data layout: [nullifier: [u8; 32]]
+    let nullifier: [u8; 32] = data
+        .try_into()
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let nullifier_seeds = NullifierSeeds::nullifier(&VaultHeader::NATIVE_ASSET_ID, &nullifier);
+    let (derived_pda, bump) = nullifier_seeds.find_address(&crate::ID);
+    if nullifier_account.address() != &derived_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if nullifier_account.lamports() > 0 {
+        if is_nullifier_reserved(nullifier_account, &crate::ID)? {
+            log!("Nullifier already reserved");
+            return Ok(());
+        }
+        log_error!("Nullifier already used");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    if !nullifier_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let space = 1;
+    let lamports = runtime::rent()?.try_minimum_balance(space)?;
+
+    let bump_seed = [bump];
+    let (seeds, seed_count) = nullifier_seeds.with_bump(&bump_seed);
+    let signer = [Signer::from(&seeds[..seed_count])];
+
+    CreateAccount {
+        from: payer,
+        to: nullifier_account,
+        lamports,
+        space: space as u64,
+        owner: &crate::ID,
+    }
+    .invoke_signed(&signer)?;
+
+    nullifier_account.try_borrow_mut()?[0] = NULLIFIER_RESERVED;
+
+    log!("Nullifier reserved");
+    Ok(())
+}
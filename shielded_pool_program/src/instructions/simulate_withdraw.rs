@@ -0,0 +1,46 @@
+use pinocchio::{AccountView, ProgramResult};
+
+use super::{
+    check_account_count,
+    configure_rebate::REBATE_ACCOUNTS,
+    withdraw::{verify_withdraw, WithdrawAccounts, WITHDRAW_ACCOUNTS},
+};
+
+use crate::runtime::set_return_data;
+
+/// Return data layout: [status: u64][amount: u64]
+/// `status` is 0 when the withdraw would succeed, otherwise the `ProgramError`
+/// code of the first failed check. `amount` is only set on success.
+pub const SIMULATE_WITHDRAW_RETURN_DATA_LEN: usize = 8 + 8;
+
+/// Dry-run of `Withdraw` for relayers. Takes the same accounts and data as
+/// `Withdraw`, runs every check and the verifier CPI, but never creates the
+/// nullifier, moves funds or pays the rebate. A failing proof aborts the transaction since CPI
+/// errors cannot be caught.
+pub fn process_simulate_withdraw(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    log!("Simulating Withdraw");
+
+    let (status, amount) = match check_account_count(
+        accounts,
+        WITHDRAW_ACCOUNTS,
+        WITHDRAW_ACCOUNTS + REBATE_ACCOUNTS,
+    )
+    .and_then(|_| WithdrawAccounts::try_from(accounts))
+    .and_then(|accounts| verify_withdraw(&accounts, data, 0))
+    {
+        Ok(verified) => (0u64, verified.amount),
+        Err(err) => (u64::from(err), 0),
+    };
+
+    let mut return_data = [0u8; SIMULATE_WITHDRAW_RETURN_DATA_LEN];
+    return_data[0..8].copy_from_slice(&status.to_le_bytes());
+    return_data[8..16].copy_from_slice(&amount.to_le_bytes());
+    set_return_data(&return_data);
+
+    log!(if status == 0 {
+        "Withdraw simulation passed"
+    } else {
+        "Withdraw simulation rejected"
+    });
+    Ok(())
+}
@@ -1,30 +1,34 @@
 use pinocchio::{
-    cpi::{invoke, Seed, Signer},
-    sysvars::{rent::Rent, Sysvar},
+    cpi::{Seed, Signer},
     AccountView, Address, ProgramResult,
 };
-use pinocchio_system::instructions::CreateAccount;
 use solana_instruction_view::InstructionView;
 use solana_program_error::ProgramError;
-use solana_program_log::log;
 
-use crate::state::AuditRecord;
+use super::{check_account_count, check_system_program};
+
+use crate::{
+    runtime::{self, invoke, system::CreateAccount},
+    state::{load_audit_record, AuditRecord},
+    witness::parse_public_inputs,
+};
 
 /// Audit Verifier program ID (RLWE correctness proof)
 pub const AUDIT_VERIFIER_PROGRAM_ID: Address =
     Address::from_str_const("2A6wr286RiTEYXVjrqmU87xCNG6nusU5rM8ynSbvfdqb");
 
 // Audit circuit constants
-const AUDIT_PROOF_LEN: usize = 388;
-const AUDIT_PUBLIC_INPUTS: usize = 2; // wa_commitment, ct_commitment
-const AUDIT_WITNESS_HEADER_LEN: usize = 12;
-const AUDIT_WITNESS_LEN: usize = AUDIT_WITNESS_HEADER_LEN + (AUDIT_PUBLIC_INPUTS * 32); // 76 bytes
+pub const AUDIT_PROOF_LEN: usize = 388;
+pub const AUDIT_PUBLIC_INPUTS: usize = 2; // wa_commitment, ct_commitment
 
 pub fn process_submit_audit(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
-    let [payer, audit_record_account, audit_verifier, _system_program] = accounts else {
+    check_account_count(accounts, 4, 4)?;
+    let [payer, audit_record_account, audit_verifier, system_program] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
+    check_system_program(system_program)?;
+
     if !payer.is_signer() {
         return Err(ProgramError::MissingRequiredSignature);
     }
@@ -39,19 +43,10 @@ pub fn process_submit_audit(accounts: &[AccountView], data: &[u8]) -> ProgramRes
     }
 
     // Expected data layout: [audit_proof][audit_witness]
-    let total_len = AUDIT_PROOF_LEN + AUDIT_WITNESS_LEN;
-    if data.len() != total_len {
-        return Err(ProgramError::InvalidInstructionData);
-    }
-
-    // Extract public inputs from witness
     // Witness layout: [12 bytes header][32 bytes wa_commitment][32 bytes ct_commitment]
-    let witness_start = AUDIT_PROOF_LEN;
-    let inputs_start = witness_start + AUDIT_WITNESS_HEADER_LEN;
-
-    let wa_commitment: [u8; 32] = data[inputs_start..inputs_start + 32]
-        .try_into()
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let inputs = parse_public_inputs(data, AUDIT_PROOF_LEN, AUDIT_PUBLIC_INPUTS)?;
+    let wa_commitment = inputs.get(0)?.0;
+    let ct_commitment = inputs.get(1)?.0;
 
     // Verify PDA for Audit Record
     // Seeds: ["audit", wa_commitment]
@@ -64,13 +59,9 @@ pub fn process_submit_audit(accounts: &[AccountView], data: &[u8]) -> ProgramRes
 
     // Check if already initialized (Idempotency)
     if audit_record_account.lamports() > 0 {
-        let state_data = audit_record_account.try_borrow()?;
-        if state_data.len() >= AuditRecord::LEN {
-            let record: &AuditRecord = bytemuck::from_bytes(&state_data[..AuditRecord::LEN]);
-            if record.is_initialized() && record.wa_commitment == wa_commitment {
-                log("Audit record already exists");
-                return Ok(());
-            }
+        if load_audit_record(audit_record_account, &crate::ID, &wa_commitment, None).is_ok() {
+            log!("Audit record already exists");
+            return Ok(());
         }
         // If account exists but data is invalid/uninitialized, we might fail or overwrite.
         // For safety, assume if lamports > 0, it's occupied.
@@ -78,29 +69,52 @@ pub fn process_submit_audit(accounts: &[AccountView], data: &[u8]) -> ProgramRes
     }
 
     // Verify Audit Proof via CPI
-    log("Verifying Audit proof...");
+    log!("Verifying Audit proof...");
     let verify_ix = InstructionView {
         program_id: audit_verifier.address(),
         accounts: &[],
         data,
     };
     invoke(&verify_ix, &[])?;
-    log("Audit proof verified");
+    log!("Audit proof verified");
+
+    create_audit_record(
+        payer,
+        audit_record_account,
+        &wa_commitment,
+        &ct_commitment,
+        bump,
+    )?;
+
+    log!("Audit Record created");
+    Ok(())
+}
 
+/// Create and fill the audit record PDA for `wa_commitment` once its audit
+/// proof has verified. Only call this after the verifier CPI succeeded; it
+/// marks the record verified. `payer` is recorded as the submitter.
+pub(crate) fn create_audit_record(
+    payer: &AccountView,
+    audit_record_account: &AccountView,
+    wa_commitment: &[u8; 32],
+    ct_commitment: &[u8; 32],
+    bump: u8,
+) -> ProgramResult {
     // Initialize Audit Record Account
-    let rent = Rent::get()?;
+    let rent = runtime::rent()?;
+    let slot = runtime::clock()?.slot;
     let space = AuditRecord::LEN;
     let lamports = rent.try_minimum_balance(space)?;
 
     let bump_seed = [bump];
     let seeds = [
         Seed::from(b"audit"),
-        Seed::from(&wa_commitment),
+        Seed::from(wa_commitment),
         Seed::from(&bump_seed),
     ];
     let signer = [Signer::from(&seeds)];
 
-    log("Creating Audit Record account...");
+    log!("Creating Audit Record account...");
     CreateAccount {
         from: payer,
         to: audit_record_account,
@@ -115,8 +129,12 @@ pub fn process_submit_audit(accounts: &[AccountView], data: &[u8]) -> ProgramRes
     let record: &mut AuditRecord = bytemuck::from_bytes_mut(&mut account_data[..AuditRecord::LEN]);
 
     record.discriminator = AuditRecord::DISCRIMINATOR;
-    record.wa_commitment = wa_commitment;
+    record.wa_commitment = *wa_commitment;
+    record.verified = 1;
+    record.version = AuditRecord::VERSION;
+    record.ct_commitment = *ct_commitment;
+    record.submitter = payer.address().to_bytes();
+    record.slot = slot;
 
-    log("Audit Record created");
     Ok(())
 }
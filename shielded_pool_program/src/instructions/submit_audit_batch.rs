@@ -0,0 +1,112 @@
+use pinocchio::{AccountView, Address, ProgramResult};
+use solana_instruction_view::InstructionView;
+use solana_program_error::ProgramError;
+
+use super::{
+    check_account_count, check_system_program,
+    submit_audit::{
+        create_audit_record, AUDIT_PROOF_LEN, AUDIT_PUBLIC_INPUTS, AUDIT_VERIFIER_PROGRAM_ID,
+    },
+};
+
+use crate::{
+    runtime::invoke,
+    state::load_audit_record,
+    witness::{parse_public_inputs, payload_len},
+};
+
+/// Most audits one `SubmitAuditBatch` submits. Each carries its own
+/// `[audit_proof][audit_witness]` of at least 464 bytes, and two of them
+/// already fill most of a 1232-byte transaction.
+pub const MAX_AUDIT_BATCH: usize = 2;
+
+/// Like `SubmitAudit` for several identities in one transaction. The data
+/// holds one `[audit_proof][audit_witness]` payload per audit record, in the
+/// order the records are passed, and each payload is verified by its own
+/// audit verifier CPI. Records that already exist are left as they are.
+pub fn process_submit_audit_batch(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [payer, audit_verifier, system_program, audit_record * N]
+    check_account_count(accounts, 4, 3 + MAX_AUDIT_BATCH)?;
+    let [payer, audit_verifier, system_program, audit_records @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_system_program(system_program)?;
+
+    if !payer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if audit_verifier.address() != &AUDIT_VERIFIER_PROGRAM_ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Data layout: ([audit_proof][audit_witness]) * N, each witness holding
+    // [wa_commitment][ct_commitment].
+    // Check every payload and PDA before any proof, and note which records
+    // still need creating.
+    let mut pending = [None; MAX_AUDIT_BATCH];
+    let mut rest = data;
+    for (i, audit_record_account) in audit_records.iter().enumerate() {
+        let len = payload_len(rest, AUDIT_PROOF_LEN)?;
+        let payload = rest
+            .get(..len)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        rest = &rest[len..];
+        let inputs = parse_public_inputs(payload, AUDIT_PROOF_LEN, AUDIT_PUBLIC_INPUTS)?;
+        let wa_commitment = inputs.get(0)?.0;
+        let ct_commitment = inputs.get(1)?.0;
+
+        let (derived_pda, bump) =
+            Address::find_program_address(&[b"audit", &wa_commitment], &crate::ID);
+        if audit_record_account.address() != &derived_pda {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if audit_record_account.lamports() > 0 {
+            if load_audit_record(audit_record_account, &crate::ID, &wa_commitment, None).is_ok() {
+                continue;
+            }
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+
+        if !audit_record_account.is_writable() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        pending[i] = Some((payload, wa_commitment, ct_commitment, bump));
+    }
+    if !rest.is_empty() {
+        log_error!("Audit count does not match the payloads");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    if pending.iter().all(Option::is_none) {
+        log!("Audit records already exist");
+        return Ok(());
+    }
+
+    for (audit_record_account, pending) in audit_records.iter().zip(pending) {
+        let Some((payload, wa_commitment, ct_commitment, bump)) = pending else {
+            continue;
+        };
+
+        log!("Verifying Audit proof...");
+        let verify_ix = InstructionView {
+            program_id: audit_verifier.address(),
+            accounts: &[],
+            data: payload,
+        };
+        invoke(&verify_ix, &[])?;
+
+        create_audit_record(
+            payer,
+            audit_record_account,
+            &wa_commitment,
+            &ct_commitment,
+            bump,
+        )?;
+    }
+
+    log!("Audit Records created");
+    Ok(())
+}
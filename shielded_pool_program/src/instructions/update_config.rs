@@ -0,0 +1,109 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{
+    event::ConfigUpdatedEvent,
+    state::{load_state_mut, ShieldedPoolState},
+};
+
+/// Bits of the `UpdateConfig` field mask, one per updatable field.
+pub const CONFIG_DEPOSIT_FEE_BPS: u8 = 1 << 0;
+pub const CONFIG_WITHDRAW_FEE_BPS: u8 = 1 << 1;
+pub const CONFIG_MIN_WITHDRAW: u8 = 1 << 2;
+pub const CONFIG_MIN_DEPOSIT_AGE_SLOTS: u8 = 1 << 3;
+const CONFIG_ALL: u8 = CONFIG_DEPOSIT_FEE_BPS
+    | CONFIG_WITHDRAW_FEE_BPS
+    | CONFIG_MIN_WITHDRAW
+    | CONFIG_MIN_DEPOSIT_AGE_SLOTS;
+
+/// Updates the fields selected by `mask` in one write, after checking every
+/// selected value, so a config change never lands half applied. Fields not
+/// in the mask keep their value whatever the data holds for them. Emits a
+/// `ConfigUpdatedEvent` with the resulting config.
+pub fn process_update_config(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, ..co_signers]
+    check_account_count(accounts, 2, 2 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !state_account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [mask: u8] [deposit_fee_bps: u16] [withdraw_fee_bps: u16]
+    //              [min_withdraw: u64] [min_deposit_age_slots: u64]
+    if data.len() != 21 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let mask = data[0];
+    if mask == 0 || mask & !CONFIG_ALL != 0 {
+        log_error!("Invalid config field mask");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let deposit_fee_bps = u16::from_le_bytes(
+        data[1..3]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    let withdraw_fee_bps = u16::from_le_bytes(
+        data[3..5]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    let min_withdraw = u64::from_le_bytes(
+        data[5..13]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    let min_deposit_age_slots = u64::from_le_bytes(
+        data[13..21]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    // Same bounds as `Initialize` and `ConfigureWithdrawFee`.
+    if mask & CONFIG_DEPOSIT_FEE_BPS != 0 && deposit_fee_bps >= ShieldedPoolState::MAX_FEE_BPS {
+        log_error!("Deposit fee must be below 100%");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if mask & CONFIG_WITHDRAW_FEE_BPS != 0 && withdraw_fee_bps >= ShieldedPoolState::MAX_FEE_BPS {
+        log_error!("Withdraw fee must be below 100%");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut state = load_state_mut(state_account, &crate::ID)?;
+    state.check_authority(authority, co_signers)?;
+
+    if mask & CONFIG_DEPOSIT_FEE_BPS != 0 {
+        state.deposit_fee_bps = deposit_fee_bps;
+    }
+    if mask & CONFIG_WITHDRAW_FEE_BPS != 0 {
+        state.withdraw_fee_bps = withdraw_fee_bps;
+    }
+    if mask & CONFIG_MIN_WITHDRAW != 0 {
+        state.min_withdraw = min_withdraw;
+    }
+    if mask & CONFIG_MIN_DEPOSIT_AGE_SLOTS != 0 {
+        state.min_deposit_age_slots = min_deposit_age_slots;
+    }
+    state.normalize();
+
+    ConfigUpdatedEvent {
+        mask,
+        deposit_fee_bps: state.deposit_fee_bps,
+        withdraw_fee_bps: state.withdraw_fee_bps,
+        min_withdraw: state.min_withdraw,
+        min_deposit_age_slots: state.min_deposit_age_slots,
+    }
+    .emit();
+
+    log!("Config updated");
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use pinocchio::{
+    cpi::{Seed, Signer},
+    AccountView, Address, ProgramResult,
+};
+use solana_program_error::ProgramError;
+
+use super::{check_account_count, check_system_program};
+
+use crate::{
+    runtime::{self, system::CreateAccount},
+    state::{load_state, ShieldedPoolState},
+};
+
+/// Adds or removes a recipient from the withdraw denylist. An entry is a
+/// zero-space `["denylist", recipient]` PDA funded by the authority; removing
+/// it closes the PDA and refunds the authority.
+pub fn process_update_denylist(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, denylist_entry, system_program, ..co_signers]
+    check_account_count(accounts, 4, 4 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, denylist_entry, system_program, co_signers @ ..] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_system_program(system_program)?;
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !authority.is_writable() || !state_account.is_writable() || !denylist_entry.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [recipient: [u8; 32]] [deny: u8]
+    if data.len() != 33 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let recipient = &data[0..32];
+    let deny = match data[32] {
+        0 => false,
+        1 => true,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    {
+        let state = load_state(state_account, &crate::ID)?;
+        state.check_authority(authority, co_signers)?;
+    }
+
+    let (derived_denylist_pda, denylist_bump) =
+        Address::find_program_address(&[b"denylist", recipient], &crate::ID);
+    if denylist_entry.address() != &derived_denylist_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let listed = denylist_entry.lamports() > 0;
+    if listed && !denylist_entry.owned_by(&crate::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if deny == listed {
+        log!("Denylist already up to date");
+        return Ok(());
+    }
+
+    if deny {
+        let lamports = runtime::rent()?.try_minimum_balance(0)?;
+
+        let bump_seed = [denylist_bump];
+        let seeds = [
+            Seed::from(b"denylist"),
+            Seed::from(recipient),
+            Seed::from(&bump_seed),
+        ];
+        let signer = [Signer::from(&seeds)];
+
+        CreateAccount {
+            from: authority,
+            to: denylist_entry,
+            lamports,
+            space: 0,
+            owner: &crate::ID,
+        }
+        .invoke_signed(&signer)?;
+    } else {
+        let refund = denylist_entry.lamports();
+        authority.set_lamports(
+            authority
+                .lamports()
+                .checked_add(refund)
+                .ok_or(ProgramError::ArithmeticOverflow)?,
+        );
+        denylist_entry.close()?;
+    }
+
+    // The state was validated above; only re-borrow it here.
+    let mut state_data = state_account.try_borrow_mut()?;
+    let state: &mut ShieldedPoolState =
+        bytemuck::from_bytes_mut(&mut state_data[..ShieldedPoolState::LEN]);
+    state.denylist_count = if deny {
+        state.denylist_count.saturating_add(1)
+    } else {
+        state.denylist_count.saturating_sub(1)
+    };
+    state.normalize();
+
+    log!(if deny {
+        "Recipient denylisted"
+    } else {
+        "Recipient removed from denylist"
+    });
+    Ok(())
+}
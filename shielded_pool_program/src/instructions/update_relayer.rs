@@ -0,0 +1,104 @@
+use pinocchio::{
+    cpi::{Seed, Signer},
+    AccountView, Address, ProgramResult,
+};
+use solana_program_error::ProgramError;
+
+use super::{check_account_count, check_system_program};
+
+use crate::{
+    runtime::{self, system::CreateAccount},
+    state::{load_state, ShieldedPoolState},
+};
+
+/// Registers or removes a relayer. A registration is a zero-space
+/// `["relayer", relayer]` PDA funded by the authority; removing it closes the
+/// PDA and refunds the authority. Registrations only matter while the relayer
+/// registry is on.
+pub fn process_update_relayer(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, relayer_entry, system_program, ..co_signers]
+    check_account_count(accounts, 4, 4 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, relayer_entry, system_program, co_signers @ ..] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_system_program(system_program)?;
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !authority.is_writable() || !relayer_entry.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [relayer: [u8; 32]] [approve: u8]
+    if data.len() != 33 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let relayer = &data[0..32];
+    let approve = match data[32] {
+        0 => false,
+        1 => true,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    {
+        let state = load_state(state_account, &crate::ID)?;
+        state.check_authority(authority, co_signers)?;
+    }
+
+    let (derived_relayer_pda, relayer_bump) =
+        Address::find_program_address(&[b"relayer", relayer], &crate::ID);
+    if relayer_entry.address() != &derived_relayer_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let registered = relayer_entry.lamports() > 0;
+    if registered && !relayer_entry.owned_by(&crate::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if approve == registered {
+        log!("Relayer registry already up to date");
+        return Ok(());
+    }
+
+    if approve {
+        let lamports = runtime::rent()?.try_minimum_balance(0)?;
+
+        let bump_seed = [relayer_bump];
+        let seeds = [
+            Seed::from(b"relayer"),
+            Seed::from(relayer),
+            Seed::from(&bump_seed),
+        ];
+        let signer = [Signer::from(&seeds)];
+
+        CreateAccount {
+            from: authority,
+            to: relayer_entry,
+            lamports,
+            space: 0,
+            owner: &crate::ID,
+        }
+        .invoke_signed(&signer)?;
+    } else {
+        let refund = relayer_entry.lamports();
+        authority.set_lamports(
+            authority
+                .lamports()
+                .checked_add(refund)
+                .ok_or(ProgramError::ArithmeticOverflow)?,
+        );
+        relayer_entry.close()?;
+    }
+
+    log!(if approve {
+        "Relayer registered"
+    } else {
+        "Relayer removed"
+    });
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use pinocchio::{
+    cpi::{Seed, Signer},
+    AccountView, Address, ProgramResult,
+};
+use solana_program_error::ProgramError;
+
+use super::{check_account_count, check_system_program};
+
+use crate::{
+    runtime::{self, system::CreateAccount},
+    state::{check_root_snapshots, load_state, RootEntry, RootSnapshots, ShieldedPoolState},
+};
+
+/// Records `(current_root, slot)` in the root snapshots ring so auditors can
+/// point at the root the pool had at a given slot. The authority passes the
+/// root it expects, and the snapshot is only taken if it is still current.
+pub fn process_verify_and_store_root(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, root_snapshots, system_program, ..co_signers]
+    check_account_count(accounts, 4, 4 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, root_snapshots, system_program, co_signers @ ..] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_system_program(system_program)?;
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !authority.is_writable() || !root_snapshots.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Data layout: [expected_root: [u8; 32]]
+    let expected_root: [u8; 32] = data
+        .try_into()
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let current_root = {
+        let state = load_state(state_account, &crate::ID)?;
+        state.check_authority(authority, co_signers)?;
+        state.current_root
+    };
+
+    if current_root != expected_root {
+        log_error!("Root changed before the snapshot");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // Create the snapshots PDA on first use.
+    if root_snapshots.lamports() == 0 {
+        let (derived_pda, bump) = Address::find_program_address(&[b"root_snapshots"], &crate::ID);
+        if root_snapshots.address() != &derived_pda {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let space = RootSnapshots::ACCOUNT_LEN;
+        let lamports = runtime::rent()?.try_minimum_balance(space)?;
+
+        let bump_seed = [bump];
+        let seeds = [Seed::from(b"root_snapshots"), Seed::from(&bump_seed)];
+        let signer = [Signer::from(&seeds)];
+
+        log!("Creating root snapshots PDA");
+        CreateAccount {
+            from: authority,
+            to: root_snapshots,
+            lamports,
+            space: space as u64,
+            owner: &crate::ID,
+        }
+        .invoke_signed(&signer)?;
+
+        let mut snapshot_data = root_snapshots.try_borrow_mut()?;
+        let header: &mut RootSnapshots =
+            bytemuck::from_bytes_mut(&mut snapshot_data[..RootSnapshots::LEN]);
+        header.discriminator = RootSnapshots::DISCRIMINATOR;
+    }
+
+    check_root_snapshots(root_snapshots, &crate::ID)?;
+
+    let slot = runtime::clock()?.slot;
+    let mut snapshot_data = root_snapshots.try_borrow_mut()?;
+    let (header, entries) = snapshot_data.split_at_mut(RootSnapshots::LEN);
+    let header: &mut RootSnapshots = bytemuck::from_bytes_mut(header);
+    let entries: &mut [RootEntry] = bytemuck::cast_slice_mut(entries);
+
+    entries[header.count as usize % RootSnapshots::CAPACITY] = RootEntry {
+        root: current_root,
+        slot,
+    };
+    header.count = header.count.wrapping_add(1);
+
+    log!("Root snapshot stored");
+    Ok(())
+}
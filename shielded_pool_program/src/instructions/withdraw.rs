@@ -1,37 +1,442 @@
 use pinocchio::{
-    cpi::{invoke, Seed, Signer},
-    sysvars::{rent::Rent, Sysvar},
+    cpi::{Seed, Signer},
+    sysvars::rent::Rent,
     AccountView, Address, ProgramResult,
 };
-use pinocchio_system::instructions::CreateAccount;
 use solana_instruction_view::InstructionView;
 use solana_program_error::ProgramError;
-use solana_program_log::log;
 
-use crate::state::{AuditRecord, ShieldedPoolState};
+use super::{
+    check_account_count, check_system_program,
+    configure_rebate::{pay_rebate, REBATE_ACCOUNTS},
+    initialize::create_fee_vault_if_missing,
+};
+use crate::{
+    error::ShieldedPoolError,
+    event::WithdrawEvent,
+    runtime::{self, invoke, set_return_data, system::CreateAccount},
+    state::{
+        check_vault_withdrawable, debit_vault, is_nullifier_reserved, load_audit_record,
+        load_state, split_state, split_state_mut, FieldElement, NullifierSeeds, NullifierSet,
+        RecipientLimit, VaultHeader, NULLIFIER_SPENT,
+    },
+    witness::{parse_public_inputs, payload_len, PublicInputs},
+};
+
+/// Public inputs read by withdraw; configured circuits may expose more. The
+/// pool's `public_input_order` places them in the witness, by default in this
+/// order.
+pub const PUBLIC_INPUTS: usize = 5; // root, nullifier, recipient, amount, wa_commitment
+
+/// Optional sixth public input: commitment of a change note returned to the
+/// pool. Zero (or a five-input circuit) means the whole note is withdrawn.
+pub const CHANGE_COMMITMENT_INPUT: usize = 5;
+
+/// Public input carrying the note owner's Solana key in owner-gated pools,
+/// encoded like the recipient.
+pub const OWNER_INPUT: usize = 6;
+
+/// First of the (recipient, amount) public input pairs that pay further
+/// recipients of a `WithdrawSplit`. Circuits exposing them also expose the
+/// change and owner inputs; unused pairs are zero.
+pub const SPLIT_INPUT: usize = 7;
+
+/// Most recipients a `WithdrawSplit` pays besides the proof's recipient.
+pub const MAX_SPLIT_PAYOUTS: usize = 3;
+
+/// Public input naming the program that must own the recipient, encoded like
+/// the recipient, for payouts into another program's PDA. Zero leaves the
+/// recipient's owner unconstrained. Follows the split payout pairs.
+pub const RECIPIENT_PROGRAM_INPUT: usize = SPLIT_INPUT + 2 * MAX_SPLIT_PAYOUTS;
+
+/// Public input naming the pool a proof belongs to: the pool's state PDA,
+/// encoded like the recipient. Circuits that expose it hash it into each
+/// note's commitment and nullifier, so a note deposited in one pool opens
+/// nothing in another, and withdraw requires it to be this pool's state PDA,
+/// so a proof built for another pool is rejected even if the roots coincide.
+/// Narrower circuits omit it and are scoped by the program ID alone.
+pub const POOL_DOMAIN_INPUT: usize = RECIPIENT_PROGRAM_INPUT + 1;
+
+/// Accounts taken by `Withdraw`; other withdraw variants append theirs after.
+pub const WITHDRAW_ACCOUNTS: usize = 13;
 
-const PROOF_LEN: usize = 388;
-const PUBLIC_INPUTS: usize = 5; // root, nullifier, recipient, amount, wa_commitment
-const WITNESS_HEADER_LEN: usize = 12;
-const WITNESS_LEN: usize = WITNESS_HEADER_LEN + (PUBLIC_INPUTS * 32);
+/// Return data layout:
+/// [amount_to_recipient: u64][fee: u64][nullifier: [u8; 32]]
+///
+/// `WithdrawSplit` appends [amount_to_recipient: u64][fee: u64] for each
+/// further payout, in the witness's order.
+pub const WITHDRAW_RETURN_DATA_LEN: usize = 8 + 8 + 32;
+
+/// Return data appended per split payout.
+pub const SPLIT_RETURN_DATA_LEN: usize = 8 + 8;
 
 /// ZK Verifier program ID
 pub const ZK_VERIFIER_PROGRAM_ID: Address =
     Address::from_str_const("3qfJCYMTnPwFgSX1T3Ncem6b5DphHtNoMmgyVeb52Yti");
 
+/// The accounts every withdraw variant starts with, by name.
+///
+/// Writable: payer (signer), recipient, vault, state, nullifier. The rest are
+/// read-only; audit_record must be.
+pub(crate) struct WithdrawAccounts<'a> {
+    pub payer: &'a AccountView,
+    pub recipient: &'a AccountView,
+    pub vault: &'a AccountView,
+    pub state: &'a AccountView,
+    pub nullifier: &'a AccountView,
+    pub zk_verifier: &'a AccountView,
+    /// `["audit", wa_commitment]` PDA for the identity the proof binds
+    pub audit_record: &'a AccountView,
+    /// Nullifier-set shard checked for reclaimed nullifiers
+    pub nullifier_set: &'a AccountView,
+    /// System program, used by the nullifier `CreateAccount` CPI
+    pub system_program: &'a AccountView,
+    /// `["denylist", recipient]` PDA; ignored while the denylist is empty
+    pub denylist_entry: &'a AccountView,
+    /// `["recipient_limit", recipient]` PDA; ignored while the pool has no
+    /// per-recipient limit, otherwise writable
+    pub recipient_limit: &'a AccountView,
+    /// `["fee_vault"]` PDA; ignored while the pool charges no withdraw fee,
+    /// otherwise writable
+    pub fee_vault: &'a AccountView,
+    /// `["relayer", payer]` PDA; ignored unless the pool has a relayer
+    /// registry and the withdrawal pays a fee
+    pub relayer_entry: &'a AccountView,
+    /// Accounts after the first `WITHDRAW_ACCOUNTS`, left to the calling
+    /// instruction (e.g. the escrow or the rebate accounts)
+    pub rest: &'a [AccountView],
+}
+
+impl<'a> TryFrom<&'a [AccountView]> for WithdrawAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountView]) -> Result<Self, Self::Error> {
+        let [payer, recipient, vault, state, nullifier, zk_verifier, audit_record, nullifier_set, system_program, denylist_entry, recipient_limit, fee_vault, relayer_entry, rest @ ..] =
+            accounts
+        else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        Ok(Self {
+            payer,
+            recipient,
+            vault,
+            state,
+            nullifier,
+            zk_verifier,
+            audit_record,
+            nullifier_set,
+            system_program,
+            denylist_entry,
+            recipient_limit,
+            fee_vault,
+            relayer_entry,
+            rest,
+        })
+    }
+}
+
+/// Values established by [`verify_withdraw`] that are needed to execute the spend.
+pub(crate) struct VerifiedWithdraw {
+    pub nullifier: [u8; 32],
+    pub nullifier_bump: u8,
+    /// Gross amount proven by the witness
+    pub amount: u64,
+    /// Withdraw fee taken out of `amount` for the fee vault
+    pub fee: u64,
+    /// Root after inserting the change commitment, if the spend has one.
+    pub change_root: Option<[u8; 32]>,
+    /// Rent-exempt minimum of the (zero-space) nullifier account.
+    pub nullifier_rent: u64,
+    /// The nullifier PDA already exists as a reservation to finalize.
+    pub nullifier_reserved: bool,
+    /// Current slot; only read (otherwise 0) when the root age check, the
+    /// change note or the recipient limit needs it.
+    pub slot: u64,
+    /// The recipient's window after this withdrawal, while the pool caps
+    /// withdrawals per recipient.
+    pub recipient_limit: Option<RecipientLimitUpdate>,
+    /// Checks the caller applies to any further payout address
+    pub policy: RecipientPolicy,
+    /// Further payouts of a split withdrawal; only the first `split_count`
+    /// are set.
+    pub splits: [SplitPayout; MAX_SPLIT_PAYOUTS],
+    pub split_count: usize,
+}
+
+impl VerifiedWithdraw {
+    /// Reports each payout's amount and fee so relayers don't have to parse
+    /// logs. Withdraw variants call this last, after any CPI that would
+    /// clear it.
+    pub(crate) fn set_return_data(&self) {
+        let mut return_data =
+            [0u8; WITHDRAW_RETURN_DATA_LEN + MAX_SPLIT_PAYOUTS * SPLIT_RETURN_DATA_LEN];
+        return_data[0..8].copy_from_slice(&(self.amount - self.fee).to_le_bytes());
+        return_data[8..16].copy_from_slice(&self.fee.to_le_bytes());
+        return_data[16..48].copy_from_slice(&self.nullifier);
+        let splits = &self.splits[..self.split_count];
+        for (split, entry) in splits
+            .iter()
+            .zip(return_data[WITHDRAW_RETURN_DATA_LEN..].chunks_exact_mut(SPLIT_RETURN_DATA_LEN))
+        {
+            entry[0..8].copy_from_slice(&(split.amount - split.fee).to_le_bytes());
+            entry[8..16].copy_from_slice(&split.fee.to_le_bytes());
+        }
+        set_return_data(
+            &return_data[..WITHDRAW_RETURN_DATA_LEN + splits.len() * SPLIT_RETURN_DATA_LEN],
+        );
+    }
+}
+
+/// One further payout of a split withdrawal, as bound by the witness.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct SplitPayout {
+    /// Recipient, encoded as in the public input
+    pub recipient: [u8; 32],
+    /// Gross amount, withdraw fee included
+    pub amount: u64,
+    pub fee: u64,
+}
+
+/// New contents of a recipient's `RecipientLimit` PDA.
+pub(crate) struct RecipientLimitUpdate {
+    pub bump: u8,
+    /// Rent to create the PDA on the recipient's first limited withdrawal; 0
+    /// once it exists.
+    pub create_lamports: u64,
+    pub withdrawn_in_window: u64,
+    pub window_start_slot: u64,
+}
+
 pub fn process_withdraw(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
-    // Keys: [payer, recipient, vault, state, nullifier, zk_verifier, audit_record, system_program]
-    // Audit verifier (Account 6) is replaced by audit_record_account (PDA)
-    let [payer, recipient, vault, state_account, nullifier_account, zk_verifier, audit_record_account, _system_program] =
-        accounts
-    else {
-        return Err(ProgramError::NotEnoughAccountKeys);
+    log!("Processing Withdraw");
+
+    // The rebate accounts are only needed while the pool pays a rebate.
+    check_account_count(
+        accounts,
+        WITHDRAW_ACCOUNTS,
+        WITHDRAW_ACCOUNTS + REBATE_ACCOUNTS,
+    )?;
+    let accounts = WithdrawAccounts::try_from(accounts)?;
+    let verified = verify_withdraw(&accounts, data, 0)?;
+
+    execute_withdraw(&accounts, &verified, accounts.recipient)?;
+    pay_rebate(accounts.state, accounts.recipient, accounts.rest)?;
+    verified.set_return_data();
+
+    log!("Withdraw successful");
+    Ok(())
+}
+
+/// Spends a verified note: creates the nullifier PDA and moves the payout from
+/// the vault to `destination` (the recipient itself, or its escrow).
+pub(crate) fn execute_withdraw(
+    accounts: &WithdrawAccounts,
+    verified: &VerifiedWithdraw,
+    destination: &AccountView,
+) -> ProgramResult {
+    let WithdrawAccounts {
+        payer,
+        recipient,
+        vault,
+        state: state_account,
+        nullifier: nullifier_account,
+        recipient_limit: recipient_limit_account,
+        fee_vault,
+        ..
+    } = *accounts;
+    let amount_u64 = verified.amount;
+
+    // Initialize nullifier account after proof verification, or mark a
+    // reserved one spent.
+    if verified.nullifier_reserved {
+        nullifier_account.try_borrow_mut()?[0] = NULLIFIER_SPENT;
+    } else {
+        let bump_seed = [verified.nullifier_bump];
+        let nullifier_seeds =
+            NullifierSeeds::nullifier(&VaultHeader::NATIVE_ASSET_ID, &verified.nullifier);
+        let (seeds, seed_count) = nullifier_seeds.with_bump(&bump_seed);
+        let signer = [Signer::from(&seeds[..seed_count])];
+
+        CreateAccount {
+            from: payer,
+            to: nullifier_account,
+            lamports: verified.nullifier_rent,
+            space: 0,
+            owner: &crate::ID,
+        }
+        .invoke_signed(&signer)?;
+    }
+
+    if verified.fee > 0 {
+        create_fee_vault_if_missing(payer, fee_vault)?;
+    }
+
+    // Transfer SOL from the vault to the destination, and the fee to the fee
+    // vault. The vault is owned by this program, so the system program cannot
+    // debit it; the runtime lets the owner debit it directly, and crediting
+    // works for any writable account, wallets included.
+    let payout = amount_u64 - verified.fee;
+    let new_destination_balance = destination
+        .lamports()
+        .checked_add(payout)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    debit_vault(vault, amount_u64, &runtime::rent()?)?;
+    destination.set_lamports(new_destination_balance);
+    if verified.fee > 0 {
+        fee_vault.set_lamports(
+            fee_vault
+                .lamports()
+                .checked_add(verified.fee)
+                .ok_or(ProgramError::ArithmeticOverflow)?,
+        );
+    }
+
+    if let Some(update) = &verified.recipient_limit {
+        write_recipient_limit(payer, recipient, recipient_limit_account, update)?;
+    }
+
+    {
+        let mut state_data = state_account.try_borrow_mut()?;
+        let (state, extra_roots) = split_state_mut(&mut state_data);
+        state.withdraw_count = state.withdraw_count.saturating_add(1);
+        state.total_shielded = state.total_shielded.saturating_sub(amount_u64);
+
+        // Insert the change note, as a deposit would.
+        if let Some(change_root) = verified.change_root {
+            state.add_root(extra_roots, change_root, verified.slot);
+            state.next_leaf_index += 1;
+        }
+        state.normalize();
+    }
+
+    WithdrawEvent {
+        nullifier: verified.nullifier,
+        recipient: recipient.address().clone(),
+        amount: payout,
+        nullifier_account: nullifier_account.address().clone(),
+    }
+    .emit();
+
+    Ok(())
+}
+
+/// Withdraw instruction data split into its parts, with the public inputs
+/// every withdraw reads.
+pub struct WithdrawInputs<'a> {
+    /// `[proof][witness]`, passed to the verifier as is
+    pub payload: &'a [u8],
+    /// All public inputs, including the ones only the verifier checks
+    pub inputs: PublicInputs<'a>,
+    pub root: [u8; 32],
+    pub nullifier: [u8; 32],
+    pub recipient: [u8; 32],
+    pub amount: FieldElement,
+    pub wa_commitment: [u8; 32],
+    /// Zero unless the circuit outputs a change note
+    pub change_commitment: [u8; 32],
+    /// `[prev_root][new_root]` for the change note, or empty
+    pub change_roots: &'a [u8],
+    pub vault_bump: Option<u8>,
+    pub audit_bump: Option<u8>,
+}
+
+/// Parse withdraw instruction data:
+/// `[withdraw_proof][withdraw_witness][change_roots?][vault_bump, audit_bump?]`.
+/// (Audit proof is removed.) Every length comes from the witness header and
+/// is bounds-checked, so malformed data is an error rather than a panic.
+/// Inputs past the ones read here are only verified. The optional bumps let
+/// the vault and audit record checks skip the bump search. `order` gives the
+/// witness position of each of the first `PUBLIC_INPUTS` inputs.
+pub fn parse_withdraw_witness<'a>(
+    data: &'a [u8],
+    proof_len: usize,
+    order: &[u8; PUBLIC_INPUTS],
+) -> Result<WithdrawInputs<'a>, ProgramError> {
+    let (payload, trailer) = payload_len(data, proof_len)
+        .ok()
+        .and_then(|len| data.split_at_checked(len))
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let (change_roots, vault_bump, audit_bump) = match *trailer {
+        [ref change_roots @ .., vault_bump, audit_bump]
+            if change_roots.is_empty() || change_roots.len() == 64 =>
+        {
+            (change_roots, Some(vault_bump), Some(audit_bump))
+        }
+        _ => (trailer, None, None),
+    };
+    let inputs = parse_public_inputs(payload, proof_len, PUBLIC_INPUTS)?;
+
+    let change_commitment = if inputs.len() > CHANGE_COMMITMENT_INPUT {
+        inputs.get(CHANGE_COMMITMENT_INPUT)?.0
+    } else {
+        [0u8; 32]
     };
 
+    let input = |logical: usize| inputs.get(order[logical] as usize);
+    Ok(WithdrawInputs {
+        payload,
+        root: input(0)?.0,
+        nullifier: input(1)?.0,
+        recipient: input(2)?.0,
+        amount: input(3)?,
+        wa_commitment: input(4)?.0,
+        change_commitment,
+        inputs,
+        change_roots,
+        vault_bump,
+        audit_bump,
+    })
+}
+
+/// Runs every withdraw check (accounts, audit record, root, nullifier, recipient,
+/// vault balance) and the verifier CPI, without mutating any account. Up to
+/// `max_splits` split payouts are accepted; the caller pays them.
+pub(crate) fn verify_withdraw(
+    accounts: &WithdrawAccounts,
+    data: &[u8],
+    max_splits: usize,
+) -> Result<VerifiedWithdraw, ProgramError> {
+    let WithdrawAccounts {
+        payer,
+        recipient,
+        vault,
+        state: state_account,
+        nullifier: nullifier_account,
+        zk_verifier,
+        audit_record: audit_record_account,
+        nullifier_set,
+        denylist_entry,
+        recipient_limit: recipient_limit_account,
+        fee_vault,
+        relayer_entry,
+        system_program,
+        ..
+    } = *accounts;
+
+    check_system_program(system_program)?;
+
     if !payer.is_signer() {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
+    // Aliased accounts would double-count lamports or conflict on borrows.
+    // Recipient vs vault/state/fee vault is reported as `InvalidRecipient`
+    // further down. The payer may withdraw to itself: it only funds the
+    // nullifier account, which is created before the payout is credited.
+    let payer_key = payer.address();
+    if payer_key == vault.address()
+        || payer_key == state_account.address()
+        || payer_key == nullifier_account.address()
+        || payer_key == fee_vault.address()
+        || nullifier_account.address() == recipient.address()
+        || nullifier_account.address() == vault.address()
+        || nullifier_account.address() == state_account.address()
+        || vault.address() == state_account.address()
+    {
+        log_error!("Duplicate account in withdraw");
+        return Err(ShieldedPoolError::DuplicateAccount.into());
+    }
+
     if !recipient.is_writable()
         || !vault.is_writable()
         || !nullifier_account.is_writable()
@@ -40,193 +445,636 @@ pub fn process_withdraw(accounts: &[AccountView], data: &[u8]) -> ProgramResult
         return Err(ProgramError::InvalidAccountData);
     }
 
-    log("Processing Withdraw");
-
-    // Verify ZK verifier program ID.
-    if zk_verifier.address() != &ZK_VERIFIER_PROGRAM_ID {
-        return Err(ProgramError::IncorrectProgramId);
+    // Audit records are shared by every withdrawal of the same identity and
+    // are only ever read; a writable meta would needlessly serialize those
+    // withdrawals and invite accidental writes.
+    if audit_record_account.is_writable() {
+        log_error!("Audit record must be read-only");
+        return Err(ProgramError::InvalidAccountData);
     }
 
     // Load state and verify the root.
-    if !state_account.owned_by(&crate::ID) {
-        return Err(ProgramError::InvalidAccountOwner);
-    }
-
-    let mut state_data = state_account.try_borrow_mut()?;
-    let state: &mut ShieldedPoolState =
-        bytemuck::from_bytes_mut(&mut state_data[..ShieldedPoolState::LEN]);
+    let state = load_state(state_account, &crate::ID)?;
 
-    if !state.is_initialized() {
-        return Err(ProgramError::UninitializedAccount);
+    // The verifier account picks the proof system: the built-in verifier, or
+    // the pool's alternate one during a circuit transition. Each has its own
+    // proof length; the public input count comes from the pool config.
+    let proof_len = if zk_verifier.address() == &ZK_VERIFIER_PROGRAM_ID {
+        state.proof_len
+    } else if state.alt_proof_len > 0 && zk_verifier.address().as_array() == &state.alt_verifier {
+        state.alt_proof_len
+    } else {
+        log_error!("Unknown withdraw verifier");
+        return Err(ProgramError::IncorrectProgramId);
+    };
+    let WithdrawInputs {
+        payload,
+        inputs,
+        root: submitted_root,
+        nullifier: submitted_nullifier,
+        recipient: submitted_recipient,
+        amount: submitted_amount,
+        wa_commitment: wa_commitment_withdraw,
+        change_commitment,
+        change_roots,
+        vault_bump,
+        audit_bump,
+    } = parse_withdraw_witness(data, proof_len as usize, &state.public_input_order)
+        .inspect_err(|_| log_error!("Invalid instruction data length"))?;
+    if inputs.len() != state.public_input_count as usize {
+        log_error!("Unexpected public input count");
+        return Err(ProgramError::InvalidInstructionData);
     }
 
-    // Instruction data layout: [withdraw_proof][withdraw_witness]
-    // (Audit proof is removed)
-    const TOTAL_DATA_LEN: usize = PROOF_LEN + WITNESS_LEN;
-    if data.len() != TOTAL_DATA_LEN {
-        log("Invalid instruction data length");
-        return Err(ProgramError::InvalidInstructionData);
+    if inputs.len() > POOL_DOMAIN_INPUT
+        && inputs.get(POOL_DOMAIN_INPUT)?.0 != address_input(state_account.address())
+    {
+        log_error!("Proof is bound to another pool");
+        return Err(ShieldedPoolError::PublicInputMismatch.into());
     }
 
-    let proof_len = PROOF_LEN;
-    let inputs_start = proof_len + WITNESS_HEADER_LEN;
+    // Read the clock once, and only if the root age check, the change note or
+    // the recipient limit needs the slot.
+    let slot = if state.min_deposit_age_slots > 0
+        || change_commitment != [0u8; 32]
+        || state.recipient_max_per_window > 0
+    {
+        runtime::clock()?.slot
+    } else {
+        0
+    };
 
-    let submitted_root: [u8; 32] = data[inputs_start..inputs_start + 32]
-        .try_into()
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
-    let submitted_nullifier: [u8; 32] = data[inputs_start + 32..inputs_start + 64]
-        .try_into()
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
-    let submitted_recipient: [u8; 32] = data[inputs_start + 64..inputs_start + 96]
-        .try_into()
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
-    let submitted_amount: [u8; 32] = data[inputs_start + 96..inputs_start + 128]
-        .try_into()
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    // Verify root against state history, including any entries stored past
+    // the state by `GrowRootHistory`. The account was validated above.
+    let state_data = state_account.try_borrow()?;
+    let (_, extra_roots) = split_state(&state_data);
+    let Some(accepted_root) = state.find_root(extra_roots, &submitted_root) else {
+        log_error!("Invalid Merkle Root");
+        return Err(ShieldedPoolError::PublicInputMismatch.into());
+    };
 
-    // Extract wa_commitment from withdraw witness (5th public input)
-    let wa_commitment_withdraw: [u8; 32] = data[inputs_start + 128..inputs_start + 160]
-        .try_into()
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    // Look up when the root was added only if a check below needs it. Only
+    // the never-deposited initial root is missing from history.
+    let root_slot = if state.min_deposit_age_slots > 0 || state.audit_required_from_slot > 0 {
+        state.root_slot(extra_roots, &submitted_root).unwrap_or(0)
+    } else {
+        0
+    };
 
     // --- Audit Verification Logic (New) ---
-    // 1. Verify PDA of audit_record_account using wa_commitment_withdraw
-    let (derived_audit_pda, _) =
-        Address::find_program_address(&[b"audit", &wa_commitment_withdraw], &crate::ID);
+    // The audit record PDA for wa_commitment_withdraw must exist and match.
+    // Audits apply to roots added from `audit_required_from_slot` on. A note
+    // deposited later is only in such roots, so it always needs a record;
+    // older notes may still spend against an older root without one.
+    let audited_wa_commitment = if root_slot >= state.audit_required_from_slot {
+        // A record passed for another identity than the witness names is a
+        // mismatch, not a missing record.
+        if audit_record_account.owned_by(&crate::ID)
+            && audit_record_account
+                .try_borrow()?
+                .get(8..40)
+                .is_some_and(|stored| stored != wa_commitment_withdraw)
+        {
+            log_error!("Audit Record is for another wa_commitment");
+            return Err(ShieldedPoolError::PublicInputMismatch.into());
+        }
+        let record = load_audit_record(
+            audit_record_account,
+            &crate::ID,
+            &wa_commitment_withdraw,
+            audit_bump,
+        )?;
+        log!("Audit Record verified");
+        record.wa_commitment
+    } else {
+        log!("Root predates audits");
+        wa_commitment_withdraw
+    };
+    // --------------------------------------
 
-    if audit_record_account.address() != &derived_audit_pda {
-        log("Invalid Audit Record PDA");
-        return Err(ProgramError::InvalidAccountData);
+    // Enforce a minimum dwell time so deposits and withdrawals can't be paired
+    // by timing.
+    if state.min_deposit_age_slots > 0 {
+        let age = slot.saturating_sub(root_slot);
+        if age < state.min_deposit_age_slots {
+            log_error!("Root too recent");
+            return Err(ShieldedPoolError::RootTooRecent.into());
+        }
     }
 
-    if !audit_record_account.owned_by(&crate::ID) {
-        log("Audit Record not owned by program");
-        return Err(ProgramError::InvalidAccountOwner);
+    // Verify nullifier PDA (prevents double spend). Its seeds are scoped to
+    // the vault's asset, which `check_vault` holds to native SOL.
+    let (derived_nullifier_pda, bump) =
+        NullifierSeeds::nullifier(&VaultHeader::NATIVE_ASSET_ID, &submitted_nullifier)
+            .find_address(&crate::ID);
+
+    if nullifier_account.address() != &derived_nullifier_pda {
+        log_error!("Nullifier account is not the witness nullifier's PDA");
+        return Err(ShieldedPoolError::PublicInputMismatch.into());
     }
 
-    // 2. Check if Audit Record is initialized and matches
-    if audit_record_account.lamports() == 0 {
-        log("Audit Record not found (Submission required)");
-        return Err(ProgramError::UninitializedAccount);
+    // A nullifier reserved by `ReserveNullifier` is not spent: the withdrawal
+    // finalizes it rather than creating it, so a reservation cannot block
+    // the note.
+    let nullifier_reserved =
+        nullifier_account.lamports() > 0 && is_nullifier_reserved(nullifier_account, &crate::ID)?;
+    if nullifier_account.lamports() > 0 && !nullifier_reserved {
+        log_error!("Nullifier already used");
+        return Err(ProgramError::AccountAlreadyInitialized);
     }
 
-    let audit_data = audit_record_account.try_borrow()?;
-    if audit_data.len() < AuditRecord::LEN {
+    // Nullifiers whose PDA was reclaimed live on in their nullifier-set shard.
+    let (derived_set_pda, _) =
+        NullifierSeeds::shard(&VaultHeader::NATIVE_ASSET_ID, &submitted_nullifier)
+            .find_address(&crate::ID);
+    if nullifier_set.address() != &derived_set_pda {
         return Err(ProgramError::InvalidAccountData);
     }
 
-    let audit_rec: &AuditRecord = bytemuck::from_bytes(&audit_data[..AuditRecord::LEN]);
-    if !audit_rec.is_initialized() {
-        return Err(ProgramError::UninitializedAccount);
+    if nullifier_set.owned_by(&crate::ID)
+        && NullifierSet::contains(&nullifier_set.try_borrow()?, &submitted_nullifier)
+    {
+        log_error!("Nullifier already used");
+        return Err(ProgramError::AccountAlreadyInitialized);
     }
-    if audit_rec.wa_commitment != wa_commitment_withdraw {
-        log("Audit Record mismatch");
-        return Err(ProgramError::InvalidAccountData);
+
+    // What every payout address must satisfy; checked once the state is
+    // released, for the recipient here and for split recipients by the caller.
+    let policy = RecipientPolicy {
+        require_signature: state.require_recipient_signature != 0,
+        require_wallet: state.require_wallet_recipient != 0,
+        program: if inputs.len() > RECIPIENT_PROGRAM_INPUT {
+            inputs.get(RECIPIENT_PROGRAM_INPUT)?.0
+        } else {
+            [0u8; 32]
+        },
+        owner: if state.owner_gated != 0 {
+            Some(inputs.get(OWNER_INPUT)?.0)
+        } else {
+            None
+        },
+        denylist_active: state.denylist_count > 0,
+    };
+
+    // Decode amount from the field element; it must fit in a u64 so the
+    // amount paid equals the proven value.
+    let amount_u64 = submitted_amount
+        .to_u64_be()
+        .inspect_err(|_| log_error!("Amount field element exceeds u64"))?;
+
+    // A zero payout would burn the note and the nullifier rent for nothing;
+    // moving value purely between notes is not a withdrawal.
+    if amount_u64 == 0 {
+        log_error!("Withdrawal amount is zero");
+        return Err(ShieldedPoolError::ZeroAmount.into());
     }
 
-    log("Audit Record verified");
-    // --------------------------------------
+    // The minimum applies to the gross amount, before any fee is split off.
+    if amount_u64 < state.min_withdraw {
+        log_error!("Withdrawal below minimum");
+        return Err(ShieldedPoolError::BelowMinWithdraw.into());
+    }
 
-    // Verify root against state history.
-    if !state.check_root(&submitted_root) {
-        log("Invalid Merkle Root");
-        return Err(ProgramError::InvalidAccountData);
+    // The recipient gets the gross amount less the withdraw fee; both come
+    // out of the vault, so the rent floor check below covers their sum.
+    let fee = state.withdraw_fee(amount_u64);
+    if amount_u64 - fee == 0 {
+        log_error!("Withdrawal amount does not cover the fee");
+        return Err(ProgramError::InsufficientFunds);
     }
 
-    // Verify nullifier PDA (prevents double spend).
-    let (derived_nullifier_pda, bump) =
-        Address::find_program_address(&[b"nullifier", &submitted_nullifier], &crate::ID);
+    // Further payouts of a split withdrawal, charged the fee like the first.
+    let mut splits = [SplitPayout::default(); MAX_SPLIT_PAYOUTS];
+    let mut split_count = 0;
+    let mut split_total = 0u64;
+    let mut split_fees = 0u64;
+    for index in (SPLIT_INPUT..inputs.len().min(RECIPIENT_PROGRAM_INPUT)).step_by(2) {
+        let recipient = inputs.get(index)?.0;
+        let amount = inputs
+            .get(index + 1)?
+            .to_u64_be()
+            .inspect_err(|_| log_error!("Amount field element exceeds u64"))?;
+        if amount == 0 {
+            if recipient != [0u8; 32] {
+                log_error!("Split payout amount is zero");
+                return Err(ShieldedPoolError::ZeroAmount.into());
+            }
+            continue;
+        }
+        if split_count == max_splits {
+            log_error!("Split payouts need WithdrawSplit");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let split_fee = state.withdraw_fee(amount);
+        if amount - split_fee == 0 {
+            log_error!("Split payout does not cover the fee");
+            return Err(ProgramError::InsufficientFunds);
+        }
+        splits[split_count] = SplitPayout {
+            recipient,
+            amount,
+            fee: split_fee,
+        };
+        split_count += 1;
+        split_total = split_total
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        split_fees += split_fee;
+    }
 
-    if nullifier_account.address() != &derived_nullifier_pda {
+    if (fee > 0 || split_fees > 0) && !fee_vault.is_writable() {
         return Err(ProgramError::InvalidAccountData);
     }
 
-    if nullifier_account.lamports() > 0 {
-        log("Nullifier already used");
-        return Err(ProgramError::AccountAlreadyInitialized);
+    // With a relayer registry, only registered relayers may submit
+    // withdrawals that pay a fee; fee-free ones stay open to anyone. The
+    // registry gates the submitter only: the fee is the pool's and still
+    // goes to the fee vault.
+    if state.relayer_registry != 0 && (fee > 0 || split_fees > 0) {
+        check_registered_submitter(relayer_entry, payer)?;
     }
 
-    // Verify recipient encoding used by the client.
-    let mut expected_recipient = [0u8; 32];
-    expected_recipient[2..32].copy_from_slice(&recipient.address().as_ref()[0..30]);
-    if submitted_recipient != expected_recipient {
-        return Err(ProgramError::InvalidAccountData);
+    // The circuit proves note_value = amount (+ split amounts) + change_value;
+    // the withdraw fee comes out of each gross amount. Like a deposit, the
+    // client inserts the change commitment off-chain and appends
+    // [prev_root: [u8; 32]][new_root: [u8; 32]] after the witness.
+    let change_root = if change_commitment != [0u8; 32] {
+        if change_roots.len() != 64 {
+            log_error!("Missing change note roots");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if !state.has_capacity() {
+            log_error!("Commitment tree is full");
+            return Err(ShieldedPoolError::TreeFull.into());
+        }
+        if change_roots[..32] != state.current_root {
+            log_error!("Change note built on a stale root");
+            return Err(ShieldedPoolError::DepositRootRace.into());
+        }
+        if change_roots[32..] == state.current_root {
+            log_error!("New root equals current root");
+            return Err(ShieldedPoolError::StaleRoot.into());
+        }
+        let mut new_root = [0u8; 32];
+        new_root.copy_from_slice(&change_roots[32..]);
+        Some(new_root)
+    } else {
+        if !change_roots.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        None
+    };
+
+    let recipient_max_per_window = state.recipient_max_per_window;
+    let recipient_window_slots = state.recipient_window_slots;
+    let min_verifier_compute_units = state.min_verifier_compute_units;
+    let public_input_order = state.public_input_order;
+
+    // Last use of the state; release it before any CPI so a bundled
+    // instruction or the caller can borrow it again.
+    drop(state_data);
+    drop(state);
+
+    // Verify the vault and that it can cover the payout while staying
+    // rent-exempt.
+    let rent = runtime::rent()?;
+    let withdrawable = check_vault_withdrawable(
+        vault,
+        &crate::ID,
+        state_account.address(),
+        vault_bump,
+        &rent,
+    )?;
+    let total_amount = amount_u64
+        .checked_add(split_total)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if total_amount > withdrawable {
+        return Err(ProgramError::InsufficientFunds);
     }
 
-    // Decode amount from the field element (big-endian, last 8 bytes).
-    let amount_u64 = u64::from_be_bytes(
-        submitted_amount[24..32]
-            .try_into()
-            .map_err(|_| ProgramError::InvalidInstructionData)?,
-    );
+    // Rebuild the public inputs from sources independent of the witness and
+    // require the witness to match them exactly, so the proof the verifier
+    // checks is bound to what this program accepted: the root as stored in
+    // the history, the nullifier whose PDA is the nullifier account, the
+    // recipient account, the amount as paid and the audited identity.
+    let mut expected_inputs = [change_commitment; PUBLIC_INPUTS + 1];
+    for (position, value) in public_input_order.iter().zip([
+        accepted_root,
+        submitted_nullifier,
+        address_input(recipient.address()),
+        FieldElement::from_u64(amount_u64).0,
+        audited_wa_commitment,
+    ]) {
+        *expected_inputs
+            .get_mut(*position as usize)
+            .ok_or(ProgramError::InvalidAccountData)? = value;
+    }
+    let enforced_count = if inputs.len() > CHANGE_COMMITMENT_INPUT {
+        CHANGE_COMMITMENT_INPUT + 1
+    } else {
+        PUBLIC_INPUTS
+    };
+    if !inputs.starts_with(&expected_inputs[..enforced_count]) {
+        log_error!("Witness public inputs do not match enforced values");
+        return Err(ShieldedPoolError::PublicInputMismatch.into());
+    }
+
+    policy.check(accounts, recipient, &submitted_recipient, denylist_entry)?;
+
+    let recipient_limit = if recipient_max_per_window > 0 {
+        Some(check_recipient_limit(
+            recipient_limit_account,
+            recipient,
+            amount_u64 - fee,
+            recipient_max_per_window,
+            recipient_window_slots,
+            slot,
+            &rent,
+        )?)
+    } else {
+        None
+    };
+
+    // The payer funds the nullifier account unless it was reserved; fail
+    // before the costly CPI if it can't.
+    let nullifier_rent = rent.try_minimum_balance(0)?;
+    if !nullifier_reserved && payer.lamports() < nullifier_rent {
+        log_error!("Payer cannot fund nullifier rent");
+        return Err(ShieldedPoolError::InsufficientRentForNullifier.into());
+    }
+
+    // Fail fast with a clear error instead of the verifier running out of
+    // compute units mid-proof.
+    if min_verifier_compute_units > 0
+        && runtime::remaining_compute_units() < min_verifier_compute_units
+    {
+        log_error!("Not enough compute units left for the verifier; raise the compute unit limit");
+        return Err(ShieldedPoolError::InsufficientComputeBudget.into());
+    }
 
     // CPI to ZK verifier.
-    log("Verifying ZK proof...");
-    let proof_data = &data[0..PROOF_LEN];
-    let witness_data = &data[PROOF_LEN..];
-    let mut verifier_data = [0u8; PROOF_LEN + WITNESS_LEN];
-    verifier_data[..PROOF_LEN].copy_from_slice(proof_data);
-    verifier_data[PROOF_LEN..].copy_from_slice(witness_data);
+    log!("Verifying ZK proof...");
     let verify_ix = InstructionView {
         program_id: zk_verifier.address(),
         accounts: &[],
-        data: &verifier_data,
+        data: payload,
     };
     invoke(&verify_ix, &[])?;
 
-    // Initialize nullifier account after proof verification.
-    let rent = Rent::get()?;
-    let space = 0;
-    let lamports = rent.try_minimum_balance(space)?;
-
-    let bump_seed = [bump];
-    let seeds = [
-        Seed::from(b"nullifier"),
-        Seed::from(&submitted_nullifier),
-        Seed::from(&bump_seed),
-    ];
-    let signer = [Signer::from(&seeds)];
-
-    CreateAccount {
-        from: payer,
-        to: nullifier_account,
-        lamports,
-        space: 0,
-        owner: &crate::ID,
-    }
-    .invoke_signed(&signer)?;
-
-    // Transfer SOL from the vault to the recipient.
-    if vault.address() != &Address::find_program_address(&[b"vault"], &crate::ID).0 {
+    Ok(VerifiedWithdraw {
+        nullifier: submitted_nullifier,
+        nullifier_bump: bump,
+        amount: amount_u64,
+        fee,
+        change_root,
+        nullifier_rent,
+        nullifier_reserved,
+        slot,
+        recipient_limit,
+        policy,
+        splits,
+        split_count,
+    })
+}
+
+/// The checks every payout address of a withdrawal must pass, read from the
+/// pool config and the witness. `Withdraw` applies them to its recipient and
+/// `WithdrawSplit` to each further one, so no payout escapes them.
+#[derive(Clone, Copy)]
+pub(crate) struct RecipientPolicy {
+    /// Recipients must sign as proof of intent, ruling out relayed withdrawals
+    pub require_signature: bool,
+    /// Recipients must be wallets (system-owned)
+    pub require_wallet: bool,
+    /// Program bound by `RECIPIENT_PROGRAM_INPUT` to own the recipients; zero
+    /// if unbound
+    pub program: [u8; 32],
+    /// Note owner of an owner-gated pool, who must sign
+    pub owner: Option<[u8; 32]>,
+    pub denylist_active: bool,
+}
+
+impl RecipientPolicy {
+    /// Checks that `recipient` is the payout address the witness binds as
+    /// `recipient_input` and that the pool may pay it.
+    pub(crate) fn check(
+        &self,
+        accounts: &WithdrawAccounts,
+        recipient: &AccountView,
+        recipient_input: &[u8; 32],
+        denylist_entry: &AccountView,
+    ) -> ProgramResult {
+        if address_input(recipient.address()) != *recipient_input {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if self.require_signature && !recipient.is_signer() {
+            log_error!("Recipient did not sign");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Program-owned recipients (PDAs of another program) are paid like
+        // wallets unless the pool requires wallets.
+        if !recipient.owned_by(&pinocchio_system::ID) {
+            if self.require_wallet {
+                log_error!("Recipient is not a wallet");
+                return Err(ShieldedPoolError::InvalidRecipient.into());
+            }
+            log!("Recipient is a program-owned account");
+        }
+
+        // A bound program keeps a relayer from pointing the payout at a
+        // look-alike owned by someone else. Which seeds derive the PDA is
+        // the program's business.
+        if self.program != [0u8; 32] && address_input(&owner_of(recipient)) != self.program {
+            log_error!("Recipient is not owned by the bound program");
+            return Err(ShieldedPoolError::InvalidRecipient.into());
+        }
+
+        // The note owner signs as the payer (self-relayed), as the proof's
+        // recipient or as this one.
+        if let Some(owner) = self.owner {
+            if ![accounts.payer, accounts.recipient, recipient]
+                .iter()
+                .any(|account| account.is_signer() && address_input(account.address()) == owner)
+            {
+                log_error!("Note owner did not sign");
+                return Err(ShieldedPoolError::MissingOwnerSignature.into());
+            }
+        }
+
+        // Paying out to the pool's own accounts would move funds in a circle.
+        if [
+            accounts.vault,
+            accounts.state,
+            accounts.nullifier,
+            accounts.fee_vault,
+        ]
+        .iter()
+        .any(|account| account.address() == recipient.address())
+            || recipient.address() == &crate::ID
+        {
+            log_error!("Recipient cannot be a pool account");
+            return Err(ShieldedPoolError::InvalidRecipient.into());
+        }
+
+        // The denylist entry is ignored while the denylist is empty.
+        if self.denylist_active {
+            check_denylist(denylist_entry, recipient)?;
+        }
+        Ok(())
+    }
+}
+
+/// A denylisted recipient has a live `["denylist", recipient]` PDA.
+fn check_denylist(denylist_entry: &AccountView, recipient: &AccountView) -> ProgramResult {
+    let (derived_denylist_pda, _) =
+        Address::find_program_address(&[b"denylist", recipient.address().as_ref()], &crate::ID);
+    if denylist_entry.address() != &derived_denylist_pda {
         return Err(ProgramError::InvalidAccountData);
     }
+    if denylist_entry.owned_by(&crate::ID) && denylist_entry.lamports() > 0 {
+        log_error!("Recipient is denylisted");
+        return Err(ShieldedPoolError::RecipientDenied.into());
+    }
+    Ok(())
+}
 
-    if !vault.owned_by(&crate::ID) {
-        return Err(ProgramError::InvalidAccountOwner);
+/// The withdrawal's submitter (its payer) is a registered relayer: it has a
+/// live `["relayer", payer]` PDA.
+fn check_registered_submitter(relayer_entry: &AccountView, payer: &AccountView) -> ProgramResult {
+    let (derived_relayer_pda, _) =
+        Address::find_program_address(&[b"relayer", payer.address().as_ref()], &crate::ID);
+    if relayer_entry.address() != &derived_relayer_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !relayer_entry.owned_by(&crate::ID) || relayer_entry.lamports() == 0 {
+        log_error!("Payer is not a registered relayer");
+        return Err(ShieldedPoolError::UnregisteredRelayer.into());
     }
+    Ok(())
+}
 
-    // Keep the vault rent-exempt while withdrawing.
-    let data_len = vault.data_len();
-    let min_balance = Rent::get()?.try_minimum_balance(data_len)?;
-    let withdrawable = vault
-        .lamports()
-        .checked_sub(min_balance)
-        .ok_or(ProgramError::InsufficientFunds)?;
-    if amount_u64 > withdrawable {
-        return Err(ProgramError::InsufficientFunds);
+/// The program owning `account`, copied out.
+fn owner_of(account: &AccountView) -> Address {
+    // SAFETY: the owner is copied out at once, and no withdraw reassigns or
+    // closes the recipient.
+    unsafe { account.owner() }.clone()
+}
+
+/// `address` as the circuits encode it in a public input: its first 30 bytes,
+/// right-aligned so the value stays below the field modulus.
+pub(crate) fn address_input(address: &Address) -> [u8; 32] {
+    let mut input = [0u8; 32];
+    input[2..32].copy_from_slice(&address.as_ref()[0..30]);
+    input
+}
+
+/// The recipient's window after withdrawing `amount`, or
+/// `RecipientLimitExceeded` if that would take it past `max_per_window`. A
+/// window that has run `window_slots` starts over at `slot`.
+pub(crate) fn check_recipient_limit(
+    account: &AccountView,
+    recipient: &AccountView,
+    amount: u64,
+    max_per_window: u64,
+    window_slots: u64,
+    slot: u64,
+    rent: &Rent,
+) -> Result<RecipientLimitUpdate, ProgramError> {
+    let (derived_pda, bump) = Address::find_program_address(
+        &[b"recipient_limit", recipient.address().as_ref()],
+        &crate::ID,
+    );
+    if account.address() != &derived_pda || !account.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
     }
 
-    let new_vault_balance = vault
-        .lamports()
-        .checked_sub(amount_u64)
-        .ok_or(ProgramError::InsufficientFunds)?;
-    let new_recipient_balance = recipient
-        .lamports()
-        .checked_add(amount_u64)
-        .ok_or(ProgramError::InsufficientFunds)?;
-    vault.set_lamports(new_vault_balance);
-    recipient.set_lamports(new_recipient_balance);
+    let (withdrawn, window_start_slot, create_lamports) = if account.lamports() == 0 {
+        (0, slot, rent.try_minimum_balance(RecipientLimit::LEN)?)
+    } else {
+        if !account.owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+        let data = account.try_borrow()?;
+        if data.len() != RecipientLimit::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let limit: &RecipientLimit = bytemuck::from_bytes(&data);
+        if !limit.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if slot.saturating_sub(limit.window_start_slot) >= window_slots {
+            (0, slot, 0)
+        } else {
+            (limit.withdrawn_in_window, limit.window_start_slot, 0)
+        }
+    };
+
+    let withdrawn_in_window = withdrawn
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if withdrawn_in_window > max_per_window {
+        log_error!("Recipient withdrawal limit exceeded");
+        return Err(ShieldedPoolError::RecipientLimitExceeded.into());
+    }
 
-    log("Withdraw successful");
+    Ok(RecipientLimitUpdate {
+        bump,
+        create_lamports,
+        withdrawn_in_window,
+        window_start_slot,
+    })
+}
+
+/// Create the recipient's `RecipientLimit` PDA if needed and store `update`.
+pub(crate) fn write_recipient_limit(
+    payer: &AccountView,
+    recipient: &AccountView,
+    account: &AccountView,
+    update: &RecipientLimitUpdate,
+) -> ProgramResult {
+    if update.create_lamports > 0 {
+        let bump_seed = [update.bump];
+        let seeds = [
+            Seed::from(b"recipient_limit"),
+            Seed::from(recipient.address().as_ref()),
+            Seed::from(&bump_seed),
+        ];
+        let signer = [Signer::from(&seeds)];
+
+        CreateAccount {
+            from: payer,
+            to: account,
+            lamports: update.create_lamports,
+            space: RecipientLimit::LEN as u64,
+            owner: &crate::ID,
+        }
+        .invoke_signed(&signer)?;
+    }
+
+    let mut data = account.try_borrow_mut()?;
+    let limit: &mut RecipientLimit = bytemuck::from_bytes_mut(&mut data[..RecipientLimit::LEN]);
+    limit.discriminator = RecipientLimit::DISCRIMINATOR;
+    limit.withdrawn_in_window = update.withdrawn_in_window;
+    limit.window_start_slot = update.window_start_slot;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_input_keeps_the_first_30_bytes_right_aligned() {
+        let bytes: [u8; 32] = core::array::from_fn(|i| 0xe0 | i as u8);
+        let input = address_input(&Address::new_from_array(bytes));
+        assert_eq!(input[..2], [0u8; 2]);
+        assert_eq!(input[2..], bytes[..30]);
+
+        // Addresses differing only in their last two bytes share an input.
+        let mut other = bytes;
+        other[30..].copy_from_slice(&[0, 0]);
+        assert_eq!(address_input(&Address::new_from_array(other)), input);
+    }
+}
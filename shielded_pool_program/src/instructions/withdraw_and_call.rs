@@ -0,0 +1,89 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_instruction_view::{InstructionAccount, InstructionView};
+use solana_program_error::ProgramError;
+
+use super::{
+    check_account_count,
+    configure_rebate::check_rebate_disabled,
+    withdraw::{execute_withdraw, verify_withdraw, WithdrawAccounts, WITHDRAW_ACCOUNTS},
+};
+
+use crate::runtime::invoke_with_bounds;
+
+/// Most accounts forwarded to the target program.
+pub const MAX_CALL_ACCOUNTS: usize = 8;
+/// Largest instruction data forwarded to the target program.
+pub const MAX_CALL_DATA_LEN: usize = 256;
+
+/// Withdraws to the proof's recipient, then CPIs into a caller-chosen program
+/// in the same instruction, e.g. to deposit the funds into a lending protocol.
+///
+/// The payout still lands on the recipient bound by the proof; the target
+/// program can only move it with the recipient's own signature, forwarded
+/// from the transaction.
+pub fn process_withdraw_and_call(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    log!("Processing Withdraw And Call");
+
+    // Data layout: [call_data_len: u16][call_data][withdraw data]
+    let call_data_len = u16::from_le_bytes(
+        data.get(0..2)
+            .ok_or(ProgramError::InvalidInstructionData)?
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    ) as usize;
+    if call_data_len > MAX_CALL_DATA_LEN {
+        log_error!("Call data too long");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (call_data, withdraw_data) = data[2..]
+        .split_at_checked(call_data_len)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+
+    // Keys: the withdraw accounts, [target_program], then the accounts
+    // forwarded to it.
+    check_account_count(
+        accounts,
+        WITHDRAW_ACCOUNTS + 1,
+        WITHDRAW_ACCOUNTS + 1 + MAX_CALL_ACCOUNTS,
+    )?;
+    let (withdraw_accounts, call_accounts) = accounts
+        .split_at_checked(WITHDRAW_ACCOUNTS)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let [target_program, call_accounts @ ..] = call_accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Calling back into the pool would let one proof drive nested pool logic,
+    // and the system program would only move forwarded lamports elsewhere.
+    if target_program.address() == &crate::ID || target_program.address() == &pinocchio_system::ID {
+        log_error!("Invalid call target");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let withdraw_accounts = WithdrawAccounts::try_from(withdraw_accounts)?;
+    let verified = verify_withdraw(&withdraw_accounts, withdraw_data, 0)?;
+    check_rebate_disabled(withdraw_accounts.state)?;
+
+    execute_withdraw(&withdraw_accounts, &verified, withdraw_accounts.recipient)?;
+
+    // Forward the accounts with the signer and writable flags they carry in
+    // this transaction; the pool adds no signatures of its own.
+    let metas: [InstructionAccount; MAX_CALL_ACCOUNTS] = core::array::from_fn(|i| {
+        InstructionAccount::from(call_accounts.get(i).unwrap_or(target_program))
+    });
+    let views: [&AccountView; MAX_CALL_ACCOUNTS] =
+        core::array::from_fn(|i| call_accounts.get(i).unwrap_or(target_program));
+
+    let call_ix = InstructionView {
+        program_id: target_program.address(),
+        accounts: &metas[..call_accounts.len()],
+        data: call_data,
+    };
+    invoke_with_bounds::<MAX_CALL_ACCOUNTS>(&call_ix, &views[..call_accounts.len()])?;
+
+    // Report the withdrawal rather than whatever the target program returned.
+    verified.set_return_data();
+
+    log!("Withdraw and call successful");
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use pinocchio::{AccountView, Address, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::check_account_count;
+
+use crate::{
+    runtime,
+    state::{load_state, ShieldedPoolState},
+};
+
+/// Moves the fees accrued in the fee vault, above its rent floor, to the
+/// treasury. Only the pool authority may call it, and only to the treasury
+/// stored as `fee_collector` at initialize.
+pub fn process_withdraw_fees(accounts: &[AccountView], _data: &[u8]) -> ProgramResult {
+    // Accounts: [authority, state, fee_vault, treasury, ..co_signers]
+    check_account_count(accounts, 4, 4 + ShieldedPoolState::MAX_CO_AUTHORITIES)?;
+    let [authority, state_account, fee_vault, treasury, co_signers @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !fee_vault.is_writable() || !treasury.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    {
+        let state = load_state(state_account, &crate::ID)?;
+        state.check_authority(authority, co_signers)?;
+        if treasury.address().as_array() != &state.fee_collector {
+            log_error!("Invalid treasury");
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    if fee_vault.address() != &Address::find_program_address(&[b"fee_vault"], &crate::ID).0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !fee_vault.owned_by(&crate::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    // Keep the fee vault rent-exempt.
+    let min_balance = runtime::rent()?.try_minimum_balance(fee_vault.data_len())?;
+    let fees = fee_vault.lamports().saturating_sub(min_balance);
+    if fees == 0 {
+        log!("No fees to withdraw");
+        return Ok(());
+    }
+
+    fee_vault.set_lamports(fee_vault.lamports() - fees);
+    treasury.set_lamports(
+        treasury
+            .lamports()
+            .checked_add(fees)
+            .ok_or(ProgramError::ArithmeticOverflow)?,
+    );
+
+    log!("Fees withdrawn");
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::{
+    check_account_count,
+    configure_rebate::check_rebate_disabled,
+    initialize::create_fee_vault_if_missing,
+    withdraw::{
+        check_recipient_limit, execute_withdraw, verify_withdraw, write_recipient_limit,
+        RecipientLimitUpdate, WithdrawAccounts, MAX_SPLIT_PAYOUTS, WITHDRAW_ACCOUNTS,
+    },
+};
+use crate::{
+    error::ShieldedPoolError,
+    event::WithdrawEvent,
+    runtime,
+    state::{debit_vault, load_state, load_state_mut},
+};
+
+/// Accounts per split payout: [recipient, denylist_entry, recipient_limit].
+/// The recipient limit PDA is ignored while the pool has no per-recipient
+/// limit, as in `Withdraw`.
+pub const SPLIT_ACCOUNTS: usize = 3;
+
+/// Pays one note out to several recipients: the proof's recipient as in
+/// `Withdraw`, plus each (recipient, amount) pair the witness binds from
+/// `SPLIT_INPUT` on. One proof is verified and one nullifier created; each
+/// payout is charged the withdraw fee, and counts against its recipient's
+/// window when the pool caps withdrawals per recipient.
+pub fn process_withdraw_split(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    log!("Processing Withdraw Split");
+
+    // Keys: the withdraw accounts followed by [recipient, denylist_entry,
+    // recipient_limit] per split payout, in the witness's order.
+    check_account_count(
+        accounts,
+        WITHDRAW_ACCOUNTS + SPLIT_ACCOUNTS,
+        WITHDRAW_ACCOUNTS + SPLIT_ACCOUNTS * MAX_SPLIT_PAYOUTS,
+    )?;
+    let accounts = WithdrawAccounts::try_from(accounts)?;
+
+    let verified = verify_withdraw(&accounts, data, MAX_SPLIT_PAYOUTS)?;
+    check_rebate_disabled(accounts.state)?;
+    let splits = &verified.splits[..verified.split_count];
+    if splits.is_empty() || accounts.rest.len() != SPLIT_ACCOUNTS * splits.len() {
+        log_error!("Expected a recipient, denylist entry and limit per split payout");
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let (max_per_window, window_slots) = {
+        let state = load_state(accounts.state, &crate::ID)?;
+        (state.recipient_max_per_window, state.recipient_window_slots)
+    };
+    let rent = runtime::rent()?;
+
+    // Each split recipient passes the checks `Withdraw` applies to its own.
+    let mut limits: [Option<RecipientLimitUpdate>; MAX_SPLIT_PAYOUTS] = Default::default();
+    let split_accounts = accounts.rest.chunks_exact(SPLIT_ACCOUNTS);
+    for (index, (split, split_accounts)) in splits.iter().zip(split_accounts).enumerate() {
+        let [recipient, denylist_entry, recipient_limit] = split_accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        if !recipient.is_writable() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        verified
+            .policy
+            .check(&accounts, recipient, &split.recipient, denylist_entry)?;
+
+        if max_per_window > 0 {
+            // Each window update is computed from the stored window, so a
+            // recipient paid twice would only be charged once.
+            let mut earlier = core::iter::once(accounts.recipient).chain(
+                accounts
+                    .rest
+                    .chunks_exact(SPLIT_ACCOUNTS)
+                    .take(index)
+                    .map(|split_accounts| &split_accounts[0]),
+            );
+            if earlier.any(|earlier| earlier.address() == recipient.address()) {
+                log_error!("Limited recipient paid twice in one split");
+                return Err(ShieldedPoolError::DuplicateAccount.into());
+            }
+            limits[index] = Some(check_recipient_limit(
+                recipient_limit,
+                recipient,
+                split.amount - split.fee,
+                max_per_window,
+                window_slots,
+                verified.slot,
+                &rent,
+            )?);
+        }
+    }
+
+    execute_withdraw(&accounts, &verified, accounts.recipient)?;
+
+    let WithdrawAccounts {
+        payer,
+        vault,
+        state: state_account,
+        nullifier,
+        fee_vault,
+        rest,
+        ..
+    } = accounts;
+
+    // The vault was checked to cover every payout; `execute_withdraw` paid
+    // the first.
+    if splits.iter().any(|split| split.fee > 0) {
+        create_fee_vault_if_missing(payer, fee_vault)?;
+    }
+    let mut split_total = 0u64;
+    let split_accounts = rest.chunks_exact(SPLIT_ACCOUNTS);
+    for ((split, split_accounts), limit) in splits.iter().zip(split_accounts).zip(&limits) {
+        let recipient = &split_accounts[0];
+        let payout = split.amount - split.fee;
+        debit_vault(vault, split.amount, &rent)?;
+        recipient.set_lamports(
+            recipient
+                .lamports()
+                .checked_add(payout)
+                .ok_or(ProgramError::ArithmeticOverflow)?,
+        );
+        if split.fee > 0 {
+            fee_vault.set_lamports(
+                fee_vault
+                    .lamports()
+                    .checked_add(split.fee)
+                    .ok_or(ProgramError::ArithmeticOverflow)?,
+            );
+        }
+        split_total += split.amount;
+        if let Some(update) = limit {
+            write_recipient_limit(payer, recipient, &split_accounts[2], update)?;
+        }
+
+        WithdrawEvent {
+            nullifier: verified.nullifier,
+            recipient: recipient.address().clone(),
+            amount: payout,
+            nullifier_account: nullifier.address().clone(),
+        }
+        .emit();
+    }
+
+    {
+        let mut state = load_state_mut(state_account, &crate::ID)?;
+        state.total_shielded = state.total_shielded.saturating_sub(split_total);
+        state.normalize();
+    }
+    verified.set_return_data();
+
+    log!("Withdraw split successful");
+    Ok(())
+}
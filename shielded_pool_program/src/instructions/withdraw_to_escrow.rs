@@ -0,0 +1,79 @@
+use pinocchio::{
+    cpi::{Seed, Signer},
+    AccountView, Address, ProgramResult,
+};
+use solana_program_error::ProgramError;
+
+use super::{
+    check_account_count,
+    configure_rebate::check_rebate_disabled,
+    withdraw::{execute_withdraw, verify_withdraw, WithdrawAccounts, WITHDRAW_ACCOUNTS},
+};
+
+use crate::runtime::{self, system::CreateAccount};
+
+/// Same as `Withdraw`, but the payout is held in the recipient's escrow PDA
+/// until the recipient calls `ClaimEscrow`. Repeated withdrawals to the same
+/// recipient accumulate in one escrow.
+pub fn process_withdraw_to_escrow(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    log!("Processing Withdraw To Escrow");
+
+    check_account_count(accounts, WITHDRAW_ACCOUNTS + 1, WITHDRAW_ACCOUNTS + 1)?;
+    let accounts = WithdrawAccounts::try_from(accounts)?;
+    let verified = verify_withdraw(&accounts, data, 0)?;
+    check_rebate_disabled(accounts.state)?;
+
+    // Keys: the withdraw accounts followed by [escrow]
+    let WithdrawAccounts {
+        payer,
+        recipient,
+        rest: [escrow],
+        ..
+    } = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !escrow.is_writable() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (derived_escrow_pda, escrow_bump) =
+        Address::find_program_address(&[b"escrow", recipient.address().as_ref()], &crate::ID);
+    if escrow.address() != &derived_escrow_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Create the escrow on first use; otherwise the payout is added on top.
+    if escrow.lamports() == 0 {
+        let lamports = runtime::rent()?.try_minimum_balance(0)?;
+
+        let bump_seed = [escrow_bump];
+        let seeds = [
+            Seed::from(b"escrow"),
+            Seed::from(recipient.address().as_ref()),
+            Seed::from(&bump_seed),
+        ];
+        let signer = [Signer::from(&seeds)];
+
+        log!("Creating escrow");
+        CreateAccount {
+            from: payer,
+            to: escrow,
+            lamports,
+            space: 0,
+            owner: &crate::ID,
+        }
+        .invoke_signed(&signer)?;
+    }
+
+    if !escrow.owned_by(&crate::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    execute_withdraw(&accounts, &verified, escrow)?;
+    verified.set_return_data();
+
+    log!("Withdraw to escrow successful");
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use pinocchio::{AccountView, ProgramResult};
+use solana_program_error::ProgramError;
+
+use super::{
+    check_account_count,
+    configure_rebate::{pay_rebate, REBATE_ACCOUNTS},
+    withdraw::{execute_withdraw, verify_withdraw, WithdrawAccounts, WITHDRAW_ACCOUNTS},
+};
+
+use crate::event::WithdrawMemoEvent;
+
+/// Longest memo `WithdrawWithMemo` logs.
+pub const MAX_WITHDRAW_MEMO_LEN: usize = 128;
+
+/// Same as `Withdraw`, plus a plaintext memo the receiving service can use to
+/// attribute the funds. The memo is not bound to the proof: it is only
+/// length-checked and logged in a `WithdrawMemoEvent` after the withdraw
+/// event, so anyone relaying the transaction can change it.
+pub fn process_withdraw_with_memo(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+    log!("Processing Withdraw With Memo");
+
+    // Data layout: [memo_len: u8] [memo] [withdraw data, as for `Withdraw`]
+    let (memo, data) = data
+        .split_first()
+        .and_then(|(&memo_len, rest)| rest.split_at_checked(memo_len as usize))
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    if memo.is_empty() || memo.len() > MAX_WITHDRAW_MEMO_LEN {
+        log_error!("Memo length out of range");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    check_account_count(
+        accounts,
+        WITHDRAW_ACCOUNTS,
+        WITHDRAW_ACCOUNTS + REBATE_ACCOUNTS,
+    )?;
+    let accounts = WithdrawAccounts::try_from(accounts)?;
+    let verified = verify_withdraw(&accounts, data, 0)?;
+
+    execute_withdraw(&accounts, &verified, accounts.recipient)?;
+    pay_rebate(accounts.state, accounts.recipient, accounts.rest)?;
+
+    WithdrawMemoEvent {
+        nullifier: verified.nullifier,
+        memo,
+    }
+    .emit();
+    verified.set_return_data();
+
+    log!("Withdraw successful");
+    Ok(())
+}
@@ -1,42 +1,241 @@
-use pinocchio::{
-    address::declare_id, entrypoint, error::ProgramError, AccountView, Address, ProgramResult,
-};
-use solana_program_log::log;
+use pinocchio::{address::declare_id, entrypoint, AccountView, Address, ProgramResult};
 
+/// Informational log, compiled out unless the `logging` feature is enabled.
+macro_rules! log {
+    ($msg:expr) => {{
+        #[cfg(feature = "logging")]
+        solana_program_log::log($msg);
+    }};
+}
+
+/// Diagnostic log on an error path, kept under the separate `error-logs`
+/// feature so failures stay explainable when `logging` is off.
+macro_rules! log_error {
+    ($fmt:literal, $($arg:expr),+) => {{
+        #[cfg(feature = "error-logs")]
+        solana_program_log::log!($fmt, $($arg),+);
+        #[cfg(not(feature = "error-logs"))]
+        let _ = ($($arg,)+);
+    }};
+    ($msg:expr) => {{
+        #[cfg(feature = "error-logs")]
+        solana_program_log::log($msg);
+    }};
+}
+
+#[cfg(all(feature = "client", not(target_os = "solana")))]
+pub mod client;
+pub mod error;
+pub mod event;
 pub mod instructions;
+pub mod runtime;
 pub mod state;
+pub mod witness;
 
 declare_id!("H76rmbsE6HxkDw7AWEJLtqYogyP6psq3Fk2wqPH7Cjes");
 
 entrypoint!(process_instruction);
 
+/// Dispatches one instruction; public so host tests can drive the program.
 #[inline(always)]
-fn process_instruction(
+pub fn process_instruction(
     _program_id: &Address,
     accounts: &[AccountView],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    let (ix_disc, data) = instruction_data
-        .split_first()
-        .ok_or(ProgramError::InvalidInstructionData)?;
+    let Some((ix_disc, data)) = instruction_data.split_first() else {
+        log_error!("No instruction discriminator provided");
+        return Err(error::ShieldedPoolError::MissingInstruction.into());
+    };
 
     match *ix_disc {
         instructions::instruction::INITIALIZE => {
-            log("Instruction: Initialize");
+            log!("Instruction: Initialize");
             instructions::process_initialize(accounts, data)
         }
         instructions::instruction::DEPOSIT => {
-            log("Instruction: Deposit");
+            log!("Instruction: Deposit");
             instructions::process_deposit(accounts, data)
         }
         instructions::instruction::WITHDRAW => {
-            log("Instruction: Withdraw");
+            log!("Instruction: Withdraw");
             instructions::process_withdraw(accounts, data)
         }
         instructions::instruction::SUBMIT_AUDIT => {
-            log("Instruction: Submit Audit");
+            log!("Instruction: Submit Audit");
             instructions::process_submit_audit(accounts, data)
         }
-        _ => Err(ProgramError::InvalidInstructionData),
+        instructions::instruction::SIMULATE_WITHDRAW => {
+            log!("Instruction: Simulate Withdraw");
+            instructions::process_simulate_withdraw(accounts, data)
+        }
+        instructions::instruction::RECLAIM_NULLIFIER_RENT => {
+            log!("Instruction: Reclaim Nullifier Rent");
+            instructions::process_reclaim_nullifier_rent(accounts, data)
+        }
+        instructions::instruction::MIGRATE_STATE => {
+            log!("Instruction: Migrate State");
+            instructions::process_migrate_state(accounts, data)
+        }
+        instructions::instruction::WITHDRAW_TO_ESCROW => {
+            log!("Instruction: Withdraw To Escrow");
+            instructions::process_withdraw_to_escrow(accounts, data)
+        }
+        instructions::instruction::CLAIM_ESCROW => {
+            log!("Instruction: Claim Escrow");
+            instructions::process_claim_escrow(accounts, data)
+        }
+        instructions::instruction::GET_ROOT_HISTORY => {
+            log!("Instruction: Get Root History");
+            instructions::process_get_root_history(accounts, data)
+        }
+        instructions::instruction::WITHDRAW_FEES => {
+            log!("Instruction: Withdraw Fees");
+            instructions::process_withdraw_fees(accounts, data)
+        }
+        instructions::instruction::UPDATE_DENYLIST => {
+            log!("Instruction: Update Denylist");
+            instructions::process_update_denylist(accounts, data)
+        }
+        instructions::instruction::GET_POOL_INFO => {
+            log!("Instruction: Get Pool Info");
+            instructions::process_get_pool_info(accounts, data)
+        }
+        instructions::instruction::WITHDRAW_AND_CALL => {
+            log!("Instruction: Withdraw And Call");
+            instructions::process_withdraw_and_call(accounts, data)
+        }
+        instructions::instruction::DEPOSIT_BATCH => {
+            log!("Instruction: Deposit Batch");
+            instructions::process_deposit_batch(accounts, data)
+        }
+        instructions::instruction::GROW_ROOT_HISTORY => {
+            log!("Instruction: Grow Root History");
+            instructions::process_grow_root_history(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_REBATE => {
+            log!("Instruction: Configure Rebate");
+            instructions::process_configure_rebate(accounts, data)
+        }
+        instructions::instruction::VERIFY_AND_STORE_ROOT => {
+            log!("Instruction: Verify And Store Root");
+            instructions::process_verify_and_store_root(accounts, data)
+        }
+        instructions::instruction::GET_ROOT_SNAPSHOTS => {
+            log!("Instruction: Get Root Snapshots");
+            instructions::process_get_root_snapshots(accounts, data)
+        }
+        instructions::instruction::SUBMIT_AUDIT_BATCH => {
+            log!("Instruction: Submit Audit Batch");
+            instructions::process_submit_audit_batch(accounts, data)
+        }
+        instructions::instruction::INITIATE_EMERGENCY => {
+            log!("Instruction: Initiate Emergency");
+            instructions::process_initiate_emergency(accounts, data)
+        }
+        instructions::instruction::EMERGENCY_WITHDRAW => {
+            log!("Instruction: Emergency Withdraw");
+            instructions::process_emergency_withdraw(accounts, data)
+        }
+        instructions::instruction::REGISTER_VIEWING_DATA => {
+            log!("Instruction: Register Viewing Data");
+            instructions::process_register_viewing_data(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_RECIPIENT_LIMIT => {
+            log!("Instruction: Configure Recipient Limit");
+            instructions::process_configure_recipient_limit(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_OWNER_GATE => {
+            log!("Instruction: Configure Owner Gate");
+            instructions::process_configure_owner_gate(accounts, data)
+        }
+        instructions::instruction::COMPACT_ROOT_HISTORY => {
+            log!("Instruction: Compact Root History");
+            instructions::process_compact_root_history(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_WITHDRAW_FEE => {
+            log!("Instruction: Configure Withdraw Fee");
+            instructions::process_configure_withdraw_fee(accounts, data)
+        }
+        instructions::instruction::PROOF_OF_RESERVES => {
+            log!("Instruction: Proof Of Reserves");
+            instructions::process_proof_of_reserves(accounts, data)
+        }
+        instructions::instruction::GET_AUDIT_RECORD => {
+            log!("Instruction: Get Audit Record");
+            instructions::process_get_audit_record(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_AUDIT_CUTOFF => {
+            log!("Instruction: Configure Audit Cutoff");
+            instructions::process_configure_audit_cutoff(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_COMPUTE_GUARD => {
+            log!("Instruction: Configure Compute Guard");
+            instructions::process_configure_compute_guard(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_AUTHORITIES => {
+            log!("Instruction: Configure Authorities");
+            instructions::process_configure_authorities(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_COMMITMENT_GUARD => {
+            log!("Instruction: Configure Commitment Guard");
+            instructions::process_configure_commitment_guard(accounts, data)
+        }
+        instructions::instruction::RESERVE_NULLIFIER => {
+            log!("Instruction: Reserve Nullifier");
+            instructions::process_reserve_nullifier(accounts, data)
+        }
+        instructions::instruction::WITHDRAW_SPLIT => {
+            log!("Instruction: Withdraw Split");
+            instructions::process_withdraw_split(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_RECIPIENT_SIGNATURE => {
+            log!("Instruction: Configure Recipient Signature");
+            instructions::process_configure_recipient_signature(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_WALLET_RECIPIENT => {
+            log!("Instruction: Configure Wallet Recipient");
+            instructions::process_configure_wallet_recipient(accounts, data)
+        }
+        instructions::instruction::CHECK_SOLVENCY => {
+            log!("Instruction: Check Solvency");
+            instructions::process_check_solvency(accounts, data)
+        }
+        instructions::instruction::UPDATE_RELAYER => {
+            log!("Instruction: Update Relayer");
+            instructions::process_update_relayer(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_RELAYER_REGISTRY => {
+            log!("Instruction: Configure Relayer Registry");
+            instructions::process_configure_relayer_registry(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_ALT_VERIFIER => {
+            log!("Instruction: Configure Alt Verifier");
+            instructions::process_configure_alt_verifier(accounts, data)
+        }
+        instructions::instruction::DEPOSIT_WITH_NOTE => {
+            log!("Instruction: Deposit With Note");
+            instructions::process_deposit_with_note(accounts, data)
+        }
+        instructions::instruction::UPDATE_CONFIG => {
+            log!("Instruction: Update Config");
+            instructions::process_update_config(accounts, data)
+        }
+        instructions::instruction::WITHDRAW_WITH_MEMO => {
+            log!("Instruction: Withdraw With Memo");
+            instructions::process_withdraw_with_memo(accounts, data)
+        }
+        instructions::instruction::CONFIGURE_PUBLIC_INPUT_ORDER => {
+            log!("Instruction: Configure Public Input Order");
+            instructions::process_configure_public_input_order(accounts, data)
+        }
+        instructions::instruction::CLOSE_AUDIT_RECORD => {
+            log!("Instruction: Close Audit Record");
+            instructions::process_close_audit_record(accounts, data)
+        }
+        unknown => {
+            log_error!("Unknown instruction discriminator {}", unknown);
+            Err(error::ShieldedPoolError::UnknownInstruction.into())
+        }
     }
 }
@@ -0,0 +1,449 @@
+//! The runtime services the program uses: sysvars, the compute meter,
+//! cross-program invocations, return data, event logs and the Poseidon hash.
+//!
+//! On chain each function is the Pinocchio call it wraps. Host builds have no
+//! runtime behind those calls, so they are served by [`host`] instead, which
+//! lets tests run instructions end to end and observe their side effects.
+
+use pinocchio::{
+    cpi::Signer,
+    instruction::InstructionView,
+    sysvars::{clock::Clock, rent::Rent},
+    AccountView, ProgramResult,
+};
+use solana_program_error::ProgramError;
+
+/// The rent sysvar.
+#[inline(always)]
+pub fn rent() -> Result<Rent, ProgramError> {
+    #[cfg(target_os = "solana")]
+    {
+        pinocchio::sysvars::Sysvar::get()
+    }
+    #[cfg(not(target_os = "solana"))]
+    {
+        Ok(host::rent())
+    }
+}
+
+/// The clock sysvar.
+#[inline(always)]
+pub fn clock() -> Result<Clock, ProgramError> {
+    #[cfg(target_os = "solana")]
+    {
+        pinocchio::sysvars::Sysvar::get()
+    }
+    #[cfg(not(target_os = "solana"))]
+    {
+        Ok(host::clock())
+    }
+}
+
+/// Compute units left in the transaction's budget.
+#[inline(always)]
+pub fn remaining_compute_units() -> u64 {
+    #[cfg(target_os = "solana")]
+    {
+        solana_program_log::logger::remaining_compute_units()
+    }
+    #[cfg(not(target_os = "solana"))]
+    {
+        host::remaining_compute_units()
+    }
+}
+
+/// Invokes `instruction` with the accounts it lists, in order.
+#[inline(always)]
+pub fn invoke<const ACCOUNTS: usize>(
+    instruction: &InstructionView,
+    accounts: &[&AccountView; ACCOUNTS],
+) -> ProgramResult {
+    invoke_signed(instruction, accounts, &[])
+}
+
+/// Invokes `instruction`, signing for the PDAs derived from `signers`.
+#[inline(always)]
+pub fn invoke_signed<const ACCOUNTS: usize>(
+    instruction: &InstructionView,
+    accounts: &[&AccountView; ACCOUNTS],
+    signers: &[Signer],
+) -> ProgramResult {
+    #[cfg(target_os = "solana")]
+    {
+        pinocchio::cpi::invoke_signed(instruction, accounts, signers)
+    }
+    #[cfg(not(target_os = "solana"))]
+    {
+        let _ = signers;
+        host::invoke(instruction, accounts)
+    }
+}
+
+/// Invokes `instruction` with at most `MAX_ACCOUNTS` accounts.
+#[inline(always)]
+pub fn invoke_with_bounds<const MAX_ACCOUNTS: usize>(
+    instruction: &InstructionView,
+    accounts: &[&AccountView],
+) -> ProgramResult {
+    #[cfg(target_os = "solana")]
+    {
+        pinocchio::cpi::invoke_with_bounds::<MAX_ACCOUNTS>(instruction, accounts)
+    }
+    #[cfg(not(target_os = "solana"))]
+    {
+        if MAX_ACCOUNTS < instruction.accounts.len() {
+            return Err(ProgramError::InvalidArgument);
+        }
+        host::invoke(instruction, accounts)
+    }
+}
+
+/// Sets the instruction's return data, replacing any set before.
+#[inline(always)]
+pub fn set_return_data(data: &[u8]) {
+    #[cfg(target_os = "solana")]
+    pinocchio::cpi::set_return_data(data);
+    #[cfg(not(target_os = "solana"))]
+    host::set_return_data(data);
+}
+
+/// Logs `fields` as a program data (event) entry.
+#[inline(always)]
+pub fn log_data(fields: &[&[u8]]) {
+    #[cfg(target_os = "solana")]
+    solana_program_log::log_data(fields);
+    #[cfg(not(target_os = "solana"))]
+    host::log_data(fields);
+}
+
+/// Poseidon hash of `left` and `right` over BN254 with the circom parameters
+/// (x^5 S-box, width 3), inputs and output big-endian: the hash the circuits
+/// build the commitment tree with. Fails if an input is not below the field
+/// modulus.
+#[inline(always)]
+pub fn poseidon(left: &[u8; 32], right: &[u8; 32]) -> Result<[u8; 32], ProgramError> {
+    #[cfg(target_os = "solana")]
+    {
+        /// `sol_poseidon` parameters and endianness for BN254 x^5, big-endian.
+        const BN254_X5: u64 = 0;
+        const BIG_ENDIAN: u64 = 0;
+
+        let inputs: [&[u8]; 2] = [left, right];
+        let mut hash = [0u8; 32];
+        // SAFETY: `inputs` holds two 32-byte slices and `hash` has room for
+        // the 32-byte result.
+        let result = unsafe {
+            pinocchio::syscalls::sol_poseidon(
+                BN254_X5,
+                BIG_ENDIAN,
+                inputs.as_ptr() as *const u8,
+                inputs.len() as u64,
+                hash.as_mut_ptr(),
+            )
+        };
+        if result != 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+        Ok(hash)
+    }
+    #[cfg(not(target_os = "solana"))]
+    {
+        use light_poseidon::{Poseidon, PoseidonBytesHasher};
+
+        Poseidon::<ark_bn254::Fr>::new_circom(2)
+            .and_then(|mut hasher| hasher.hash_bytes_be(&[left, right]))
+            .map_err(|_| ProgramError::InvalidArgument)
+    }
+}
+
+/// System program instructions, invoked through this module.
+pub mod system {
+    use pinocchio::{cpi::Signer, AccountView, Address, ProgramResult};
+
+    /// Create `to` with `space` bytes owned by `owner`, funded by `from`.
+    pub struct CreateAccount<'a, 'b> {
+        pub from: &'a AccountView,
+        pub to: &'a AccountView,
+        pub lamports: u64,
+        pub space: u64,
+        pub owner: &'b Address,
+    }
+
+    impl CreateAccount<'_, '_> {
+        #[inline(always)]
+        pub fn invoke(&self) -> ProgramResult {
+            self.invoke_signed(&[])
+        }
+
+        #[inline(always)]
+        pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+            #[cfg(target_os = "solana")]
+            {
+                pinocchio_system::instructions::CreateAccount {
+                    from: self.from,
+                    to: self.to,
+                    lamports: self.lamports,
+                    space: self.space,
+                    owner: self.owner,
+                }
+                .invoke_signed(signers)
+            }
+            #[cfg(not(target_os = "solana"))]
+            {
+                let _ = signers;
+                super::host::create_account(
+                    self.from,
+                    self.to,
+                    self.lamports,
+                    self.space,
+                    self.owner,
+                )
+            }
+        }
+    }
+
+    /// Move `lamports` from the system account `from` to `to`.
+    pub struct Transfer<'a> {
+        pub from: &'a AccountView,
+        pub to: &'a AccountView,
+        pub lamports: u64,
+    }
+
+    impl Transfer<'_> {
+        #[inline(always)]
+        pub fn invoke(&self) -> ProgramResult {
+            self.invoke_signed(&[])
+        }
+
+        #[inline(always)]
+        pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+            #[cfg(target_os = "solana")]
+            {
+                pinocchio_system::instructions::Transfer {
+                    from: self.from,
+                    to: self.to,
+                    lamports: self.lamports,
+                }
+                .invoke_signed(signers)
+            }
+            #[cfg(not(target_os = "solana"))]
+            {
+                let _ = signers;
+                super::host::transfer(self.from, self.to, self.lamports)
+            }
+        }
+    }
+}
+
+/// Host stand-in for the runtime, one per thread.
+///
+/// Rent uses the mainnet defaults and the clock reads the slot set by
+/// [`host::set_slot`]. The compute meter reports the largest transaction
+/// budget unless [`host::set_remaining_compute_units`] says otherwise. System program CPIs are applied to the accounts
+/// directly; every other CPI is recorded and passed to the handler set by
+/// [`host::set_cpi_handler`], or succeeds if there is none. As on chain, a
+/// CPI clears the return data.
+#[cfg(not(target_os = "solana"))]
+pub mod host {
+    use std::cell::RefCell;
+
+    use pinocchio::{
+        instruction::InstructionView,
+        sysvars::{clock::Clock, rent::Rent},
+        AccountView, Address, ProgramResult,
+    };
+    use solana_program_error::ProgramError;
+
+    /// Handles a non-system CPI: the invoked program, the instruction data and
+    /// the instruction's accounts.
+    pub type CpiHandler = Box<dyn FnMut(&Address, &[u8], &[&AccountView]) -> ProgramResult>;
+
+    /// A CPI as the program issued it.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct RecordedCpi {
+        pub program_id: Address,
+        pub data: Vec<u8>,
+    }
+
+    /// The compute meter's reading before any override: the most units a
+    /// transaction can request.
+    pub const MAX_COMPUTE_UNITS: u64 = 1_400_000;
+
+    #[derive(Default)]
+    struct Host {
+        slot: u64,
+        remaining_compute_units: Option<u64>,
+        return_data: Vec<u8>,
+        events: Vec<Vec<Vec<u8>>>,
+        cpis: Vec<RecordedCpi>,
+        cpi_handler: Option<CpiHandler>,
+    }
+
+    thread_local! {
+        static HOST: RefCell<Host> = RefCell::new(Host::default());
+    }
+
+    /// Clears the slot, compute meter, return data, events, recorded CPIs and
+    /// CPI handler.
+    pub fn reset() {
+        HOST.with_borrow_mut(|host| *host = Host::default());
+    }
+
+    /// Sets the slot the clock reports.
+    pub fn set_slot(slot: u64) {
+        HOST.with_borrow_mut(|host| host.slot = slot);
+    }
+
+    /// Sets the compute units the meter reports as left.
+    pub fn set_remaining_compute_units(units: u64) {
+        HOST.with_borrow_mut(|host| host.remaining_compute_units = Some(units));
+    }
+
+    /// Return data set by the last instruction or CPI.
+    pub fn return_data() -> Vec<u8> {
+        HOST.with_borrow(|host| host.return_data.clone())
+    }
+
+    /// Sets the return data, as a handled CPI would.
+    pub fn set_return_data(data: &[u8]) {
+        HOST.with_borrow_mut(|host| host.return_data = data.to_vec());
+    }
+
+    /// Events logged since the last call, each as its data fields.
+    pub fn take_events() -> Vec<Vec<Vec<u8>>> {
+        HOST.with_borrow_mut(|host| std::mem::take(&mut host.events))
+    }
+
+    /// Non-system CPIs issued since the last call.
+    pub fn take_cpis() -> Vec<RecordedCpi> {
+        HOST.with_borrow_mut(|host| std::mem::take(&mut host.cpis))
+    }
+
+    /// Routes non-system CPIs to `handler`.
+    pub fn set_cpi_handler(
+        handler: impl FnMut(&Address, &[u8], &[&AccountView]) -> ProgramResult + 'static,
+    ) {
+        HOST.with_borrow_mut(|host| host.cpi_handler = Some(Box::new(handler)));
+    }
+
+    pub(super) fn rent() -> Rent {
+        // `lamports_per_byte`, then the `2.0` exemption threshold as f64 bytes.
+        let sysvar: [u64; 2] = [3480, 2.0f64.to_bits()];
+        // SAFETY: `[u64; 2]` is 16 bytes, aligned like `Rent`.
+        let bytes = unsafe { core::slice::from_raw_parts(sysvar.as_ptr().cast::<u8>(), 16) };
+        Rent::from_bytes(bytes)
+            .expect("aligned rent sysvar")
+            .clone()
+    }
+
+    pub(super) fn clock() -> Clock {
+        Clock {
+            slot: HOST.with_borrow(|host| host.slot),
+            epoch_start_timestamp: 0,
+            epoch: 0,
+            leader_schedule_epoch: 0,
+            unix_timestamp: 0,
+        }
+    }
+
+    pub(super) fn remaining_compute_units() -> u64 {
+        HOST.with_borrow(|host| host.remaining_compute_units.unwrap_or(MAX_COMPUTE_UNITS))
+    }
+
+    pub(super) fn log_data(fields: &[&[u8]]) {
+        let event = fields.iter().map(|field| field.to_vec()).collect();
+        HOST.with_borrow_mut(|host| host.events.push(event));
+    }
+
+    pub(super) fn invoke(
+        instruction: &InstructionView,
+        accounts: &[&AccountView],
+    ) -> ProgramResult {
+        if accounts.len() < instruction.accounts.len() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        for (account, meta) in accounts.iter().zip(instruction.accounts) {
+            if account.address() != meta.address {
+                return Err(ProgramError::InvalidArgument);
+            }
+            let borrowed = if meta.is_writable {
+                account.is_borrowed()
+            } else {
+                account.is_borrowed_mut()
+            };
+            if borrowed {
+                return Err(ProgramError::AccountBorrowFailed);
+            }
+        }
+
+        // Take the handler out for the call, so it may use this module.
+        let mut handler = HOST.with_borrow_mut(|host| {
+            host.return_data.clear();
+            host.cpis.push(RecordedCpi {
+                program_id: instruction.program_id.clone(),
+                data: instruction.data.to_vec(),
+            });
+            host.cpi_handler.take()
+        });
+        let result = match handler.as_mut() {
+            Some(handler) => handler(
+                instruction.program_id,
+                instruction.data,
+                &accounts[..instruction.accounts.len()],
+            ),
+            None => Ok(()),
+        };
+        HOST.with_borrow_mut(|host| {
+            if host.cpi_handler.is_none() {
+                host.cpi_handler = handler;
+            }
+        });
+        result
+    }
+
+    /// The system program's `CreateAccount`. `to`'s buffer must have room for
+    /// `space` bytes of data.
+    pub(super) fn create_account(
+        from: &AccountView,
+        to: &AccountView,
+        lamports: u64,
+        space: u64,
+        owner: &Address,
+    ) -> ProgramResult {
+        HOST.with_borrow_mut(|host| host.return_data.clear());
+        if to.lamports() > 0 || !to.is_data_empty() {
+            // SystemError::AccountAlreadyInUse
+            return Err(ProgramError::Custom(0));
+        }
+        transfer(from, to, lamports)?;
+        to.check_borrow_mut()?;
+        // SAFETY: the account is not borrowed and the caller guarantees the
+        // buffer holds `space` bytes; new data is zeroed as on chain.
+        unsafe {
+            (*to.account_ptr().cast_mut()).data_len = space;
+            core::ptr::write_bytes(to.data_ptr(), 0, space as usize);
+            to.assign(owner);
+        }
+        Ok(())
+    }
+
+    /// The system program's `Transfer`. Signatures, PDA ones included, are
+    /// not checked.
+    pub(super) fn transfer(from: &AccountView, to: &AccountView, lamports: u64) -> ProgramResult {
+        HOST.with_borrow_mut(|host| host.return_data.clear());
+        if !from.is_writable() || !to.is_writable() {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let Some(remaining) = from.lamports().checked_sub(lamports) else {
+            // SystemError::ResultWithNegativeLamports
+            return Err(ProgramError::Custom(1));
+        };
+        let credited = to
+            .lamports()
+            .checked_add(lamports)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        from.set_lamports(remaining);
+        to.set_lamports(credited);
+        Ok(())
+    }
+}
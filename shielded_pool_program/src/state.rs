@@ -1,6 +1,75 @@
 use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account::{Ref, RefMut},
+    cpi::Seed,
+    sysvars::rent::Rent,
+    AccountView, Address, ProgramResult,
+};
+use solana_program_error::ProgramError;
+
+use crate::{error::ShieldedPoolError, runtime};
+
+/// A BN254 field element as it appears in a public witness: 32 bytes,
+/// big-endian. Instruction-data integers, by contrast, are little-endian.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldElement(pub [u8; 32]);
+
+impl FieldElement {
+    /// The element as a u64, rejecting values whose high-order 24 bytes are
+    /// not zero so the integer always equals the proven value. This is how
+    /// proven amounts are read; see [`decode_amount_le`] for instruction data.
+    pub fn to_u64_be(&self) -> Result<u64, ProgramError> {
+        if self.0[..24] != [0u8; 24] {
+            return Err(ShieldedPoolError::AmountOutOfRange.into());
+        }
+        let mut low = [0u8; 8];
+        low.copy_from_slice(&self.0[24..32]);
+        Ok(u64::from_be_bytes(low))
+    }
+
+    pub fn from_u64(value: u64) -> FieldElement {
+        let mut bytes = [0u8; 32];
+        bytes[24..32].copy_from_slice(&value.to_be_bytes());
+        FieldElement(bytes)
+    }
+}
+
+/// An amount taken from instruction data (8 bytes, little-endian), as
+/// clients serialize every integer. Amounts inside a proof's witness are
+/// field elements instead and go through [`FieldElement::to_u64_be`]; the two
+/// encodings must not be mixed up.
+pub fn decode_amount_le(bytes: &[u8]) -> Result<u64, ProgramError> {
+    bytes
+        .try_into()
+        .map(u64::from_le_bytes)
+        .map_err(|_| ProgramError::InvalidInstructionData)
+}
+
+/// Hash of an empty subtree of height `level`: the zero leaf at level 0, and
+/// above it the Poseidon hash of two empty subtrees one level down, as the
+/// circuits and the client tree pad unfilled branches.
+pub fn zeros(level: u8) -> Result<[u8; 32], ProgramError> {
+    let mut node = [0u8; 32];
+    for _ in 0..level {
+        node = runtime::poseidon(&node, &node)?;
+    }
+    Ok(node)
+}
+
+/// Root of a commitment tree of `depth` levels holding no notes.
+pub fn empty_tree_root(depth: u8) -> Result<[u8; 32], ProgramError> {
+    zeros(depth)
+}
+
+/// Number of recent roots kept inline in `ShieldedPoolState::roots`. Pools
+/// grown by `GrowRootHistory` keep the rest in `RootEntry`s after the state.
+pub const ROOT_HISTORY_SIZE: usize = 32;
 
 /// Global state for the shielded pool
+///
+/// Fields are only ever appended (or carved out of zeroed padding) so an older
+/// account resized to `LEN` reads every new field as zero. `MigrateState`
+/// performs that resize and fills in any non-zero defaults.
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct ShieldedPoolState {
@@ -8,42 +77,488 @@ pub struct ShieldedPoolState {
     pub discriminator: [u8; 8],
     /// Current merkle root of the commitment tree
     pub current_root: [u8; 32],
-    /// Circular buffer of the last 32 valid roots
-    pub roots: [[u8; 32]; 32],
+    /// First 32 entries of the circular buffer of recent valid roots
+    pub roots: [[u8; 32]; ROOT_HISTORY_SIZE],
     /// Current index in the roots buffer (where to write next)
     pub roots_index: u32,
-    /// Padding to align to 8 bytes (32*32 bytes + 32 + 8 + 4 = 1024 + 44 = 1068. +4 = 1072)
-    pub _padding: [u8; 4],
+    /// Protocol fee charged on deposits, in basis points of the deposited amount
+    pub deposit_fee_bps: u16,
+    /// Depth of the commitment Merkle tree (capacity is `2^tree_depth` leaves)
+    pub tree_depth: u8,
+    /// Layout version (0 for accounts created before versioning)
+    pub version: u8,
+    /// Treasury: the only account `WithdrawFees` sweeps the fee vault to.
+    /// Fees accrue in the `["fee_vault"]` PDA, never here directly.
+    pub fee_collector: [u8; 32],
+    /// Admin allowed to run maintenance instructions
+    pub authority: [u8; 32],
+    /// Number of commitments inserted so far (index of the next leaf)
+    pub next_leaf_index: u64,
+    /// Smallest gross amount (lamports) a single withdrawal may take out
+    pub min_withdraw: u64,
+    /// Expected withdraw proof length in bytes
+    pub proof_len: u32,
+    /// Expected number of public inputs in the withdraw witness
+    pub public_input_count: u8,
+    /// Padding to align `withdraw_fee_bps`
+    pub _padding: u8,
+    /// Protocol fee charged on withdrawals, in basis points of the gross amount
+    pub withdraw_fee_bps: u16,
+    /// Minimum age (in slots) of a root before withdrawals may reference it; 0 disables
+    pub min_deposit_age_slots: u64,
+    /// Slot at which each entry of `roots` was added
+    pub root_slots: [u64; ROOT_HISTORY_SIZE],
+    /// Number of denylisted recipients; withdrawals skip the denylist while 0
+    pub denylist_count: u64,
+    /// Successful deposits since initialize (or since migrating to v5)
+    pub deposit_count: u64,
+    /// Successful withdrawals since initialize (or since migrating to v5)
+    pub withdraw_count: u64,
+    /// Entries in the root history ring: the inline `roots` plus the
+    /// `RootEntry`s stored after the state. A power of two.
+    pub root_history_size: u32,
+    /// Non-zero when withdrawals also pay out `rebate_amount` of `rebate_mint`
+    pub rebate_enabled: u8,
+    /// Decimals of `rebate_mint`, checked by the token transfer
+    pub rebate_decimals: u8,
+    /// Non-zero once `InitiateEmergency` ran; deposits are rejected
+    pub paused: u8,
+    /// Non-zero when withdrawals must be signed by the owner key the witness
+    /// commits to
+    pub owner_gated: u8,
+    /// Token rebated to the recipient of every withdrawal
+    pub rebate_mint: [u8; 32],
+    /// Rebate paid per withdrawal, in base units of `rebate_mint`
+    pub rebate_amount: u64,
+    /// Slot from which `EmergencyWithdraw` may drain the vault; 0 until
+    /// `InitiateEmergency` sets it
+    pub emergency_unlock_slot: u64,
+    /// Most lamports one recipient may receive per window; 0 disables the limit
+    pub recipient_max_per_window: u64,
+    /// Length of the per-recipient withdrawal window, in slots
+    pub recipient_window_slots: u64,
+    /// Lamports of notes the vault owes: net deposits minus gross withdrawals
+    pub total_shielded: u64,
+    /// Withdrawals against roots added from this slot on need an audit
+    /// record; 0 requires one for every withdrawal. A note deposited before
+    /// the cutoff skips the audit only while a root from before the cutoff
+    /// is still in the history: once the ring rolls over, every root left
+    /// postdates the cutoff and the note needs a record like any other.
+    pub audit_required_from_slot: u64,
+    /// Compute units that must remain before the verifier CPI; 0 disables
+    /// the check
+    pub min_verifier_compute_units: u64,
+    /// Keys that share control of the pool with `authority`; only the first
+    /// `co_authority_count` are set
+    pub co_authorities: [[u8; 32]; ShieldedPoolState::MAX_CO_AUTHORITIES],
+    /// Distinct authority keys that must sign each privileged instruction
+    pub authority_threshold: u8,
+    /// Number of keys set in `co_authorities`
+    pub co_authority_count: u8,
+    /// Non-zero when each deposit must create a `["commitment", commitment]`
+    /// marker PDA, rejecting repeated commitments
+    pub unique_commitments: u8,
+    /// Non-zero when every withdrawal's recipient must sign
+    pub require_recipient_signature: u8,
+    /// Non-zero when every withdrawal's recipient must be a system-owned
+    /// (wallet) account rather than a program-owned one
+    pub require_wallet_recipient: u8,
+    /// Non-zero when withdrawals that pay a withdraw fee must be submitted by
+    /// a payer with a `["relayer", payer]` PDA; the fee still goes to the fee
+    /// vault
+    pub relayer_registry: u8,
+    pub _padding2: [u8; 2],
+    /// Second withdraw verifier accepted during a circuit transition, used
+    /// when the withdraw names it instead of the built-in verifier
+    pub alt_verifier: [u8; 32],
+    /// Proof length for `alt_verifier`; 0 means there is none
+    pub alt_proof_len: u32,
+    pub _padding3: [u8; 4],
+    /// Witness position of each withdraw input, in the order root,
+    /// nullifier, recipient, amount, wa_commitment; a permutation of 0..5
+    pub public_input_order: [u8; 5],
+    pub _padding4: [u8; 3],
+    /// Hash of an empty subtree at each level below the root, from the zero
+    /// leaf up; see [`zeros`]. Levels from `tree_depth` on are zero
+    pub zero_roots: [[u8; 32]; ShieldedPoolState::MAX_TREE_DEPTH as usize],
 }
 
 impl ShieldedPoolState {
     pub const LEN: usize = core::mem::size_of::<ShieldedPoolState>();
     pub const DISCRIMINATOR: [u8; 8] = *b"poolstat"; // Simple 8-byte tag
+    /// Layout version written by this program.
+    pub const VERSION: u8 = 22;
+    /// Size of the original, unversioned (v0) layout.
+    pub const V0_LEN: usize = 1072;
+    /// Account size of each versioned layout, indexed by version (v0 varies).
+    pub const VERSION_LENS: [usize; Self::VERSION as usize + 1] = [
+        Self::V0_LEN,
+        1152,
+        1160,
+        1424,
+        1432,
+        1448,
+        1456,
+        1496,
+        1504,
+        1520,
+        1520,
+        1520,
+        1528,
+        1536,
+        1544,
+        1616,
+        1616,
+        1616,
+        1616,
+        1616,
+        1656,
+        1664,
+        2496,
+    ];
+    /// Most keys that may share control of the pool besides `authority`.
+    pub const MAX_CO_AUTHORITIES: usize = 2;
+    /// Basis points in 100%; every fee must stay below it.
+    pub const MAX_FEE_BPS: u16 = 10_000;
+    /// Bounds for the tree depth chosen at initialize.
+    pub const MIN_TREE_DEPTH: u8 = 10;
+    pub const MAX_TREE_DEPTH: u8 = 26;
+    /// Depth used by the reference circuit, applied to migrated v0 pools.
+    pub const DEFAULT_TREE_DEPTH: u8 = 16;
+    /// Withdraw proof configuration of the reference circuit.
+    pub const DEFAULT_PROOF_LEN: u32 = 388;
+    pub const DEFAULT_PUBLIC_INPUT_COUNT: u8 = 5;
+    pub const DEFAULT_PUBLIC_INPUT_ORDER: [u8; 5] = [0, 1, 2, 3, 4];
+    /// Largest proof that still fits in a transaction alongside its witness.
+    pub const MAX_PROOF_LEN: u32 = 1024;
+    /// Largest root history `GrowRootHistory` accepts.
+    pub const MAX_ROOT_HISTORY_SIZE: u32 = 256;
+    /// Slots between `InitiateEmergency` and the earliest `EmergencyWithdraw`
+    /// (about two days), so depositors can withdraw first.
+    pub const EMERGENCY_TIMELOCK_SLOTS: u64 = 432_000;
 
     pub fn is_initialized(&self) -> bool {
         self.discriminator == Self::DISCRIMINATOR
     }
 
-    /// Add a new root to the history and update current root
-    pub fn add_root(&mut self, new_root: [u8; 32]) {
+    /// Zero every padding field so identical states hash identically. Called
+    /// by each handler that writes the state, before it releases the borrow.
+    pub fn normalize(&mut self) {
+        self._padding = 0;
+        self._padding2 = [0u8; 2];
+        self._padding3 = [0u8; 4];
+        self._padding4 = [0u8; 3];
+    }
+
+    /// Compute `zero_roots` for `tree_depth`.
+    pub fn fill_zero_roots(&mut self) -> ProgramResult {
+        let depth = (self.tree_depth as usize).min(self.zero_roots.len());
+        let mut node = [0u8; 32];
+        for (level, zero_root) in self.zero_roots.iter_mut().enumerate() {
+            *zero_root = if level < depth { node } else { [0u8; 32] };
+            if level + 1 < depth {
+                node = runtime::poseidon(&node, &node)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Root of the tree before any deposit, one hash above the top
+    /// `zero_roots` entry.
+    pub fn empty_root(&self) -> Result<[u8; 32], ProgramError> {
+        match (self.tree_depth as usize).checked_sub(1) {
+            Some(top) => {
+                let zero_root = self
+                    .zero_roots
+                    .get(top)
+                    .ok_or(ProgramError::InvalidAccountData)?;
+                runtime::poseidon(zero_root, zero_root)
+            }
+            None => Ok([0u8; 32]),
+        }
+    }
+
+    /// Number of entries in the root history ring
+    pub fn history_size(&self) -> usize {
+        (self.root_history_size as usize).max(ROOT_HISTORY_SIZE)
+    }
+
+    /// Account size of a state whose root history holds `history_size` entries
+    pub fn account_len(history_size: usize) -> usize {
+        Self::LEN + history_size.saturating_sub(ROOT_HISTORY_SIZE) * RootEntry::LEN
+    }
+
+    /// Root and slot of history entry `index`; `extra` holds the entries past
+    /// the inline ones.
+    pub fn history_entry<'a>(
+        &'a self,
+        extra: &'a [RootEntry],
+        index: usize,
+    ) -> (&'a [u8; 32], u64) {
+        match index.checked_sub(ROOT_HISTORY_SIZE) {
+            None => (&self.roots[index], self.root_slots[index]),
+            Some(i) => (&extra[i].root, extra[i].slot),
+        }
+    }
+
+    fn set_history_entry(
+        &mut self,
+        extra: &mut [RootEntry],
+        index: usize,
+        root: [u8; 32],
+        slot: u64,
+    ) {
+        match index.checked_sub(ROOT_HISTORY_SIZE) {
+            None => {
+                self.roots[index] = root;
+                self.root_slots[index] = slot;
+            }
+            Some(i) => extra[i] = RootEntry { root, slot },
+        }
+    }
+
+    /// Add a new root, added at `slot`, to the history and update current root
+    pub fn add_root(&mut self, extra: &mut [RootEntry], new_root: [u8; 32], slot: u64) {
         self.current_root = new_root;
-        let idx = self.roots_index as usize % 32;
-        self.roots[idx] = new_root;
+        let idx = self.roots_index as usize % self.history_size();
+        self.set_history_entry(extra, idx, new_root, slot);
         self.roots_index = self.roots_index.wrapping_add(1);
     }
 
+    /// Spread the history over `new_size` entries, keeping roots in ring order
+    /// so that the next `add_root` still overwrites the oldest one. `extra`
+    /// must already span `new_size`, with the added entries zeroed.
+    ///
+    /// Both sizes are powers of two, so `roots_index` keeps addressing the
+    /// ring consistently across its u32 wraparound.
+    pub fn grow_history(&mut self, extra: &mut [RootEntry], new_size: usize) {
+        let old_size = self.history_size();
+        let next = self.roots_index as usize;
+        for index in 0..old_size {
+            // Age rank of the entry (0 = oldest), and its slot in the grown
+            // ring counted back from `next`.
+            let rank = (index + old_size - next % old_size) % old_size;
+            let target = (next % new_size + new_size - old_size + rank) % new_size;
+            if target != index {
+                let (root, slot) = self.history_entry(extra, index);
+                let root = *root;
+                self.set_history_entry(extra, target, root, slot);
+                self.set_history_entry(extra, index, [0u8; 32], 0);
+            }
+        }
+        self.root_history_size = new_size as u32;
+    }
+
+    /// Keep only the newest `new_size` history entries, each moved to its slot
+    /// in the smaller ring so that the next `add_root` overwrites the oldest
+    /// one kept. `extra` still spans the old size; the entries past
+    /// `new_size` are left for the caller to truncate.
+    ///
+    /// The kept entries fall in distinct slots modulo `new_size`, so no entry
+    /// is overwritten before it is moved.
+    pub fn shrink_history(&mut self, extra: &mut [RootEntry], new_size: usize) {
+        let old_size = self.history_size();
+        let next = self.roots_index as usize % old_size;
+        for age in 1..=new_size {
+            let index = (next + old_size - age) % old_size;
+            let target = index % new_size;
+            if target != index {
+                let (root, slot) = self.history_entry(extra, index);
+                let root = *root;
+                self.set_history_entry(extra, target, root, slot);
+            }
+        }
+        self.root_history_size = new_size as u32;
+    }
+
+    /// Check if the tree has room for another leaf
+    pub fn has_capacity(&self) -> bool {
+        self.has_capacity_for(1)
+    }
+
+    /// Check if the tree has room for `count` more leaves
+    pub fn has_capacity_for(&self, count: u64) -> bool {
+        self.next_leaf_index
+            .checked_add(count)
+            .is_some_and(|end| end <= 1u64 << self.tree_depth)
+    }
+
+    /// Fee owed on a deposit of `amount` lamports (rounded down).
+    pub fn deposit_fee(&self, amount: u64) -> u64 {
+        (amount as u128 * self.deposit_fee_bps as u128 / Self::MAX_FEE_BPS as u128) as u64
+    }
+
+    /// Fee owed on a withdrawal of `amount` lamports (rounded down).
+    pub fn withdraw_fee(&self, amount: u64) -> u64 {
+        (amount as u128 * self.withdraw_fee_bps as u128 / Self::MAX_FEE_BPS as u128) as u64
+    }
+
     /// Check if a root is valid (either current or in history)
-    pub fn check_root(&self, root: &[u8; 32]) -> bool {
+    pub fn check_root(&self, extra: &[RootEntry], root: &[u8; 32]) -> bool {
+        self.find_root(extra, root).is_some()
+    }
+
+    /// The stored copy of `root`, current or from the history, if the pool
+    /// accepts it.
+    pub fn find_root(&self, extra: &[RootEntry], root: &[u8; 32]) -> Option<[u8; 32]> {
         if &self.current_root == root {
-            return true;
+            return Some(self.current_root);
+        }
+        (0..self.history_size())
+            .map(|i| self.history_entry(extra, i).0)
+            .find(|entry| *entry == root)
+            .copied()
+    }
+
+    /// Slot at which `root` was most recently added to the history, if present
+    pub fn root_slot(&self, extra: &[RootEntry], root: &[u8; 32]) -> Option<u64> {
+        (0..self.history_size())
+            .map(|i| self.history_entry(extra, i))
+            .filter(|(r, _)| *r == root)
+            .map(|(_, slot)| slot)
+            .max()
+    }
+
+    /// Whether `key` is `authority` or one of the set co-authorities
+    pub fn is_authority(&self, key: &Address) -> bool {
+        key.as_array() == &self.authority
+            || self
+                .co_authorities
+                .iter()
+                .take(self.co_authority_count as usize)
+                .any(|co_authority| key.as_array() == co_authority)
+    }
+
+    /// Check that `authority` is an authority key and that it and the
+    /// authority keys among `co_signers` make up `authority_threshold`
+    /// distinct signatures.
+    pub fn check_authority(
+        &self,
+        authority: &AccountView,
+        co_signers: &[AccountView],
+    ) -> Result<(), ProgramError> {
+        if !authority.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
         }
-        for r in &self.roots {
-            if r == root {
-                return true;
+        if !self.is_authority(authority.address()) {
+            log_error!("Signer is not the pool authority");
+            return Err(ProgramError::IncorrectAuthority);
+        }
+
+        let mut signatures = 1;
+        for (i, signer) in co_signers.iter().enumerate() {
+            let key = signer.address();
+            if signer.is_signer()
+                && self.is_authority(key)
+                && key != authority.address()
+                && !co_signers[..i].iter().any(|other| other.address() == key)
+            {
+                signatures += 1;
             }
         }
-        false
+
+        let threshold = (self.authority_threshold as usize).max(1);
+        if signatures < threshold {
+            log_error!(
+                "Expected {} authority signatures, got {}",
+                threshold,
+                signatures
+            );
+            return Err(ShieldedPoolError::AuthorityThresholdNotMet.into());
+        }
+        Ok(())
+    }
+}
+
+// Root history entries follow the state in the same account, so the state
+// must end on their 8-byte alignment.
+const _: () = assert!(ShieldedPoolState::LEN.is_multiple_of(core::mem::align_of::<RootEntry>()));
+
+/// Root history entry stored after `ShieldedPoolState` once the history
+/// grows past the inline `roots`.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct RootEntry {
+    pub root: [u8; 32],
+    /// Slot at which `root` was added
+    pub slot: u64,
+}
+
+impl RootEntry {
+    pub const LEN: usize = core::mem::size_of::<RootEntry>();
+}
+
+/// Split raw state account data into the state and the root history entries
+/// stored after it. The account must already be validated.
+pub fn split_state(data: &[u8]) -> (&ShieldedPoolState, &[RootEntry]) {
+    let (state, extra) = data.split_at(ShieldedPoolState::LEN);
+    (bytemuck::from_bytes(state), bytemuck::cast_slice(extra))
+}
+
+/// Mutable counterpart of [`split_state`].
+pub fn split_state_mut(data: &mut [u8]) -> (&mut ShieldedPoolState, &mut [RootEntry]) {
+    let (state, extra) = data.split_at_mut(ShieldedPoolState::LEN);
+    (
+        bytemuck::from_bytes_mut(state),
+        bytemuck::cast_slice_mut(extra),
+    )
+}
+
+/// Validate the pool state account (PDA, owner, size, discriminator) and
+/// borrow it.
+pub fn load_state<'a>(
+    account: &'a AccountView,
+    program_id: &Address,
+) -> Result<Ref<'a, ShieldedPoolState>, ProgramError> {
+    check_state_account(account, program_id)?;
+    let state = Ref::map(account.try_borrow()?, |data| {
+        bytemuck::from_bytes::<ShieldedPoolState>(&data[..ShieldedPoolState::LEN])
+    });
+    if !state.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    check_history_len(account, &state)?;
+    Ok(state)
+}
+
+/// Mutable counterpart of [`load_state`].
+pub fn load_state_mut<'a>(
+    account: &'a AccountView,
+    program_id: &Address,
+) -> Result<RefMut<'a, ShieldedPoolState>, ProgramError> {
+    check_state_account(account, program_id)?;
+    let state = RefMut::map(account.try_borrow_mut()?, |data| {
+        bytemuck::from_bytes_mut::<ShieldedPoolState>(&mut data[..ShieldedPoolState::LEN])
+    });
+    if !state.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    check_history_len(account, &state)?;
+    Ok(state)
+}
+
+/// The account must hold exactly the root history entries the state claims.
+fn check_history_len(account: &AccountView, state: &ShieldedPoolState) -> Result<(), ProgramError> {
+    if account.data_len() != ShieldedPoolState::account_len(state.history_size()) {
+        log_error!("State size does not match its root history");
+        return Err(ProgramError::InvalidAccountData);
     }
+    Ok(())
+}
+
+fn check_state_account(account: &AccountView, program_id: &Address) -> Result<(), ProgramError> {
+    if account.address() != &Address::find_program_address(&[b"pool_state"], program_id).0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !account.owned_by(program_id) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+    // Older layouts are shorter until `MigrateState` grows them.
+    if account.data_len() < ShieldedPoolState::LEN {
+        log_error!("State account needs migration");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
 }
 
 /// Audit record for a specific user identity (wa_commitment)
@@ -54,13 +569,602 @@ pub struct AuditRecord {
     pub discriminator: [u8; 8],
     /// The wa_commitment this record verifies
     pub wa_commitment: [u8; 32],
+    /// Nonzero once the audit verifier CPI for `wa_commitment` succeeded
+    pub verified: u8,
+    /// Layout version; records older than `VERSION` load with it zeroed
+    pub version: u8,
+    pub _padding: [u8; 6],
+    /// The ct_commitment the audit proof attested alongside `wa_commitment`
+    pub ct_commitment: [u8; 32],
+    /// The payer who submitted the audit
+    pub submitter: [u8; 32],
+    /// Slot the record was written in
+    pub slot: u64,
 }
 
 impl AuditRecord {
     pub const LEN: usize = core::mem::size_of::<AuditRecord>();
+    /// Size of records written before `verified` existed. Only
+    /// `submit_audit` ever wrote those, after its verifier CPI, so they load
+    /// as verified.
+    pub const LEGACY_LEN: usize = 40;
+    /// Size of version 0 records, which end after `verified`. Their
+    /// `ct_commitment`, `submitter` and `slot` load as zero.
+    pub const V0_LEN: usize = 48;
+    pub const VERSION: u8 = 1;
     pub const DISCRIMINATOR: [u8; 8] = *b"auditrec";
 
     pub fn is_initialized(&self) -> bool {
         self.discriminator == Self::DISCRIMINATOR
     }
+
+    pub fn is_verified(&self) -> bool {
+        self.verified != 0
+    }
+}
+
+/// Header stored in the vault PDA so it can't be confused with any other
+/// zero-data program account.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct VaultHeader {
+    /// Discriminator to identify account type
+    pub discriminator: [u8; 8],
+    /// Asset held by the vault; all zeros for native SOL
+    pub asset_id: [u8; 32],
+    /// Pool state account this vault belongs to
+    pub state: [u8; 32],
+}
+
+impl VaultHeader {
+    pub const LEN: usize = core::mem::size_of::<VaultHeader>();
+    pub const DISCRIMINATOR: [u8; 8] = *b"poolvalt";
+    /// `asset_id` of the native SOL vault.
+    pub const NATIVE_ASSET_ID: [u8; 32] = [0u8; 32];
+
+    pub fn new(state: &Address) -> Self {
+        Self {
+            discriminator: Self::DISCRIMINATOR,
+            asset_id: Self::NATIVE_ASSET_ID,
+            state: state.to_bytes(),
+        }
+    }
+}
+
+/// Address of the PDA for `seeds` (at most two). Without a `bump` this
+/// searches for the canonical one; with it, it costs a single
+/// `create_program_address`.
+///
+/// Only pass a client-supplied bump for an account the caller then requires
+/// to be program-owned and initialized. The program only ever creates PDAs at
+/// their canonical bump, so another bump's address fails that check. PDAs
+/// the program creates itself, like nullifiers, must keep the search: a
+/// non-canonical bump would give the same seeds a second, unused address.
+pub fn pda_address(
+    seeds: &[&[u8]],
+    bump: Option<u8>,
+    program_id: &Address,
+) -> Result<Address, ProgramError> {
+    let Some(bump) = bump else {
+        return Ok(Address::find_program_address(seeds, program_id).0);
+    };
+    let bump_seed = [bump];
+    let with_bump: &[&[u8]] = match seeds {
+        [a] => &[a, &bump_seed],
+        [a, b] => &[a, b, &bump_seed],
+        _ => return Err(ProgramError::InvalidSeeds),
+    };
+    Address::create_program_address(with_bump, program_id).map_err(|_| pda_mismatch(Some(bump)))
+}
+
+/// Error for an account that is not the PDA [`pda_address`] derived:
+/// `InvalidBump` when a client-supplied bump was used, so a stale cached bump
+/// is told apart from a wrong account.
+fn pda_mismatch(bump: Option<u8>) -> ProgramError {
+    if bump.is_some() {
+        log_error!("Client-supplied bump does not derive the PDA");
+        ShieldedPoolError::InvalidBump.into()
+    } else {
+        ProgramError::InvalidAccountData
+    }
+}
+
+/// Validate the vault PDA (address, owner, size, header) and that it belongs
+/// to `state`. A client-supplied `bump` replaces the bump search; see
+/// [`pda_address`].
+pub fn check_vault(
+    account: &AccountView,
+    program_id: &Address,
+    state: &Address,
+    bump: Option<u8>,
+) -> Result<(), ProgramError> {
+    if account.address() != &pda_address(&[b"vault"], bump, program_id)? {
+        return Err(pda_mismatch(bump));
+    }
+
+    if !account.owned_by(program_id) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    // Any other size would also throw off the rent floor used on withdraw.
+    // Vaults created before the header have zero space until migrated.
+    if account.data_len() != VaultHeader::LEN {
+        log_error!("Vault has unexpected account data");
+        return Err(ShieldedPoolError::VaultHasData.into());
+    }
+
+    let data = account.try_borrow()?;
+    let header: &VaultHeader = bytemuck::from_bytes(&data[..VaultHeader::LEN]);
+    if header.discriminator != VaultHeader::DISCRIMINATOR
+        || header.asset_id != VaultHeader::NATIVE_ASSET_ID
+        || &header.state != state.as_array()
+    {
+        log_error!("Invalid vault header");
+        return Err(ShieldedPoolError::InvalidVaultHeader.into());
+    }
+    Ok(())
+}
+
+/// Validate the vault like [`check_vault`], then return the lamports it can
+/// pay out while staying rent-exempt. The rent floor is only computed once
+/// the account is known to be the pool's vault.
+pub fn check_vault_withdrawable(
+    account: &AccountView,
+    program_id: &Address,
+    state: &Address,
+    bump: Option<u8>,
+    rent: &Rent,
+) -> Result<u64, ProgramError> {
+    check_vault(account, program_id, state, bump)?;
+    account
+        .lamports()
+        .checked_sub(rent.try_minimum_balance(VaultHeader::LEN)?)
+        .ok_or(ProgramError::InsufficientFunds)
+}
+
+/// Take `amount` lamports out of a checked vault, keeping it rent-exempt.
+/// Dipping below the rent floor is `InsufficientFunds`; a balance below
+/// `amount` itself means the caller's own balance check was skipped, and is
+/// reported as `ArithmeticOverflow`.
+pub fn debit_vault(vault: &AccountView, amount: u64, rent: &Rent) -> Result<(), ProgramError> {
+    let new_balance = vault
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if new_balance < rent.try_minimum_balance(VaultHeader::LEN)? {
+        log_error!("Withdrawal would leave the vault below its rent floor");
+        return Err(ProgramError::InsufficientFunds);
+    }
+    vault.set_lamports(new_balance);
+    Ok(())
+}
+
+/// Validate the audit record PDA for `wa_commitment` (address, owner, size,
+/// discriminator, stored commitment, verified flag) and return a copy of it.
+/// A [`AuditRecord::LEGACY_LEN`] record is returned as verified. A
+/// client-supplied `bump` replaces the bump search; see [`pda_address`].
+pub fn load_audit_record(
+    account: &AccountView,
+    program_id: &Address,
+    wa_commitment: &[u8; 32],
+    bump: Option<u8>,
+) -> Result<AuditRecord, ProgramError> {
+    let derived_audit_pda = pda_address(&[b"audit", wa_commitment], bump, program_id)?;
+    if account.address() != &derived_audit_pda {
+        log_error!("Invalid Audit Record PDA");
+        return Err(pda_mismatch(bump));
+    }
+
+    // A missing record belongs to the system program, so check for it before
+    // the owner.
+    if account.lamports() == 0 {
+        log_error!("Audit Record not found (Submission required)");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    if !account.owned_by(program_id) {
+        log_error!("Audit Record not owned by program");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let len = account.data_len();
+    if len != AuditRecord::LEN && len != AuditRecord::V0_LEN && len != AuditRecord::LEGACY_LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut record = AuditRecord::zeroed();
+    bytemuck::bytes_of_mut(&mut record)[..len].copy_from_slice(&account.try_borrow()?);
+    if len == AuditRecord::LEGACY_LEN {
+        record.verified = 1;
+    }
+    // A funded, program-owned record of the right size was written by this
+    // program, so anything else in it is corruption rather than a missing
+    // record.
+    if !record.is_initialized() {
+        log_error!("Audit Record has the wrong discriminator");
+        return Err(ShieldedPoolError::AuditRecordCorrupt.into());
+    }
+    if &record.wa_commitment != wa_commitment {
+        log_error!("Audit Record mismatch");
+        return Err(ShieldedPoolError::AuditRecordCorrupt.into());
+    }
+    if !record.is_verified() {
+        log_error!("Audit Record was not written by a verified audit");
+        return Err(ShieldedPoolError::AuditRecordCorrupt.into());
+    }
+    Ok(record)
+}
+
+/// Header of a nullifier-set shard.
+///
+/// Spent nullifiers whose standalone PDA has been closed to reclaim rent are
+/// recorded here instead, so they stay un-reusable. Shards are PDAs seeded by
+/// [`NullifierSeeds::shard`] and hold `count` 32-byte nullifiers back
+/// to back after the header, sorted ascending so withdrawals look them up by
+/// binary search.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct NullifierSet {
+    /// Discriminator to identify account type
+    pub discriminator: [u8; 8],
+    /// Number of nullifiers stored after the header
+    pub count: u32,
+    /// Padding to align to 8 bytes
+    pub _padding: [u8; 4],
+}
+
+impl NullifierSet {
+    pub const LEN: usize = core::mem::size_of::<NullifierSet>();
+    pub const DISCRIMINATOR: [u8; 8] = *b"nullset_";
+    pub const ENTRY_LEN: usize = 32;
+    /// Most nullifiers a shard holds, which bounds its size and the lookup
+    /// every withdrawal does in it. Once a shard is full, nullifiers of that
+    /// shard keep their standalone PDA.
+    pub const CAPACITY: usize = 256;
+
+    pub fn is_initialized(&self) -> bool {
+        self.discriminator == Self::DISCRIMINATOR
+    }
+
+    /// Position of `nullifier` among a shard's sorted entries (`Ok`), or
+    /// where it would be inserted (`Err`), from the shard's full account
+    /// data. Data that is not a well-formed shard is an error.
+    pub fn search(data: &[u8], nullifier: &[u8; 32]) -> Result<Result<usize, usize>, ProgramError> {
+        let (header, entries) = data
+            .split_at_checked(Self::LEN)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        let header: &NullifierSet = bytemuck::from_bytes(header);
+        let count = header.count as usize;
+        if !header.is_initialized()
+            || count > Self::CAPACITY
+            || entries.len() != count * Self::ENTRY_LEN
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let entries: &[[u8; 32]] = bytemuck::cast_slice(entries);
+        Ok(entries.binary_search(nullifier))
+    }
+
+    /// Check if `nullifier` is recorded in a shard's full account data.
+    pub fn contains(data: &[u8], nullifier: &[u8; 32]) -> bool {
+        matches!(Self::search(data, nullifier), Ok(Ok(_)))
+    }
+}
+
+/// PDA seeds of a nullifier or of its nullifier-set shard, scoped to an
+/// asset.
+///
+/// Seeds are `[prefix, asset_id, key]`, so equal nullifiers of different
+/// assets never share a PDA while a nullifier stays spent within its asset.
+/// The native asset keeps the legacy `[prefix, key]` seeds, so the PDAs and
+/// shards written before nullifiers were asset-scoped still block their notes.
+pub struct NullifierSeeds<'a> {
+    seeds: [&'a [u8]; 3],
+    len: usize,
+}
+
+impl<'a> NullifierSeeds<'a> {
+    /// Seeds of the PDA a spend of `nullifier` creates.
+    pub fn nullifier(asset_id: &'a [u8; 32], nullifier: &'a [u8; 32]) -> Self {
+        Self::new(b"nullifier", asset_id, nullifier)
+    }
+
+    /// Seeds of the shard recording `nullifier` once its PDA is reclaimed:
+    /// the key is the nullifier's first byte.
+    pub fn shard(asset_id: &'a [u8; 32], nullifier: &'a [u8; 32]) -> Self {
+        Self::new(b"nullifier_set", asset_id, &nullifier[..1])
+    }
+
+    fn new(prefix: &'a [u8], asset_id: &'a [u8; 32], key: &'a [u8]) -> Self {
+        if asset_id == &VaultHeader::NATIVE_ASSET_ID {
+            Self {
+                seeds: [prefix, key, &[]],
+                len: 2,
+            }
+        } else {
+            Self {
+                seeds: [prefix, asset_id, key],
+                len: 3,
+            }
+        }
+    }
+
+    pub fn as_slice(&self) -> &[&'a [u8]] {
+        &self.seeds[..self.len]
+    }
+
+    /// The canonical PDA and its bump.
+    pub fn find_address(&self, program_id: &Address) -> (Address, u8) {
+        Address::find_program_address(self.as_slice(), program_id)
+    }
+
+    /// Signer seeds for creating the PDA: the first `len` of the returned
+    /// seeds, ending with `bump`.
+    pub fn with_bump<'b>(&'b self, bump: &'b [u8; 1]) -> ([Seed<'b>; 4], usize) {
+        let [first, second, third] = self.seeds;
+        let mut seeds = [
+            Seed::from(first),
+            Seed::from(second),
+            Seed::from(third),
+            Seed::from(bump),
+        ];
+        seeds[self.len] = Seed::from(bump);
+        (seeds, self.len + 1)
+    }
+}
+
+/// Data of a nullifier PDA created ahead of its withdrawal by
+/// `ReserveNullifier`. Nullifier PDAs created by a withdrawal have no data.
+pub const NULLIFIER_RESERVED: u8 = 1;
+/// Data of a reserved nullifier PDA once its withdrawal went through.
+pub const NULLIFIER_SPENT: u8 = 2;
+
+/// Whether an existing nullifier PDA is only reserved, so its note is not
+/// spent yet.
+pub fn is_nullifier_reserved(
+    account: &AccountView,
+    program_id: &Address,
+) -> Result<bool, ProgramError> {
+    Ok(account.owned_by(program_id) && *account.try_borrow()? == [NULLIFIER_RESERVED])
+}
+
+/// Header of the `["root_snapshots"]` PDA.
+///
+/// Point-in-time record of the pool root for external auditors: a ring of
+/// `CAPACITY` `RootEntry`s after the header, each the current root and the
+/// slot at which the authority recorded it.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct RootSnapshots {
+    /// Discriminator to identify account type
+    pub discriminator: [u8; 8],
+    /// Snapshots recorded so far; the next one goes to `count % CAPACITY`
+    pub count: u32,
+    /// Padding to align to 8 bytes
+    pub _padding: [u8; 4],
+}
+
+impl RootSnapshots {
+    pub const LEN: usize = core::mem::size_of::<RootSnapshots>();
+    pub const DISCRIMINATOR: [u8; 8] = *b"rootsnap";
+    /// Snapshots kept before the oldest is overwritten.
+    pub const CAPACITY: usize = 64;
+    /// Full account size, header and entries.
+    pub const ACCOUNT_LEN: usize = Self::LEN + Self::CAPACITY * RootEntry::LEN;
+
+    pub fn is_initialized(&self) -> bool {
+        self.discriminator == Self::DISCRIMINATOR
+    }
+}
+
+/// Validate the root snapshots PDA (address, owner, size, discriminator).
+pub fn check_root_snapshots(
+    account: &AccountView,
+    program_id: &Address,
+) -> Result<(), ProgramError> {
+    if account.address() != &Address::find_program_address(&[b"root_snapshots"], program_id).0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !account.owned_by(program_id) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+    if account.data_len() != RootSnapshots::ACCOUNT_LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let data = account.try_borrow()?;
+    if !bytemuck::from_bytes::<RootSnapshots>(&data[..RootSnapshots::LEN]).is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(())
+}
+
+/// Header of a `["viewing", wa_commitment, viewing_key]` PDA.
+///
+/// Holds a note payload the depositor encrypted to an auditor's viewing key,
+/// for selective disclosure of the identity `wa_commitment` commits to. The
+/// `data_len` bytes of ciphertext follow the header.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ViewingData {
+    /// Discriminator to identify account type
+    pub discriminator: [u8; 8],
+    /// The wa_commitment the payload discloses
+    pub wa_commitment: [u8; 32],
+    /// Auditor viewing key the payload is encrypted to
+    pub viewing_key: [u8; 32],
+    /// Account that registered (and paid for) the payload
+    pub registrant: [u8; 32],
+    /// Length of the encrypted payload after the header
+    pub data_len: u32,
+    /// Padding to align to 8 bytes
+    pub _padding: [u8; 4],
+}
+
+impl ViewingData {
+    pub const LEN: usize = core::mem::size_of::<ViewingData>();
+    pub const DISCRIMINATOR: [u8; 8] = *b"viewdata";
+    /// Largest encrypted payload a registration may store.
+    pub const MAX_DATA_LEN: usize = 512;
+
+    pub fn is_initialized(&self) -> bool {
+        self.discriminator == Self::DISCRIMINATOR
+    }
+}
+
+/// A `["recipient_limit", recipient]` PDA: what the recipient has received in
+/// its current window, while the pool caps withdrawals per recipient.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct RecipientLimit {
+    /// Discriminator to identify account type
+    pub discriminator: [u8; 8],
+    /// Lamports withdrawn to the recipient since `window_start_slot`
+    pub withdrawn_in_window: u64,
+    /// Slot at which the current window opened
+    pub window_start_slot: u64,
+}
+
+impl RecipientLimit {
+    pub const LEN: usize = core::mem::size_of::<RecipientLimit>();
+    pub const DISCRIMINATOR: [u8; 8] = *b"rcptlimt";
+
+    pub fn is_initialized(&self) -> bool {
+        self.discriminator == Self::DISCRIMINATOR
+    }
+}
+
+// On-chain layouts. A struct edit that changes a size fails here; for the
+// state, record the new size as a new entry in `VERSION_LENS`.
+const _: () = assert!(
+    ShieldedPoolState::LEN == ShieldedPoolState::VERSION_LENS[ShieldedPoolState::VERSION as usize]
+);
+const _: () = assert!(AuditRecord::LEN == 120);
+const _: () = assert!(VaultHeader::LEN == 72);
+const _: () = assert!(NullifierSet::LEN == 16);
+const _: () = assert!(RootSnapshots::LEN == 16);
+const _: () = assert!(RootEntry::LEN == 40);
+const _: () = assert!(ViewingData::LEN == 112);
+const _: () = assert!(RecipientLimit::LEN == 24);
+
+// Account data is 8-byte aligned; no account type may need more.
+const _: () = assert!(core::mem::align_of::<ShieldedPoolState>() <= 8);
+const _: () = assert!(core::mem::align_of::<AuditRecord>() <= 8);
+const _: () = assert!(core::mem::align_of::<VaultHeader>() <= 8);
+const _: () = assert!(core::mem::align_of::<NullifierSet>() <= 8);
+const _: () = assert!(core::mem::align_of::<RootSnapshots>() <= 8);
+const _: () = assert!(core::mem::align_of::<RootEntry>() <= 8);
+const _: () = assert!(core::mem::align_of::<ViewingData>() <= 8);
+const _: () = assert!(core::mem::align_of::<RecipientLimit>() <= 8);
+
+// Every program-owned account type starts with a distinct discriminator, so
+// no account can be loaded as another type. New account types go here too.
+const _: () = assert!(discriminators_distinct(&[
+    ShieldedPoolState::DISCRIMINATOR,
+    AuditRecord::DISCRIMINATOR,
+    VaultHeader::DISCRIMINATOR,
+    NullifierSet::DISCRIMINATOR,
+    RootSnapshots::DISCRIMINATOR,
+    ViewingData::DISCRIMINATOR,
+    RecipientLimit::DISCRIMINATOR,
+]));
+
+const fn discriminators_distinct(discriminators: &[[u8; 8]]) -> bool {
+    let mut i = 0;
+    while i < discriminators.len() {
+        let mut j = i + 1;
+        while j < discriminators.len() {
+            if u64::from_le_bytes(discriminators[i]) == u64::from_le_bytes(discriminators[j]) {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use pinocchio::account::{RuntimeAccount, NOT_BORROWED};
+
+    use super::*;
+
+    /// A data-less vault holding `lamports`.
+    fn vault(lamports: u64) -> Box<RuntimeAccount> {
+        Box::new(RuntimeAccount {
+            borrow_state: NOT_BORROWED,
+            is_writable: 1,
+            lamports,
+            ..RuntimeAccount::default()
+        })
+    }
+
+    fn debit(account: &mut RuntimeAccount, amount: u64) -> Result<u64, ProgramError> {
+        // SAFETY: `account` outlives the view and carries no data.
+        let view = unsafe { AccountView::new_unchecked(account) };
+        debit_vault(&view, amount, &runtime::rent()?)?;
+        Ok(view.lamports())
+    }
+
+    fn rent_floor() -> u64 {
+        runtime::rent()
+            .unwrap()
+            .try_minimum_balance(VaultHeader::LEN)
+            .unwrap()
+    }
+
+    #[test]
+    fn field_element_round_trips_a_u64() {
+        for value in [0, 1, 0x0102_0304_0506_0708, u64::MAX] {
+            let element = FieldElement::from_u64(value);
+            assert_eq!(element.0[..24], [0u8; 24]);
+            assert_eq!(element.0[24..], value.to_be_bytes());
+            assert_eq!(element.to_u64_be(), Ok(value));
+        }
+    }
+
+    #[test]
+    fn field_element_wider_than_a_u64_is_out_of_range() {
+        for byte in [0, 23] {
+            let mut element = FieldElement::from_u64(1);
+            element.0[byte] = 1;
+            assert_eq!(
+                element.to_u64_be(),
+                Err(ShieldedPoolError::AmountOutOfRange.into())
+            );
+        }
+    }
+
+    #[test]
+    fn debit_vault_may_leave_exactly_the_rent_floor() {
+        let floor = rent_floor();
+        let mut account = vault(floor + 500);
+        assert_eq!(debit(&mut account, 500), Ok(floor));
+        assert_eq!(debit(&mut account, 0), Ok(floor));
+    }
+
+    #[test]
+    fn debit_vault_below_the_rent_floor_is_insufficient_funds() {
+        let floor = rent_floor();
+        let mut account = vault(floor + 500);
+        assert_eq!(
+            debit(&mut account, 501),
+            Err(ProgramError::InsufficientFunds)
+        );
+        assert_eq!(account.lamports, floor + 500);
+    }
+
+    #[test]
+    fn debit_vault_past_its_balance_is_an_overflow() {
+        let mut account = vault(500);
+        assert_eq!(
+            debit(&mut account, 501),
+            Err(ProgramError::ArithmeticOverflow)
+        );
+        assert_eq!(
+            debit(&mut account, u64::MAX),
+            Err(ProgramError::ArithmeticOverflow)
+        );
+        assert_eq!(account.lamports, 500);
+    }
 }
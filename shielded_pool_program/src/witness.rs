@@ -0,0 +1,93 @@
+use solana_program_error::ProgramError;
+
+use crate::state::FieldElement;
+
+/// Header preceding the public inputs in a gnark public witness:
+/// [public_input_count: u32 BE][secret_count: u32 BE][vector_len: u32 BE]
+pub const WITNESS_HEADER_LEN: usize = 12;
+/// Upper bound on public inputs accepted from a witness header: enough for a
+/// withdraw circuit with every split payout, the recipient program and the
+/// pool domain.
+pub const MAX_PUBLIC_INPUTS: usize = 15;
+
+/// Public inputs of a `[proof][witness]` payload, 32 bytes each.
+pub struct PublicInputs<'a> {
+    inputs: &'a [u8],
+}
+
+impl PublicInputs<'_> {
+    pub fn len(&self) -> usize {
+        self.inputs.len() / 32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// The `index`-th public input.
+    pub fn get(&self, index: usize) -> Result<FieldElement, ProgramError> {
+        self.inputs
+            .get(index * 32..index * 32 + 32)
+            .and_then(|input| input.try_into().ok())
+            .map(FieldElement)
+            .ok_or(ProgramError::InvalidInstructionData)
+    }
+
+    /// Whether the leading public inputs are exactly `expected`, byte for byte.
+    pub fn starts_with(&self, expected: &[[u8; 32]]) -> bool {
+        self.inputs.len() >= expected.len() * 32
+            && self
+                .inputs
+                .chunks_exact(32)
+                .zip(expected)
+                .all(|(input, expected)| input == expected)
+    }
+}
+
+/// Length of the `[proof][witness]` payload at the start of `data`, per the
+/// public input count in the witness header. Anything after it is left to the
+/// instruction.
+pub fn payload_len(data: &[u8], proof_len: usize) -> Result<usize, ProgramError> {
+    let count = header_count(data, proof_len)?;
+    Ok(proof_len + WITNESS_HEADER_LEN + count * 32)
+}
+
+/// Parse `[proof][witness]` instruction data, honoring the public input count
+/// in the witness header. Requires at least `min_inputs` public inputs and
+/// that `data` is exactly as long as the header says.
+pub fn parse_public_inputs(
+    data: &[u8],
+    proof_len: usize,
+    min_inputs: usize,
+) -> Result<PublicInputs<'_>, ProgramError> {
+    let count = header_count(data, proof_len)?;
+    if count < min_inputs {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let inputs_start = proof_len + WITNESS_HEADER_LEN;
+    if data.len() != inputs_start + count * 32 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(PublicInputs {
+        inputs: &data[inputs_start..],
+    })
+}
+
+/// Public input count from the witness header, bounded by `MAX_PUBLIC_INPUTS`.
+fn header_count(data: &[u8], proof_len: usize) -> Result<usize, ProgramError> {
+    let header = data
+        .get(proof_len..proof_len + WITNESS_HEADER_LEN)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let count = u32::from_be_bytes(
+        header[0..4]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    ) as usize;
+
+    if count > MAX_PUBLIC_INPUTS {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(count)
+}
@@ -0,0 +1,151 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{proof, witness, Pool, PROOF_REJECTED};
+use shielded_pool_pinocchio::{
+    client,
+    instructions::{
+        get_audit_record::AUDIT_RECORD_RETURN_DATA_LEN,
+        submit_audit::{AUDIT_PROOF_LEN, AUDIT_VERIFIER_PROGRAM_ID},
+    },
+    state::{AuditRecord, ShieldedPoolState},
+};
+use solana_program_error::ProgramError;
+
+#[test]
+fn get_audit_record_returns_the_stored_audit() {
+    let mut pool = Pool::new();
+    let wa_commitment = [0x5a; 32];
+    pool.bank.set_slot(77);
+    pool.audit(&wa_commitment);
+
+    pool.bank
+        .process(&client::get_audit_record(&wa_commitment))
+        .unwrap();
+    let data = pool.bank.return_data();
+    assert_eq!(data.len(), AUDIT_RECORD_RETURN_DATA_LEN);
+    assert_eq!(&data[..32], &wa_commitment);
+    assert_eq!(&data[32..64], &[0x0c; 32]);
+    assert_eq!(&data[64..96], &pool.authority.to_bytes());
+    assert_eq!(data[96..], 77u64.to_le_bytes());
+
+    let record = client::audit_record_pda(&wa_commitment).0;
+    let stored: AuditRecord = *bytemuck::from_bytes(pool.bank.data(&record));
+    assert_eq!(stored.version, AuditRecord::VERSION);
+    assert!(stored.is_verified());
+}
+
+#[test]
+fn get_audit_record_fails_for_a_missing_record() {
+    let mut pool = Pool::new();
+    assert_eq!(
+        pool.bank.process(&client::get_audit_record(&[0x5b; 32])),
+        Err(ProgramError::UninitializedAccount)
+    );
+}
+
+fn audit_payload(wa_commitment: [u8; 32], valid: bool) -> (Vec<u8>, Vec<u8>) {
+    (
+        proof(AUDIT_PROOF_LEN, valid),
+        witness(&[wa_commitment, [0x0c; 32]]),
+    )
+}
+
+#[test]
+fn submit_audit_batch_verifies_each_audit_separately() {
+    let mut pool = Pool::new();
+    let audits = [
+        audit_payload([0x51; 32], true),
+        audit_payload([0x52; 32], true),
+    ];
+    let ix = client::submit_audit_batch(
+        &pool.authority,
+        &audits
+            .iter()
+            .map(|(proof, witness)| (proof.as_slice(), witness.as_slice()))
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+    pool.bank.cpis();
+    pool.bank.process(&ix).unwrap();
+
+    let cpis = pool.bank.cpis();
+    assert_eq!(cpis.len(), 2);
+    for ((program_id, data), (proof, witness)) in cpis.iter().zip(&audits) {
+        assert_eq!(program_id, &AUDIT_VERIFIER_PROGRAM_ID);
+        assert_eq!(data, &[proof.as_slice(), witness.as_slice()].concat());
+    }
+    for wa_commitment in [[0x51; 32], [0x52; 32]] {
+        pool.bank
+            .process(&client::get_audit_record(&wa_commitment))
+            .unwrap();
+    }
+
+    // Existing records are skipped without another proof.
+    pool.bank.process(&ix).unwrap();
+    assert!(pool.bank.cpis().is_empty());
+}
+
+#[test]
+fn submit_audit_batch_fails_if_any_audit_fails() {
+    let mut pool = Pool::new();
+    let audits = [
+        audit_payload([0x51; 32], true),
+        audit_payload([0x52; 32], false),
+    ];
+    let ix = client::submit_audit_batch(
+        &pool.authority,
+        &audits
+            .iter()
+            .map(|(proof, witness)| (proof.as_slice(), witness.as_slice()))
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+    assert_eq!(pool.bank.process(&ix), Err(PROOF_REJECTED));
+    assert_eq!(
+        pool.bank.process(&client::get_audit_record(&[0x51; 32])),
+        Err(ProgramError::UninitializedAccount)
+    );
+
+    // Each record needs its own payload.
+    let mut ix = ix;
+    ix.data
+        .truncate(ix.data.len() - audits[1].0.len() - audits[1].1.len());
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn an_audit_record_is_never_loaded_as_the_pool_state() {
+    let mut pool = Pool::new();
+    let wa_commitment = [0x5a; 32];
+    pool.audit(&wa_commitment);
+    let record = client::audit_record_pda(&wa_commitment).0;
+    let record_data = pool.bank.data(&record).to_vec();
+    assert_eq!(record_data[..8], AuditRecord::DISCRIMINATOR);
+
+    // Passed in the state's place.
+    let mut ix = client::get_pool_info();
+    ix.accounts[0].address = record.clone();
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ProgramError::InvalidAccountData)
+    );
+
+    // Written to the state PDA, as is or padded out to a state's size.
+    let state = client::state_pda().0;
+    let lamports = pool.bank.lamports(&state);
+    let mut padded = record_data.clone();
+    padded.resize(ShieldedPoolState::LEN, 0);
+    for (data, expected) in [
+        (record_data, ProgramError::InvalidAccountData),
+        (padded, ProgramError::UninitializedAccount),
+    ] {
+        pool.bank
+            .set_account(&state, &shielded_pool_pinocchio::ID, lamports, &data);
+        assert_eq!(pool.bank.process(&client::get_pool_info()), Err(expected));
+    }
+}
@@ -0,0 +1,157 @@
+#![cfg(feature = "client")]
+
+//! The `client` builders must produce data the program's own parsers read
+//! back unchanged.
+
+mod common;
+
+use common::{address, address_input, amount_input, pool_config, proof, witness, PROOF_LEN};
+use shielded_pool_pinocchio::{
+    client,
+    instructions::{
+        deposit::DepositArgs,
+        initialize::{InitializeArgs, InitializeParams},
+        instruction,
+        withdraw::{parse_withdraw_witness, WITHDRAW_ACCOUNTS},
+    },
+};
+
+#[test]
+fn initialize_round_trips() {
+    let mut config = pool_config();
+    config.deposit_fee_bps = 125;
+    config.min_withdraw = 7;
+    config.min_deposit_age_slots = 9;
+    config.initial_root = None;
+    let ix = client::initialize(&address(0xa1), &config);
+
+    assert_eq!(ix.data[0], instruction::INITIALIZE);
+    let args = InitializeArgs::parse(&ix.data[1..]).unwrap();
+    assert_eq!(
+        args,
+        InitializeArgs {
+            deposit_fee_bps: 125,
+            fee_collector: config.fee_collector.to_bytes(),
+            tree_depth: config.tree_depth,
+            min_withdraw: 7,
+            proof_len: config.proof_len,
+            public_input_count: config.public_input_count,
+            min_deposit_age_slots: 9,
+        }
+    );
+    assert_eq!(ix.accounts[1].address, client::state_pda().0);
+    assert_eq!(ix.accounts[2].address, client::vault_pda().0);
+}
+
+#[test]
+fn initialize_params_round_trip() {
+    let params = InitializeParams {
+        withdraw_fee_bps: 30,
+        recipient_max_per_window: 2,
+        recipient_window_slots: 100,
+        rebate_mint: address(0xd0).to_bytes(),
+        rebate_amount: 10,
+        co_authority_count: 1,
+        co_authorities: [address(0xa2).to_bytes(), [0u8; 32]],
+        public_input_order: [1, 0, 2, 3, 4],
+        ..Default::default()
+    };
+    let ix = client::initialize_with_params(&address(0xa1), &pool_config(), &params);
+
+    let params_start = 1 + InitializeArgs::LEN + 32;
+    assert_eq!(ix.data.len(), params_start + InitializeParams::LEN);
+    assert_eq!(
+        InitializeParams::parse(&ix.data[params_start..]).unwrap(),
+        params
+    );
+    assert_eq!(ix.accounts[5].address, address(0xd0));
+}
+
+#[test]
+fn deposit_round_trips() {
+    let commitment = [0x11; 32];
+    let ix = client::deposit(&address(1), 42, &commitment, &[0x22; 32], &[0x33; 32]);
+
+    assert_eq!(ix.data[0], instruction::DEPOSIT);
+    assert_eq!(
+        DepositArgs::parse(&ix.data[1..]).unwrap(),
+        DepositArgs {
+            amount: 42,
+            commitment,
+            prev_root: [0x22; 32],
+            new_root: [0x33; 32],
+        }
+    );
+    assert_eq!(
+        ix.accounts[5].address,
+        client::commitment_pda(&commitment).0
+    );
+}
+
+#[test]
+fn withdraw_round_trips() {
+    let recipient = address(2);
+    let inputs = [
+        [0x0a; 32],
+        [0x4e; 32],
+        address_input(&recipient),
+        amount_input(1_000),
+        [0x3a; 32],
+    ];
+    let ix = client::withdraw(
+        &address(9),
+        &recipient,
+        &proof(PROOF_LEN, true),
+        &witness(&inputs),
+    )
+    .unwrap();
+
+    assert_eq!(ix.data[0], instruction::WITHDRAW);
+    assert_eq!(ix.accounts.len(), WITHDRAW_ACCOUNTS);
+    let parsed = parse_withdraw_witness(&ix.data[1..], PROOF_LEN, &[0, 1, 2, 3, 4]).unwrap();
+    assert_eq!(parsed.root, inputs[0]);
+    assert_eq!(parsed.nullifier, inputs[1]);
+    assert_eq!(parsed.recipient, inputs[2]);
+    assert_eq!(parsed.amount.to_u64_be().unwrap(), 1_000);
+    assert_eq!(parsed.wa_commitment, inputs[4]);
+    assert!(parsed.change_roots.is_empty());
+    assert_eq!((parsed.vault_bump, parsed.audit_bump), (None, None));
+    assert_eq!(ix.accounts[4].address, client::nullifier_pda(&inputs[1]).0);
+    assert_eq!(
+        ix.accounts[6].address,
+        client::audit_record_pda(&inputs[4]).0
+    );
+}
+
+#[test]
+fn withdraw_with_change_and_bumps_round_trips() {
+    let recipient = address(2);
+    let inputs = [
+        [0x0a; 32],
+        [0x4e; 32],
+        address_input(&recipient),
+        amount_input(1_000),
+        [0x3a; 32],
+        [0x5c; 32],
+    ];
+    let ix = client::withdraw_with_change(
+        &address(9),
+        &recipient,
+        &proof(PROOF_LEN, true),
+        &witness(&inputs),
+        &[0x66; 32],
+        &[0x77; 32],
+    )
+    .unwrap();
+    let ix = client::with_pda_bumps(ix, &inputs[4]);
+
+    let parsed = parse_withdraw_witness(&ix.data[1..], PROOF_LEN, &[0, 1, 2, 3, 4]).unwrap();
+    assert_eq!(parsed.change_commitment, inputs[5]);
+    assert_eq!(parsed.change_roots[..32], [0x66; 32]);
+    assert_eq!(parsed.change_roots[32..], [0x77; 32]);
+    assert_eq!(parsed.vault_bump, Some(client::vault_pda().1));
+    assert_eq!(
+        parsed.audit_bump,
+        Some(client::audit_record_pda(&inputs[4]).1)
+    );
+}
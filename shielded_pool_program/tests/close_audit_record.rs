@@ -0,0 +1,72 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{address, pool_config, proof, Pool, Spend, PROOF_LEN};
+use shielded_pool_pinocchio::client;
+use solana_program_error::ProgramError;
+
+fn close(pool: &mut Pool, spend: &Spend, valid: bool) -> Result<(), ProgramError> {
+    let authority = pool.authority.clone();
+    let ix = client::close_audit_record(
+        &authority,
+        &pool_config().fee_collector,
+        &proof(PROOF_LEN, valid),
+        &spend.witness(),
+    )?;
+    pool.bank.process(&ix)
+}
+
+#[test]
+fn close_audit_record_refuses_an_unspent_note() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let record = client::audit_record_pda(&spend.wa_commitment).0;
+    let rent = pool.bank.lamports(&record);
+
+    assert_eq!(
+        close(&mut pool, &spend, true),
+        Err(ProgramError::UninitializedAccount)
+    );
+    assert_eq!(pool.bank.lamports(&record), rent);
+}
+
+#[test]
+fn close_audit_record_refunds_a_spent_note_to_the_treasury() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    pool.bank.process(&ix).unwrap();
+
+    let record = client::audit_record_pda(&spend.wa_commitment).0;
+    let treasury = pool_config().fee_collector;
+    let rent = pool.bank.lamports(&record);
+    let treasury_before = pool.bank.lamports(&treasury);
+
+    // The proof must tie the spent nullifier to the record's identity.
+    assert!(close(&mut pool, &spend, false).is_err());
+
+    // Only the authority may close records.
+    let mut ix = client::close_audit_record(
+        &address(0x77),
+        &treasury,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    pool.bank.fund(&address(0x77), 1_000_000);
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ProgramError::IncorrectAuthority)
+    );
+    ix.accounts[0].address = pool.authority.clone();
+    pool.bank.process(&ix).unwrap();
+
+    assert_eq!(pool.bank.lamports(&record), 0);
+    assert_eq!(pool.bank.lamports(&treasury), treasury_before + rent);
+}
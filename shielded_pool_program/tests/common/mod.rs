@@ -0,0 +1,577 @@
+//! Host test bank: owns account buffers laid out as the loader passes them,
+//! runs instructions through `process_instruction` and serves the runtime via
+//! `runtime::host`, with a mock verifier in place of the proof programs and a
+//! mock SPL Token `TransferChecked` for the rebate.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+pub use pinocchio::Address;
+use pinocchio::{
+    account::{RuntimeAccount, NOT_BORROWED},
+    AccountView,
+};
+use shielded_pool_pinocchio::{
+    client::{self, Instruction, PoolConfig},
+    instructions::{
+        configure_rebate::TOKEN_PROGRAM_ID, submit_audit::AUDIT_VERIFIER_PROGRAM_ID,
+        withdraw::ZK_VERIFIER_PROGRAM_ID,
+    },
+    runtime::host,
+    state::{FieldElement, ShieldedPoolState},
+    witness::WITNESS_HEADER_LEN,
+};
+use solana_program_error::{ProgramError, ProgramResult};
+
+/// Withdraw proof length the test pools are configured with.
+pub const PROOF_LEN: usize = 64;
+/// Leading proof byte the mock verifiers accept; any other proof fails.
+pub const VALID_PROOF: u8 = 1;
+/// Error the mock verifiers return for a rejected proof.
+pub const PROOF_REJECTED: ProgramError = ProgramError::Custom(0xbad);
+
+/// Room for account data, enough for any account the program creates or
+/// grows in a test.
+const DATA_CAPACITY: usize = 64 * 1024;
+const HEADER_LEN: usize = core::mem::size_of::<RuntimeAccount>();
+
+/// One account, as a `RuntimeAccount` header followed by its data.
+struct Buffer(Box<[u64]>);
+
+impl Buffer {
+    fn new(address: &Address) -> Self {
+        let mut buffer = Buffer(vec![0u64; (HEADER_LEN + DATA_CAPACITY).div_ceil(8)].into());
+        let header = buffer.header_mut();
+        header.borrow_state = NOT_BORROWED;
+        header.address = address.clone();
+        header.owner = pinocchio_system::ID;
+        buffer
+    }
+
+    fn header(&self) -> &RuntimeAccount {
+        // SAFETY: the buffer starts with a header and is aligned to 8 bytes.
+        unsafe { &*self.0.as_ptr().cast::<RuntimeAccount>() }
+    }
+
+    fn header_mut(&mut self) -> &mut RuntimeAccount {
+        // SAFETY: as above.
+        unsafe { &mut *self.0.as_mut_ptr().cast::<RuntimeAccount>() }
+    }
+
+    fn data(&self) -> &[u8] {
+        let len = self.header().data_len as usize;
+        // SAFETY: the data follows the header and `data_len` fits the buffer.
+        unsafe { core::slice::from_raw_parts(self.0.as_ptr().cast::<u8>().add(HEADER_LEN), len) }
+    }
+
+    fn view(&mut self) -> AccountView {
+        // SAFETY: the buffer outlives every view `Bank::process` hands out.
+        unsafe { AccountView::new_unchecked(self.0.as_mut_ptr().cast::<RuntimeAccount>()) }
+    }
+}
+
+/// Accounts by address, and the runtime around them.
+pub struct Bank {
+    accounts: HashMap<Address, Buffer>,
+}
+
+impl Default for Bank {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bank {
+    /// An empty bank at slot 0, with both verifiers mocked.
+    pub fn new() -> Self {
+        host::reset();
+        host::set_cpi_handler(mock_verifiers);
+        Self {
+            accounts: HashMap::new(),
+        }
+    }
+
+    /// Sets `address`'s balance, creating it as a system account if needed.
+    pub fn fund(&mut self, address: &Address, lamports: u64) {
+        self.buffer(address).header_mut().lamports = lamports;
+    }
+
+    /// Replaces `address` with an account holding `data`.
+    pub fn set_account(&mut self, address: &Address, owner: &Address, lamports: u64, data: &[u8]) {
+        let buffer = self.buffer(address);
+        let header = buffer.header_mut();
+        header.owner = owner.clone();
+        header.lamports = lamports;
+        header.data_len = data.len() as u64;
+        // SAFETY: `data` fits the buffer's data capacity.
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                buffer.0.as_mut_ptr().cast::<u8>().add(HEADER_LEN),
+                data.len(),
+            )
+        };
+    }
+
+    pub fn lamports(&self, address: &Address) -> u64 {
+        self.accounts
+            .get(address)
+            .map_or(0, |buffer| buffer.header().lamports)
+    }
+
+    pub fn data(&self, address: &Address) -> &[u8] {
+        self.accounts
+            .get(address)
+            .map_or(&[], |buffer| buffer.data())
+    }
+
+    pub fn owner(&self, address: &Address) -> Address {
+        self.accounts
+            .get(address)
+            .map_or(pinocchio_system::ID, |buffer| buffer.header().owner.clone())
+    }
+
+    /// Balance of an SPL Token account.
+    pub fn token_balance(&self, address: &Address) -> u64 {
+        let data = self.data(address);
+        u64::from_le_bytes(data[64..72].try_into().unwrap())
+    }
+
+    pub fn state(&self) -> ShieldedPoolState {
+        let data = self.data(&client::state_pda().0);
+        *bytemuck::from_bytes(&data[..ShieldedPoolState::LEN])
+    }
+
+    /// Overwrites the pool state, keeping any root history stored past it.
+    pub fn set_state(&mut self, state: &ShieldedPoolState) {
+        let address = client::state_pda().0;
+        let mut data = self.data(&address).to_vec();
+        data[..ShieldedPoolState::LEN].copy_from_slice(bytemuck::bytes_of(state));
+        let lamports = self.lamports(&address);
+        self.set_account(&address, &shielded_pool_pinocchio::ID, lamports, &data);
+    }
+
+    pub fn set_slot(&mut self, slot: u64) {
+        host::set_slot(slot);
+    }
+
+    pub fn set_remaining_compute_units(&mut self, units: u64) {
+        host::set_remaining_compute_units(units);
+    }
+
+    /// Return data of the last instruction.
+    pub fn return_data(&self) -> Vec<u8> {
+        host::return_data()
+    }
+
+    /// Events logged since the last call; a failed instruction discards the
+    /// pending ones.
+    pub fn events(&self) -> Vec<Vec<Vec<u8>>> {
+        host::take_events()
+    }
+
+    /// Routes non-system CPIs to `handler` instead of `mock_verifiers`.
+    pub fn set_cpi_handler(
+        &mut self,
+        handler: impl FnMut(&Address, &[u8], &[&AccountView]) -> ProgramResult + 'static,
+    ) {
+        host::set_cpi_handler(handler);
+    }
+
+    /// A view of `address` for a CPI handler to inspect while an
+    /// instruction runs. Only valid while the bank is alive.
+    pub fn view(&mut self, address: &Address) -> AccountView {
+        self.buffer(address).view()
+    }
+
+    /// Non-system CPIs issued since the last call, as (program, data).
+    pub fn cpis(&self) -> Vec<(Address, Vec<u8>)> {
+        host::take_cpis()
+            .into_iter()
+            .map(|cpi| (cpi.program_id, cpi.data))
+            .collect()
+    }
+
+    /// Runs `ix` like a transaction: on failure every account is rolled
+    /// back, and on success lamports must balance.
+    pub fn process(&mut self, ix: &Instruction) -> ProgramResult {
+        let mut signers = HashMap::<Address, (bool, bool)>::new();
+        for meta in &ix.accounts {
+            let flags = signers.entry(meta.address.clone()).or_default();
+            flags.0 |= meta.is_signer;
+            flags.1 |= meta.is_writable;
+        }
+        for (address, (is_signer, is_writable)) in &signers {
+            let header = self.buffer(address).header_mut();
+            header.is_signer = *is_signer as u8;
+            header.is_writable = *is_writable as u8;
+            header.resize_delta = 0;
+            header.borrow_state = NOT_BORROWED;
+        }
+
+        let snapshot: Vec<(Address, Box<[u64]>)> = signers
+            .keys()
+            .map(|address| (address.clone(), self.accounts[address].0.clone()))
+            .collect();
+        let lamports_before: u128 = signers
+            .keys()
+            .map(|address| self.lamports(address) as u128)
+            .sum();
+
+        let views: Vec<AccountView> = ix
+            .accounts
+            .iter()
+            .map(|meta| self.accounts.get_mut(&meta.address).unwrap().view())
+            .collect();
+        host::set_return_data(&[]);
+        let result = shielded_pool_pinocchio::process_instruction(&ix.program_id, &views, &ix.data);
+
+        if result.is_err() {
+            for (address, buffer) in snapshot {
+                self.accounts.get_mut(&address).unwrap().0 = buffer;
+            }
+            host::take_events();
+        } else {
+            let lamports_after: u128 = signers
+                .keys()
+                .map(|address| self.lamports(address) as u128)
+                .sum();
+            assert_eq!(lamports_before, lamports_after, "lamports not conserved");
+        }
+        result
+    }
+
+    /// Runs `ixs` in order as one transaction: if any fails, every account
+    /// is rolled back to before the first.
+    pub fn process_transaction(&mut self, ixs: &[Instruction]) -> ProgramResult {
+        let snapshot: Vec<(Address, Box<[u64]>)> = ixs
+            .iter()
+            .flat_map(|ix| &ix.accounts)
+            .map(|meta| (meta.address.clone(), self.buffer(&meta.address).0.clone()))
+            .collect();
+        for ix in ixs {
+            if let Err(err) = self.process(ix) {
+                for (address, buffer) in snapshot {
+                    self.accounts.get_mut(&address).unwrap().0 = buffer;
+                }
+                host::take_events();
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn buffer(&mut self, address: &Address) -> &mut Buffer {
+        self.accounts
+            .entry(address.clone())
+            .or_insert_with(|| Buffer::new(address))
+    }
+}
+
+/// Accepts a verifier CPI iff its proof starts with `VALID_PROOF`, and moves
+/// tokens for a `TransferChecked` [source, mint, destination, authority].
+pub fn mock_verifiers(
+    program_id: &Address,
+    data: &[u8],
+    accounts: &[&AccountView],
+) -> ProgramResult {
+    if program_id == &TOKEN_PROGRAM_ID {
+        let amount = u64::from_le_bytes(data[1..9].try_into().unwrap());
+        let mut source = accounts[0].try_borrow_mut()?;
+        let balance = u64::from_le_bytes(source[64..72].try_into().unwrap());
+        let remaining = balance
+            .checked_sub(amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        source[64..72].copy_from_slice(&remaining.to_le_bytes());
+        let mut destination = accounts[2].try_borrow_mut()?;
+        let balance = u64::from_le_bytes(destination[64..72].try_into().unwrap());
+        destination[64..72].copy_from_slice(&(balance + amount).to_le_bytes());
+        return Ok(());
+    }
+    if program_id != &ZK_VERIFIER_PROGRAM_ID && program_id != &AUDIT_VERIFIER_PROGRAM_ID {
+        return Ok(());
+    }
+    match data.first() {
+        Some(&VALID_PROOF) => Ok(()),
+        _ => Err(PROOF_REJECTED),
+    }
+}
+
+/// A new address, distinct per `seed`.
+pub fn address(seed: u8) -> Address {
+    let mut bytes = [0xa0; 32];
+    bytes[0] = seed;
+    Address::new_from_array(bytes)
+}
+
+/// Proof bytes the mock verifiers accept (`valid`) or reject.
+pub fn proof(len: usize, valid: bool) -> Vec<u8> {
+    vec![if valid { VALID_PROOF } else { 0 }; len]
+}
+
+/// A public witness carrying `inputs`.
+pub fn witness(inputs: &[[u8; 32]]) -> Vec<u8> {
+    let count = (inputs.len() as u32).to_be_bytes();
+    let mut witness = Vec::with_capacity(WITNESS_HEADER_LEN + inputs.len() * 32);
+    witness.extend_from_slice(&count);
+    witness.extend_from_slice(&0u32.to_be_bytes());
+    witness.extend_from_slice(&count);
+    for input in inputs {
+        witness.extend_from_slice(input);
+    }
+    witness
+}
+
+/// `address` encoded as a public input, as the circuits do.
+pub fn address_input(address: &Address) -> [u8; 32] {
+    let mut input = [0u8; 32];
+    input[2..].copy_from_slice(&address.as_ref()[..30]);
+    input
+}
+
+pub fn amount_input(amount: u64) -> [u8; 32] {
+    FieldElement::from_u64(amount).0
+}
+
+/// A pool configuration with no fees, guards or minimums.
+pub fn pool_config() -> PoolConfig {
+    PoolConfig {
+        deposit_fee_bps: 0,
+        fee_collector: address(0xfc),
+        tree_depth: 20,
+        min_withdraw: 0,
+        proof_len: PROOF_LEN as u32,
+        public_input_count: 5,
+        min_deposit_age_slots: 0,
+        initial_root: Some([0xee; 32]),
+    }
+}
+
+/// Builder-style tweaks of a test `PoolConfig`.
+pub trait PoolConfigExt {
+    fn deposit_fee_bps(self, bps: u16) -> Self;
+    fn public_input_count(self, count: u8) -> Self;
+}
+
+impl PoolConfigExt for PoolConfig {
+    fn deposit_fee_bps(mut self, bps: u16) -> Self {
+        self.deposit_fee_bps = bps;
+        self
+    }
+
+    fn public_input_count(mut self, count: u8) -> Self {
+        self.public_input_count = count;
+        self
+    }
+}
+
+/// Gross amount of the note `Pool::with_note` deposits.
+pub const NOTE: u64 = 2_000_000_000;
+
+/// Pool state, vault and a funded authority in a fresh bank.
+pub struct Pool {
+    pub bank: Bank,
+    pub authority: Address,
+    next_leaf: u8,
+}
+
+impl Pool {
+    pub fn new() -> Self {
+        Self::with_config(&pool_config())
+    }
+
+    pub fn with_config(config: &PoolConfig) -> Self {
+        let mut bank = Bank::new();
+        let authority = address(0xa1);
+        bank.fund(&authority, 100_000_000_000);
+        bank.process(&client::initialize(&authority, config))
+            .expect("initialize");
+        Self {
+            bank,
+            authority,
+            next_leaf: 0,
+        }
+    }
+
+    /// A pool holding one deposited note whose identity passed its audit,
+    /// and the spend withdrawing that note to a fresh recipient.
+    pub fn with_note(config: &PoolConfig) -> (Self, Spend) {
+        let mut pool = Self::with_config(config);
+        let depositor = address(1);
+        pool.bank.fund(&depositor, 10 * NOTE);
+        let (_, root) = pool.deposit(&depositor, NOTE);
+        let wa_commitment = [0x3a; 32];
+        pool.audit(&wa_commitment);
+
+        let spend = Spend {
+            root,
+            nullifier: [0x4e; 32],
+            recipient: address(2),
+            amount: NOTE,
+            wa_commitment,
+            extra_inputs: Vec::new(),
+        };
+        (pool, spend)
+    }
+
+    /// A funded relayer to pay for withdrawals.
+    pub fn relayer(&mut self) -> Address {
+        let relayer = address(9);
+        if self.bank.lamports(&relayer) == 0 {
+            self.bank.fund(&relayer, 1_000_000_000);
+        }
+        relayer
+    }
+
+    /// Runs an instruction signed by the pool authority.
+    pub fn configure(&mut self, ix: &Instruction) {
+        self.bank.process(ix).expect("configure");
+    }
+
+    pub fn vault(&self) -> Address {
+        client::vault_pda().0
+    }
+
+    /// Deposits `amount` from `payer` as a fresh note, returning its
+    /// commitment and the root that contains it.
+    pub fn deposit(&mut self, payer: &Address, amount: u64) -> ([u8; 32], [u8; 32]) {
+        self.next_leaf += 1;
+        let commitment = [self.next_leaf; 32];
+        let mut new_root = [0x70; 32];
+        new_root[0] = self.next_leaf;
+        let prev_root = self.bank.state().current_root;
+        self.bank
+            .process(&client::deposit(
+                payer,
+                amount,
+                &commitment,
+                &prev_root,
+                &new_root,
+            ))
+            .expect("deposit");
+        (commitment, new_root)
+    }
+
+    /// Stores a verified audit record for `wa_commitment`.
+    pub fn audit(&mut self, wa_commitment: &[u8; 32]) {
+        let ix = client::submit_audit(
+            &self.authority,
+            &proof(
+                shielded_pool_pinocchio::instructions::submit_audit::AUDIT_PROOF_LEN,
+                true,
+            ),
+            &witness(&[*wa_commitment, [0x0c; 32]]),
+        )
+        .unwrap();
+        self.bank.process(&ix).expect("submit audit");
+    }
+}
+
+/// The rebate a pool pays from `Pool::enable_rebate`.
+pub struct Rebate {
+    pub mint: Address,
+    pub vault: Address,
+    pub amount: u64,
+}
+
+/// Lamports that keep a token account or mint alive in tests.
+const TOKEN_RENT: u64 = 2_039_280;
+
+impl Pool {
+    /// Turns on a rebate of `amount` tokens, paid from a vault holding
+    /// `funded` of them.
+    pub fn enable_rebate(&mut self, amount: u64, funded: u64) -> Rebate {
+        let mint = address(0xd0);
+        let mut mint_data = vec![0u8; 82];
+        mint_data[44] = 6;
+        mint_data[45] = 1;
+        self.bank
+            .set_account(&mint, &TOKEN_PROGRAM_ID, TOKEN_RENT, &mint_data);
+        let vault = address(0xd1);
+        self.bank.set_account(
+            &vault,
+            &TOKEN_PROGRAM_ID,
+            TOKEN_RENT,
+            &token_account(&mint, &client::rebate_authority_pda().0, funded),
+        );
+        let authority = self.authority.clone();
+        self.configure(&client::configure_rebate(&authority, true, &mint, amount));
+        Rebate {
+            mint,
+            vault,
+            amount,
+        }
+    }
+
+    /// The rebate accounts for paying `recipient`, creating its empty token
+    /// account.
+    pub fn rebate_accounts(
+        &mut self,
+        rebate: &Rebate,
+        recipient: &Address,
+    ) -> Vec<client::AccountMeta> {
+        let token = rebate_token_account(recipient);
+        if self.bank.lamports(&token) == 0 {
+            self.bank.set_account(
+                &token,
+                &TOKEN_PROGRAM_ID,
+                TOKEN_RENT,
+                &token_account(&rebate.mint, recipient, 0),
+            );
+        }
+        client::rebate_accounts(&rebate.vault, &token, &rebate.mint)
+    }
+}
+
+/// `recipient`'s token account for the rebate mint.
+pub fn rebate_token_account(recipient: &Address) -> Address {
+    let mut bytes = recipient.to_bytes();
+    bytes[31] ^= 0xff;
+    Address::new_from_array(bytes)
+}
+
+/// SPL Token account data: [mint][owner][amount], zero-padded.
+fn token_account(mint: &Address, owner: &Address, amount: u64) -> Vec<u8> {
+    let mut data = vec![0u8; 165];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[64..72].copy_from_slice(&amount.to_le_bytes());
+    data
+}
+
+impl Default for Pool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Public inputs of a withdraw in the default order.
+#[derive(Clone)]
+pub struct Spend {
+    pub root: [u8; 32],
+    pub nullifier: [u8; 32],
+    pub recipient: Address,
+    pub amount: u64,
+    pub wa_commitment: [u8; 32],
+    /// Inputs after the first five, for wider circuits
+    pub extra_inputs: Vec<[u8; 32]>,
+}
+
+impl Spend {
+    pub fn inputs(&self) -> Vec<[u8; 32]> {
+        let mut inputs = vec![
+            self.root,
+            self.nullifier,
+            address_input(&self.recipient),
+            amount_input(self.amount),
+            self.wa_commitment,
+        ];
+        inputs.extend_from_slice(&self.extra_inputs);
+        inputs
+    }
+
+    pub fn witness(&self) -> Vec<u8> {
+        witness(&self.inputs())
+    }
+}
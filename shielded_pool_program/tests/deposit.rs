@@ -0,0 +1,391 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{address, pool_config, Pool, PoolConfigExt, NOTE};
+use shielded_pool_pinocchio::{
+    client,
+    client::PoolConfig,
+    error::ShieldedPoolError,
+    event::{DepositEvent, EventKind},
+    instructions::{deposit::MAX_ENCRYPTED_NOTE_LEN, instruction},
+};
+use solana_program_error::ProgramError;
+
+#[test]
+fn deposit_moves_funds_into_the_vault() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let vault_before = pool.bank.lamports(&pool.vault());
+
+    let (commitment, root) = pool.deposit(&depositor, NOTE);
+
+    assert_eq!(pool.bank.lamports(&pool.vault()), vault_before + NOTE);
+    assert_eq!(pool.bank.lamports(&depositor), 9 * NOTE);
+    let state = pool.bank.state();
+    assert_eq!(state.current_root, root);
+    assert_eq!(state.next_leaf_index, 1);
+    assert_eq!(state.deposit_count, 1);
+    assert_eq!(state.total_shielded, NOTE);
+
+    let events = pool.bank.events();
+    let deposit_event = &events.last().unwrap()[0];
+    assert_eq!(&deposit_event[1..33], &commitment);
+}
+
+#[test]
+fn deposit_on_a_stale_root_is_rejected() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let stale = pool.bank.state().current_root;
+    pool.deposit(&depositor, NOTE);
+
+    let ix = client::deposit(&depositor, NOTE, &[7; 32], &stale, &[8; 32]);
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::DepositRootRace.into())
+    );
+    assert_eq!(pool.bank.state().next_leaf_index, 1);
+}
+
+#[test]
+fn zero_deposit_is_rejected() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, NOTE);
+    let root = pool.bank.state().current_root;
+
+    let ix = client::deposit(&depositor, 0, &[7; 32], &root, &[8; 32]);
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::ZeroAmount.into())
+    );
+}
+
+#[test]
+fn deposit_only_takes_a_single_note() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let prev_root = pool.bank.state().current_root;
+
+    // A batch's layout under the `Deposit` discriminator.
+    let mut ix = client::deposit_batch(
+        &depositor,
+        &[(NOTE, [7; 32]), (NOTE, [8; 32])],
+        &prev_root,
+        &[9; 32],
+    );
+    ix.data[0] = instruction::DEPOSIT;
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(pool.bank.state().deposit_count, 0);
+}
+
+#[test]
+fn deposits_past_the_last_leaf_of_a_depth_10_tree_are_rejected() {
+    let mut pool = Pool::with_config(&PoolConfig {
+        tree_depth: 10,
+        ..pool_config()
+    });
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let mut state = pool.bank.state();
+    assert_eq!(state.tree_depth, 10);
+    state.next_leaf_index = (1 << 10) - 2;
+    pool.bank.set_state(&state);
+
+    pool.deposit(&depositor, NOTE);
+
+    // Two notes don't fit in the last leaf...
+    let batch = client::deposit_batch(
+        &depositor,
+        &[(NOTE, [0xb1; 32]), (NOTE, [0xb2; 32])],
+        &pool.bank.state().current_root,
+        &[0xb3; 32],
+    );
+    assert_eq!(
+        pool.bank.process(&batch),
+        Err(ShieldedPoolError::TreeFull.into())
+    );
+
+    // ...one does, and then the tree is full.
+    let (_, root) = pool.deposit(&depositor, NOTE);
+    assert_eq!(pool.bank.state().next_leaf_index, 1 << 10);
+    let ix = client::deposit(&depositor, NOTE, &[0xb4; 32], &root, &[0xb5; 32]);
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::TreeFull.into())
+    );
+    assert_eq!(pool.bank.state().total_shielded, 2 * NOTE);
+}
+
+#[test]
+fn a_deposit_that_leaves_the_root_unchanged_is_rejected() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let root = pool.bank.state().current_root;
+
+    let ix = client::deposit(&depositor, NOTE, &[7; 32], &root, &root);
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::StaleRoot.into())
+    );
+    let batch = client::deposit_batch(&depositor, &[(NOTE, [7; 32])], &root, &root);
+    assert_eq!(
+        pool.bank.process(&batch),
+        Err(ShieldedPoolError::StaleRoot.into())
+    );
+
+    let state = pool.bank.state();
+    assert_eq!(state.next_leaf_index, 0);
+    assert_eq!(state.total_shielded, 0);
+    assert_eq!(pool.bank.lamports(&depositor), 10 * NOTE);
+}
+
+#[test]
+fn the_second_of_two_deposits_built_on_one_root_must_rebuild() {
+    let mut pool = Pool::new();
+    let (alice, bob) = (address(1), address(2));
+    pool.bank.fund(&alice, 10 * NOTE);
+    pool.bank.fund(&bob, 10 * NOTE);
+    let shared = pool.bank.state().current_root;
+
+    pool.bank
+        .process(&client::deposit(
+            &alice,
+            NOTE,
+            &[0xa1; 32],
+            &shared,
+            &[0xa2; 32],
+        ))
+        .unwrap();
+
+    let single = client::deposit(&bob, NOTE, &[0xb1; 32], &shared, &[0xb2; 32]);
+    let batch = client::deposit_batch(&bob, &[(NOTE, [0xb1; 32])], &shared, &[0xb2; 32]);
+    for ix in [single, batch] {
+        assert_eq!(
+            pool.bank.process(&ix),
+            Err(ShieldedPoolError::DepositRootRace.into())
+        );
+    }
+    assert_eq!(pool.bank.lamports(&bob), 10 * NOTE);
+
+    // Rebuilt on top of Alice's note, Bob's lands.
+    pool.bank
+        .process(&client::deposit(
+            &bob,
+            NOTE,
+            &[0xb1; 32],
+            &[0xa2; 32],
+            &[0xb3; 32],
+        ))
+        .unwrap();
+    let state = pool.bank.state();
+    assert_eq!(state.current_root, [0xb3; 32]);
+    assert_eq!(state.next_leaf_index, 2);
+}
+
+#[test]
+fn a_batch_logs_one_deposit_event_per_leaf() {
+    let mut pool = Pool::with_config(&pool_config().deposit_fee_bps(100));
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    pool.deposit(&depositor, NOTE);
+    let entries = [
+        (NOTE, [0xb1; 32]),
+        (2 * NOTE, [0xb2; 32]),
+        (NOTE, [0xb3; 32]),
+    ];
+    let batch = client::deposit_batch(
+        &depositor,
+        &entries,
+        &pool.bank.state().current_root,
+        &[0xb4; 32],
+    );
+    pool.bank.events();
+
+    pool.bank.process(&batch).unwrap();
+
+    let events = pool.bank.events();
+    assert_eq!(events.len(), entries.len());
+    for (leaf_index, (event, (amount, commitment))) in (1u64..).zip(events.iter().zip(entries)) {
+        assert_eq!(event.len(), 1);
+        assert_eq!(event[0].len(), DepositEvent::LEN);
+        assert_eq!(event[0][0], EventKind::Deposit as u8);
+        assert_eq!(event[0][1..33], commitment);
+        assert_eq!(event[0][33..41], leaf_index.to_le_bytes());
+        // Net of the 1% deposit fee.
+        assert_eq!(event[0][41..49], (amount - amount / 100).to_le_bytes());
+    }
+    assert_eq!(pool.bank.state().next_leaf_index, 4);
+}
+
+#[test]
+fn a_batch_may_fill_the_tree_but_not_overflow_it() {
+    let mut pool = Pool::with_config(&PoolConfig {
+        tree_depth: 10,
+        ..pool_config()
+    });
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let mut state = pool.bank.state();
+    state.next_leaf_index = (1 << 10) - 3;
+    pool.bank.set_state(&state);
+    let current_root = state.current_root;
+    let entries = [(NOTE, [0xb1; 32]), (NOTE, [0xb2; 32]), (NOTE, [0xb3; 32])];
+
+    // One leaf too many is rejected whole.
+    let overflowing = [entries.as_slice(), &[(NOTE, [0xb4; 32])]].concat();
+    let batch = client::deposit_batch(&depositor, &overflowing, &current_root, &[0xb5; 32]);
+    assert_eq!(
+        pool.bank.process(&batch),
+        Err(ShieldedPoolError::TreeFull.into())
+    );
+    assert_eq!(pool.bank.state().next_leaf_index, (1 << 10) - 3);
+
+    let batch = client::deposit_batch(&depositor, &entries, &current_root, &[0xb5; 32]);
+    pool.bank.process(&batch).unwrap();
+    let state = pool.bank.state();
+    assert_eq!(state.next_leaf_index, 1 << 10);
+    assert_eq!(state.total_shielded, 3 * NOTE);
+
+    let batch = client::deposit_batch(&depositor, &[(NOTE, [0xb6; 32])], &[0xb5; 32], &[0xb7; 32]);
+    assert_eq!(
+        pool.bank.process(&batch),
+        Err(ShieldedPoolError::TreeFull.into())
+    );
+}
+
+#[test]
+fn deposit_rejects_a_vault_without_its_header() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let vault = pool.vault();
+    let lamports = pool.bank.lamports(&vault);
+    let header = pool.bank.data(&vault).to_vec();
+    let prev_root = pool.bank.state().current_root;
+    let ix = client::deposit(&depositor, NOTE, &[0x01; 32], &prev_root, &[0x71; 32]);
+
+    // A zero-data account at the vault address, the program's or not.
+    pool.bank
+        .set_account(&vault, &shielded_pool_pinocchio::ID, lamports, &[]);
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::VaultHasData.into())
+    );
+    pool.bank
+        .set_account(&vault, &pinocchio_system::ID, lamports, &[]);
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ProgramError::InvalidAccountOwner)
+    );
+    assert_eq!(pool.bank.lamports(&vault), lamports);
+    assert_eq!(pool.bank.state().deposit_count, 0);
+
+    pool.bank
+        .set_account(&vault, &shielded_pool_pinocchio::ID, lamports, &header);
+    pool.bank.process(&ix).unwrap();
+    assert_eq!(pool.bank.lamports(&vault), lamports + NOTE);
+}
+
+#[test]
+fn a_repeated_commitment_is_rejected_once_the_guard_is_on() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let commitment = [0xc1; 32];
+    let deposit = |pool: &mut Pool, new_root: u8| {
+        let prev_root = pool.bank.state().current_root;
+        pool.bank.process(&client::deposit(
+            &depositor,
+            NOTE,
+            &commitment,
+            &prev_root,
+            &[new_root; 32],
+        ))
+    };
+
+    // Without the guard a commitment may repeat, and leaves no marker.
+    deposit(&mut pool, 0x71).unwrap();
+    deposit(&mut pool, 0x72).unwrap();
+    let marker = client::commitment_pda(&commitment).0;
+    assert_eq!(pool.bank.lamports(&marker), 0);
+
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_commitment_guard(&authority, true));
+    deposit(&mut pool, 0x73).unwrap();
+    assert_eq!(pool.bank.owner(&marker), shielded_pool_pinocchio::ID);
+
+    let before = pool.bank.state();
+    assert_eq!(
+        deposit(&mut pool, 0x74),
+        Err(ShieldedPoolError::DuplicateCommitment.into())
+    );
+    let state = pool.bank.state();
+    assert_eq!(state.current_root, before.current_root);
+    assert_eq!(state.next_leaf_index, 3);
+    assert_eq!(state.total_shielded, 3 * NOTE);
+}
+
+#[test]
+fn deposit_rejects_a_bogus_system_program() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let prev_root = pool.bank.state().current_root;
+    let mut ix = client::deposit(&depositor, NOTE, &[0x01; 32], &prev_root, &[0x71; 32]);
+    ix.accounts[4].address = address(0x5b);
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ProgramError::IncorrectProgramId)
+    );
+    assert_eq!(pool.bank.state().deposit_count, 0);
+}
+
+#[test]
+fn a_deposit_with_note_logs_the_note_up_to_its_maximum_length() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let prev_root = pool.bank.state().current_root;
+    let deposit_with_note = |encrypted_note: &[u8]| {
+        client::deposit_with_note(
+            &depositor,
+            NOTE,
+            &[0x01; 32],
+            &prev_root,
+            &[0x71; 32],
+            encrypted_note,
+        )
+    };
+
+    for len in [0, MAX_ENCRYPTED_NOTE_LEN + 1] {
+        assert_eq!(
+            pool.bank.process(&deposit_with_note(&vec![0xe7; len])),
+            Err(ProgramError::InvalidInstructionData),
+            "{len} byte note"
+        );
+    }
+    assert_eq!(pool.bank.state().deposit_count, 0);
+
+    let encrypted_note = [0xe7; MAX_ENCRYPTED_NOTE_LEN];
+    pool.bank.events();
+    pool.bank
+        .process(&deposit_with_note(&encrypted_note))
+        .unwrap();
+
+    let events = pool.bank.events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].len(), 2);
+    assert_eq!(events[0][0][1..33], [0x01; 32]);
+    assert_eq!(events[0][1], encrypted_note);
+    assert_eq!(pool.bank.state().deposit_count, 1);
+}
@@ -0,0 +1,107 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{address, pool_config, Address, Pool, NOTE};
+use shielded_pool_pinocchio::{client, error::ShieldedPoolError, state::ShieldedPoolState};
+
+#[test]
+fn emergency_withdraw_drains_the_vault_and_its_accounting() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    pool.deposit(&depositor, NOTE);
+    pool.deposit(&depositor, NOTE);
+    assert_eq!(pool.bank.state().total_shielded, 2 * NOTE);
+
+    let authority = pool.authority.clone();
+    let treasury = pool_config().fee_collector;
+    pool.configure(&client::initiate_emergency(&authority));
+    assert_eq!(
+        pool.bank
+            .process(&client::emergency_withdraw(&authority, &treasury)),
+        Err(ShieldedPoolError::EmergencyTimelock.into())
+    );
+
+    pool.bank
+        .set_slot(ShieldedPoolState::EMERGENCY_TIMELOCK_SLOTS);
+    pool.configure(&client::emergency_withdraw(&authority, &treasury));
+
+    assert_eq!(pool.bank.lamports(&treasury), 2 * NOTE);
+    assert_eq!(pool.bank.state().total_shielded, 0);
+}
+
+#[test]
+fn emergency_withdraw_drains_donated_lamports_too() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    pool.deposit(&depositor, NOTE);
+    // Lamports sent straight to the vault are not shielded.
+    let vault = pool.vault();
+    let donation = 12_345;
+    pool.bank
+        .fund(&vault, pool.bank.lamports(&vault) + donation);
+
+    let authority = pool.authority.clone();
+    let treasury = pool_config().fee_collector;
+    pool.configure(&client::initiate_emergency(&authority));
+    pool.bank
+        .set_slot(ShieldedPoolState::EMERGENCY_TIMELOCK_SLOTS);
+    pool.configure(&client::emergency_withdraw(&authority, &treasury));
+
+    assert_eq!(pool.bank.lamports(&treasury), NOTE + donation);
+    assert_eq!(pool.bank.state().total_shielded, 0);
+}
+
+/// A pool whose authority shares control with two co-authorities, any
+/// `threshold` of the three signing.
+fn shared_pool(threshold: u8) -> (Pool, [Address; 2]) {
+    let mut pool = Pool::new();
+    let authority = pool.authority.clone();
+    let co_authorities = [address(0xa2), address(0xa3)];
+    pool.configure(&client::configure_authorities(
+        &authority,
+        threshold,
+        &co_authorities,
+    ));
+    (pool, co_authorities)
+}
+
+fn pause(pool: &Pool, co_signers: &[Address]) -> client::Instruction {
+    client::with_co_signers(client::initiate_emergency(&pool.authority), co_signers)
+}
+
+#[test]
+fn a_2_of_3_pool_pauses_with_two_signatures_but_not_one() {
+    let (mut pool, co_authorities) = shared_pool(2);
+    let authority = pool.authority.clone();
+
+    // The authority alone, the authority again, or a signer that is no
+    // authority at all: one signature each time.
+    for co_signers in [vec![], vec![authority], vec![address(0x77)]] {
+        assert_eq!(
+            pool.bank.process(&pause(&pool, &co_signers)),
+            Err(ShieldedPoolError::AuthorityThresholdNotMet.into())
+        );
+    }
+    assert_eq!(pool.bank.state().paused, 0);
+
+    pool.configure(&pause(&pool, &co_authorities[1..]));
+    assert_eq!(pool.bank.state().paused, 1);
+}
+
+#[test]
+fn a_repeated_co_signer_counts_once() {
+    let (mut pool, co_authorities) = shared_pool(3);
+    let repeated = [co_authorities[0].clone(), co_authorities[0].clone()];
+
+    assert_eq!(
+        pool.bank.process(&pause(&pool, &repeated)),
+        Err(ShieldedPoolError::AuthorityThresholdNotMet.into())
+    );
+    assert_eq!(pool.bank.state().paused, 0);
+
+    pool.configure(&pause(&pool, &co_authorities));
+    assert_eq!(pool.bank.state().paused, 1);
+}
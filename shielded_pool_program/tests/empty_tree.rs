@@ -0,0 +1,73 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{address, pool_config, Bank};
+use shielded_pool_pinocchio::{
+    client,
+    state::{empty_tree_root, zeros},
+};
+
+/// Empty-subtree hashes of circomlib's Poseidon over a zero leaf.
+const POSEIDON_ZERO_1: &str = "2098f5fb9e239eab3ceac3f27b81e481dc3124d55ffed523a839ee8446b64864";
+const EMPTY_ROOT_20: &str = "2134e76ac5d21aab186c2be1dd8f84ee880a1e46eaf712f9d371b6df22191f3e";
+const EMPTY_ROOT_32: &str = "2f68a1c58e257e42a17a6c61dff5551ed560b9922ab119d5ac8e184c9734ead9";
+
+fn hex(value: &str) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (byte, pair) in bytes.iter_mut().zip(value.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap();
+    }
+    bytes
+}
+
+#[test]
+fn zeros_hash_up_from_the_zero_leaf() {
+    assert_eq!(zeros(0).unwrap(), [0u8; 32]);
+    assert_eq!(zeros(1).unwrap(), hex(POSEIDON_ZERO_1));
+}
+
+#[test]
+fn empty_tree_roots_match_the_reference_vectors() {
+    assert_eq!(empty_tree_root(20).unwrap(), hex(EMPTY_ROOT_20));
+    assert_eq!(empty_tree_root(32).unwrap(), hex(EMPTY_ROOT_32));
+}
+
+#[test]
+fn initialize_without_a_root_starts_at_the_empty_tree_root() {
+    let mut bank = Bank::new();
+    let authority = address(0xa1);
+    bank.fund(&authority, 100_000_000_000);
+    let config = client::PoolConfig {
+        initial_root: None,
+        ..pool_config()
+    };
+    bank.process(&client::initialize(&authority, &config))
+        .unwrap();
+
+    assert_eq!(config.tree_depth, 20);
+    let state = bank.state();
+    assert_eq!(state.current_root, hex(EMPTY_ROOT_20));
+    assert_eq!(state.empty_root().unwrap(), hex(EMPTY_ROOT_20));
+}
+
+#[test]
+fn initialize_stores_the_zero_roots() {
+    let mut bank = Bank::new();
+    let authority = address(0xa1);
+    bank.fund(&authority, 100_000_000_000);
+    bank.process(&client::initialize(&authority, &pool_config()))
+        .unwrap();
+
+    let state = bank.state();
+    assert_eq!(state.zero_roots[0], [0u8; 32]);
+    assert_eq!(state.zero_roots[1], hex(POSEIDON_ZERO_1));
+    for level in 0..20 {
+        assert_eq!(state.zero_roots[level as usize], zeros(level).unwrap());
+    }
+    assert!(state.zero_roots[20..]
+        .iter()
+        .all(|level| level == &[0u8; 32]));
+    // A given initial root is kept; the zero roots don't depend on it.
+    assert_eq!(state.current_root, [0xee; 32]);
+}
@@ -0,0 +1,31 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::Pool;
+use shielded_pool_pinocchio::{client, error::ShieldedPoolError, instructions::instruction};
+use solana_program_error::ProgramError;
+
+#[test]
+fn empty_instruction_data_is_a_missing_instruction() {
+    let mut pool = Pool::new();
+    let mut ix = client::get_pool_info();
+    ix.data.clear();
+
+    let err = pool.bank.process(&ix).unwrap_err();
+    assert_eq!(err, ShieldedPoolError::MissingInstruction.into());
+    assert_eq!(err, ProgramError::Custom(15));
+}
+
+#[test]
+fn an_unassigned_discriminator_is_an_unknown_instruction() {
+    let mut pool = Pool::new();
+    for discriminator in [instruction::CLOSE_AUDIT_RECORD + 1, u8::MAX] {
+        let mut ix = client::get_pool_info();
+        ix.data = vec![discriminator];
+
+        let err = pool.bank.process(&ix).unwrap_err();
+        assert_eq!(err, ShieldedPoolError::UnknownInstruction.into());
+        assert_eq!(err, ProgramError::Custom(16));
+    }
+}
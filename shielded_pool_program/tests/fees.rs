@@ -0,0 +1,249 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{address, pool_config, proof, Bank, Pool, PoolConfigExt, NOTE, PROOF_LEN};
+use shielded_pool_pinocchio::{
+    client,
+    error::ShieldedPoolError,
+    event::ConfigUpdatedEvent,
+    instructions::update_config::{
+        CONFIG_DEPOSIT_FEE_BPS, CONFIG_MIN_DEPOSIT_AGE_SLOTS, CONFIG_MIN_WITHDRAW,
+        CONFIG_WITHDRAW_FEE_BPS,
+    },
+    runtime,
+    state::ShieldedPoolState,
+};
+use solana_program_error::ProgramError;
+
+#[test]
+fn deposit_fee_goes_to_the_fee_vault() {
+    let mut pool = Pool::with_config(&pool_config().deposit_fee_bps(250));
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let fee_vault = client::fee_vault_pda().0;
+    let fee_vault_before = pool.bank.lamports(&fee_vault);
+    let vault_before = pool.bank.lamports(&pool.vault());
+
+    pool.deposit(&depositor, NOTE);
+
+    let fee = NOTE * 250 / 10_000;
+    assert_eq!(pool.bank.lamports(&fee_vault), fee_vault_before + fee);
+    assert_eq!(pool.bank.lamports(&pool.vault()), vault_before + NOTE - fee);
+    assert_eq!(pool.bank.state().total_shielded, NOTE - fee);
+}
+
+#[test]
+fn withdraw_fee_goes_to_the_fee_vault() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_withdraw_fee(&authority, 100));
+    let fee_vault = client::fee_vault_pda().0;
+    let fee_vault_before = pool.bank.lamports(&fee_vault);
+
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    pool.bank.process(&ix).unwrap();
+
+    let fee = NOTE / 100;
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE - fee);
+    assert_eq!(pool.bank.lamports(&fee_vault), fee_vault_before + fee);
+    assert_eq!(pool.bank.return_data()[8..16], fee.to_le_bytes());
+}
+
+#[test]
+fn the_first_withdraw_fee_creates_a_rent_exempt_fee_vault() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_withdraw_fee(&authority, 100));
+    // A pool from before the fee vault existed.
+    let fee_vault = client::fee_vault_pda().0;
+    pool.bank
+        .set_account(&fee_vault, &pinocchio_system::ID, 0, &[]);
+    let rent_floor = runtime::rent().unwrap().try_minimum_balance(0).unwrap();
+
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    pool.bank.process(&ix).unwrap();
+
+    // The payer funds the rent; the whole fee stays sweepable above it.
+    let fee = NOTE / 100;
+    assert_eq!(pool.bank.owner(&fee_vault), shielded_pool_pinocchio::ID);
+    assert_eq!(pool.bank.lamports(&fee_vault), rent_floor + fee);
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE - fee);
+
+    let treasury = pool_config().fee_collector;
+    pool.configure(&client::withdraw_fees(&authority, &treasury));
+    assert_eq!(pool.bank.lamports(&treasury), fee);
+    assert_eq!(pool.bank.lamports(&fee_vault), rent_floor);
+}
+
+#[test]
+fn relayer_registry_gates_the_submitter_not_the_fee() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_withdraw_fee(&authority, 100));
+    pool.configure(&client::configure_relayer_registry(&authority, true));
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::UnregisteredRelayer.into())
+    );
+
+    pool.configure(&client::update_relayer(&authority, &relayer, true));
+    let fee_vault = client::fee_vault_pda().0;
+    let fee_vault_before = pool.bank.lamports(&fee_vault);
+    let relayer_before = pool.bank.lamports(&relayer);
+    pool.bank.process(&ix).unwrap();
+
+    let fee = NOTE / 100;
+    assert_eq!(pool.bank.lamports(&fee_vault), fee_vault_before + fee);
+    assert!(pool.bank.lamports(&relayer) < relayer_before);
+}
+
+#[test]
+fn withdraw_fees_sweeps_the_fee_vault_to_the_treasury() {
+    let mut pool = Pool::with_config(&pool_config().deposit_fee_bps(250));
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    pool.deposit(&depositor, NOTE);
+    let treasury = pool_config().fee_collector;
+
+    let authority = pool.authority.clone();
+    // Only the stored treasury can receive the sweep.
+    let other = address(0x77);
+    assert_eq!(
+        pool.bank
+            .process(&client::withdraw_fees(&authority, &other)),
+        Err(ProgramError::InvalidAccountData)
+    );
+    assert_eq!(pool.bank.lamports(&other), 0);
+
+    pool.configure(&client::withdraw_fees(&authority, &treasury));
+
+    assert_eq!(pool.bank.lamports(&treasury), NOTE * 250 / 10_000);
+}
+
+#[test]
+fn initialize_rejects_a_deposit_fee_of_100_percent() {
+    let mut bank = Bank::new();
+    let authority = address(0xa1);
+    bank.fund(&authority, 100_000_000_000);
+
+    let full = pool_config().deposit_fee_bps(ShieldedPoolState::MAX_FEE_BPS);
+    assert_eq!(
+        bank.process(&client::initialize(&authority, &full)),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    let highest = pool_config().deposit_fee_bps(ShieldedPoolState::MAX_FEE_BPS - 1);
+    bank.process(&client::initialize(&authority, &highest))
+        .unwrap();
+    assert_eq!(
+        bank.state().deposit_fee_bps,
+        ShieldedPoolState::MAX_FEE_BPS - 1
+    );
+}
+
+#[test]
+fn fee_updates_reject_100_percent() {
+    let mut pool = Pool::new();
+    let authority = pool.authority.clone();
+
+    for update in [
+        client::ConfigUpdate {
+            deposit_fee_bps: Some(ShieldedPoolState::MAX_FEE_BPS),
+            ..Default::default()
+        },
+        client::ConfigUpdate {
+            withdraw_fee_bps: Some(ShieldedPoolState::MAX_FEE_BPS),
+            ..Default::default()
+        },
+    ] {
+        assert_eq!(
+            pool.bank
+                .process(&client::update_config(&authority, &update)),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+    assert_eq!(
+        pool.bank.process(&client::configure_withdraw_fee(
+            &authority,
+            ShieldedPoolState::MAX_FEE_BPS
+        )),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    let state = pool.bank.state();
+    assert_eq!(state.deposit_fee_bps, 0);
+    assert_eq!(state.withdraw_fee_bps, 0);
+}
+
+#[test]
+fn update_config_sets_every_field_at_once() {
+    let mut pool = Pool::new();
+    let authority = pool.authority.clone();
+    let update = client::ConfigUpdate {
+        deposit_fee_bps: Some(120),
+        withdraw_fee_bps: Some(45),
+        min_withdraw: Some(NOTE / 4),
+        min_deposit_age_slots: Some(30),
+    };
+    pool.bank.events();
+
+    pool.configure(&client::update_config(&authority, &update));
+
+    let state = pool.bank.state();
+    assert_eq!(state.deposit_fee_bps, 120);
+    assert_eq!(state.withdraw_fee_bps, 45);
+    assert_eq!(state.min_withdraw, NOTE / 4);
+    assert_eq!(state.min_deposit_age_slots, 30);
+    let expected = ConfigUpdatedEvent {
+        mask: CONFIG_DEPOSIT_FEE_BPS
+            | CONFIG_WITHDRAW_FEE_BPS
+            | CONFIG_MIN_WITHDRAW
+            | CONFIG_MIN_DEPOSIT_AGE_SLOTS,
+        deposit_fee_bps: 120,
+        withdraw_fee_bps: 45,
+        min_withdraw: NOTE / 4,
+        min_deposit_age_slots: 30,
+    };
+    assert_eq!(pool.bank.events(), [vec![expected.to_bytes().to_vec()]]);
+
+    // One out-of-range field fails the whole update.
+    let rejected = client::ConfigUpdate {
+        deposit_fee_bps: Some(10),
+        withdraw_fee_bps: Some(ShieldedPoolState::MAX_FEE_BPS),
+        min_withdraw: Some(1),
+        min_deposit_age_slots: Some(0),
+    };
+    assert_eq!(
+        pool.bank
+            .process(&client::update_config(&authority, &rejected)),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+        bytemuck::bytes_of(&pool.bank.state()),
+        bytemuck::bytes_of(&state)
+    );
+}
@@ -0,0 +1,260 @@
+#![cfg(feature = "client")]
+
+//! End-to-end flows through initialize, deposit, submit_audit and withdraw,
+//! checking the state each step leaves behind.
+
+mod common;
+
+use common::{address, pool_config, proof, Pool, Spend, NOTE, PROOF_LEN};
+use shielded_pool_pinocchio::{
+    client,
+    instructions::get_pool_info::POOL_INFO_RETURN_DATA_LEN,
+    state::{AuditRecord, ShieldedPoolState},
+};
+use solana_address::Address;
+use solana_program_error::ProgramError;
+
+/// A pool with a funded depositor, walked one instruction at a time.
+struct Flow {
+    pool: Pool,
+    depositor: Address,
+}
+
+impl Flow {
+    fn new() -> Self {
+        let mut pool = Pool::with_config(&pool_config());
+        let depositor = address(1);
+        pool.bank.fund(&depositor, 10 * NOTE);
+        Self { pool, depositor }
+    }
+
+    fn state(&self) -> ShieldedPoolState {
+        self.pool.bank.state()
+    }
+
+    fn vault_balance(&self) -> u64 {
+        self.pool.bank.lamports(&self.pool.vault())
+    }
+
+    /// Deposits one note, returning the root that contains it.
+    fn deposit(&mut self) -> [u8; 32] {
+        let depositor = self.depositor.clone();
+        self.pool.deposit(&depositor, NOTE).1
+    }
+
+    /// `GetPoolInfo`'s `(next_leaf_index, deposit_count, withdraw_count,
+    /// current_root)`.
+    fn pool_info(&mut self) -> (u64, u64, u64, [u8; 32]) {
+        self.pool.bank.process(&client::get_pool_info()).unwrap();
+        let data = self.pool.bank.return_data();
+        assert_eq!(data.len(), POOL_INFO_RETURN_DATA_LEN);
+        let u64_at = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+        (
+            u64_at(4),
+            u64_at(20),
+            u64_at(28),
+            data[36..68].try_into().unwrap(),
+        )
+    }
+
+    fn withdraw(&mut self, spend: &Spend) -> Result<(), ProgramError> {
+        let relayer = self.pool.relayer();
+        let ix = client::withdraw(
+            &relayer,
+            &spend.recipient,
+            &proof(PROOF_LEN, true),
+            &spend.witness(),
+        )?;
+        self.pool.bank.process(&ix)
+    }
+}
+
+fn spend(root: [u8; 32], nullifier: u8, recipient: u8, wa_commitment: [u8; 32]) -> Spend {
+    Spend {
+        root,
+        nullifier: [nullifier; 32],
+        recipient: address(recipient),
+        amount: NOTE,
+        wa_commitment,
+        extra_inputs: Vec::new(),
+    }
+}
+
+#[test]
+fn each_step_leaves_the_expected_state() {
+    let mut flow = Flow::new();
+
+    // Initialize: an empty tree on the seeded root, owned by the authority.
+    let state = flow.state();
+    let initial_root = pool_config().initial_root.unwrap();
+    assert_eq!(state.current_root, initial_root);
+    assert_eq!(state.authority, flow.pool.authority.to_bytes());
+    assert_eq!((state.next_leaf_index, state.total_shielded), (0, 0));
+    let vault_rent = flow.vault_balance();
+    assert!(vault_rent > 0);
+
+    // Deposit: the note's root becomes current and joins the history.
+    let root = flow.deposit();
+    let state = flow.state();
+    assert_eq!(state.current_root, root);
+    assert!(state.roots.contains(&initial_root));
+    assert!(state.roots.contains(&root));
+    assert_eq!(state.next_leaf_index, 1);
+    assert_eq!(state.deposit_count, 1);
+    assert_eq!(state.total_shielded, NOTE);
+    assert_eq!(flow.vault_balance(), vault_rent + NOTE);
+
+    // Submit audit: a verified record for the identity, owned by the program.
+    let wa_commitment = [0x3a; 32];
+    let record = client::audit_record_pda(&wa_commitment).0;
+    assert_eq!(flow.pool.bank.lamports(&record), 0);
+    flow.pool.audit(&wa_commitment);
+    assert_eq!(flow.pool.bank.owner(&record), shielded_pool_pinocchio::ID);
+    let stored: &AuditRecord = bytemuck::from_bytes(flow.pool.bank.data(&record));
+    assert_eq!(stored.wa_commitment, wa_commitment);
+    assert_eq!(stored.verified, 1);
+
+    // Withdraw: the nullifier PDA is created and the note leaves the vault.
+    let spend = spend(root, 0x4e, 2, wa_commitment);
+    let nullifier = client::nullifier_pda(&spend.nullifier).0;
+    assert_eq!(flow.pool.bank.lamports(&nullifier), 0);
+    flow.withdraw(&spend).unwrap();
+
+    assert_eq!(
+        flow.pool.bank.owner(&nullifier),
+        shielded_pool_pinocchio::ID
+    );
+    assert_eq!(flow.pool.bank.lamports(&spend.recipient), NOTE);
+    assert_eq!(flow.vault_balance(), vault_rent);
+    let state = flow.state();
+    assert_eq!(state.withdraw_count, 1);
+    assert_eq!(state.total_shielded, 0);
+    // A withdraw doesn't touch the tree.
+    assert_eq!(state.current_root, root);
+    assert_eq!(state.next_leaf_index, 1);
+
+    // The spent nullifier can't be replayed.
+    assert_eq!(
+        flow.withdraw(&Spend {
+            recipient: address(3),
+            ..spend
+        }),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+}
+
+#[test]
+fn notes_stay_spendable_against_the_root_they_were_deposited_under() {
+    let mut flow = Flow::new();
+    let vault_rent = flow.vault_balance();
+    let wa_commitment = [0x3a; 32];
+    flow.pool.audit(&wa_commitment);
+
+    let first_root = flow.deposit();
+    let second_root = flow.deposit();
+    assert_ne!(first_root, second_root);
+    assert_eq!(flow.state().total_shielded, 2 * NOTE);
+    assert_eq!(flow.vault_balance(), vault_rent + 2 * NOTE);
+
+    // The older root is still in the history after the newer deposit.
+    flow.withdraw(&spend(first_root, 0x41, 2, wa_commitment))
+        .unwrap();
+    flow.withdraw(&spend(second_root, 0x42, 3, wa_commitment))
+        .unwrap();
+
+    let state = flow.state();
+    assert_eq!(state.withdraw_count, 2);
+    assert_eq!(state.total_shielded, 0);
+    assert_eq!(flow.vault_balance(), vault_rent);
+    for nullifier in [[0x41; 32], [0x42; 32]] {
+        let pda = client::nullifier_pda(&nullifier).0;
+        assert_eq!(flow.pool.bank.owner(&pda), shielded_pool_pinocchio::ID);
+    }
+}
+
+#[test]
+fn a_failed_withdraw_leaves_the_pool_as_it_was() {
+    let mut flow = Flow::new();
+    let wa_commitment = [0x3a; 32];
+    flow.pool.audit(&wa_commitment);
+    let root = flow.deposit();
+    let before = flow.state();
+    let vault_before = flow.vault_balance();
+
+    // More than the pool holds.
+    let spend = Spend {
+        amount: 2 * NOTE,
+        ..spend(root, 0x4e, 2, wa_commitment)
+    };
+    assert_eq!(flow.withdraw(&spend), Err(ProgramError::InsufficientFunds));
+
+    let nullifier = client::nullifier_pda(&spend.nullifier).0;
+    assert_eq!(flow.pool.bank.lamports(&nullifier), 0);
+    assert_eq!(flow.vault_balance(), vault_before);
+    assert_eq!(
+        bytemuck::bytes_of(&flow.state()),
+        bytemuck::bytes_of(&before)
+    );
+}
+
+#[test]
+fn pool_info_counts_deposits_and_withdrawals() {
+    let mut flow = Flow::new();
+    let initial_root = pool_config().initial_root.unwrap();
+    assert_eq!(flow.pool_info(), (0, 0, 0, initial_root));
+
+    let wa_commitment = [0x3a; 32];
+    flow.pool.audit(&wa_commitment);
+    let first_root = flow.deposit();
+    let second_root = flow.deposit();
+    assert_eq!(flow.pool_info(), (2, 2, 0, second_root));
+
+    flow.withdraw(&spend(first_root, 0x41, 2, wa_commitment))
+        .unwrap();
+    assert_eq!(flow.pool_info(), (2, 2, 1, second_root));
+
+    // A failed withdraw counts for nothing.
+    assert_eq!(
+        flow.withdraw(&spend(first_root, 0x41, 3, wa_commitment)),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    assert_eq!(flow.pool_info(), (2, 2, 1, second_root));
+
+    let state = flow.state();
+    assert_eq!((state.deposit_count, state.withdraw_count), (2, 1));
+}
+
+#[test]
+fn every_state_write_zeroes_the_padding() {
+    let mut flow = Flow::new();
+    let wa_commitment = [0x3a; 32];
+    flow.pool.audit(&wa_commitment);
+    let dirty = |flow: &mut Flow| {
+        let mut state = flow.state();
+        state._padding = 0xff;
+        state._padding2 = [0xff; 2];
+        state._padding3 = [0xff; 4];
+        state._padding4 = [0xff; 3];
+        flow.pool.bank.set_state(&state);
+    };
+    let assert_clean = |state: ShieldedPoolState| {
+        assert_eq!(state._padding, 0);
+        assert_eq!(state._padding2, [0; 2]);
+        assert_eq!(state._padding3, [0; 4]);
+        assert_eq!(state._padding4, [0; 3]);
+    };
+
+    dirty(&mut flow);
+    let root = flow.deposit();
+    assert_clean(flow.state());
+
+    dirty(&mut flow);
+    flow.withdraw(&spend(root, 0x4e, 2, wa_commitment)).unwrap();
+    assert_clean(flow.state());
+
+    dirty(&mut flow);
+    let authority = flow.pool.authority.clone();
+    flow.pool
+        .configure(&client::configure_withdraw_fee(&authority, 25));
+    assert_clean(flow.state());
+}
@@ -0,0 +1,232 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{address, pool_config, Bank, PoolConfigExt, NOTE};
+use shielded_pool_pinocchio::{
+    client,
+    error::ShieldedPoolError,
+    instructions::{configure_rebate::TOKEN_PROGRAM_ID, initialize::InitializeParams},
+    state::ShieldedPoolState,
+};
+use solana_program_error::ProgramError;
+
+fn funded_bank() -> (Bank, common::Address) {
+    let mut bank = Bank::new();
+    let authority = address(0xa1);
+    bank.fund(&authority, 100_000_000_000);
+    (bank, authority)
+}
+
+fn full_params() -> InitializeParams {
+    InitializeParams {
+        withdraw_fee_bps: 75,
+        min_verifier_compute_units: 250_000,
+        audit_required_from_slot: 42,
+        recipient_max_per_window: 3,
+        recipient_window_slots: 1_000,
+        rebate_mint: address(0xd0).to_bytes(),
+        rebate_amount: 5_000,
+        owner_gated: 1,
+        unique_commitments: 1,
+        require_recipient_signature: 1,
+        require_wallet_recipient: 1,
+        relayer_registry: 1,
+        authority_threshold: 2,
+        co_authority_count: 2,
+        co_authorities: [address(0xa2).to_bytes(), address(0xa3).to_bytes()],
+        alt_verifier: address(0xb7).to_bytes(),
+        alt_proof_len: 512,
+        public_input_order: [4, 3, 2, 1, 0],
+    }
+}
+
+#[test]
+fn initialize_writes_every_param() {
+    let (mut bank, authority) = funded_bank();
+    let mint = address(0xd0);
+    let mut mint_data = vec![0u8; 82];
+    mint_data[44] = 9;
+    mint_data[45] = 1;
+    bank.set_account(&mint, &TOKEN_PROGRAM_ID, 1_461_600, &mint_data);
+
+    let config = pool_config().public_input_count(7);
+    let params = full_params();
+    bank.process(&client::initialize_with_params(
+        &authority, &config, &params,
+    ))
+    .unwrap();
+
+    let state = bank.state();
+    assert_eq!(state.current_root, [0xee; 32]);
+    assert_eq!(state.withdraw_fee_bps, 75);
+    assert_eq!(state.min_verifier_compute_units, 250_000);
+    assert_eq!(state.audit_required_from_slot, 42);
+    assert_eq!(state.recipient_max_per_window, 3);
+    assert_eq!(state.recipient_window_slots, 1_000);
+    assert_eq!(state.rebate_enabled, 1);
+    assert_eq!(state.rebate_mint, mint.to_bytes());
+    assert_eq!(state.rebate_amount, 5_000);
+    assert_eq!(state.rebate_decimals, 9);
+    assert_eq!(state.owner_gated, 1);
+    assert_eq!(state.unique_commitments, 1);
+    assert_eq!(state.require_recipient_signature, 1);
+    assert_eq!(state.require_wallet_recipient, 1);
+    assert_eq!(state.relayer_registry, 1);
+    assert_eq!(state.authority_threshold, 2);
+    assert_eq!(state.co_authority_count, 2);
+    assert_eq!(state.co_authorities, params.co_authorities);
+    assert_eq!(state.alt_verifier, params.alt_verifier);
+    assert_eq!(state.alt_proof_len, 512);
+    assert_eq!(state.public_input_order, [4, 3, 2, 1, 0]);
+    assert_eq!(state.authority, authority.to_bytes());
+}
+
+#[test]
+fn initialize_without_params_keeps_the_defaults() {
+    let (mut bank, authority) = funded_bank();
+    bank.process(&client::initialize_with_params(
+        &authority,
+        &pool_config(),
+        &InitializeParams::default(),
+    ))
+    .unwrap();
+
+    let state = bank.state();
+    assert_eq!(state.rebate_enabled, 0);
+    assert_eq!(state.authority_threshold, 1);
+    assert_eq!(
+        state.public_input_order,
+        ShieldedPoolState::DEFAULT_PUBLIC_INPUT_ORDER
+    );
+}
+
+#[test]
+fn initialize_rejects_out_of_range_params() {
+    let (mut bank, authority) = funded_bank();
+    let config = pool_config().public_input_count(7);
+    let no_rebate = || InitializeParams {
+        rebate_amount: 0,
+        rebate_mint: [0u8; 32],
+        ..full_params()
+    };
+
+    for params in [
+        InitializeParams {
+            withdraw_fee_bps: ShieldedPoolState::MAX_FEE_BPS,
+            ..no_rebate()
+        },
+        InitializeParams {
+            owner_gated: 2,
+            ..no_rebate()
+        },
+        InitializeParams {
+            recipient_window_slots: 0,
+            ..no_rebate()
+        },
+        InitializeParams {
+            authority_threshold: 4,
+            ..no_rebate()
+        },
+        InitializeParams {
+            co_authorities: [authority.to_bytes(), address(0xa3).to_bytes()],
+            ..no_rebate()
+        },
+        InitializeParams {
+            alt_verifier: [0u8; 32],
+            ..no_rebate()
+        },
+        InitializeParams {
+            public_input_order: [0, 0, 2, 3, 4],
+            ..no_rebate()
+        },
+    ] {
+        assert_eq!(
+            bank.process(&client::initialize_with_params(
+                &authority, &config, &params
+            )),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+
+    // Owner gating needs an owner input in the circuit.
+    assert_eq!(
+        bank.process(&client::initialize_with_params(
+            &authority,
+            &pool_config(),
+            &no_rebate()
+        )),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    // A rebate needs its mint account.
+    let mut ix = client::initialize_with_params(&authority, &config, &full_params());
+    ix.accounts.pop();
+    assert_eq!(bank.process(&ix), Err(ProgramError::NotEnoughAccountKeys));
+}
+
+#[test]
+fn initialize_with_a_seeded_root_builds_on_it() {
+    let (mut bank, authority) = funded_bank();
+    let seed = [0x5e; 32];
+    let config = client::PoolConfig {
+        initial_root: Some(seed),
+        ..pool_config()
+    };
+    bank.process(&client::initialize(&authority, &config))
+        .unwrap();
+
+    let state = bank.state();
+    assert_eq!(state.current_root, seed);
+    assert_eq!(state.roots[state.roots_index as usize - 1], seed);
+    assert_ne!(seed, state.empty_root().unwrap());
+    assert_eq!(state.next_leaf_index, 0);
+
+    // The first deposit must be built on the seeded root.
+    let depositor = address(1);
+    bank.fund(&depositor, 10 * NOTE);
+    let empty_root = state.empty_root().unwrap();
+    assert_eq!(
+        bank.process(&client::deposit(
+            &depositor,
+            NOTE,
+            &[0x01; 32],
+            &empty_root,
+            &[0x71; 32]
+        )),
+        Err(ShieldedPoolError::DepositRootRace.into())
+    );
+    bank.process(&client::deposit(
+        &depositor,
+        NOTE,
+        &[0x01; 32],
+        &seed,
+        &[0x71; 32],
+    ))
+    .unwrap();
+    let state = bank.state();
+    assert_eq!(state.current_root, [0x71; 32]);
+    assert!(state.roots.contains(&seed));
+}
+
+#[test]
+fn initialize_with_params_seeds_the_root_unless_it_is_zero() {
+    let seed = [0x5e; 32];
+    for (initial_root, expected) in [(Some(seed), Some(seed)), (None, None)] {
+        let (mut bank, authority) = funded_bank();
+        let config = client::PoolConfig {
+            initial_root,
+            ..pool_config()
+        };
+        bank.process(&client::initialize_with_params(
+            &authority,
+            &config,
+            &InitializeParams::default(),
+        ))
+        .unwrap();
+
+        let state = bank.state();
+        let expected = expected.unwrap_or_else(|| state.empty_root().unwrap());
+        assert_eq!(state.current_root, expected);
+    }
+}
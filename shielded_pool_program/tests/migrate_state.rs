@@ -0,0 +1,210 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use core::mem::offset_of;
+
+use solana_address::Address;
+use solana_program_error::ProgramError;
+
+use common::{address, Pool, NOTE};
+use shielded_pool_pinocchio::{
+    client,
+    error::ShieldedPoolError,
+    instructions::migrate_state::BPF_LOADER_UPGRADEABLE_ID,
+    runtime,
+    state::{zeros, ShieldedPoolState, VaultHeader, ROOT_HISTORY_SIZE},
+};
+
+/// Rewrites the pool's state as the v20 layout it was migrated from.
+fn downgrade_to_v20(pool: &mut Pool) {
+    let state = client::state_pda().0;
+    let lamports = pool.bank.lamports(&state);
+    let mut data = pool.bank.data(&state)[..ShieldedPoolState::VERSION_LENS[20]].to_vec();
+    data[offset_of!(ShieldedPoolState, version)] = 20;
+    pool.bank
+        .set_account(&state, &shielded_pool_pinocchio::ID, lamports, &data);
+}
+
+/// Rewrites the pool as a v0 deployment: the unversioned state with only its
+/// original fields, and a zero-space vault holding `shielded` above its rent.
+/// `upgrade_authority` is recorded as the program's upgrade authority.
+fn downgrade_to_v0(pool: &mut Pool, upgrade_authority: &Address, shielded: u64) {
+    let state = client::state_pda().0;
+    let lamports = pool.bank.lamports(&state);
+    let mut data = pool.bank.data(&state)[..ShieldedPoolState::V0_LEN].to_vec();
+    // v0 had padding where the depth and version now live.
+    data[offset_of!(ShieldedPoolState, tree_depth)] = 0;
+    data[offset_of!(ShieldedPoolState, version)] = 0;
+    pool.bank
+        .set_account(&state, &shielded_pool_pinocchio::ID, lamports, &data);
+
+    let rent = runtime::rent().unwrap();
+    pool.bank.set_account(
+        &pool.vault(),
+        &shielded_pool_pinocchio::ID,
+        rent.try_minimum_balance(0).unwrap() + shielded,
+        &[],
+    );
+
+    // ProgramData: [tag = 3][slot][Some(upgrade_authority)]
+    let mut program_data = 3u32.to_le_bytes().to_vec();
+    program_data.extend_from_slice(&0u64.to_le_bytes());
+    program_data.push(1);
+    program_data.extend_from_slice(upgrade_authority.as_ref());
+    let program_data_address = Address::find_program_address(
+        &[shielded_pool_pinocchio::ID.as_ref()],
+        &BPF_LOADER_UPGRADEABLE_ID,
+    )
+    .0;
+    pool.bank.set_account(
+        &program_data_address,
+        &BPF_LOADER_UPGRADEABLE_ID,
+        1_000_000,
+        &program_data,
+    );
+}
+
+#[test]
+fn a_v0_pool_migrates_and_takes_deposits() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    pool.deposit(&depositor, NOTE);
+    let before = pool.bank.state();
+    let upgrade_authority = address(0xa4);
+    pool.bank.fund(&upgrade_authority, 1_000_000_000);
+    downgrade_to_v0(&mut pool, &upgrade_authority, 3 * NOTE);
+
+    // Only the upgrade authority may claim a v0 pool.
+    let authority = pool.authority.clone();
+    assert_eq!(
+        pool.bank.process(&client::migrate_state(&authority)),
+        Err(ProgramError::IncorrectAuthority)
+    );
+    pool.bank
+        .process(&client::migrate_state(&upgrade_authority))
+        .unwrap();
+
+    let state = pool.bank.state();
+    assert_eq!(state.version, ShieldedPoolState::VERSION);
+    assert_eq!(state.authority, upgrade_authority.to_bytes());
+    assert_eq!(state.tree_depth, ShieldedPoolState::DEFAULT_TREE_DEPTH);
+    assert_eq!(state.root_history_size, ROOT_HISTORY_SIZE as u32);
+    assert_eq!(state.current_root, before.current_root);
+    assert_eq!(state.roots, before.roots);
+    // Everything the vault held above rent is owed to note holders.
+    assert_eq!(state.total_shielded, 3 * NOTE);
+    assert_eq!(pool.bank.data(&pool.vault()).len(), VaultHeader::LEN);
+
+    let (_, root) = pool.deposit(&depositor, NOTE);
+    let state = pool.bank.state();
+    assert_eq!(state.current_root, root);
+    assert_eq!(state.total_shielded, 4 * NOTE);
+    assert_eq!(state.deposit_count, 1);
+}
+
+#[test]
+fn migrate_state_upgrades_the_layout() {
+    let mut pool = Pool::new();
+    downgrade_to_v20(&mut pool);
+    let authority = pool.authority.clone();
+
+    pool.bank
+        .process(&client::migrate_state(&authority))
+        .unwrap();
+
+    let state = pool.bank.state();
+    assert_eq!(state.version, ShieldedPoolState::VERSION);
+    assert_eq!(
+        pool.bank.data(&client::state_pda().0).len(),
+        ShieldedPoolState::LEN
+    );
+    // v22 computes the empty-subtree hashes for the pool's depth.
+    for level in 0..state.tree_depth {
+        assert_eq!(state.zero_roots[level as usize], zeros(level).unwrap());
+    }
+    assert_eq!(state.zero_roots[state.tree_depth as usize], [0u8; 32]);
+}
+
+#[test]
+fn migrate_state_needs_the_authority_threshold() {
+    let mut pool = Pool::new();
+    let authority = pool.authority.clone();
+    let co_authorities = [address(0xa2)];
+    pool.configure(&client::configure_authorities(
+        &authority,
+        2,
+        &co_authorities,
+    ));
+    downgrade_to_v20(&mut pool);
+
+    assert_eq!(
+        pool.bank.process(&client::migrate_state(&authority)),
+        Err(ShieldedPoolError::AuthorityThresholdNotMet.into())
+    );
+    assert_eq!(
+        pool.bank.process(&client::migrate_state(&address(0xa3))),
+        Err(solana_program_error::ProgramError::IncorrectAuthority)
+    );
+
+    pool.bank
+        .process(&client::with_co_signers(
+            client::migrate_state(&authority),
+            &co_authorities,
+        ))
+        .unwrap();
+    assert_eq!(pool.bank.state().version, ShieldedPoolState::VERSION);
+}
+
+#[test]
+fn migrate_state_rejects_a_newer_layout() {
+    let mut pool = Pool::new();
+    let state = client::state_pda().0;
+    let lamports = pool.bank.lamports(&state);
+    let mut data = pool.bank.data(&state).to_vec();
+    data[offset_of!(ShieldedPoolState, version)] = ShieldedPoolState::VERSION + 1;
+    pool.bank
+        .set_account(&state, &shielded_pool_pinocchio::ID, lamports, &data);
+    let authority = pool.authority.clone();
+
+    assert_eq!(
+        pool.bank.process(&client::migrate_state(&authority)),
+        Err(ProgramError::InvalidAccountData)
+    );
+    assert_eq!(pool.bank.data(&state), &data[..]);
+}
+
+#[test]
+fn migrate_state_rejects_a_size_that_does_not_match_the_version() {
+    let mut pool = Pool::new();
+    let state = client::state_pda().0;
+    let lamports = pool.bank.lamports(&state);
+    let authority = pool.authority.clone();
+    let v20_len = ShieldedPoolState::VERSION_LENS[20];
+    let mut data = pool.bank.data(&state).to_vec();
+    data[offset_of!(ShieldedPoolState, version)] = 20;
+
+    for len in [v20_len - 8, v20_len + 8, ShieldedPoolState::LEN] {
+        pool.bank
+            .set_account(&state, &shielded_pool_pinocchio::ID, lamports, &data[..len]);
+        assert_eq!(
+            pool.bank.process(&client::migrate_state(&authority)),
+            Err(ProgramError::InvalidAccountData),
+            "{len} bytes"
+        );
+        assert_eq!(pool.bank.data(&state).len(), len);
+    }
+
+    // Shorter than any layout is not a pool state at all.
+    pool.bank.set_account(
+        &state,
+        &shielded_pool_pinocchio::ID,
+        lamports,
+        &data[..ShieldedPoolState::V0_LEN - 1],
+    );
+    assert_eq!(
+        pool.bank.process(&client::migrate_state(&authority)),
+        Err(ProgramError::UninitializedAccount)
+    );
+}
@@ -0,0 +1,158 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{address, pool_config, proof, Address, Pool, Spend, PROOF_LEN};
+use shielded_pool_pinocchio::{
+    client,
+    error::ShieldedPoolError,
+    state::{NullifierSet, VaultHeader},
+};
+use solana_program_error::ProgramError;
+
+fn withdraw(pool: &mut Pool, spend: &Spend) -> Result<(), ProgramError> {
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )?;
+    pool.bank.process(&ix)
+}
+
+fn reclaim(pool: &mut Pool, nullifier: &[u8; 32]) -> Result<(), ProgramError> {
+    let authority = pool.authority.clone();
+    let treasury = pool_config().fee_collector;
+    pool.bank.process(&client::reclaim_nullifier_rent(
+        &authority, nullifier, &treasury,
+    ))
+}
+
+/// A shard holding `count` sorted nullifiers sharing the first byte `shard`.
+fn shard_data(shard: u8, count: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(NullifierSet::LEN + count * NullifierSet::ENTRY_LEN);
+    data.extend_from_slice(&NullifierSet::DISCRIMINATOR);
+    data.extend_from_slice(&(count as u32).to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+    for i in 0..count {
+        let mut entry = [0u8; 32];
+        entry[0] = shard;
+        entry[30..].copy_from_slice(&(2 * i as u16 + 2).to_be_bytes());
+        data.extend_from_slice(&entry);
+    }
+    data
+}
+
+#[test]
+fn a_reclaimed_nullifier_still_rejects_a_second_spend() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    withdraw(&mut pool, &spend).unwrap();
+
+    reclaim(&mut pool, &spend.nullifier).unwrap();
+    let nullifier_pda = client::nullifier_pda(&spend.nullifier).0;
+    assert_eq!(
+        pool.bank.lamports(&nullifier_pda),
+        0,
+        "nullifier PDA closed"
+    );
+
+    let again = Spend {
+        recipient: address(3),
+        ..spend
+    };
+    assert_eq!(
+        withdraw(&mut pool, &again),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    assert_eq!(pool.bank.lamports(&again.recipient), 0);
+}
+
+#[test]
+fn reclaim_keeps_the_shard_sorted() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let shard = client::nullifier_set_pda(&spend.nullifier).0;
+    let data = shard_data(spend.nullifier[0], 3);
+    let lamports = 10_000_000;
+    pool.bank
+        .set_account(&shard, &shielded_pool_pinocchio::ID, lamports, &data);
+    withdraw(&mut pool, &spend).unwrap();
+
+    reclaim(&mut pool, &spend.nullifier).unwrap();
+
+    let data = pool.bank.data(&shard);
+    let entries: Vec<&[u8]> = data[NullifierSet::LEN..]
+        .chunks_exact(NullifierSet::ENTRY_LEN)
+        .collect();
+    assert_eq!(entries.len(), 4);
+    assert!(entries.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(NullifierSet::contains(data, &spend.nullifier));
+}
+
+#[test]
+fn a_full_shard_refuses_reclaims() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let shard = client::nullifier_set_pda(&spend.nullifier).0;
+    let data = shard_data(spend.nullifier[0], NullifierSet::CAPACITY);
+    pool.bank
+        .set_account(&shard, &shielded_pool_pinocchio::ID, 100_000_000, &data);
+    withdraw(&mut pool, &spend).unwrap();
+
+    assert_eq!(
+        reclaim(&mut pool, &spend.nullifier),
+        Err(ShieldedPoolError::NullifierSetFull.into())
+    );
+    // The nullifier keeps its PDA, so the note stays spent.
+    let nullifier_pda = client::nullifier_pda(&spend.nullifier).0;
+    assert!(pool.bank.lamports(&nullifier_pda) > 0);
+}
+
+#[test]
+fn nullifier_pdas_are_scoped_to_their_asset() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let native = VaultHeader::NATIVE_ASSET_ID;
+    let other_asset = [0x5a; 32];
+
+    // The native asset keeps the legacy seeds, so existing PDAs still count.
+    assert_eq!(
+        client::nullifier_pda(&spend.nullifier).0,
+        Address::find_program_address(
+            &[b"nullifier".as_slice(), &spend.nullifier],
+            &shielded_pool_pinocchio::ID
+        )
+        .0
+    );
+    assert_eq!(
+        client::nullifier_set_pda(&spend.nullifier).0,
+        Address::find_program_address(
+            &[b"nullifier_set".as_slice(), &spend.nullifier[..1]],
+            &shielded_pool_pinocchio::ID
+        )
+        .0
+    );
+    assert_ne!(
+        client::nullifier_pda_for_asset(&other_asset, &spend.nullifier).0,
+        client::nullifier_pda_for_asset(&native, &spend.nullifier).0
+    );
+    assert_ne!(
+        client::nullifier_set_pda_for_asset(&other_asset, &spend.nullifier).0,
+        client::nullifier_set_pda_for_asset(&native, &spend.nullifier).0
+    );
+
+    // Spending in one asset leaves the same nullifier free in another, and
+    // blocks it within its own.
+    withdraw(&mut pool, &spend).unwrap();
+    assert_eq!(
+        pool.bank
+            .lamports(&client::nullifier_pda_for_asset(&other_asset, &spend.nullifier).0),
+        0
+    );
+    let again = Spend {
+        recipient: address(3),
+        ..spend
+    };
+    assert_eq!(
+        withdraw(&mut pool, &again),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+}
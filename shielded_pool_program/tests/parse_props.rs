@@ -0,0 +1,242 @@
+#![cfg(feature = "client")]
+
+//! Property tests for the instruction data parsers: arbitrary bytes never
+//! panic, and well-formed data parses back to what was encoded.
+
+use proptest::{collection::vec, prelude::*};
+use shielded_pool_pinocchio::{
+    instructions::{
+        deposit::DepositArgs,
+        deposit_batch::{DepositBatch, MAX_BATCH_DEPOSITS},
+        initialize::InitializeArgs,
+        withdraw::{parse_withdraw_witness, PUBLIC_INPUTS},
+    },
+    state::ShieldedPoolState,
+    witness::{MAX_PUBLIC_INPUTS, WITNESS_HEADER_LEN},
+};
+
+const ORDER: [u8; PUBLIC_INPUTS] = ShieldedPoolState::DEFAULT_PUBLIC_INPUT_ORDER;
+
+fn encode_deposit(args: &DepositArgs) -> Vec<u8> {
+    let mut data = Vec::with_capacity(DepositArgs::LEN);
+    data.extend_from_slice(&args.amount.to_le_bytes());
+    data.extend_from_slice(&args.commitment);
+    data.extend_from_slice(&args.prev_root);
+    data.extend_from_slice(&args.new_root);
+    data
+}
+
+fn encode_initialize(args: &InitializeArgs) -> Vec<u8> {
+    let mut data = Vec::with_capacity(InitializeArgs::LEN);
+    data.extend_from_slice(&args.deposit_fee_bps.to_le_bytes());
+    data.extend_from_slice(&args.fee_collector);
+    data.push(args.tree_depth);
+    data.extend_from_slice(&args.min_withdraw.to_le_bytes());
+    data.extend_from_slice(&args.proof_len.to_le_bytes());
+    data.push(args.public_input_count);
+    data.extend_from_slice(&args.min_deposit_age_slots.to_le_bytes());
+    data
+}
+
+fn encode_batch(prev_root: &[u8; 32], new_root: &[u8; 32], entries: &[(u64, [u8; 32])]) -> Vec<u8> {
+    let mut data = vec![entries.len() as u8];
+    data.extend_from_slice(prev_root);
+    data.extend_from_slice(new_root);
+    for (amount, commitment) in entries {
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(commitment);
+    }
+    data
+}
+
+fn encode_withdraw(proof: &[u8], inputs: &[[u8; 32]], trailer: &[u8]) -> Vec<u8> {
+    let count = (inputs.len() as u32).to_be_bytes();
+    let mut data = proof.to_vec();
+    data.extend_from_slice(&count);
+    data.extend_from_slice(&0u32.to_be_bytes());
+    data.extend_from_slice(&count);
+    for input in inputs {
+        data.extend_from_slice(input);
+    }
+    data.extend_from_slice(trailer);
+    data
+}
+
+fn deposit_args() -> impl Strategy<Value = DepositArgs> {
+    (
+        any::<u64>(),
+        any::<[u8; 32]>(),
+        any::<[u8; 32]>(),
+        any::<[u8; 32]>(),
+    )
+        .prop_map(|(amount, commitment, prev_root, new_root)| DepositArgs {
+            amount,
+            commitment,
+            prev_root,
+            new_root,
+        })
+}
+
+fn initialize_args() -> impl Strategy<Value = InitializeArgs> {
+    (
+        any::<u16>(),
+        any::<[u8; 32]>(),
+        any::<u8>(),
+        any::<u64>(),
+        any::<u32>(),
+        any::<u8>(),
+        any::<u64>(),
+    )
+        .prop_map(
+            |(
+                deposit_fee_bps,
+                fee_collector,
+                tree_depth,
+                min_withdraw,
+                proof_len,
+                public_input_count,
+                min_deposit_age_slots,
+            )| InitializeArgs {
+                deposit_fee_bps,
+                fee_collector,
+                tree_depth,
+                min_withdraw,
+                proof_len,
+                public_input_count,
+                min_deposit_age_slots,
+            },
+        )
+}
+
+/// What the withdraw parser accepts after the witness: nothing, the two
+/// bumps, or the change roots followed by the bumps.
+fn withdraw_trailer() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![Just(0usize), Just(2), Just(66)].prop_flat_map(|len| vec(any::<u8>(), len))
+}
+
+proptest! {
+    #[test]
+    fn deposit_args_never_panic(data in vec(any::<u8>(), 0..2 * DepositArgs::LEN)) {
+        let parsed = DepositArgs::parse(&data);
+        prop_assert_eq!(parsed.is_ok(), data.len() == DepositArgs::LEN);
+    }
+
+    #[test]
+    fn deposit_args_round_trip(args in deposit_args()) {
+        prop_assert_eq!(DepositArgs::parse(&encode_deposit(&args)), Ok(args));
+    }
+
+    #[test]
+    fn initialize_args_never_panic(data in vec(any::<u8>(), 0..2 * InitializeArgs::LEN)) {
+        let parsed = InitializeArgs::parse(&data);
+        prop_assert_eq!(parsed.is_ok(), data.len() == InitializeArgs::LEN);
+    }
+
+    #[test]
+    fn initialize_args_round_trip(args in initialize_args()) {
+        prop_assert_eq!(InitializeArgs::parse(&encode_initialize(&args)), Ok(args));
+    }
+
+    #[test]
+    fn deposit_batch_never_panics(data in vec(any::<u8>(), 0..1 + 64 + 41 * MAX_BATCH_DEPOSITS)) {
+        let _ = DepositBatch::parse(&data).map(|batch| batch.entries().count());
+    }
+
+    #[test]
+    fn deposit_batch_round_trips(
+        prev_root in any::<[u8; 32]>(),
+        new_root in any::<[u8; 32]>(),
+        entries in vec(any::<(u64, [u8; 32])>(), 1..=MAX_BATCH_DEPOSITS),
+    ) {
+        let data = encode_batch(&prev_root, &new_root, &entries);
+        let batch = DepositBatch::parse(&data).unwrap();
+        prop_assert_eq!(batch.prev_root, prev_root);
+        prop_assert_eq!(batch.new_root, new_root);
+        prop_assert_eq!(batch.count(), entries.len());
+        prop_assert_eq!(batch.entries().collect::<Vec<_>>(), entries);
+    }
+
+    #[test]
+    fn deposit_batch_rejects_a_miscounted_batch(
+        entries in vec(any::<(u64, [u8; 32])>(), 1..=MAX_BATCH_DEPOSITS),
+        count in any::<u8>(),
+    ) {
+        let mut data = encode_batch(&[1u8; 32], &[2u8; 32], &entries);
+        data[0] = count;
+        prop_assert_eq!(DepositBatch::parse(&data).is_ok(), count as usize == entries.len());
+    }
+
+    #[test]
+    fn withdraw_witness_never_panics(
+        data in vec(any::<u8>(), 0..1024),
+        proof_len in 0usize..512,
+        order in any::<[u8; PUBLIC_INPUTS]>(),
+    ) {
+        let _ = parse_withdraw_witness(&data, proof_len, &order);
+    }
+
+    #[test]
+    fn withdraw_witness_with_an_arbitrary_header_never_panics(
+        proof in vec(any::<u8>(), 0..128),
+        header in any::<[u8; WITNESS_HEADER_LEN]>(),
+        rest in vec(any::<u8>(), 0..600),
+    ) {
+        let mut data = proof.clone();
+        data.extend_from_slice(&header);
+        data.extend_from_slice(&rest);
+        let _ = parse_withdraw_witness(&data, proof.len(), &ORDER);
+    }
+
+    #[test]
+    fn withdraw_witness_round_trips(
+        proof in vec(any::<u8>(), 0..128),
+        inputs in vec(any::<[u8; 32]>(), PUBLIC_INPUTS..=MAX_PUBLIC_INPUTS),
+        trailer in withdraw_trailer(),
+    ) {
+        let data = encode_withdraw(&proof, &inputs, &trailer);
+        let parsed = parse_withdraw_witness(&data, proof.len(), &ORDER).unwrap();
+        let payload_len = data.len() - trailer.len();
+
+        prop_assert_eq!(parsed.payload, &data[..payload_len]);
+        prop_assert_eq!(parsed.inputs.len(), inputs.len());
+        prop_assert_eq!(parsed.root, inputs[0]);
+        prop_assert_eq!(parsed.nullifier, inputs[1]);
+        prop_assert_eq!(parsed.recipient, inputs[2]);
+        prop_assert_eq!(parsed.amount.0, inputs[3]);
+        prop_assert_eq!(parsed.wa_commitment, inputs[4]);
+        let change_commitment = inputs.get(PUBLIC_INPUTS).copied().unwrap_or([0u8; 32]);
+        prop_assert_eq!(parsed.change_commitment, change_commitment);
+
+        let bumps = trailer.len().checked_sub(2).map(|at| (Some(trailer[at]), Some(trailer[at + 1])));
+        prop_assert_eq!((parsed.vault_bump, parsed.audit_bump), bumps.unwrap_or((None, None)));
+        prop_assert_eq!(parsed.change_roots, &trailer[..trailer.len().saturating_sub(2)]);
+    }
+
+    #[test]
+    fn withdraw_witness_rejects_too_few_inputs(
+        proof in vec(any::<u8>(), 0..128),
+        inputs in vec(any::<[u8; 32]>(), 0..PUBLIC_INPUTS),
+    ) {
+        let data = encode_withdraw(&proof, &inputs, &[]);
+        prop_assert!(parse_withdraw_witness(&data, proof.len(), &ORDER).is_err());
+    }
+}
+
+// Under the `borsh` feature the arguments parse with Borsh, whose encoding
+// must stay byte-for-byte the layout clients already send.
+#[cfg(feature = "borsh")]
+proptest! {
+    #[test]
+    fn deposit_args_borsh_matches_the_wire_layout(args in deposit_args()) {
+        let encoded = borsh::to_vec(&args).unwrap();
+        prop_assert_eq!(&encoded, &encode_deposit(&args));
+        prop_assert_eq!(DepositArgs::parse(&encoded), Ok(args));
+    }
+
+    #[test]
+    fn initialize_args_borsh_matches_the_wire_layout(args in initialize_args()) {
+        let encoded = borsh::to_vec(&args).unwrap();
+        prop_assert_eq!(&encoded, &encode_initialize(&args));
+        prop_assert_eq!(InitializeArgs::parse(&encoded), Ok(args));
+    }
+}
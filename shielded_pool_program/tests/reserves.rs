@@ -0,0 +1,89 @@
+#![cfg(feature = "client")]
+
+//! ProofOfReserves and CheckSolvency: what the vault holds against what the
+//! pool owes note holders.
+
+mod common;
+
+use common::{address, pool_config, proof, Pool, NOTE, PROOF_LEN};
+use shielded_pool_pinocchio::{
+    client,
+    instructions::{
+        check_solvency::CHECK_SOLVENCY_RETURN_DATA_LEN,
+        proof_of_reserves::PROOF_OF_RESERVES_RETURN_DATA_LEN,
+    },
+    runtime,
+    state::VaultHeader,
+};
+
+/// `(vault_lamports, total_shielded, rent_reserve)` from `ProofOfReserves`.
+fn proof_of_reserves(pool: &mut Pool) -> (u64, u64, u64) {
+    pool.bank.process(&client::proof_of_reserves()).unwrap();
+    let data = pool.bank.return_data();
+    assert_eq!(data.len(), PROOF_OF_RESERVES_RETURN_DATA_LEN);
+    let u64_at = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+    (u64_at(0), u64_at(8), u64_at(16))
+}
+
+fn solvency_delta(pool: &mut Pool) -> i64 {
+    pool.bank.process(&client::check_solvency()).unwrap();
+    let data = pool.bank.return_data();
+    assert_eq!(data.len(), CHECK_SOLVENCY_RETURN_DATA_LEN);
+    i64::from_le_bytes(data.try_into().unwrap())
+}
+
+fn vault_rent() -> u64 {
+    runtime::rent()
+        .unwrap()
+        .try_minimum_balance(VaultHeader::LEN)
+        .unwrap()
+}
+
+#[test]
+fn proof_of_reserves_reports_the_vault_against_what_it_owes() {
+    let mut pool = Pool::new();
+    let rent = vault_rent();
+    assert_eq!(proof_of_reserves(&mut pool), (rent, 0, rent));
+
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    pool.deposit(&depositor, NOTE);
+    pool.deposit(&depositor, 2 * NOTE);
+    assert_eq!(
+        proof_of_reserves(&mut pool),
+        (rent + 3 * NOTE, 3 * NOTE, rent)
+    );
+}
+
+#[test]
+fn check_solvency_reports_a_donation_as_a_surplus() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    assert_eq!(solvency_delta(&mut pool), 0);
+
+    // Lamports sent straight to the vault are owed to no one.
+    let vault = pool.vault();
+    let donation = 12_345;
+    let lamports = pool.bank.lamports(&vault) + donation;
+    let data = pool.bank.data(&vault).to_vec();
+    pool.bank
+        .set_account(&vault, &shielded_pool_pinocchio::ID, lamports, &data);
+    assert_eq!(solvency_delta(&mut pool), donation as i64);
+
+    // Deposits and withdrawals leave the surplus as it was.
+    let depositor = address(1);
+    pool.deposit(&depositor, NOTE);
+    assert_eq!(solvency_delta(&mut pool), donation as i64);
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    pool.bank.process(&ix).unwrap();
+    assert_eq!(solvency_delta(&mut pool), donation as i64);
+
+    let (vault_lamports, total_shielded, rent) = proof_of_reserves(&mut pool);
+    assert_eq!(vault_lamports - rent - total_shielded, donation);
+}
@@ -0,0 +1,228 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{address, pool_config, proof, Pool, Spend, NOTE, PROOF_LEN};
+use shielded_pool_pinocchio::{
+    client,
+    error::ShieldedPoolError,
+    instructions::get_root_history::{ROOT_HISTORY_CHUNK, ROOT_HISTORY_RETURN_DATA_LEN},
+    state::{ShieldedPoolState, ROOT_HISTORY_SIZE},
+};
+use solana_program_error::ProgramError;
+
+/// A pool with `count` deposits, and their roots in deposit order.
+fn pool_with_deposits(count: usize) -> (Pool, Vec<[u8; 32]>) {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 100 * NOTE);
+    let roots = (0..count)
+        .map(|_| pool.deposit(&depositor, 1_000_000).1)
+        .collect();
+    (pool, roots)
+}
+
+/// Every chunk of the ring read through `GetRootHistory`, and `roots_index`.
+fn read_history(pool: &mut Pool) -> (Vec<[u8; 32]>, usize) {
+    let mut ring = Vec::new();
+    let mut roots_index = 0;
+    for chunk in 0.. {
+        let data = match pool.bank.process(&client::get_root_history(chunk)) {
+            Ok(()) => pool.bank.return_data(),
+            Err(err) => {
+                assert_eq!(err, ProgramError::InvalidInstructionData);
+                break;
+            }
+        };
+        assert_eq!(data.len(), ROOT_HISTORY_RETURN_DATA_LEN);
+        roots_index = u32::from_le_bytes(data[0..4].try_into().unwrap()) as usize;
+        let size = u32::from_le_bytes(data[data.len() - 4..].try_into().unwrap());
+        assert_eq!(size, pool.bank.state().root_history_size);
+        ring.extend(
+            data[4..4 + ROOT_HISTORY_CHUNK * 32]
+                .chunks_exact(32)
+                .map(|root| <[u8; 32]>::try_from(root).unwrap()),
+        );
+    }
+    (ring, roots_index)
+}
+
+/// The ring read newest to oldest, skipping never-written slots.
+fn newest_first(ring: &[[u8; 32]], roots_index: usize) -> Vec<[u8; 32]> {
+    (1..=ring.len())
+        .map(|age| ring[(roots_index + ring.len() - age) % ring.len()])
+        .filter(|root| root != &[0u8; 32])
+        .collect()
+}
+
+fn withdraw(pool: &mut Pool, spend: &Spend) -> Result<(), ProgramError> {
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )?;
+    pool.bank.process(&ix)
+}
+
+/// A pool holding an audited note, its history grown to `history_size`, and
+/// then `later` more deposits. Returns the note's spend and the later roots.
+fn grown_pool(history_size: u32, later: usize) -> (Pool, Spend, Vec<[u8; 32]>) {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let authority = pool.authority.clone();
+    pool.configure(&client::grow_root_history(&authority, history_size));
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 100 * NOTE);
+    let roots = (0..later)
+        .map(|_| pool.deposit(&depositor, 1_000_000).1)
+        .collect();
+    (pool, spend, roots)
+}
+
+#[test]
+fn root_history_is_read_a_chunk_at_a_time() {
+    // More roots than one chunk holds.
+    let (mut pool, roots) = pool_with_deposits(ROOT_HISTORY_CHUNK + 4);
+
+    let (ring, roots_index) = read_history(&mut pool);
+    assert_eq!(ring.len(), ROOT_HISTORY_SIZE);
+    assert_eq!(ring[..], pool.bank.state().roots[..]);
+
+    let history = newest_first(&ring, roots_index);
+    let expected: Vec<_> = roots.iter().rev().copied().collect();
+    assert_eq!(history[..roots.len()], expected[..]);
+    assert_eq!(history[0], pool.bank.state().current_root);
+}
+
+#[test]
+fn root_history_keeps_the_newest_roots_once_the_ring_wraps() {
+    let (mut pool, roots) = pool_with_deposits(ROOT_HISTORY_SIZE + 5);
+
+    let (ring, roots_index) = read_history(&mut pool);
+    let history = newest_first(&ring, roots_index);
+    let expected: Vec<_> = roots
+        .iter()
+        .rev()
+        .take(ROOT_HISTORY_SIZE)
+        .copied()
+        .collect();
+    assert_eq!(history, expected);
+}
+
+#[test]
+fn a_chunk_past_the_ring_is_rejected() {
+    let mut pool = Pool::new();
+    let chunks = (ROOT_HISTORY_SIZE / ROOT_HISTORY_CHUNK) as u8;
+
+    pool.bank
+        .process(&client::get_root_history(chunks - 1))
+        .unwrap();
+    assert_eq!(
+        pool.bank.process(&client::get_root_history(chunks)),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn a_grown_history_keeps_roots_a_32_entry_ring_would_drop() {
+    let (mut pool, spend, roots) = grown_pool(64, ROOT_HISTORY_SIZE + 8);
+
+    let state = pool.bank.state();
+    assert_eq!(state.root_history_size, 64);
+    assert_eq!(
+        pool.bank.data(&client::state_pda().0).len(),
+        ShieldedPoolState::account_len(64)
+    );
+    let (ring, roots_index) = read_history(&mut pool);
+    assert_eq!(ring.len(), 64);
+    let history = newest_first(&ring, roots_index);
+    let expected: Vec<_> = roots.iter().rev().copied().collect();
+    assert_eq!(history[..roots.len()], expected[..]);
+    assert_eq!(history[roots.len()], spend.root);
+
+    // The note's root is 40 deposits old, and still spendable.
+    withdraw(&mut pool, &spend).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn grow_root_history_only_takes_larger_powers_of_two() {
+    let (mut pool, _) = Pool::with_note(&pool_config());
+    let authority = pool.authority.clone();
+
+    for size in [
+        ROOT_HISTORY_SIZE as u32,
+        48,
+        2 * ShieldedPoolState::MAX_ROOT_HISTORY_SIZE,
+    ] {
+        assert_eq!(
+            pool.bank
+                .process(&client::grow_root_history(&authority, size)),
+            Err(ProgramError::InvalidInstructionData),
+            "size {size}"
+        );
+    }
+    assert_eq!(
+        pool.bank.state().root_history_size,
+        ROOT_HISTORY_SIZE as u32
+    );
+}
+
+#[test]
+fn compacting_keeps_the_newest_roots_and_refunds_the_rent() {
+    let (mut pool, spend, roots) = grown_pool(64, ROOT_HISTORY_SIZE + 8);
+    let authority = pool.authority.clone();
+    let state_address = client::state_pda().0;
+    let authority_before = pool.bank.lamports(&authority);
+    let state_before = pool.bank.lamports(&state_address);
+
+    pool.configure(&client::compact_root_history(
+        &authority,
+        ROOT_HISTORY_SIZE as u32,
+    ));
+
+    assert_eq!(pool.bank.data(&state_address).len(), ShieldedPoolState::LEN);
+    let refund = state_before - pool.bank.lamports(&state_address);
+    assert!(refund > 0);
+    assert_eq!(pool.bank.lamports(&authority), authority_before + refund);
+
+    let state = pool.bank.state();
+    assert_eq!(state.root_history_size, ROOT_HISTORY_SIZE as u32);
+    let (ring, roots_index) = read_history(&mut pool);
+    let expected: Vec<_> = roots
+        .iter()
+        .rev()
+        .take(ROOT_HISTORY_SIZE)
+        .copied()
+        .collect();
+    assert_eq!(newest_first(&ring, roots_index), expected);
+
+    // The note's root was dropped; the oldest kept root is still spendable.
+    assert_eq!(
+        withdraw(&mut pool, &spend),
+        Err(ShieldedPoolError::PublicInputMismatch.into())
+    );
+    let oldest_kept = Spend {
+        root: *expected.last().unwrap(),
+        ..spend
+    };
+    withdraw(&mut pool, &oldest_kept).unwrap();
+    assert_eq!(pool.bank.lamports(&oldest_kept.recipient), NOTE);
+}
+
+#[test]
+fn compact_root_history_only_shrinks_to_a_smaller_power_of_two() {
+    let (mut pool, _, _) = grown_pool(64, 0);
+    let authority = pool.authority.clone();
+
+    for size in [ROOT_HISTORY_SIZE as u32 / 2, 48, 64, 128] {
+        assert_eq!(
+            pool.bank
+                .process(&client::compact_root_history(&authority, size)),
+            Err(ProgramError::InvalidInstructionData),
+            "size {size}"
+        );
+    }
+    assert_eq!(pool.bank.state().root_history_size, 64);
+}
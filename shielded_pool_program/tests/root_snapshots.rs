@@ -0,0 +1,82 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{address, Pool, NOTE};
+use shielded_pool_pinocchio::{
+    client,
+    state::{RootEntry, RootSnapshots},
+};
+use solana_program_error::ProgramError;
+
+/// The snapshot header's count and its entries, in storage order.
+fn snapshots(pool: &Pool) -> (u32, Vec<RootEntry>) {
+    let data = pool.bank.data(&client::root_snapshots_pda().0);
+    let header: &RootSnapshots = bytemuck::from_bytes(&data[..RootSnapshots::LEN]);
+    let entries: &[RootEntry] = bytemuck::cast_slice(&data[RootSnapshots::LEN..]);
+    (header.count, entries.to_vec())
+}
+
+#[test]
+fn the_authority_snapshots_the_current_root_at_the_current_slot() {
+    let mut pool = Pool::new();
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    let (_, root) = pool.deposit(&depositor, NOTE);
+    let authority = pool.authority.clone();
+    pool.bank.set_slot(1_234);
+
+    pool.configure(&client::verify_and_store_root(&authority, &root));
+
+    let snapshots_pda = client::root_snapshots_pda().0;
+    assert_eq!(pool.bank.owner(&snapshots_pda), shielded_pool_pinocchio::ID);
+    assert_eq!(
+        pool.bank.data(&snapshots_pda).len(),
+        RootSnapshots::ACCOUNT_LEN
+    );
+    let (count, entries) = snapshots(&pool);
+    assert_eq!(count, 1);
+    assert_eq!((entries[0].root, entries[0].slot), (root, 1_234));
+
+    // A later snapshot goes to the next entry.
+    let (_, root) = pool.deposit(&depositor, NOTE);
+    pool.bank.set_slot(1_300);
+    pool.configure(&client::verify_and_store_root(&authority, &root));
+    let (count, entries) = snapshots(&pool);
+    assert_eq!(count, 2);
+    assert_eq!((entries[1].root, entries[1].slot), (root, 1_300));
+}
+
+#[test]
+fn only_the_authority_may_snapshot_a_root() {
+    let mut pool = Pool::new();
+    let root = pool.bank.state().current_root;
+    let stranger = address(0xa9);
+    pool.bank.fund(&stranger, NOTE);
+
+    assert_eq!(
+        pool.bank
+            .process(&client::verify_and_store_root(&stranger, &root)),
+        Err(ProgramError::IncorrectAuthority)
+    );
+    // Nothing was created on the stranger's dime.
+    assert_eq!(pool.bank.lamports(&client::root_snapshots_pda().0), 0);
+    assert_eq!(pool.bank.lamports(&stranger), NOTE);
+}
+
+#[test]
+fn a_snapshot_of_a_root_that_is_no_longer_current_is_rejected() {
+    let mut pool = Pool::new();
+    let stale = pool.bank.state().current_root;
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    pool.deposit(&depositor, NOTE);
+    let authority = pool.authority.clone();
+
+    assert_eq!(
+        pool.bank
+            .process(&client::verify_and_store_root(&authority, &stale)),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(pool.bank.lamports(&client::root_snapshots_pda().0), 0);
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{address, Bank};
+use shielded_pool_pinocchio::{client, state::ViewingData};
+use solana_program_error::ProgramError;
+
+#[test]
+fn each_registrant_gets_its_own_viewing_data() {
+    let mut bank = Bank::new();
+    let (first, second) = (address(0x31), address(0x32));
+    bank.fund(&first, 1_000_000_000);
+    bank.fund(&second, 1_000_000_000);
+    let (wa_commitment, viewing_key) = ([0x5a; 32], [0x6b; 32]);
+
+    bank.process(
+        &client::register_viewing_data(&first, &wa_commitment, &viewing_key, b"first").unwrap(),
+    )
+    .unwrap();
+    // Another registrant can't block the pair, and registers its own payload.
+    bank.process(
+        &client::register_viewing_data(&second, &wa_commitment, &viewing_key, b"second").unwrap(),
+    )
+    .unwrap();
+
+    for (registrant, payload) in [(&first, b"first".as_slice()), (&second, b"second")] {
+        let pda = client::viewing_data_pda(&wa_commitment, &viewing_key, registrant).0;
+        let data = bank.data(&pda);
+        let header: &ViewingData = bytemuck::from_bytes(&data[..ViewingData::LEN]);
+        assert_eq!(header.registrant, registrant.to_bytes());
+        assert_eq!(&data[ViewingData::LEN..], payload);
+    }
+}
+
+#[test]
+fn register_viewing_data_rejects_another_registrants_pda() {
+    let mut bank = Bank::new();
+    let (first, second) = (address(0x31), address(0x32));
+    bank.fund(&second, 1_000_000_000);
+    let (wa_commitment, viewing_key) = ([0x5a; 32], [0x6b; 32]);
+
+    let mut ix =
+        client::register_viewing_data(&second, &wa_commitment, &viewing_key, b"payload").unwrap();
+    ix.accounts[1].address = client::viewing_data_pda(&wa_commitment, &viewing_key, &first).0;
+    assert_eq!(bank.process(&ix), Err(ProgramError::InvalidAccountData));
+}
@@ -0,0 +1,1509 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use std::{cell::Cell, rc::Rc};
+
+use common::{
+    address, address_input, amount_input, mock_verifiers, pool_config, proof, Pool, PoolConfigExt,
+    Spend, NOTE, PROOF_LEN, PROOF_REJECTED,
+};
+use shielded_pool_pinocchio::{
+    client,
+    error::ShieldedPoolError,
+    event::{EventKind, WithdrawEvent},
+    instructions::{
+        configure_rebate::REBATE_ACCOUNTS,
+        simulate_withdraw::SIMULATE_WITHDRAW_RETURN_DATA_LEN,
+        withdraw::{
+            OWNER_INPUT, POOL_DOMAIN_INPUT, PUBLIC_INPUTS, RECIPIENT_PROGRAM_INPUT,
+            WITHDRAW_ACCOUNTS, WITHDRAW_RETURN_DATA_LEN, ZK_VERIFIER_PROGRAM_ID,
+        },
+    },
+    state::{
+        AuditRecord, RecipientLimit, ShieldedPoolState, NULLIFIER_RESERVED, NULLIFIER_SPENT,
+        ROOT_HISTORY_SIZE,
+    },
+    witness::WITNESS_HEADER_LEN,
+};
+use solana_program_error::ProgramError;
+
+fn withdraw(pool: &mut Pool, spend: &Spend, valid_proof: bool) -> Result<(), ProgramError> {
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, valid_proof),
+        &spend.witness(),
+    )?;
+    pool.bank.process(&ix)
+}
+
+#[test]
+fn withdraw_pays_the_recipient() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let vault_before = pool.bank.lamports(&pool.vault());
+
+    withdraw(&mut pool, &spend, true).unwrap();
+
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+    assert_eq!(pool.bank.lamports(&pool.vault()), vault_before - NOTE);
+    let nullifier = client::nullifier_pda(&spend.nullifier).0;
+    assert_eq!(pool.bank.owner(&nullifier), shielded_pool_pinocchio::ID);
+    let state = pool.bank.state();
+    assert_eq!(state.withdraw_count, 1);
+    assert_eq!(state.total_shielded, 0);
+}
+
+#[test]
+fn a_recipient_credit_past_u64_max_is_an_overflow() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let full = u64::MAX - NOTE / 2;
+    pool.bank.fund(&spend.recipient, full);
+    let vault_before = pool.bank.lamports(&pool.vault());
+
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ProgramError::ArithmeticOverflow)
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), full);
+    assert_eq!(pool.bank.lamports(&pool.vault()), vault_before);
+    assert_eq!(pool.bank.state().withdraw_count, 0);
+}
+
+#[test]
+fn a_denylisted_recipient_is_rejected_until_removed() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let authority = pool.authority.clone();
+    pool.configure(&client::update_denylist(&authority, &spend.recipient, true));
+
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::RecipientDenied.into())
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+    // Another recipient of the same note is unaffected.
+    let other = Spend {
+        recipient: address(3),
+        ..spend.clone()
+    };
+    withdraw(&mut pool, &other, true).unwrap();
+    assert_eq!(pool.bank.lamports(&other.recipient), NOTE);
+
+    // Removing the entry lets the recipient be paid again.
+    pool.configure(&client::update_denylist(
+        &authority,
+        &spend.recipient,
+        false,
+    ));
+    let (_, root) = pool.deposit(&address(1), NOTE);
+    let spend = Spend {
+        root,
+        nullifier: [0x4f; 32],
+        ..spend
+    };
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn a_recipient_over_its_limit_is_paid_again_once_the_window_ends() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_recipient_limit(&authority, NOTE, 100));
+    let (_, root) = pool.deposit(&address(1), NOTE);
+    let second = Spend {
+        root,
+        nullifier: [0x4f; 32],
+        ..spend.clone()
+    };
+    let limit = client::recipient_limit_pda(&spend.recipient).0;
+    let window = |pool: &Pool| {
+        let limit: &RecipientLimit = bytemuck::from_bytes(pool.bank.data(&limit));
+        (limit.withdrawn_in_window, limit.window_start_slot)
+    };
+
+    pool.bank.set_slot(10);
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(window(&pool), (NOTE, 10));
+
+    // The last slot of the window is still in it.
+    for slot in [50, 109] {
+        pool.bank.set_slot(slot);
+        assert_eq!(
+            withdraw(&mut pool, &second, true),
+            Err(ShieldedPoolError::RecipientLimitExceeded.into()),
+            "slot {slot}"
+        );
+    }
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+
+    pool.bank.set_slot(110);
+    withdraw(&mut pool, &second, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), 2 * NOTE);
+    assert_eq!(window(&pool), (NOTE, 110));
+}
+
+#[test]
+fn withdraw_rejects_its_pdas_passed_out_of_order() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    // A non-empty denylist, so the recipient's entry is checked.
+    let authority = pool.authority.clone();
+    pool.configure(&client::update_denylist(&authority, &address(0x77), true));
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    let vault_before = pool.bank.lamports(&pool.vault());
+
+    // [vault, state], [nullifier, audit_record], [denylist_entry,
+    // recipient_limit] and [vault, fee_vault] swapped.
+    for (a, b) in [(2, 3), (4, 6), (9, 10), (2, 11)] {
+        let mut swapped = ix.clone();
+        swapped.accounts.swap(a, b);
+        assert_eq!(
+            pool.bank.process(&swapped),
+            Err(ProgramError::InvalidAccountData),
+            "accounts {a} and {b} swapped"
+        );
+    }
+    assert_eq!(pool.bank.lamports(&pool.vault()), vault_before);
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    pool.bank.process(&ix).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn withdraw_takes_its_accounts_plus_at_most_the_rebate_accounts() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    assert_eq!(ix.accounts.len(), WITHDRAW_ACCOUNTS);
+
+    let mut too_few = ix.clone();
+    too_few.accounts.pop();
+    assert_eq!(
+        pool.bank.process(&too_few),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+
+    let mut too_many = ix.clone();
+    too_many.accounts.extend(
+        (0..=REBATE_ACCOUNTS as u8)
+            .map(|seed| client::AccountMeta::readonly(address(0x60 + seed), false)),
+    );
+    assert_eq!(
+        pool.bank.process(&too_many),
+        Err(ShieldedPoolError::TooManyAccounts.into())
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    pool.bank.process(&ix).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn withdraw_with_a_rejected_proof_fails() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+
+    assert_eq!(withdraw(&mut pool, &spend, false), Err(PROOF_REJECTED));
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+}
+
+#[test]
+fn a_nullifier_cannot_be_spent_twice() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    withdraw(&mut pool, &spend, true).unwrap();
+
+    let again = Spend {
+        recipient: address(3),
+        ..spend
+    };
+    assert_eq!(
+        withdraw(&mut pool, &again, true),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    assert_eq!(pool.bank.lamports(&again.recipient), 0);
+}
+
+#[test]
+fn withdraw_against_an_unknown_root_fails() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let spend = Spend {
+        root: [0x55; 32],
+        ..spend
+    };
+
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::PublicInputMismatch.into())
+    );
+}
+
+#[test]
+fn withdraw_to_another_recipient_than_proven_fails() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let relayer = pool.relayer();
+    let thief = address(7);
+    let ix = client::withdraw(&relayer, &thief, &proof(PROOF_LEN, true), &spend.witness()).unwrap();
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::PublicInputMismatch.into())
+    );
+    assert_eq!(pool.bank.lamports(&thief), 0);
+}
+
+#[test]
+fn a_payer_can_withdraw_to_itself() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let payer = spend.recipient.clone();
+    pool.bank.fund(&payer, 1_000_000_000);
+    let ix = client::withdraw(&payer, &payer, &proof(PROOF_LEN, true), &spend.witness()).unwrap();
+
+    pool.bank.process(&ix).unwrap();
+
+    let nullifier_rent = pool
+        .bank
+        .lamports(&client::nullifier_pda(&spend.nullifier).0);
+    assert_eq!(
+        pool.bank.lamports(&payer),
+        1_000_000_000 - nullifier_rent + NOTE
+    );
+}
+
+#[test]
+fn a_payer_aliasing_a_pool_account_is_rejected() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    for payer in [
+        pool.vault(),
+        client::state_pda().0,
+        client::nullifier_pda(&spend.nullifier).0,
+        client::fee_vault_pda().0,
+    ] {
+        let ix = client::withdraw(
+            &payer,
+            &spend.recipient,
+            &proof(PROOF_LEN, true),
+            &spend.witness(),
+        )
+        .unwrap();
+        assert_eq!(
+            pool.bank.process(&ix),
+            Err(ShieldedPoolError::DuplicateAccount.into())
+        );
+    }
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+}
+
+/// A withdraw built for `spend` whose witness slot `slot` is then replaced
+/// by `value`, leaving the accounts as built.
+fn tampered_withdraw(
+    pool: &mut Pool,
+    spend: &Spend,
+    slot: usize,
+    value: [u8; 32],
+) -> Result<(), ProgramError> {
+    let relayer = pool.relayer();
+    let mut ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )?;
+    let start = 1 + PROOF_LEN + WITNESS_HEADER_LEN + 32 * slot;
+    ix.data[start..start + 32].copy_from_slice(&value);
+    pool.bank.process(&ix)
+}
+
+#[test]
+fn every_enforced_witness_slot_is_bound_to_its_source() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+
+    for (slot, value) in [
+        (0, [0x55; 32]),
+        (1, [0x4f; 32]),
+        (2, address_input(&address(7))),
+        (4, [0x3b; 32]),
+    ] {
+        assert_eq!(
+            tampered_withdraw(&mut pool, &spend, slot, value),
+            Err(ShieldedPoolError::PublicInputMismatch.into()),
+            "slot {slot}"
+        );
+    }
+    // An amount that isn't a u64 can't be paid as proven.
+    let mut amount = amount_input(NOTE);
+    amount[0] = 1;
+    assert_eq!(
+        tampered_withdraw(&mut pool, &spend, 3, amount),
+        Err(ShieldedPoolError::AmountOutOfRange.into())
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    tampered_withdraw(&mut pool, &spend, 0, spend.root).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn withdraw_pays_only_the_proven_recipient_and_amount() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let relayer = pool.relayer();
+
+    // The recipient account differs from the witness recipient.
+    let other = address(8);
+    let ix = client::withdraw(&relayer, &other, &proof(PROOF_LEN, true), &spend.witness()).unwrap();
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::PublicInputMismatch.into())
+    );
+
+    // The witness amount is more than the vault holds for the pool.
+    let greedy = Spend {
+        amount: 2 * NOTE,
+        ..spend.clone()
+    };
+    assert_eq!(
+        withdraw(&mut pool, &greedy, true),
+        Err(ProgramError::InsufficientFunds)
+    );
+    assert_eq!(pool.bank.lamports(&other), 0);
+    assert_eq!(pool.bank.lamports(&greedy.recipient), 0);
+
+    let smaller = Spend {
+        amount: NOTE / 2,
+        ..spend
+    };
+    withdraw(&mut pool, &smaller, true).unwrap();
+    assert_eq!(pool.bank.lamports(&smaller.recipient), NOTE / 2);
+}
+
+#[test]
+fn withdraw_without_an_audit_record_fails() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let spend = Spend {
+        wa_commitment: [0x3b; 32],
+        ..spend
+    };
+
+    assert!(withdraw(&mut pool, &spend, true).is_err());
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+}
+
+#[test]
+fn withdraw_rejects_a_corrupt_audit_record() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let record = client::audit_record_pda(&spend.wa_commitment).0;
+    let lamports = pool.bank.lamports(&record);
+    let stored = pool.bank.data(&record).to_vec();
+    assert_eq!(stored.len(), AuditRecord::LEN);
+
+    // The right size, but another account type's discriminator.
+    let mut wrong_discriminator = stored.clone();
+    wrong_discriminator[..8].copy_from_slice(&ShieldedPoolState::DISCRIMINATOR);
+    pool.bank.set_account(
+        &record,
+        &shielded_pool_pinocchio::ID,
+        lamports,
+        &wrong_discriminator,
+    );
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::AuditRecordCorrupt.into())
+    );
+
+    // Another identity's record doesn't match the witness.
+    let mut wrong_commitment = stored.clone();
+    wrong_commitment[8..40].copy_from_slice(&[0x3b; 32]);
+    pool.bank.set_account(
+        &record,
+        &shielded_pool_pinocchio::ID,
+        lamports,
+        &wrong_commitment,
+    );
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::PublicInputMismatch.into())
+    );
+
+    // A size no record layout has.
+    pool.bank.set_account(
+        &record,
+        &shielded_pool_pinocchio::ID,
+        lamports,
+        &stored[..AuditRecord::LEN - 1],
+    );
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ProgramError::InvalidAccountData)
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    pool.bank
+        .set_account(&record, &shielded_pool_pinocchio::ID, lamports, &stored);
+    withdraw(&mut pool, &spend, true).unwrap();
+}
+
+#[test]
+fn withdraw_rejects_an_audit_record_not_marked_verified() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let record = client::audit_record_pda(&spend.wa_commitment).0;
+    let lamports = pool.bank.lamports(&record);
+    let stored = pool.bank.data(&record).to_vec();
+
+    // A current record, and a version 0 one that ends at `verified`.
+    let mut unverified = stored.clone();
+    unverified[40] = 0;
+    for data in [&unverified[..], &unverified[..AuditRecord::V0_LEN]] {
+        pool.bank
+            .set_account(&record, &shielded_pool_pinocchio::ID, lamports, data);
+        assert_eq!(
+            withdraw(&mut pool, &spend, true),
+            Err(ShieldedPoolError::AuditRecordCorrupt.into()),
+            "{} byte record",
+            data.len()
+        );
+    }
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    pool.bank
+        .set_account(&record, &shielded_pool_pinocchio::ID, lamports, &stored);
+    withdraw(&mut pool, &spend, true).unwrap();
+}
+
+#[test]
+fn withdraw_rejects_a_client_bump_that_does_not_derive_the_pda() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let relayer = pool.relayer();
+    let ix = client::with_pda_bumps(
+        client::withdraw(
+            &relayer,
+            &spend.recipient,
+            &proof(PROOF_LEN, true),
+            &spend.witness(),
+        )
+        .unwrap(),
+        &spend.wa_commitment,
+    );
+    let len = ix.data.len();
+
+    // [vault_bump, audit_bump] close the instruction data.
+    for at in [len - 2, len - 1] {
+        let mut wrong_bump = ix.clone();
+        wrong_bump.data[at] = wrong_bump.data[at].wrapping_sub(1);
+        assert_eq!(
+            pool.bank.process(&wrong_bump),
+            Err(ShieldedPoolError::InvalidBump.into()),
+            "bump at {at}"
+        );
+    }
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    pool.bank.process(&ix).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn a_reserved_nullifier_is_spent_by_the_withdraw() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let relayer = pool.relayer();
+    let nullifier = client::nullifier_pda(&spend.nullifier).0;
+
+    pool.bank
+        .process(&client::reserve_nullifier(&relayer, &spend.nullifier))
+        .unwrap();
+    assert_eq!(pool.bank.data(&nullifier), [NULLIFIER_RESERVED]);
+    let nullifier_rent = pool.bank.lamports(&nullifier);
+    // Reserving again is a no-op.
+    pool.bank
+        .process(&client::reserve_nullifier(&relayer, &spend.nullifier))
+        .unwrap();
+    assert_eq!(pool.bank.lamports(&nullifier), nullifier_rent);
+
+    // The withdraw finalizes the reservation instead of creating the account.
+    let relayer_before = pool.bank.lamports(&relayer);
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.data(&nullifier), [NULLIFIER_SPENT]);
+    assert_eq!(pool.bank.lamports(&nullifier), nullifier_rent);
+    assert_eq!(pool.bank.lamports(&relayer), relayer_before);
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+
+    // A spent nullifier can be neither reserved nor spent again.
+    assert_eq!(
+        pool.bank
+            .process(&client::reserve_nullifier(&relayer, &spend.nullifier)),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+}
+
+#[test]
+fn a_third_partys_reservation_cannot_block_the_note() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let griefer = address(0x66);
+    pool.bank.fund(&griefer, NOTE);
+    let nullifier = client::nullifier_pda(&spend.nullifier).0;
+
+    // Anyone may reserve a nullifier, but that doesn't spend the note.
+    pool.bank
+        .process(&client::reserve_nullifier(&griefer, &spend.nullifier))
+        .unwrap();
+    assert_eq!(pool.bank.data(&nullifier), [NULLIFIER_RESERVED]);
+    assert_eq!(pool.bank.state().withdraw_count, 0);
+
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+    assert_eq!(pool.bank.data(&nullifier), [NULLIFIER_SPENT]);
+}
+
+#[test]
+fn withdraw_rejects_a_bogus_system_program() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let relayer = pool.relayer();
+    let mut ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    let system_program = ix
+        .accounts
+        .iter()
+        .position(|meta| meta.address == pinocchio_system::ID)
+        .unwrap();
+    ix.accounts[system_program].address = address(0x5b);
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ProgramError::IncorrectProgramId)
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+}
+
+#[test]
+fn a_wallet_only_pool_rejects_a_program_owned_recipient() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    // A PDA of some other program.
+    pool.bank
+        .set_account(&spend.recipient, &address(0x77), 1_000_000, &[]);
+
+    // Rejected while the pool requires wallets...
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_wallet_recipient(&authority, true));
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::InvalidRecipient.into())
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), 1_000_000);
+
+    // ...and paid once the requirement is lifted.
+    pool.configure(&client::configure_wallet_recipient(&authority, false));
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), 1_000_000 + NOTE);
+}
+
+#[test]
+fn withdraw_reports_the_payout() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+
+    withdraw(&mut pool, &spend, true).unwrap();
+
+    let return_data = pool.bank.return_data();
+    assert_eq!(return_data.len(), WITHDRAW_RETURN_DATA_LEN);
+    assert_eq!(return_data[0..8], NOTE.to_le_bytes());
+    assert_eq!(return_data[8..16], 0u64.to_le_bytes());
+    assert_eq!(return_data[16..48], spend.nullifier);
+}
+
+#[test]
+fn withdraw_logs_the_nullifier_account_it_created() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    pool.bank.events();
+
+    withdraw(&mut pool, &spend, true).unwrap();
+
+    let nullifier_account = client::nullifier_pda(&spend.nullifier).0;
+    assert_eq!(
+        pool.bank.owner(&nullifier_account),
+        shielded_pool_pinocchio::ID
+    );
+    let expected = WithdrawEvent {
+        nullifier: spend.nullifier,
+        recipient: spend.recipient.clone(),
+        amount: NOTE,
+        nullifier_account,
+    };
+    assert_eq!(pool.bank.events(), [vec![expected.to_bytes().to_vec()]]);
+}
+
+#[test]
+fn withdraw_reports_the_payout_after_paying_the_rebate() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let rebate = pool.enable_rebate(5_000, 1_000_000);
+    let relayer = pool.relayer();
+    let mut ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    ix.accounts
+        .extend(pool.rebate_accounts(&rebate, &spend.recipient));
+
+    pool.bank.process(&ix).unwrap();
+
+    let token = common::rebate_token_account(&spend.recipient);
+    assert_eq!(pool.bank.token_balance(&token), rebate.amount);
+    let return_data = pool.bank.return_data();
+    assert_eq!(return_data.len(), WITHDRAW_RETURN_DATA_LEN);
+    assert_eq!(return_data[0..8], NOTE.to_le_bytes());
+    assert_eq!(return_data[16..48], spend.nullifier);
+}
+
+#[test]
+fn an_underfunded_rebate_vault_is_reported() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let rebate = pool.enable_rebate(5_000, 4_999);
+    let relayer = pool.relayer();
+    let mut ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    ix.accounts
+        .extend(pool.rebate_accounts(&rebate, &spend.recipient));
+    pool.bank.events();
+
+    pool.bank.process(&ix).unwrap();
+
+    let token = common::rebate_token_account(&spend.recipient);
+    assert_eq!(pool.bank.token_balance(&token), 0);
+    let events = pool.bank.events();
+    let skipped = &events.last().unwrap()[0];
+    assert_eq!(skipped[0], EventKind::RebateSkipped as u8);
+    assert_eq!(&skipped[1..33], spend.recipient.as_ref());
+    assert_eq!(skipped[33..41], 5_000u64.to_le_bytes());
+    assert_eq!(skipped[41..49], 4_999u64.to_le_bytes());
+}
+
+#[test]
+fn a_pda_recipient_must_be_owned_by_the_bound_program() {
+    let config = pool_config().public_input_count(RECIPIENT_PROGRAM_INPUT as u8 + 1);
+    let (mut pool, mut spend) = Pool::with_note(&config);
+    let bound_program = address(0x70);
+    let mut extra_inputs = vec![[0u8; 32]; RECIPIENT_PROGRAM_INPUT - PUBLIC_INPUTS];
+    extra_inputs.push(address_input(&bound_program));
+    spend.extra_inputs = extra_inputs;
+
+    // A wallet, or a look-alike PDA of another program, is not the bound
+    // program's account.
+    for owner in [pinocchio_system::ID, address(0x71)] {
+        pool.bank
+            .set_account(&spend.recipient, &owner, 1_000_000, &[]);
+        assert_eq!(
+            withdraw(&mut pool, &spend, true),
+            Err(ShieldedPoolError::InvalidRecipient.into())
+        );
+    }
+
+    pool.bank
+        .set_account(&spend.recipient, &bound_program, 1_000_000, &[]);
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), 1_000_000 + NOTE);
+}
+
+#[test]
+fn an_owner_gated_withdraw_needs_the_note_owners_signature() {
+    let config = pool_config().public_input_count(OWNER_INPUT as u8 + 1);
+    let (mut pool, spend) = Pool::with_note(&config);
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_owner_gate(&authority, true));
+    let owner = address(0x6a);
+    let spend = Spend {
+        extra_inputs: vec![[0u8; 32], address_input(&owner)],
+        ..spend
+    };
+
+    // Relayed, with or without the recipient's signature.
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::MissingOwnerSignature.into())
+    );
+    let relayer = pool.relayer();
+    let mut ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    ix.accounts[1].is_signer = true;
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::MissingOwnerSignature.into())
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    // Self-relayed by the owner.
+    pool.bank.fund(&owner, 1_000_000_000);
+    let ix = client::withdraw(
+        &owner,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    pool.bank.process(&ix).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn a_proof_bound_to_another_pool_is_rejected() {
+    let config = pool_config().public_input_count(POOL_DOMAIN_INPUT as u8 + 1);
+    let (mut pool, mut spend) = Pool::with_note(&config);
+    let mut domain_inputs = vec![[0u8; 32]; POOL_DOMAIN_INPUT - PUBLIC_INPUTS];
+    domain_inputs.push(address_input(&address(0x99)));
+    spend.extra_inputs = domain_inputs;
+
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::PublicInputMismatch.into())
+    );
+
+    *spend.extra_inputs.last_mut().unwrap() = address_input(&client::state_pda().0);
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert!(pool.bank.lamports(&spend.recipient) > 0);
+}
+
+#[test]
+fn withdraw_without_the_verifier_compute_budget_fails_before_the_cpi() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_compute_guard(&authority, 300_000));
+    pool.bank.cpis();
+
+    pool.bank.set_remaining_compute_units(299_999);
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::InsufficientComputeBudget.into())
+    );
+    assert!(pool.bank.cpis().is_empty());
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    pool.bank.set_remaining_compute_units(300_000);
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+/// A pool whose audits apply from slot 100, holding one note deposited at
+/// slot 10, and a spend of that note by an identity without an audit record.
+fn pool_with_pre_cutoff_note() -> (Pool, Spend) {
+    let mut pool = Pool::new();
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_audit_cutoff(&authority, 100));
+    let depositor = address(1);
+    pool.bank.fund(&depositor, 10 * NOTE);
+    pool.bank.set_slot(10);
+    let (_, root) = pool.deposit(&depositor, NOTE);
+    let spend = Spend {
+        root,
+        nullifier: [0x4e; 32],
+        recipient: address(2),
+        amount: NOTE,
+        wa_commitment: [0x3b; 32],
+        extra_inputs: Vec::new(),
+    };
+    (pool, spend)
+}
+
+#[test]
+fn a_root_from_before_the_audit_cutoff_needs_no_audit_record() {
+    let (mut pool, spend) = pool_with_pre_cutoff_note();
+    pool.bank.set_slot(200);
+
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn a_root_from_after_the_audit_cutoff_needs_an_audit_record() {
+    let (mut pool, spend) = pool_with_pre_cutoff_note();
+    pool.bank.set_slot(150);
+    let depositor = address(1);
+    let (_, root) = pool.deposit(&depositor, NOTE);
+    let spend = Spend { root, ..spend };
+
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ProgramError::UninitializedAccount)
+    );
+
+    pool.audit(&spend.wa_commitment);
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn a_pre_cutoff_note_needs_an_audit_once_its_roots_roll_out_of_history() {
+    let (mut pool, spend) = pool_with_pre_cutoff_note();
+    pool.bank.set_slot(150);
+    let depositor = address(1);
+    let mut latest_root = spend.root;
+    for _ in 0..ROOT_HISTORY_SIZE {
+        latest_root = pool.deposit(&depositor, 1_000_000).1;
+    }
+
+    // The note's own root is gone...
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::PublicInputMismatch.into())
+    );
+    // ...and every root left postdates the cutoff.
+    let spend = Spend {
+        root: latest_root,
+        ..spend
+    };
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ProgramError::UninitializedAccount)
+    );
+
+    pool.audit(&spend.wa_commitment);
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn withdraw_rejects_a_vault_with_unexpected_data() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let vault = pool.vault();
+    let lamports = pool.bank.lamports(&vault);
+    let header = pool.bank.data(&vault).to_vec();
+
+    // Reallocated past the header, or stripped of it.
+    let mut grown = header.clone();
+    grown.extend_from_slice(&[0u8; 8]);
+    for data in [grown, Vec::new()] {
+        pool.bank
+            .set_account(&vault, &shielded_pool_pinocchio::ID, lamports, &data);
+        assert_eq!(
+            withdraw(&mut pool, &spend, true),
+            Err(ShieldedPoolError::VaultHasData.into())
+        );
+    }
+
+    // The right size, but not the pool's header.
+    let mut foreign = header.clone();
+    foreign[0] ^= 0xff;
+    pool.bank
+        .set_account(&vault, &shielded_pool_pinocchio::ID, lamports, &foreign);
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::InvalidVaultHeader.into())
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    pool.bank
+        .set_account(&vault, &shielded_pool_pinocchio::ID, lamports, &header);
+    withdraw(&mut pool, &spend, true).unwrap();
+}
+
+#[test]
+fn withdraw_rejects_a_vault_the_program_does_not_own() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let vault = pool.vault();
+    let lamports = pool.bank.lamports(&vault);
+    let header = pool.bank.data(&vault).to_vec();
+
+    // The pool's header, under another owner.
+    for owner in [pinocchio_system::ID, address(0x5c)] {
+        pool.bank.set_account(&vault, &owner, lamports, &header);
+        assert_eq!(
+            withdraw(&mut pool, &spend, true),
+            Err(ProgramError::InvalidAccountOwner)
+        );
+    }
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+    assert_eq!(pool.bank.lamports(&vault), lamports);
+}
+
+/// `SimulateWithdraw` of `spend`: its status and amount.
+fn simulate(pool: &mut Pool, spend: &Spend) -> (u64, u64) {
+    let relayer = pool.relayer();
+    let ix = client::simulate_withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    let touched = [
+        pool.vault(),
+        spend.recipient.clone(),
+        relayer,
+        client::nullifier_pda(&spend.nullifier).0,
+    ];
+    let lamports_before = touched.clone().map(|account| pool.bank.lamports(&account));
+    let state_before = pool.bank.state();
+
+    pool.bank.process(&ix).unwrap();
+
+    assert_eq!(
+        touched.map(|account| pool.bank.lamports(&account)),
+        lamports_before
+    );
+    assert_eq!(
+        bytemuck::bytes_of(&pool.bank.state()),
+        bytemuck::bytes_of(&state_before)
+    );
+    let data = pool.bank.return_data();
+    assert_eq!(data.len(), SIMULATE_WITHDRAW_RETURN_DATA_LEN);
+    (
+        u64::from_le_bytes(data[0..8].try_into().unwrap()),
+        u64::from_le_bytes(data[8..16].try_into().unwrap()),
+    )
+}
+
+#[test]
+fn simulate_withdraw_predicts_withdraw_without_touching_the_pool() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+
+    let unknown_root = Spend {
+        root: [0x55; 32],
+        ..spend.clone()
+    };
+    let expected: ProgramError = ShieldedPoolError::PublicInputMismatch.into();
+    assert_eq!(
+        simulate(&mut pool, &unknown_root),
+        (u64::from(expected.clone()), 0)
+    );
+    assert_eq!(withdraw(&mut pool, &unknown_root, true), Err(expected));
+
+    assert_eq!(simulate(&mut pool, &spend), (0, NOTE));
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+
+    // Once spent, the simulation reports the replay like the withdraw does.
+    let replay = Spend {
+        recipient: address(3),
+        ..spend
+    };
+    let (status, _) = simulate(&mut pool, &replay);
+    assert_eq!(
+        withdraw(&mut pool, &replay, true).map_err(u64::from),
+        Err(status)
+    );
+}
+
+#[test]
+fn withdraw_to_a_pool_account_is_rejected() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    for recipient in [
+        pool.vault(),
+        client::state_pda().0,
+        shielded_pool_pinocchio::ID,
+    ] {
+        let spend = Spend {
+            recipient,
+            ..spend.clone()
+        };
+        assert_eq!(
+            withdraw(&mut pool, &spend, true),
+            Err(ShieldedPoolError::InvalidRecipient.into())
+        );
+    }
+    withdraw(&mut pool, &spend, true).unwrap();
+}
+
+#[test]
+fn withdraw_below_the_minimum_is_rejected() {
+    let (mut pool, spend) = Pool::with_note(&client::PoolConfig {
+        min_withdraw: NOTE / 2,
+        ..pool_config()
+    });
+    let dust = Spend {
+        amount: NOTE / 2 - 1,
+        ..spend.clone()
+    };
+
+    assert_eq!(
+        withdraw(&mut pool, &dust, true),
+        Err(ShieldedPoolError::BelowMinWithdraw.into())
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    let minimum = Spend {
+        amount: NOTE / 2,
+        ..spend
+    };
+    withdraw(&mut pool, &minimum, true).unwrap();
+    assert_eq!(pool.bank.lamports(&minimum.recipient), NOTE / 2);
+}
+
+#[test]
+fn a_witness_amount_wider_than_u64_is_out_of_range() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let relayer = pool.relayer();
+
+    for high_byte in [0, 12, 23] {
+        let mut inputs = spend.inputs();
+        inputs[3][high_byte] = 1;
+        let ix = client::withdraw(
+            &relayer,
+            &spend.recipient,
+            &proof(PROOF_LEN, true),
+            &common::witness(&inputs),
+        )
+        .unwrap();
+        assert_eq!(
+            pool.bank.process(&ix),
+            Err(ShieldedPoolError::AmountOutOfRange.into()),
+            "byte {high_byte}"
+        );
+    }
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    // The widest u64 converts, and is then more than the vault holds.
+    let everything = Spend {
+        amount: u64::MAX,
+        ..spend
+    };
+    assert_eq!(
+        withdraw(&mut pool, &everything, true),
+        Err(ProgramError::InsufficientFunds)
+    );
+}
+
+/// A withdraw of `spend` whose `[proof][witness]` is replaced by `proof` and
+/// a witness of `inputs`.
+fn withdraw_with_payload(
+    pool: &mut Pool,
+    spend: &Spend,
+    proof: &[u8],
+    inputs: &[[u8; 32]],
+) -> Result<(), ProgramError> {
+    let relayer = pool.relayer();
+    let mut ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &common::proof(PROOF_LEN, true),
+        &spend.witness(),
+    )?;
+    ix.data.truncate(1);
+    ix.data.extend_from_slice(proof);
+    ix.data.extend_from_slice(&common::witness(inputs));
+    pool.bank.process(&ix)
+}
+
+#[test]
+fn the_witness_must_carry_the_pools_public_input_count() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let inputs = spend.inputs();
+    assert_eq!(inputs.len(), PUBLIC_INPUTS);
+    let mut six = inputs.clone();
+    six.push([0u8; 32]);
+
+    for inputs in [&inputs[..PUBLIC_INPUTS - 1], &six[..]] {
+        assert_eq!(
+            withdraw_with_payload(&mut pool, &spend, &proof(PROOF_LEN, true), inputs),
+            Err(ProgramError::InvalidInstructionData),
+            "{} inputs",
+            inputs.len()
+        );
+    }
+
+    withdraw_with_payload(&mut pool, &spend, &proof(PROOF_LEN, true), &inputs).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn a_six_input_pool_rejects_five_input_witnesses() {
+    let (mut pool, spend) = Pool::with_note(&pool_config().public_input_count(6));
+    let spend = Spend {
+        extra_inputs: vec![[0u8; 32]],
+        ..spend
+    };
+
+    assert_eq!(
+        withdraw_with_payload(
+            &mut pool,
+            &spend,
+            &proof(PROOF_LEN, true),
+            &spend.inputs()[..PUBLIC_INPUTS]
+        ),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
+
+#[test]
+fn a_nullifier_first_pool_reads_its_witness_in_that_order() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_public_input_order(
+        &authority,
+        [1, 0, 2, 3, 4],
+    ));
+    assert_eq!(pool.bank.state().public_input_order, [1, 0, 2, 3, 4]);
+
+    // A root-first witness now reads the nullifier as the root.
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::PublicInputMismatch.into())
+    );
+
+    let mut inputs = spend.inputs();
+    inputs.swap(0, 1);
+    withdraw_with_payload(&mut pool, &spend, &proof(PROOF_LEN, true), &inputs).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+    let nullifier = client::nullifier_pda(&spend.nullifier).0;
+    assert_eq!(pool.bank.owner(&nullifier), shielded_pool_pinocchio::ID);
+}
+
+fn withdraw_with_change(
+    pool: &mut Pool,
+    spend: &Spend,
+    prev_root: &[u8; 32],
+    new_root: &[u8; 32],
+) -> Result<(), ProgramError> {
+    let relayer = pool.relayer();
+    let ix = client::withdraw_with_change(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+        prev_root,
+        new_root,
+    )?;
+    pool.bank.process(&ix)
+}
+
+#[test]
+fn a_partial_withdraw_inserts_the_change_note() {
+    let (mut pool, spend) = Pool::with_note(&pool_config().public_input_count(6));
+    let change_commitment = [0xc4; 32];
+    let spend = Spend {
+        amount: NOTE / 4,
+        extra_inputs: vec![change_commitment],
+        ..spend
+    };
+    let before = pool.bank.state();
+    let vault_before = pool.bank.lamports(&pool.vault());
+    let change_root = [0xc5; 32];
+
+    // The change note needs roots built on the current root.
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+        withdraw_with_change(&mut pool, &spend, &[0x01; 32], &change_root),
+        Err(ShieldedPoolError::DepositRootRace.into())
+    );
+    assert_eq!(
+        withdraw_with_change(
+            &mut pool,
+            &spend,
+            &before.current_root,
+            &before.current_root
+        ),
+        Err(ShieldedPoolError::StaleRoot.into())
+    );
+
+    withdraw_with_change(&mut pool, &spend, &before.current_root, &change_root).unwrap();
+
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE / 4);
+    assert_eq!(pool.bank.lamports(&pool.vault()), vault_before - NOTE / 4);
+    let state = pool.bank.state();
+    assert_eq!(state.current_root, change_root);
+    assert!(state.roots.contains(&change_root));
+    assert!(state.roots.contains(&before.current_root));
+    assert_eq!(state.next_leaf_index, before.next_leaf_index + 1);
+    assert_eq!(state.total_shielded, before.total_shielded - NOTE / 4);
+
+    // The change note is spendable against the root it was inserted under.
+    let change_spend = Spend {
+        root: change_root,
+        nullifier: [0x4f; 32],
+        amount: NOTE - NOTE / 4,
+        extra_inputs: vec![[0u8; 32]],
+        ..spend.clone()
+    };
+    withdraw(&mut pool, &change_spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+    assert_eq!(pool.bank.state().total_shielded, 0);
+}
+
+#[test]
+fn a_pool_reads_proofs_of_its_configured_length() {
+    let long_proof = 2 * PROOF_LEN;
+    let (mut pool, spend) = Pool::with_note(&client::PoolConfig {
+        proof_len: long_proof as u32,
+        ..pool_config()
+    });
+    let inputs = spend.inputs();
+
+    // A proof of the default length is read as a truncated one.
+    assert_eq!(
+        withdraw_with_payload(&mut pool, &spend, &proof(PROOF_LEN, true), &inputs),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+        withdraw_with_payload(&mut pool, &spend, &proof(long_proof + 1, true), &inputs),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    pool.bank.cpis();
+    withdraw_with_payload(&mut pool, &spend, &proof(long_proof, true), &inputs).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+    // The verifier gets the whole proof and witness.
+    let cpis = pool.bank.cpis();
+    assert_eq!(
+        cpis[0].1.len(),
+        long_proof + WITNESS_HEADER_LEN + 32 * PUBLIC_INPUTS
+    );
+}
+
+#[test]
+fn a_withdraw_may_name_either_verifier_while_both_are_set() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let authority = pool.authority.clone();
+    let alt_verifier = address(0xb7);
+    let alt_proof_len = 2 * PROOF_LEN;
+    pool.configure(&client::configure_alt_verifier(
+        &authority,
+        &alt_verifier,
+        alt_proof_len as u32,
+    ));
+    let alt = alt_verifier.clone();
+    pool.bank
+        .set_cpi_handler(move |program_id, data, accounts| {
+            if program_id == &alt {
+                return mock_verifiers(&ZK_VERIFIER_PROGRAM_ID, data, accounts);
+            }
+            mock_verifiers(program_id, data, accounts)
+        });
+    let depositor = address(1);
+    let (_, root) = pool.deposit(&depositor, NOTE);
+    let second = Spend {
+        root,
+        nullifier: [0x4f; 32],
+        recipient: address(3),
+        ..spend.clone()
+    };
+
+    let relayer = pool.relayer();
+    let withdraw_via =
+        |pool: &mut Pool, spend: &Spend, verifier: &common::Address, proof: &[u8]| {
+            let mut ix = client::withdraw(&relayer, &spend.recipient, proof, &spend.witness())?;
+            ix.accounts[5].address = verifier.clone();
+            pool.bank.process(&ix)
+        };
+
+    // Each verifier takes only its own proof length.
+    assert_eq!(
+        withdraw_via(&mut pool, &spend, &alt_verifier, &proof(PROOF_LEN, true)),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+        withdraw_via(
+            &mut pool,
+            &spend,
+            &ZK_VERIFIER_PROGRAM_ID,
+            &proof(alt_proof_len, true)
+        ),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+        withdraw_via(
+            &mut pool,
+            &spend,
+            &alt_verifier,
+            &proof(alt_proof_len, false)
+        ),
+        Err(PROOF_REJECTED)
+    );
+
+    pool.bank.cpis();
+    withdraw_via(
+        &mut pool,
+        &spend,
+        &alt_verifier,
+        &proof(alt_proof_len, true),
+    )
+    .unwrap();
+    assert_eq!(pool.bank.cpis()[0].0, alt_verifier);
+    withdraw_via(
+        &mut pool,
+        &second,
+        &ZK_VERIFIER_PROGRAM_ID,
+        &proof(PROOF_LEN, true),
+    )
+    .unwrap();
+    assert_eq!(pool.bank.cpis()[0].0, ZK_VERIFIER_PROGRAM_ID);
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+    assert_eq!(pool.bank.lamports(&second.recipient), NOTE);
+
+    // Once cleared, the alternate verifier is unknown again.
+    pool.configure(&client::configure_alt_verifier(
+        &authority,
+        &alt_verifier,
+        0,
+    ));
+    let third = Spend {
+        nullifier: [0x50; 32],
+        ..second
+    };
+    assert_eq!(
+        withdraw_via(
+            &mut pool,
+            &third,
+            &alt_verifier,
+            &proof(alt_proof_len, true)
+        ),
+        Err(ProgramError::IncorrectProgramId)
+    );
+}
+
+#[test]
+fn a_payer_that_cannot_fund_the_nullifier_fails_before_the_verifier() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let payer = address(0x99);
+    pool.bank.fund(&payer, 1_000);
+    let ix = client::withdraw(
+        &payer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    pool.bank.cpis();
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::InsufficientRentForNullifier.into())
+    );
+    assert!(pool.bank.cpis().is_empty());
+    assert_eq!(pool.bank.lamports(&payer), 1_000);
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+}
+
+#[test]
+fn a_withdraw_holds_no_account_borrows_across_the_verifier_cpi() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let nullifier = client::nullifier_pda(&spend.nullifier).0;
+    let held = [
+        pool.bank.view(&client::state_pda().0),
+        pool.bank.view(&pool.vault()),
+        pool.bank.view(&nullifier),
+        pool.bank
+            .view(&client::audit_record_pda(&spend.wa_commitment).0),
+    ];
+    let verified = Rc::new(Cell::new(false));
+    let seen = verified.clone();
+    pool.bank
+        .set_cpi_handler(move |program_id, data, accounts| {
+            if program_id == &ZK_VERIFIER_PROGRAM_ID {
+                for account in &held {
+                    drop(account.try_borrow_mut()?);
+                }
+                seen.set(true);
+            }
+            mock_verifiers(program_id, data, accounts)
+        });
+
+    // A deposit and a withdraw in one transaction.
+    let depositor = address(1);
+    let root = pool.bank.state().current_root;
+    let relayer = pool.relayer();
+    let transaction = [
+        client::deposit(&depositor, NOTE, &[0xd1; 32], &root, &[0xd2; 32]),
+        client::withdraw(
+            &relayer,
+            &spend.recipient,
+            &proof(PROOF_LEN, true),
+            &spend.witness(),
+        )
+        .unwrap(),
+    ];
+    pool.bank.process_transaction(&transaction).unwrap();
+
+    assert!(verified.get());
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+    let state = pool.bank.state();
+    assert_eq!(state.current_root, [0xd2; 32]);
+    assert_eq!(state.total_shielded, NOTE);
+}
+
+#[test]
+fn a_failed_withdraw_rolls_back_the_whole_transaction() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let depositor = address(1);
+    let depositor_before = pool.bank.lamports(&depositor);
+    let state_before = pool.bank.state();
+    let relayer = pool.relayer();
+    let transaction = [
+        client::deposit(
+            &depositor,
+            NOTE,
+            &[0xd1; 32],
+            &state_before.current_root,
+            &[0xd2; 32],
+        ),
+        client::withdraw(
+            &relayer,
+            &spend.recipient,
+            &proof(PROOF_LEN, false),
+            &spend.witness(),
+        )
+        .unwrap(),
+    ];
+
+    assert_eq!(
+        pool.bank.process_transaction(&transaction),
+        Err(PROOF_REJECTED)
+    );
+    assert_eq!(pool.bank.lamports(&depositor), depositor_before);
+    assert_eq!(
+        bytemuck::bytes_of(&pool.bank.state()),
+        bytemuck::bytes_of(&state_before)
+    );
+}
+
+#[test]
+fn a_root_younger_than_the_minimum_age_is_rejected_until_it_matures() {
+    let (mut pool, spend) = Pool::with_note(&client::PoolConfig {
+        min_deposit_age_slots: 50,
+        ..pool_config()
+    });
+
+    pool.bank.set_slot(49);
+    assert_eq!(
+        withdraw(&mut pool, &spend, true),
+        Err(ShieldedPoolError::RootTooRecent.into())
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    pool.bank.set_slot(50);
+    withdraw(&mut pool, &spend, true).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+}
@@ -0,0 +1,443 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{
+    address, address_input, amount_input, mock_verifiers, pool_config, proof, Pool, PoolConfigExt,
+    Spend, NOTE, PROOF_LEN,
+};
+use shielded_pool_pinocchio::{
+    client,
+    error::ShieldedPoolError,
+    event::EventKind,
+    instructions::{
+        withdraw::{SPLIT_RETURN_DATA_LEN, WITHDRAW_RETURN_DATA_LEN},
+        withdraw_with_memo::MAX_WITHDRAW_MEMO_LEN,
+    },
+    runtime::host,
+    state::RecipientLimit,
+};
+use solana_program_error::ProgramError;
+
+#[test]
+fn withdraw_to_escrow_holds_the_payout_until_claimed() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let relayer = pool.relayer();
+    let ix = client::withdraw_to_escrow(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+
+    pool.bank.process(&ix).unwrap();
+
+    let escrow = client::escrow_pda(&spend.recipient).0;
+    let escrow_balance = pool.bank.lamports(&escrow);
+    assert!(escrow_balance > NOTE, "payout plus the escrow's rent");
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    // Only the recipient can claim, and gets the rent as well.
+    let mut claim = client::claim_escrow(&spend.recipient);
+    claim.accounts[0].is_signer = false;
+    assert_eq!(
+        pool.bank.process(&claim),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    pool.bank
+        .process(&client::claim_escrow(&spend.recipient))
+        .unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), escrow_balance);
+    assert_eq!(pool.bank.lamports(&escrow), 0);
+}
+
+#[test]
+fn an_escrow_is_claimed_once_and_only_by_its_recipient() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let relayer = pool.relayer();
+    let ix = client::withdraw_to_escrow(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+    pool.bank.process(&ix).unwrap();
+    let escrow = client::escrow_pda(&spend.recipient).0;
+    let escrow_balance = pool.bank.lamports(&escrow);
+
+    // Another signer pointing at the recipient's escrow.
+    let thief = address(7);
+    let mut claim = client::claim_escrow(&thief);
+    claim.accounts[1].address = escrow.clone();
+    assert_eq!(
+        pool.bank.process(&claim),
+        Err(ProgramError::InvalidAccountData)
+    );
+    // The thief's own escrow is empty.
+    assert_eq!(
+        pool.bank.process(&client::claim_escrow(&thief)),
+        Err(ProgramError::UninitializedAccount)
+    );
+    assert_eq!(pool.bank.lamports(&escrow), escrow_balance);
+
+    let claim = client::claim_escrow(&spend.recipient);
+    pool.bank.process(&claim).unwrap();
+    assert_eq!(
+        pool.bank.process(&claim),
+        Err(ProgramError::UninitializedAccount)
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), escrow_balance);
+    assert_eq!(pool.bank.lamports(&thief), 0);
+}
+
+#[test]
+fn withdraw_to_escrow_is_refused_while_a_rebate_is_paid() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    pool.enable_rebate(5_000, 1_000_000);
+    let relayer = pool.relayer();
+    let ix = client::withdraw_to_escrow(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::RebateUnsupported.into())
+    );
+}
+
+fn withdraw_and_call(
+    pool: &mut Pool,
+    spend: &Spend,
+    target_program: &common::Address,
+) -> Result<(), ProgramError> {
+    let relayer = pool.relayer();
+    let ix = client::withdraw_and_call(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+        target_program,
+        &[],
+        b"call",
+    )?;
+    pool.bank.process(&ix)
+}
+
+#[test]
+fn withdraw_and_call_reports_the_withdrawal_over_the_target_return_data() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let target = address(0x7a);
+    let called = target.clone();
+    pool.bank
+        .set_cpi_handler(move |program_id, data, accounts| {
+            if program_id == &called {
+                assert_eq!(data, b"call");
+                host::set_return_data(b"target return data");
+                return Ok(());
+            }
+            mock_verifiers(program_id, data, accounts)
+        });
+
+    withdraw_and_call(&mut pool, &spend, &target).unwrap();
+
+    let return_data = pool.bank.return_data();
+    assert_eq!(return_data.len(), WITHDRAW_RETURN_DATA_LEN);
+    assert_eq!(return_data[0..8], NOTE.to_le_bytes());
+    assert_eq!(return_data[16..48], spend.nullifier);
+}
+
+#[test]
+fn withdraw_and_call_refuses_the_system_program() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+
+    for target in [pinocchio_system::ID, shielded_pool_pinocchio::ID] {
+        assert_eq!(
+            withdraw_and_call(&mut pool, &spend, &target),
+            Err(ProgramError::IncorrectProgramId)
+        );
+    }
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+}
+
+#[test]
+fn withdraw_with_memo_logs_a_memo_up_to_its_maximum_length() {
+    let (mut pool, spend) = Pool::with_note(&pool_config());
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+
+    for len in [0, MAX_WITHDRAW_MEMO_LEN + 1] {
+        assert_eq!(
+            pool.bank
+                .process(&client::with_memo(ix.clone(), &vec![b'm'; len])),
+            Err(ProgramError::InvalidInstructionData),
+            "{len} byte memo"
+        );
+    }
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+
+    let memo = [b'm'; MAX_WITHDRAW_MEMO_LEN];
+    pool.bank.events();
+    pool.bank.process(&client::with_memo(ix, &memo)).unwrap();
+
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE);
+    let events = pool.bank.events();
+    let memo_event = events.last().unwrap();
+    assert_eq!(memo_event.len(), 2);
+    assert_eq!(memo_event[0][0], EventKind::WithdrawMemo as u8);
+    assert_eq!(memo_event[0][1..33], spend.nullifier);
+    assert_eq!(memo_event[1], memo);
+}
+
+/// A split of the note: half to the proof's recipient, a quarter to each of
+/// two more.
+fn split_spend(spend: Spend) -> (Spend, [common::Address; 2]) {
+    let others = [address(4), address(5)];
+    let spend = Spend {
+        amount: NOTE / 2,
+        extra_inputs: vec![
+            [0; 32],
+            [0; 32],
+            address_input(&others[0]),
+            amount_input(NOTE / 4),
+            address_input(&others[1]),
+            amount_input(NOTE / 4),
+            [0; 32],
+            [0; 32],
+        ],
+        ..spend
+    };
+    (spend, others)
+}
+
+#[test]
+fn withdraw_split_pays_every_recipient() {
+    let (mut pool, spend) = Pool::with_note(&pool_config().public_input_count(13));
+    let (spend, others) = split_spend(spend);
+    let vault_before = pool.bank.lamports(&pool.vault());
+    let relayer = pool.relayer();
+    let ix = client::withdraw_split(
+        &relayer,
+        &spend.recipient,
+        &others,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+
+    pool.bank.process(&ix).unwrap();
+
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE / 2);
+    assert_eq!(pool.bank.lamports(&others[0]), NOTE / 4);
+    assert_eq!(pool.bank.lamports(&others[1]), NOTE / 4);
+    assert_eq!(pool.bank.lamports(&pool.vault()), vault_before - NOTE);
+    assert_eq!(pool.bank.state().total_shielded, 0);
+
+    // The proof's payout, then each split payout's amount and fee.
+    let return_data = pool.bank.return_data();
+    assert_eq!(
+        return_data.len(),
+        WITHDRAW_RETURN_DATA_LEN + 2 * SPLIT_RETURN_DATA_LEN
+    );
+    assert_eq!(return_data[0..8], (NOTE / 2).to_le_bytes());
+    for split in return_data[WITHDRAW_RETURN_DATA_LEN..].chunks_exact(SPLIT_RETURN_DATA_LEN) {
+        assert_eq!(split[0..8], (NOTE / 4).to_le_bytes());
+        assert_eq!(split[8..16], 0u64.to_le_bytes());
+    }
+}
+
+#[test]
+fn withdraw_split_rejects_a_substituted_recipient() {
+    let (mut pool, spend) = Pool::with_note(&pool_config().public_input_count(13));
+    let (spend, others) = split_spend(spend);
+    let relayer = pool.relayer();
+    let ix = client::withdraw_split(
+        &relayer,
+        &spend.recipient,
+        &[others[0].clone(), address(6)],
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ProgramError::InvalidAccountData)
+    );
+    assert_eq!(pool.bank.lamports(&address(6)), 0);
+}
+
+#[test]
+fn plain_withdraw_rejects_split_payouts() {
+    let (mut pool, spend) = Pool::with_note(&pool_config().public_input_count(13));
+    let (spend, _) = split_spend(spend);
+    let relayer = pool.relayer();
+    let ix = client::withdraw(
+        &relayer,
+        &spend.recipient,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+fn split_ix(pool: &mut Pool, spend: &Spend, others: &[common::Address]) -> client::Instruction {
+    let relayer = pool.relayer();
+    client::withdraw_split(
+        &relayer,
+        &spend.recipient,
+        others,
+        &proof(PROOF_LEN, true),
+        &spend.witness(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn withdraw_split_rejects_a_denylisted_split_recipient() {
+    let (mut pool, spend) = Pool::with_note(&pool_config().public_input_count(13));
+    let (spend, others) = split_spend(spend);
+    let authority = pool.authority.clone();
+    pool.configure(&client::update_denylist(&authority, &others[1], true));
+    let ix = split_ix(&mut pool, &spend, &others);
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::RecipientDenied.into())
+    );
+    assert_eq!(pool.bank.lamports(&others[1]), 0);
+}
+
+#[test]
+fn withdraw_split_requires_every_recipient_to_sign_when_configured() {
+    let (mut pool, spend) = Pool::with_note(&pool_config().public_input_count(13));
+    let (spend, others) = split_spend(spend);
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_recipient_signature(&authority, true));
+    let mut ix = split_ix(&mut pool, &spend, &others);
+    ix.accounts[1].is_signer = true;
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+
+    for meta in ix
+        .accounts
+        .iter_mut()
+        .filter(|meta| others.contains(&meta.address))
+    {
+        meta.is_signer = true;
+    }
+    pool.bank.process(&ix).unwrap();
+    assert_eq!(pool.bank.lamports(&others[0]), NOTE / 4);
+}
+
+#[test]
+fn withdraw_split_rejects_program_owned_split_recipients_of_wallet_only_pools() {
+    let (mut pool, spend) = Pool::with_note(&pool_config().public_input_count(13));
+    let (spend, others) = split_spend(spend);
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_wallet_recipient(&authority, true));
+    pool.bank
+        .set_account(&others[0], &address(0x77), 1_000_000, &[]);
+    let ix = split_ix(&mut pool, &spend, &others);
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::InvalidRecipient.into())
+    );
+}
+
+#[test]
+fn withdraw_split_counts_each_payout_against_its_recipient_limit() {
+    let (mut pool, spend) = Pool::with_note(&pool_config().public_input_count(13));
+    let (spend, others) = split_spend(spend);
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_recipient_limit(
+        &authority,
+        NOTE / 2,
+        100,
+    ));
+    let ix = split_ix(&mut pool, &spend, &others);
+
+    pool.bank.process(&ix).unwrap();
+
+    for (recipient, amount) in [
+        (&spend.recipient, NOTE / 2),
+        (&others[0], NOTE / 4),
+        (&others[1], NOTE / 4),
+    ] {
+        assert_eq!(pool.bank.lamports(recipient), amount);
+        let limit = client::recipient_limit_pda(recipient).0;
+        let limit: &RecipientLimit = bytemuck::from_bytes(pool.bank.data(&limit));
+        assert_eq!(limit.withdrawn_in_window, amount);
+    }
+}
+
+#[test]
+fn withdraw_split_rejects_a_split_recipient_over_its_limit() {
+    let (mut pool, spend) = Pool::with_note(&pool_config().public_input_count(13));
+    let (spend, others) = split_spend(spend);
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_recipient_limit(
+        &authority,
+        NOTE / 2,
+        100,
+    ));
+    // The second split recipient was already paid most of its window.
+    let mut limit = vec![0u8; RecipientLimit::LEN];
+    limit[..8].copy_from_slice(&RecipientLimit::DISCRIMINATOR);
+    limit[8..16].copy_from_slice(&(NOTE / 2 - NOTE / 8).to_le_bytes());
+    pool.bank.set_account(
+        &client::recipient_limit_pda(&others[1]).0,
+        &shielded_pool_pinocchio::ID,
+        1_000_000,
+        &limit,
+    );
+    let ix = split_ix(&mut pool, &spend, &others);
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::RecipientLimitExceeded.into())
+    );
+    assert_eq!(pool.bank.lamports(&spend.recipient), 0);
+    assert_eq!(pool.bank.lamports(&others[0]), 0);
+}
+
+#[test]
+fn withdraw_split_rejects_a_limited_recipient_paid_twice() {
+    let (mut pool, spend) = Pool::with_note(&pool_config().public_input_count(13));
+    let (mut spend, mut others) = split_spend(spend);
+    others[0] = spend.recipient.clone();
+    spend.extra_inputs[2] = address_input(&others[0]);
+    let authority = pool.authority.clone();
+    pool.configure(&client::configure_recipient_limit(&authority, NOTE, 100));
+    let ix = split_ix(&mut pool, &spend, &others);
+
+    assert_eq!(
+        pool.bank.process(&ix),
+        Err(ShieldedPoolError::DuplicateAccount.into())
+    );
+
+    // Without a limit there is no window to double count.
+    pool.configure(&client::configure_recipient_limit(&authority, 0, 0));
+    pool.bank.process(&ix).unwrap();
+    assert_eq!(pool.bank.lamports(&spend.recipient), NOTE / 2 + NOTE / 4);
+}
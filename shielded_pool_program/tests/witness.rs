@@ -0,0 +1,168 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{proof, witness, PROOF_LEN};
+use shielded_pool_pinocchio::{
+    instructions::withdraw::{parse_withdraw_witness, PUBLIC_INPUTS},
+    state::ShieldedPoolState,
+    witness::{MAX_PUBLIC_INPUTS, WITNESS_HEADER_LEN},
+};
+use solana_program_error::ProgramError;
+
+const ORDER: [u8; PUBLIC_INPUTS] = ShieldedPoolState::DEFAULT_PUBLIC_INPUT_ORDER;
+
+/// `count` distinct public inputs: input `i` is filled with `i + 1`.
+fn inputs(count: usize) -> Vec<[u8; 32]> {
+    (0..count).map(|i| [i as u8 + 1; 32]).collect()
+}
+
+fn data(inputs: &[[u8; 32]], trailer: &[u8]) -> Vec<u8> {
+    let mut data = proof(PROOF_LEN, true);
+    data.extend_from_slice(&witness(inputs));
+    data.extend_from_slice(trailer);
+    data
+}
+
+fn parse_err(data: &[u8], proof_len: usize) -> Option<ProgramError> {
+    parse_withdraw_witness(data, proof_len, &ORDER).err()
+}
+
+#[test]
+fn truncated_header_is_rejected() {
+    let data = data(&inputs(PUBLIC_INPUTS), &[]);
+    for len in [0, PROOF_LEN, PROOF_LEN + WITNESS_HEADER_LEN - 1] {
+        assert_eq!(
+            parse_err(&data[..len], PROOF_LEN),
+            Some(ProgramError::InvalidInstructionData),
+            "{len} bytes"
+        );
+    }
+}
+
+#[test]
+fn header_count_above_the_maximum_is_rejected() {
+    let mut data = data(&inputs(MAX_PUBLIC_INPUTS), &[]);
+    let count = (MAX_PUBLIC_INPUTS as u32 + 1).to_be_bytes();
+    data[PROOF_LEN..PROOF_LEN + 4].copy_from_slice(&count);
+    data.extend_from_slice(&[0u8; 32]);
+    assert_eq!(
+        parse_err(&data, PROOF_LEN),
+        Some(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn wrong_proof_length_is_rejected() {
+    let data = data(&inputs(PUBLIC_INPUTS), &[]);
+    for proof_len in [PROOF_LEN - 1, PROOF_LEN + 1, 2 * PROOF_LEN] {
+        assert_eq!(
+            parse_err(&data, proof_len),
+            Some(ProgramError::InvalidInstructionData),
+            "proof_len {proof_len}"
+        );
+    }
+}
+
+#[test]
+fn inputs_short_of_the_header_count_are_rejected() {
+    let data = data(&inputs(PUBLIC_INPUTS), &[]);
+    assert_eq!(
+        parse_err(&data[..data.len() - 1], PROOF_LEN),
+        Some(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn four_inputs_are_too_few() {
+    let data = data(&inputs(PUBLIC_INPUTS - 1), &[]);
+    assert_eq!(
+        parse_err(&data, PROOF_LEN),
+        Some(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn five_inputs_parse_without_a_change_commitment() {
+    let inputs = inputs(PUBLIC_INPUTS);
+    let data = data(&inputs, &[]);
+    let Ok(parsed) = parse_withdraw_witness(&data, PROOF_LEN, &ORDER) else {
+        panic!("five inputs should parse");
+    };
+
+    assert_eq!(parsed.payload, &data[..]);
+    assert_eq!(parsed.inputs.len(), PUBLIC_INPUTS);
+    assert_eq!(parsed.root, inputs[0]);
+    assert_eq!(parsed.nullifier, inputs[1]);
+    assert_eq!(parsed.recipient, inputs[2]);
+    assert_eq!(parsed.amount.0, inputs[3]);
+    assert_eq!(parsed.wa_commitment, inputs[4]);
+    assert_eq!(parsed.change_commitment, [0u8; 32]);
+    assert!(parsed.change_roots.is_empty());
+    assert_eq!((parsed.vault_bump, parsed.audit_bump), (None, None));
+}
+
+#[test]
+fn fifteen_inputs_parse_with_the_change_commitment() {
+    let inputs = inputs(MAX_PUBLIC_INPUTS);
+    let data = data(&inputs, &[]);
+    let Ok(parsed) = parse_withdraw_witness(&data, PROOF_LEN, &ORDER) else {
+        panic!("fifteen inputs should parse");
+    };
+
+    assert_eq!(parsed.inputs.len(), MAX_PUBLIC_INPUTS);
+    assert_eq!(parsed.wa_commitment, inputs[4]);
+    assert_eq!(parsed.change_commitment, inputs[5]);
+}
+
+#[test]
+fn public_input_order_picks_the_logical_inputs() {
+    let inputs = inputs(PUBLIC_INPUTS);
+    let data = data(&inputs, &[]);
+    let Ok(parsed) = parse_withdraw_witness(&data, PROOF_LEN, &[4, 3, 2, 1, 0]) else {
+        panic!("reordered inputs should parse");
+    };
+
+    assert_eq!(parsed.root, inputs[4]);
+    assert_eq!(parsed.amount.0, inputs[1]);
+    assert_eq!(parsed.wa_commitment, inputs[0]);
+}
+
+#[test]
+fn trailing_bumps_and_change_roots_are_split_off() {
+    let inputs = inputs(PUBLIC_INPUTS);
+
+    let data_with_bumps = data(&inputs, &[7, 9]);
+    let Ok(parsed) = parse_withdraw_witness(&data_with_bumps, PROOF_LEN, &ORDER) else {
+        panic!("bumps should parse");
+    };
+    assert_eq!(parsed.payload.len(), data_with_bumps.len() - 2);
+    assert!(parsed.change_roots.is_empty());
+    assert_eq!((parsed.vault_bump, parsed.audit_bump), (Some(7), Some(9)));
+
+    let mut trailer = [0x55u8; 66];
+    trailer[64..].copy_from_slice(&[7, 9]);
+    let data_with_roots = data(&inputs, &trailer);
+    let Ok(parsed) = parse_withdraw_witness(&data_with_roots, PROOF_LEN, &ORDER) else {
+        panic!("change roots should parse");
+    };
+    assert_eq!(parsed.change_roots, &trailer[..64]);
+    assert_eq!((parsed.vault_bump, parsed.audit_bump), (Some(7), Some(9)));
+}
+
+/// Trailing bytes that are neither bumps nor `[change_roots][bumps]` are kept
+/// whole as `change_roots`, which the withdraw then rejects as malformed.
+#[test]
+fn unexpected_trailing_bytes_are_not_taken_as_bumps() {
+    let inputs = inputs(PUBLIC_INPUTS);
+    for len in [1, 3, 65, 67] {
+        let trailer = vec![0x55u8; len];
+        let data = data(&inputs, &trailer);
+        let Ok(parsed) = parse_withdraw_witness(&data, PROOF_LEN, &ORDER) else {
+            panic!("{len} trailing bytes should still split");
+        };
+        assert_eq!(parsed.payload.len(), data.len() - len);
+        assert_eq!(parsed.change_roots, &trailer[..], "{len} trailing bytes");
+        assert_eq!((parsed.vault_bump, parsed.audit_bump), (None, None));
+    }
+}